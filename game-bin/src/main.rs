@@ -1,9 +1,29 @@
-use std::time::SystemTime;
+use std::{fs, time::SystemTime};
 
 use engine::{Engine, EngineLimits, allocators::LinearAllocator, static_allocator};
-use game_lib::Game;
+use game_lib::{Game, GameConfig, SaveStorage};
+use platform::Platform;
 use platform_sdl2::Sdl2Platform;
 
+/// Keeps the save files in the working directory.
+struct FileSaveStorage;
+
+impl SaveStorage for FileSaveStorage {
+    fn exists(&self, name: &str) -> bool {
+        fs::exists(name).unwrap_or(false)
+    }
+
+    fn write(&self, name: &str, save: &[u8]) -> bool {
+        fs::write(name, save).is_ok()
+    }
+
+    fn read(&self, name: &str, buffer: &mut [u8]) -> Option<usize> {
+        let save = fs::read(name).ok()?;
+        buffer.get_mut(..save.len())?.copy_from_slice(&save);
+        Some(save.len())
+    }
+}
+
 fn main() {
     #[cfg(feature = "tracing-subscriber")]
     tracing_subscriber::fmt::init();
@@ -33,7 +53,8 @@ fn main() {
         .duration_since(SystemTime::UNIX_EPOCH)
         .map(|t| t.as_secs())
         .unwrap_or(0);
-    let mut game = Game::new(ARENA, &engine, &platform, seed);
+    let mut game = Game::new(ARENA, &engine, platform.now(), seed, &GameConfig::default());
+    game.set_save_storage(&FileSaveStorage);
 
     platform.run_game_loop(&mut engine, |timestamp, platform, engine| {
         game.iterate(engine, platform, timestamp);
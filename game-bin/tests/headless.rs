@@ -0,0 +1,122 @@
+//! Runs the game without drawing it, stepping it with made up timestamps
+//! instead of waiting for real time to pass.
+
+use std::{env, time::Duration};
+
+use engine::{Engine, EngineLimits, allocators::LinearAllocator, static_allocator};
+use game_lib::{Game, GameConfig, MILLIS_PER_TICK};
+use platform::{Instant, Platform};
+use platform_sdl2::Sdl2Platform;
+
+static ARENA: &LinearAllocator = static_allocator!(64 * 1024 * 1024);
+
+fn main() {
+    // SAFETY: nothing else is running yet, so there's no one to read the
+    // environment at the same time.
+    unsafe {
+        env::set_var("SDL_VIDEODRIVER", "dummy");
+        env::set_var("SDL_AUDIODRIVER", "dummy");
+    }
+    // The resources are loaded from the working directory, like in the game
+    env::set_current_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/..")).unwrap();
+
+    let platform = Sdl2Platform::new("Diving for Oxygen (headless tests)");
+    let mut engine = Engine::new(
+        &platform,
+        ARENA,
+        EngineLimits {
+            frame_arena_size: 4 * 1024 * 1024,
+            resource_database_loaded_chunks_count: 64,
+            resource_database_buffer_size: 1024 * 1024,
+            ..EngineLimits::DEFAULT
+        },
+    );
+
+    let tests: &[(&str, fn(&mut Engine, &dyn Platform))] = &[
+        (
+            "updating_without_rendering_advances_the_game",
+            updating_without_rendering_advances_the_game,
+        ),
+        (
+            "games_with_the_same_start_step_identically",
+            games_with_the_same_start_step_identically,
+        ),
+        (
+            "games_without_music_clips_iterate",
+            games_without_music_clips_iterate,
+        ),
+    ];
+    for (name, test) in tests {
+        test(&mut engine, &platform);
+        println!("test {name} ... ok");
+    }
+}
+
+/// The timestamp `ticks` ticks after `start`.
+fn after_ticks(start: Instant, ticks: u64) -> Instant {
+    start + Duration::from_millis(ticks * MILLIS_PER_TICK)
+}
+
+fn updating_without_rendering_advances_the_game(engine: &mut Engine, platform: &dyn Platform) {
+    let start = platform.now();
+    let mut game = Game::new(ARENA, engine, start, 1234, &GameConfig::default());
+    game.set_paused(false);
+    let initial_fingerprint = game.state_fingerprint();
+
+    for tick in 0..100 {
+        game.update(engine, platform, after_ticks(start, tick));
+        engine.frame_arena.reset();
+    }
+    // The first tick is due right at the start
+    assert_eq!(game.current_tick(), 100);
+    assert_ne!(game.state_fingerprint(), initial_fingerprint);
+}
+
+fn games_with_the_same_start_step_identically(engine: &mut Engine, platform: &dyn Platform) {
+    // Taken once, after which the time only moves via the timestamps below
+    let start = platform.now();
+    let new_game = |engine: &Engine| {
+        let mut game = Game::new(ARENA, engine, start, 4321, &GameConfig::default());
+        game.set_paused(false);
+        game
+    };
+    let mut games = [new_game(engine), new_game(engine)];
+
+    // Uneven frame times, with a few frames landing between ticks
+    let mut millis = 0;
+    for frame in 0..200 {
+        millis += [16, 33, 7, 120][frame % 4];
+        let timestamp = start + Duration::from_millis(millis);
+        for game in &mut games {
+            game.update(engine, platform, timestamp);
+            engine.frame_arena.reset();
+        }
+        let [a, b] = &mut games;
+        assert_eq!(a.current_tick(), b.current_tick());
+        assert_eq!(a.current_tick(), millis / MILLIS_PER_TICK + 1);
+        assert_eq!(
+            a.state_fingerprint(),
+            b.state_fingerprint(),
+            "frame {frame}"
+        );
+    }
+}
+
+fn games_without_music_clips_iterate(engine: &mut Engine, platform: &dyn Platform) {
+    let config = GameConfig {
+        music: false,
+        ..GameConfig::default()
+    };
+    let start = platform.now();
+    let mut game = Game::new(ARENA, engine, start, 1234, &config);
+    game.set_paused(false);
+
+    // A new game picks its first music clip right away once unpaused, and
+    // the next one 45 seconds later
+    for second in 0..100 {
+        let timestamp = start + Duration::from_secs(second);
+        game.iterate(engine, platform, timestamp);
+        engine.frame_arena.reset();
+    }
+    assert!(game.current_tick() > 0);
+}
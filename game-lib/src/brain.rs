@@ -1,26 +1,62 @@
 //! Character behavior controllers.
 
-use core::cmp::Reverse;
+use core::{
+    cmp::Reverse,
+    fmt::{self, Write},
+    hash::{Hash, Hasher},
+    ops::{Index, IndexMut},
+};
 
 use arrayvec::ArrayVec;
-use bytemuck::Zeroable;
+use bytemuck::{Pod, Zeroable};
 use engine::{
     allocators::LinearAllocator, collections::FixedVec, define_system, game_objects::Scene,
 };
+use seahash::SeaHasher;
 use tracing::{debug, trace};
 
 use crate::{
     GameTicks, Sprite,
     game_object::{
-        CharacterStatus, JobStationStatus, JobStationVariant, Personality, Resource,
-        ResourceVariant, Stockpile, StockpileReliantTag, TilePosition,
+        CharacterStatus, InteractionKind, JobStationStatus, JobStationVariant, Personality,
+        Resource, ResourceVariant, Stockpile, StockpileReliantTag, TilePosition,
     },
-    grid::BitGrid,
+    grid::{BitGrid, Grid},
     notifications::{NotificationId, NotificationSet},
-    pathfinding::{Direction, Path, find_path_to, find_path_to_any},
+    pathfinding::{
+        Direction, Path, PathOptions, find_path_to, find_path_to_any, find_path_to_nearest,
+    },
+    resource_index::ResourceIndex,
+    save::{SaveReader, SaveWriter},
 };
 
 pub const MAX_GOALS: usize = 8;
+/// How many move ticks a character can fail to move along its path before its
+/// goals are cleared to force a re-plan.
+pub const STUCK_MOVE_TICKS_BEFORE_REPLAN: u8 = 5;
+/// How many move ticks a character can fail to move along its path before
+/// it's nudged to the nearest free tile as a last resort.
+pub const STUCK_MOVE_TICKS_BEFORE_NUDGE: u8 = 15;
+/// How many of the latest [`BrainEvent`]s are kept around per brain.
+pub const MAX_BRAIN_EVENTS: usize = 8;
+/// How much oxygen a character breathes in per think tick next to a refill
+/// station. Loose oxygen is breathed in one unit at a time.
+pub const REFILL_STATION_OXYGEN_PER_TICK: u8 = 4;
+/// The most a hauler can learn to carry per trip by leveling up.
+pub const MAX_HAUL_AMOUNT: u8 = 6;
+/// How many haulers can be hauling parts of the same haul job at once. The
+/// rest of the job waits for one of them to finish.
+pub const MAX_HAULERS_PER_JOB: u8 = 1;
+/// How many ticks a requested haul waits for a hauler before it's dropped, so
+/// that requests no one can service don't pile up. Workers still waiting for
+/// the resources request them again.
+pub const HAUL_REQUEST_TTL: GameTicks = 200;
+/// How many times over its [`Brain::wait_ticks`] a character needs to have
+/// been idle before it goes to sleep.
+pub const SLEEP_AFTER_WAITS: GameTicks = 3;
+/// How many ticks a character sleeps for once it's in bed, unless it's fully
+/// rested before that.
+pub const SLEEP_TICKS: GameTicks = 100;
 
 #[derive(Debug)]
 pub struct HaulDescription {
@@ -29,6 +65,29 @@ pub struct HaulDescription {
     destination: (JobStationVariant, TilePosition),
 }
 
+impl HaulDescription {
+    /// Haul requests with the same key are for the same need, whatever the
+    /// amount.
+    fn request_key(&self) -> (ResourceVariant, (JobStationVariant, TilePosition)) {
+        (self.resource, self.destination)
+    }
+
+    fn save(&self, writer: &mut SaveWriter) {
+        writer.write_pod(&self.resource);
+        writer.write_u8(self.amount);
+        writer.write_pod(&self.destination.0);
+        writer.write_pod(&self.destination.1);
+    }
+
+    fn load(reader: &mut SaveReader) -> Option<HaulDescription> {
+        Some(HaulDescription {
+            resource: reader.read_pod()?,
+            amount: reader.read_u8()?,
+            destination: (reader.read_pod()?, reader.read_pod()?),
+        })
+    }
+}
+
 #[derive(Debug)]
 pub enum Goal {
     Work {
@@ -45,14 +104,38 @@ pub enum Goal {
     Relax {
         relax_start_tick: GameTicks,
         walk_aabb: (TilePosition, TilePosition),
+        /// The spot picked to walk to, if one was found. Relaxing only counts
+        /// if the character gets there.
+        relax_spot: Option<TilePosition>,
     },
     RefillOxygen,
+    /// Eats non-reserved [`ResourceVariant::FOOD`] next to the character
+    /// until full.
+    Eat,
+    /// Walks to [`Brain::sleep_spot`] and sleeps there, which recovers
+    /// morale faster than relaxing.
+    Sleep {
+        /// The tick the character got to bed, if it has.
+        start_tick: Option<GameTicks>,
+    },
     // TODO: Add a goal or another way to "stop" a character while an animation
     // or notification effect is happening (maybe an Animatable component or
     // something?)
 }
 
 impl Goal {
+    pub fn kind(&self) -> GoalKind {
+        match self {
+            Goal::Work { .. } => GoalKind::Work,
+            Goal::Haul { .. } => GoalKind::Haul,
+            Goal::FollowPath { .. } => GoalKind::FollowPath,
+            Goal::Relax { .. } => GoalKind::Relax,
+            Goal::RefillOxygen => GoalKind::RefillOxygen,
+            Goal::Eat => GoalKind::Eat,
+            Goal::Sleep { .. } => GoalKind::Sleep,
+        }
+    }
+
     pub fn sprite(&self, personality: Personality) -> Option<Sprite> {
         match self {
             Goal::Work { .. } => Some(Sprite::GoalWork),
@@ -63,8 +146,111 @@ impl Goal {
             }
             Goal::Relax { .. } => Some(Sprite::GoalRelax),
             Goal::RefillOxygen => Some(Sprite::GoalOxygen),
+            Goal::Eat => Some(Sprite::GoalEat),
+            Goal::Sleep { .. } => Some(Sprite::GoalSleep),
+        }
+    }
+
+    /// Writes the goal into the save. Haul requests aren't saved, so a
+    /// [`Goal::Work`] waiting for a haul will request it again after loading.
+    fn save(&self, writer: &mut SaveWriter) {
+        match self {
+            Goal::Work { job, .. } => {
+                writer.write_u8(0);
+                writer.write_pod(job);
+            }
+            Goal::Haul { description } => {
+                writer.write_u8(1);
+                description.save(writer);
+            }
+            Goal::FollowPath { from, path } => {
+                writer.write_u8(2);
+                writer.write_pod(from);
+                path.save(writer);
+            }
+            Goal::Relax {
+                relax_start_tick,
+                walk_aabb,
+                relax_spot,
+            } => {
+                writer.write_u8(3);
+                writer.write_u64(*relax_start_tick);
+                writer.write_pod(&walk_aabb.0);
+                writer.write_pod(&walk_aabb.1);
+                writer.write_bool(relax_spot.is_some());
+                writer.write_pod(&relax_spot.unwrap_or(TilePosition::zeroed()));
+            }
+            Goal::RefillOxygen => writer.write_u8(4),
+            Goal::Eat => writer.write_u8(5),
+            Goal::Sleep { start_tick } => {
+                writer.write_u8(6);
+                writer.write_bool(start_tick.is_some());
+                writer.write_u64(start_tick.unwrap_or(0));
+            }
         }
     }
+
+    fn load(reader: &mut SaveReader) -> Option<Goal> {
+        match reader.read_u8()? {
+            0 => Some(Goal::Work {
+                haul_wait_timeout: None,
+                job: reader.read_pod()?,
+            }),
+            1 => Some(Goal::Haul {
+                description: HaulDescription::load(reader)?,
+            }),
+            2 => Some(Goal::FollowPath {
+                from: reader.read_pod()?,
+                path: Path::load(reader)?,
+            }),
+            3 => Some(Goal::Relax {
+                relax_start_tick: reader.read_u64()?,
+                walk_aabb: (reader.read_pod()?, reader.read_pod()?),
+                relax_spot: {
+                    let has_relax_spot = reader.read_bool()?;
+                    let relax_spot = reader.read_pod()?;
+                    has_relax_spot.then_some(relax_spot)
+                },
+            }),
+            4 => Some(Goal::RefillOxygen),
+            5 => Some(Goal::Eat),
+            6 => Some(Goal::Sleep {
+                start_tick: {
+                    let has_start_tick = reader.read_bool()?;
+                    let start_tick = reader.read_u64()?;
+                    has_start_tick.then_some(start_tick)
+                },
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// A [`Goal`] without any of its state, for describing what a brain is doing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GoalKind {
+    Work,
+    Haul,
+    FollowPath,
+    Relax,
+    RefillOxygen,
+    Eat,
+    Sleep,
+}
+
+/// A decision made by a brain, recorded for inspecting why a character is
+/// doing what it's doing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BrainEvent {
+    GoalPushed(GoalKind),
+    GoalFinished(GoalKind),
+    GoalAbandoned(GoalKind),
+    GoalsCleared,
+    HaulAccepted {
+        resource: ResourceVariant,
+        amount: u8,
+        destination: JobStationVariant,
+    },
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -74,11 +260,12 @@ pub enum Occupation {
     Operator(JobStationVariant),
 }
 
-const OCCUPATION_LIST: [Occupation; 4] = [
+const OCCUPATION_LIST: [Occupation; 5] = [
     Occupation::Idle,
     Occupation::Hauler,
     Occupation::Operator(JobStationVariant::ENERGY_GENERATOR),
     Occupation::Operator(JobStationVariant::OXYGEN_GENERATOR),
+    Occupation::Operator(JobStationVariant::FOOD_SYNTHESIZER),
 ];
 
 impl Occupation {
@@ -92,6 +279,9 @@ impl Occupation {
             Occupation::Operator(JobStationVariant::OXYGEN_GENERATOR) => {
                 Some(Sprite::OccupationWorkOxygen)
             }
+            Occupation::Operator(JobStationVariant::FOOD_SYNTHESIZER) => {
+                Some(Sprite::OccupationWorkFood)
+            }
             Occupation::Operator(_) => None,
         }
     }
@@ -114,6 +304,163 @@ impl Occupation {
             Occupation::Idle
         }
     }
+
+    fn save(self, writer: &mut SaveWriter) {
+        match self {
+            Occupation::Idle => writer.write_u8(0),
+            Occupation::Hauler => writer.write_u8(1),
+            Occupation::Operator(job) => {
+                writer.write_u8(2);
+                writer.write_pod(&job);
+            }
+        }
+    }
+
+    fn load(reader: &mut SaveReader) -> Option<Occupation> {
+        match reader.read_u8()? {
+            0 => Some(Occupation::Idle),
+            1 => Some(Occupation::Hauler),
+            2 => Some(Occupation::Operator(reader.read_pod()?)),
+            _ => None,
+        }
+    }
+}
+
+/// A snapshot of a brain's idling-related state, for balancing and debugging.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BrainStats {
+    pub goal_stack_depth: usize,
+    pub ticks_without_goal: GameTicks,
+    pub wait_ticks: GameTicks,
+}
+
+/// Identifies a brain in [`Brains`]. The generation is bumped every time a
+/// slot is reused, so an id held on to after its brain has been removed won't
+/// refer to whichever brain took its place.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Zeroable, Pod)]
+#[repr(C)]
+pub struct BrainId {
+    pub index: u8,
+    pub generation: u8,
+}
+
+/// The brains of all the characters. Brains never move between slots, and
+/// removed brains' slots are reused for new ones, so [`BrainId`]s stay valid
+/// for as long as their brain is around.
+pub struct Brains {
+    slots: FixedVec<'static, Brain>,
+}
+
+impl Brains {
+    pub fn new(arena: &'static LinearAllocator, capacity: usize) -> Option<Brains> {
+        Some(Brains {
+            slots: FixedVec::new(arena, capacity)?,
+        })
+    }
+
+    /// Stores the brain in a free slot, returning `None` if there's no room.
+    pub fn add(&mut self, mut brain: Brain) -> Option<BrainId> {
+        if let Some(index) = self.slots.iter().position(|slot| slot.retired) {
+            let generation = self.slots[index].generation.wrapping_add(1);
+            brain.generation = generation;
+            self.slots[index] = brain;
+            return Some(BrainId {
+                index: index as u8,
+                generation,
+            });
+        }
+
+        let index = self.slots.len();
+        brain.generation = 0;
+        self.slots.push(brain).ok()?;
+        Some(BrainId {
+            index: index as u8,
+            generation: 0,
+        })
+    }
+
+    /// Retires the brain, freeing up its slot. Returns false if the id was
+    /// already stale.
+    pub fn remove(&mut self, id: BrainId) -> bool {
+        let Some(brain) = self.get_mut(id) else {
+            return false;
+        };
+        *brain = Brain {
+            retired: true,
+            generation: id.generation,
+            ..Brain::new()
+        };
+        true
+    }
+
+    pub fn get(&self, id: BrainId) -> Option<&Brain> {
+        let brain = self.slots.get(id.index as usize)?;
+        (!brain.retired && brain.generation == id.generation).then_some(brain)
+    }
+
+    pub fn get_mut(&mut self, id: BrainId) -> Option<&mut Brain> {
+        let brain = self.slots.get_mut(id.index as usize)?;
+        (!brain.retired && brain.generation == id.generation).then_some(brain)
+    }
+
+    /// The ids of all the brains that haven't been removed.
+    pub fn ids(&self) -> impl Iterator<Item = BrainId> + '_ {
+        self.slots.iter().enumerate().filter_map(|(index, brain)| {
+            (!brain.retired).then_some(BrainId {
+                index: index as u8,
+                generation: brain.generation,
+            })
+        })
+    }
+
+    /// All the brain slots, including retired ones, indexed by
+    /// [`BrainId::index`].
+    pub fn slots_mut(&mut self) -> &mut [Brain] {
+        &mut self.slots
+    }
+
+    /// Feeds the state of all the brains into `hasher`. Retired brains are
+    /// included, since their generations affect the ids handed out next.
+    pub fn hash_state<H: Hasher>(&self, hasher: &mut H) {
+        for brain in self.slots.iter() {
+            brain.hash_state(hasher);
+        }
+    }
+
+    /// Writes all the brains into the save, including the retired ones, so
+    /// that the [`BrainId`]s stay the same after loading.
+    pub fn save(&self, writer: &mut SaveWriter) {
+        writer.write_u8(self.slots.len() as u8);
+        for brain in self.slots.iter() {
+            brain.save(writer);
+        }
+    }
+
+    /// Reads brains written by [`Brains::save`] over these ones, reusing the
+    /// slots. Returns `None` if the save is cut short, or has more brains
+    /// than fit, in which case some of the brains may have been loaded.
+    pub fn load(&mut self, reader: &mut SaveReader) -> Option<()> {
+        while self.slots.pop().is_some() {}
+        for _ in 0..reader.read_u8()? {
+            self.slots.push(Brain::load(reader)?).ok()?;
+        }
+        Some(())
+    }
+}
+
+impl Index<BrainId> for Brains {
+    type Output = Brain;
+    fn index(&self, id: BrainId) -> &Brain {
+        self.get(id)
+            .expect("brain ids should not outlive their brains")
+    }
+}
+
+impl IndexMut<BrainId> for Brains {
+    fn index_mut(&mut self, id: BrainId) -> &mut Brain {
+        self.get_mut(id)
+            .expect("brain ids should not outlive their brains")
+    }
 }
 
 #[derive(Debug)]
@@ -124,6 +471,27 @@ pub struct Brain {
     pub wait_ticks: GameTicks,
     pub ticks_without_goal: GameTicks,
     pub has_relaxed: bool,
+    /// Set while the character is in bed, cleared when the sleep is counted
+    /// toward morale like [`Brain::has_relaxed`].
+    pub has_slept: bool,
+    /// Where the character goes to sleep, if it has a bed.
+    pub sleep_spot: Option<TilePosition>,
+    /// Set when morale drops to the character's low morale threshold, and
+    /// cleared only once it climbs above
+    /// [`CharacterStatus::RECOVERY_MORALE_THRESHOLD`].
+    pub recovering_morale: bool,
+    pub stuck_move_ticks: u8,
+    /// Set by the player to keep the character standing still. Halted brains
+    /// drop their goals and don't pick new ones until they're let go again.
+    pub halted: bool,
+    /// Set when the character using this brain has been fired. No character
+    /// refers to a retired brain, so its slot can be given to a new hire.
+    pub retired: bool,
+    generation: u8,
+    events: ArrayVec<BrainEvent, MAX_BRAIN_EVENTS>,
+    /// The haul job this brain is hauling a part of, claimed until the haul
+    /// goal is gone. Not saved, since the haul jobs aren't either.
+    haul_claim: Option<NotificationId>,
 }
 
 impl Brain {
@@ -135,17 +503,150 @@ impl Brain {
             wait_ticks: 30,
             ticks_without_goal: 0,
             has_relaxed: false,
+            has_slept: false,
+            sleep_spot: None,
+            recovering_morale: false,
+            stuck_move_ticks: 0,
+            halted: false,
+            retired: false,
+            generation: 0,
+            events: ArrayVec::new(),
+            haul_claim: None,
         }
     }
 
-    pub fn next_move_direction(&self) -> Option<Direction> {
-        if let Some(Goal::FollowPath { path, .. }) = self.goal_stack.last() {
-            Some(path.into_iter().next()?)
+    pub fn stats(&self) -> BrainStats {
+        BrainStats {
+            goal_stack_depth: self.goal_stack.len(),
+            ticks_without_goal: self.ticks_without_goal,
+            wait_ticks: self.wait_ticks,
+        }
+    }
+
+    /// Feeds the brain's state into `hasher`. Goes through the [`Debug`]
+    /// output, which has all of it, including the paths and the events.
+    pub fn hash_state<H: Hasher>(&self, hasher: &mut H) {
+        struct HashWriter<'a, H>(&'a mut H);
+        impl<H: Hasher> Write for HashWriter<'_, H> {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                self.0.write(s.as_bytes());
+                Ok(())
+            }
+        }
+        let result = write!(HashWriter(hasher), "{self:?}");
+        debug_assert!(result.is_ok(), "formatting a brain should not fail");
+    }
+
+    /// Writes the brain into the save. The events are left out, since they're
+    /// only for inspecting the brain.
+    fn save(&self, writer: &mut SaveWriter) {
+        writer.write_u8(self.goal_stack.len() as u8);
+        for goal in &self.goal_stack {
+            goal.save(writer);
+        }
+        self.job.save(writer);
+        writer.write_u8(self.max_haul_amount);
+        writer.write_u64(self.wait_ticks);
+        writer.write_u64(self.ticks_without_goal);
+        writer.write_bool(self.has_relaxed);
+        writer.write_bool(self.has_slept);
+        writer.write_bool(self.sleep_spot.is_some());
+        writer.write_pod(&self.sleep_spot.unwrap_or(TilePosition::zeroed()));
+        writer.write_bool(self.recovering_morale);
+        writer.write_u8(self.stuck_move_ticks);
+        writer.write_bool(self.halted);
+        writer.write_bool(self.retired);
+        writer.write_u8(self.generation);
+    }
+
+    fn load(reader: &mut SaveReader) -> Option<Brain> {
+        let mut goal_stack = ArrayVec::new();
+        for _ in 0..reader.read_u8()? {
+            goal_stack.try_push(Goal::load(reader)?).ok()?;
+        }
+        Some(Brain {
+            goal_stack,
+            job: Occupation::load(reader)?,
+            max_haul_amount: reader.read_u8()?,
+            wait_ticks: reader.read_u64()?,
+            ticks_without_goal: reader.read_u64()?,
+            has_relaxed: reader.read_bool()?,
+            has_slept: reader.read_bool()?,
+            sleep_spot: {
+                let has_sleep_spot = reader.read_bool()?;
+                let sleep_spot = reader.read_pod()?;
+                has_sleep_spot.then_some(sleep_spot)
+            },
+            recovering_morale: reader.read_bool()?,
+            stuck_move_ticks: reader.read_u8()?,
+            halted: reader.read_bool()?,
+            retired: reader.read_bool()?,
+            generation: reader.read_u8()?,
+            events: ArrayVec::new(),
+            haul_claim: None,
+        })
+    }
+
+    /// The latest decisions made by this brain, oldest first.
+    pub fn recent_events(&self) -> &[BrainEvent] {
+        &self.events
+    }
+
+    fn record_event(&mut self, event: BrainEvent) {
+        if self.events.is_full() {
+            self.events.remove(0);
+        }
+        self.events.push(event);
+    }
+
+    /// The path the character is walking along, and the tile it continues
+    /// from, if the character is on the move.
+    pub fn active_path(&self) -> Option<(&TilePosition, &Path)> {
+        if let Some(Goal::FollowPath { from, path }) = self.goal_stack.last() {
+            Some((from, path))
         } else {
             None
         }
     }
 
+    pub fn next_move_direction(&self) -> Option<Direction> {
+        self.active_path()?.1.nth(0)
+    }
+
+    /// Keeps track of how long the character has been unable to move along
+    /// its path, clearing the goals if it's been stuck for a while. Returns
+    /// true if the character should be nudged out of its current spot.
+    pub fn update_stuckness(&mut self, moved: bool) -> bool {
+        if moved {
+            self.stuck_move_ticks = 0;
+            return false;
+        }
+
+        self.stuck_move_ticks = self.stuck_move_ticks.saturating_add(1);
+        if self.stuck_move_ticks == STUCK_MOVE_TICKS_BEFORE_REPLAN {
+            debug!("stuck, clearing goals to re-plan");
+            self.goal_stack.clear();
+            self.record_event(BrainEvent::GoalsCleared);
+        } else if self.stuck_move_ticks >= STUCK_MOVE_TICKS_BEFORE_NUDGE {
+            debug!("still stuck after re-planning, needs a nudge");
+            self.stuck_move_ticks = 0;
+            return true;
+        }
+        false
+    }
+
+    /// Sets how much the character carries per haul, at least one and at most
+    /// [`MAX_HAUL_AMOUNT`]. Used for the next haul the character accepts.
+    pub fn set_max_haul_amount(&mut self, amount: u8) {
+        self.max_haul_amount = amount.clamp(1, MAX_HAUL_AMOUNT);
+    }
+
+    /// The sprite for what the character is doing, i.e. the topmost goal
+    /// which has a sprite (paths are just a means to an end).
+    pub fn thought_sprite(&self, personality: Personality) -> Option<Sprite> {
+        (self.goal_stack.iter().rev()).find_map(|goal| goal.sprite(personality))
+    }
+
     pub fn current_job(&self) -> Option<JobStationVariant> {
         if let Some(Goal::Work { job, .. }) = self.goal_stack.last() {
             Some(*job)
@@ -154,17 +655,38 @@ impl Brain {
         }
     }
 
+    /// Releases the claimed haul job, if any, so that other haulers can take
+    /// it on.
+    pub fn release_haul_claim(
+        &mut self,
+        haul_notifications: &mut NotificationSet<HaulDescription>,
+    ) {
+        if let Some(haul_id) = self.haul_claim.take() {
+            haul_notifications.release(haul_id);
+        }
+    }
+
+    /// Picks a new goal if needed. Paths are found around `walls`, and
+    /// around `soft_obstacles` and the tiles with `tile_costs` if the detour
+    /// is short enough.
+    #[allow(clippy::too_many_arguments)]
     pub fn update_goals(
         &mut self,
         (current_brain_index, current_position, current_tick): (u8, TilePosition, GameTicks),
         scene: &mut Scene,
         haul_notifications: &mut NotificationSet<HaulDescription>,
+        resource_index: &mut ResourceIndex,
         walls: &BitGrid,
+        soft_obstacles: &BitGrid,
+        tile_costs: Option<&Grid<u8>>,
         temp_arena: &mut LinearAllocator,
     ) {
         let span = tracing::info_span!("", current_brain_index);
         let _enter = span.enter();
 
+        let paths = PathOptions::new(walls, soft_obstacles).with_tile_costs(tile_costs);
+        let paths_to_walls = paths.with_wall_destination();
+
         let mut hashed_bytes = ArrayVec::<u8, 13>::new();
         for bytes in [
             &[current_brain_index][..],
@@ -187,16 +709,52 @@ impl Brain {
             }
         }));
 
-        if current_status.oxygen == 0 {
+        if current_status.is_out_of_oxygen() {
             self.goal_stack.clear();
+            self.release_haul_claim(haul_notifications);
             // TODO: display/animate running out of oxygen
             return;
         }
-        let demoralized = current_status.morale <= CharacterStatus::LOW_MORALE_THRESHOLD;
+        if self.halted {
+            if !self.goal_stack.is_empty() {
+                debug!("halted, dropping goals");
+                self.goal_stack.clear();
+                self.record_event(BrainEvent::GoalsCleared);
+            }
+            self.release_haul_claim(haul_notifications);
+            return;
+        }
+        let recovery_morale_threshold =
+            CharacterStatus::RECOVERY_MORALE_THRESHOLD.max(current_status.low_morale_threshold);
+        if current_status.morale <= current_status.low_morale_threshold {
+            if !self.recovering_morale {
+                debug!("demoralized, relaxing until morale recovers");
+            }
+            self.recovering_morale = true;
+        } else if current_status.morale > recovery_morale_threshold {
+            if self.recovering_morale {
+                debug!("morale recovered, back to work");
+            }
+            self.recovering_morale = false;
+        }
+        let demoralized = self.recovering_morale;
+
+        // Running on the reserve tanks, drop everything that isn't refilling.
+        let on_reserve_oxygen = current_status.oxygen == 0;
+        if on_reserve_oxygen && !matches!(self.goal_stack.first(), Some(Goal::RefillOxygen)) {
+            debug!("running on reserve oxygen, dropping everything to refill");
+            self.goal_stack.clear();
+            self.record_event(BrainEvent::GoalsCleared);
+        }
+
+        // The part of the haul job this brain took on is done or abandoned
+        if !(self.goal_stack.iter()).any(|goal| matches!(goal, Goal::Haul { .. })) {
+            self.release_haul_claim(haul_notifications);
+        }
 
         // This branch picks something occupation-based to do, so it's not ran
-        // when on low morale.
-        if self.goal_stack.is_empty() && !demoralized {
+        // when on low morale or oxygen.
+        if self.goal_stack.is_empty() && !demoralized && !on_reserve_oxygen {
             match self.job {
                 Occupation::Idle => {
                     // Idling!
@@ -204,13 +762,14 @@ impl Brain {
                 }
                 Occupation::Operator(job) => {
                     debug!("finding work at {job:?}");
-                    self.goal_stack.push(Goal::Work {
+                    self.push_goal(Goal::Work {
                         haul_wait_timeout: None,
                         job,
                     });
                 }
                 Occupation::Hauler => {
-                    // Find the closest haul job (by destination) and take it
+                    // Find the closest haul job (by destination) of the
+                    // highest priority and take it
                     trace!("finding hauling work to do");
 
                     let Some(mut hauls_by_distance) =
@@ -219,56 +778,87 @@ impl Brain {
                         debug_assert!(false, "not enough memory for haul distance calculation");
                         return;
                     };
+                    let Some(priorities) =
+                        haul_destination_priorities(scene, haul_notifications, temp_arena)
+                    else {
+                        debug_assert!(false, "not enough memory for haul priorities");
+                        return;
+                    };
                     for (id, desc) in haul_notifications.iter() {
+                        if haul_notifications.claims(id) >= MAX_HAULERS_PER_JOB {
+                            trace!("skipping {desc:?}, it's already being hauled");
+                            continue;
+                        }
+                        // Destinations that are gone are left at the lowest priority
+                        let priority = (priorities.iter())
+                            .find(|(destination, _)| *destination == desc.destination)
+                            .map_or(0, |(_, priority)| *priority);
                         let dist = desc.destination.1.manhattan_distance(*current_position);
-                        let could_add = hauls_by_distance.push((id, dist));
+                        let could_add = hauls_by_distance.push((id, priority, dist));
                         debug_assert!(could_add.is_ok());
                     }
-                    hauls_by_distance.sort_unstable_by_key(|(_, dist)| Reverse(*dist));
+                    hauls_by_distance.sort_unstable_by_key(|(id, priority, dist)| {
+                        Reverse((
+                            Reverse(*priority),
+                            *dist,
+                            haul_tie_breaker(current_brain_index, *id),
+                        ))
+                    });
 
                     let capacity_left = temp_arena.total() - temp_arena.allocated();
                     let mut temp_arena = LinearAllocator::new(temp_arena, capacity_left).unwrap();
-                    while let Some((notif_id, _)) = hauls_by_distance.pop() {
+                    while let Some((notif_id, _, _)) = hauls_by_distance.pop() {
                         temp_arena.reset();
                         if let Some(description) = haul_notifications.get_mut(notif_id) {
                             // Check that the destination is reachable
                             let dst = description.destination;
                             let path_to_dest =
-                                find_path_to(current_position, dst.1, true, walls, &temp_arena);
-                            if path_to_dest.is_none() {
+                                find_path_to(current_position, dst.1, &paths_to_walls, &temp_arena);
+                            if path_to_dest.is_err() {
                                 continue;
                             }
 
                             // Check that the resource is reachable
-                            let Some(dsts) = find_non_reserved_resources(
-                                scene,
-                                description.resource,
-                                &temp_arena,
-                                walls,
-                            ) else {
+                            let Some(dsts) =
+                                resource_index.destinations(scene, description.resource)
+                            else {
                                 continue;
                             };
-                            let path_to_resource =
-                                find_path_to_any(current_position, &dsts, true, walls, &temp_arena);
-                            if path_to_resource.is_none() {
+                            let path_to_resource = find_path_to_any(
+                                current_position,
+                                dsts,
+                                &paths_to_walls,
+                                &temp_arena,
+                            );
+                            if path_to_resource.is_err() {
                                 continue;
                             }
 
                             // Accept the job
                             debug!("hauling {description:?}");
+                            self.record_event(BrainEvent::HaulAccepted {
+                                resource: description.resource,
+                                amount: description.amount.min(self.max_haul_amount),
+                                destination: description.destination.0,
+                            });
                             if description.amount > self.max_haul_amount {
                                 description.amount -= self.max_haul_amount;
-                                self.goal_stack.push(Goal::Haul {
+                                self.push_goal(Goal::Haul {
                                     description: HaulDescription {
                                         resource: description.resource,
                                         amount: self.max_haul_amount,
                                         destination: dst,
                                     },
                                 });
+                                haul_notifications.claim(notif_id);
+                                self.haul_claim = Some(notif_id);
                             } else {
                                 let description = haul_notifications.remove(notif_id).unwrap();
-                                self.goal_stack.push(Goal::Haul { description });
+                                self.push_goal(Goal::Haul { description });
                             }
+                            // One job at a time, since only one claim is kept
+                            // track of for releasing it later
+                            break;
                         }
                     }
                 }
@@ -277,53 +867,115 @@ impl Brain {
 
         temp_arena.reset();
 
-        if current_status.oxygen <= CharacterStatus::LOW_OXYGEN_THRESHOLD
+        // Pushed before refilling oxygen, so that the oxygen comes first if
+        // both are running low
+        if current_status.food <= CharacterStatus::LOW_FOOD_THRESHOLD
+            && !(self.goal_stack.iter()).any(|goal| matches!(goal, Goal::Eat))
+        {
+            let from = current_position;
+            let path_to_food =
+                find_path_to_food(from, scene, resource_index, &paths_to_walls, temp_arena);
+            if let Some((path, food_pos)) = path_to_food {
+                debug!("hungry, found path to food at {food_pos:?}: {path:?}");
+                self.push_goal(Goal::Eat);
+                self.push_goal(Goal::FollowPath { from, path });
+            } else {
+                debug!("hungry but there's no food to eat :(");
+            }
+        }
+
+        temp_arena.reset();
+
+        if current_status.oxygen <= current_status.low_oxygen_threshold
             && self
                 .goal_stack
                 .iter()
                 .all(|goal| !matches!(goal, Goal::RefillOxygen))
         {
-            if let Some(oxygen) =
-                find_non_reserved_resources(scene, ResourceVariant::OXYGEN, temp_arena, walls)
-            {
-                let from = current_position;
-                if let Some(path) = find_path_to_any(from, &oxygen, true, walls, temp_arena) {
-                    debug!("found path to oxygen: {path:?}");
-                    self.goal_stack.push(Goal::RefillOxygen);
-                    self.goal_stack.push(Goal::FollowPath { from, path });
-                } else {
-                    debug!("the tanks are runnign out but there's no oxygen to refill with :(");
-                }
+            let from = current_position;
+            let path_to_oxygen =
+                find_path_to_oxygen(from, scene, resource_index, &paths_to_walls, temp_arena);
+            if let Some((path, oxygen_pos)) = path_to_oxygen {
+                debug!("found path to oxygen at {oxygen_pos:?}: {path:?}");
+                self.push_goal(Goal::RefillOxygen);
+                self.push_goal(Goal::FollowPath { from, path });
             } else {
-                debug_assert!(false, "ran out of memory to find oxygen?");
+                debug!("the tanks are runnign out but there's no oxygen to refill with :(");
             }
         }
 
         temp_arena.reset();
 
         if self.goal_stack.is_empty() {
-            if self.ticks_without_goal >= self.wait_ticks || demoralized {
-                self.goal_stack.push(Goal::Relax {
+            // Healthy characters that have had nothing to do for a long while
+            // catch some sleep, which also keeps them out of the haul jobs
+            let healthy =
+                !demoralized && current_status.oxygen > current_status.low_oxygen_threshold;
+            if healthy
+                && self.sleep_spot.is_some()
+                && current_status.morale < CharacterStatus::MAX_MORALE
+                && self.ticks_without_goal >= self.wait_ticks.saturating_mul(SLEEP_AFTER_WAITS)
+            {
+                debug!("idled for a long time, going to sleep");
+                self.push_goal(Goal::Sleep { start_tick: None });
+            } else if self.ticks_without_goal >= self.wait_ticks || demoralized {
+                // Still counting, relaxing isn't really doing anything
+                self.ticks_without_goal = self.ticks_without_goal.saturating_add(1);
+                self.push_goal(Goal::Relax {
                     relax_start_tick: current_tick,
                     walk_aabb: (
                         TilePosition::new(
-                            current_position.x.saturating_sub(5),
-                            current_position.y.saturating_sub(5),
+                            current_position.x.saturating_sub(5).max(0),
+                            current_position.y.saturating_sub(5).max(0),
                         ),
                         TilePosition::new(
                             (current_position.x.saturating_add(5)).min(walls.width() as i16 - 1),
                             (current_position.y.saturating_add(5)).min(walls.height() as i16 - 1),
                         ),
                     ),
+                    relax_spot: None,
                 });
             } else {
                 self.ticks_without_goal += 1;
             }
+        } else if self.is_relaxing() {
+            // Strolling around is idling too, so it counts toward going to
+            // sleep
+            self.ticks_without_goal = self.ticks_without_goal.saturating_add(1);
         }
 
         let mut new_instrumental_goal = None;
         let mut goal_not_acheivable = false;
         let mut goal_finished = false;
+        let mut leveled_up_hauling = false;
+
+        // Release the haul (along with whatever it's waiting on) if the
+        // destination doesn't need the resources anymore
+        if let Some(haul_index) =
+            (self.goal_stack.iter()).position(|goal| matches!(goal, Goal::Haul { .. }))
+        {
+            if let Goal::Haul { description } = &self.goal_stack[haul_index] {
+                if !haul_destination_needs_resources(scene, description) {
+                    debug!(
+                        "the destination of {description:?} no longer takes it, releasing the haul"
+                    );
+                    self.goal_stack.truncate(haul_index);
+                    self.record_event(BrainEvent::GoalAbandoned(GoalKind::Haul));
+                }
+            }
+        }
+
+        // Anything carried around is for hauling, so put it down if the haul
+        // is gone (e.g. the goals were cleared to re-plan)
+        if !self
+            .goal_stack
+            .iter()
+            .any(|goal| matches!(goal, Goal::Haul { .. }))
+        {
+            if drop_held_resources(scene, current_brain_index, current_position) {
+                resource_index.invalidate();
+            }
+        }
 
         let Some(current_goal) = self.goal_stack.last_mut() else {
             return;
@@ -353,26 +1005,37 @@ impl Brain {
                             job_stations.iter_mut().zip(stockpiles).zip(positions)
                         {
                             if job_station.variant == *job
-                                && current_position.manhattan_distance(**pos) < 2
+                                && current_position.can_interact(*pos, InteractionKind::Work)
                             {
                                 within_working_distance = true;
                                 if let Some(details) = job_station.variant.details() {
                                     let resources =
                                         stockpile.get_resources_mut(details.resource_variant);
                                     let current_amount = resources.map(|a| *a).unwrap_or(0);
-                                    if current_amount >= details.resource_amount {
-                                        if haul_wait_timeout.is_some() {
-                                            *haul_wait_timeout = None;
-                                            debug!("got resources while waiting");
-                                        }
-                                    } else if haul_wait_timeout.is_none() {
+                                    let can_work = current_amount >= details.resource_amount;
+                                    if can_work && haul_wait_timeout.is_some() {
+                                        *haul_wait_timeout = None;
+                                        debug!("got resources while waiting");
+                                    }
+                                    let input_target = job_station.input_target();
+                                    if current_amount < input_target
+                                        && haul_wait_timeout.is_none()
+                                        && job_station.settings.is_enabled()
+                                    {
                                         let description = HaulDescription {
                                             resource: details.resource_variant,
                                             destination: (job_station.variant, *pos),
-                                            amount: details.resource_amount,
+                                            amount: input_target - current_amount,
                                         };
                                         debug!("requesting {description:?}");
-                                        match haul_notifications.notify(description) {
+                                        match haul_notifications.notify_unique_with_ttl(
+                                            description,
+                                            HAUL_REQUEST_TTL,
+                                            HaulDescription::request_key,
+                                        ) {
+                                            // Topping up the stock isn't worth
+                                            // waiting for while there's work to do
+                                            Ok(_) if can_work => {}
                                             Ok(haul_id) => {
                                                 *haul_wait_timeout =
                                                     Some((haul_id, self.wait_ticks));
@@ -396,7 +1059,12 @@ impl Brain {
                 if let Some((haul_id, ticks_left)) = haul_wait_timeout.take() {
                     let haul_still_waiting = haul_notifications.check(haul_id);
                     if ticks_left == 0 {
-                        if haul_still_waiting {
+                        if haul_still_waiting && haul_notifications.claims(haul_id) > 0 {
+                            // A hauler is already bringing some of it over,
+                            // hauling the rest too would bring it twice
+                            debug!("someone is on the haul job, waiting some more");
+                            *haul_wait_timeout = Some((haul_id, self.wait_ticks));
+                        } else if haul_still_waiting {
                             // Do it yourself:
                             let description = haul_notifications.remove(haul_id).unwrap();
                             debug!(
@@ -435,8 +1103,8 @@ impl Brain {
 
                     // Find path
                     let from = current_position;
-                    if let Some(path) =
-                        find_path_to_any(from, &destinations, true, walls, temp_arena)
+                    if let Ok(path) =
+                        find_path_to_any(from, &destinations, &paths_to_walls, temp_arena)
                     {
                         debug!("found path to work: {path:?}");
                         new_instrumental_goal = Some(Goal::FollowPath { from, path });
@@ -456,56 +1124,71 @@ impl Brain {
                         amount: requested_amount,
                     },
             } => {
-                // Try to pick the resource from the current tile
-                let mut picked_up_thus_far = 0;
+                // See how much is already in our stockpile
+                let mut current_amount = 0;
                 scene.run_system(define_system!(
-                    |_, positions: &[TilePosition], stockpiles: &mut [Stockpile]| {
-                        for (position, stockpile) in positions.iter().zip(stockpiles) {
-                            if position.manhattan_distance(*current_position) < 2
-                                && stockpile.has_non_reserved_resources(*resource)
-                            {
-                                let stockpile_amount =
-                                    stockpile.get_resources_mut(*resource).unwrap();
-                                let picked_up =
-                                    (*requested_amount - picked_up_thus_far).min(*stockpile_amount);
-                                *stockpile_amount -= picked_up;
-                                picked_up_thus_far += picked_up;
-                                debug!("picked up {picked_up}x {resource:?}");
-                            }
-                            if picked_up_thus_far >= *requested_amount {
+                    |_, characters: &[CharacterStatus], stockpiles: &[Stockpile]| {
+                        for (character, stockpile) in characters.iter().zip(stockpiles) {
+                            if character.brain_index == current_brain_index {
+                                current_amount = stockpile.get_resources(*resource).unwrap_or(0);
                                 break;
                             }
                         }
                     }
                 ));
 
-                // Check if we have enough items in our stockpile (or move the
-                // stuff we just picked up into our stockpile if we picked up
-                // some)
-                let mut resources_acquired = false;
-                let mut current_amount = 0;
-                scene.run_system(define_system!(
-                    |_, characters: &[CharacterStatus], stockpiles: &mut [Stockpile]| {
-                        for (character, stockpile) in characters.iter().zip(stockpiles) {
-                            if character.brain_index == current_brain_index {
-                                current_amount = stockpile.get_resources(*resource).unwrap_or(0);
-                                if picked_up_thus_far > 0 && current_amount < *requested_amount {
-                                    let pocketed =
-                                        picked_up_thus_far.min(*requested_amount - current_amount);
-                                    debug!("adding {pocketed}x {resource:?} to my stockpile");
-                                    stockpile.add_resource(*resource, pocketed).unwrap();
-                                    stockpile.mark_reserved(*resource, true);
-                                    picked_up_thus_far -= pocketed;
-                                    current_amount += pocketed;
+                // Try to pick the rest of the resources from the current tile
+                let wanted_amount = requested_amount.saturating_sub(current_amount);
+                let mut picked_up_thus_far = 0;
+                if wanted_amount > 0 {
+                    scene.run_system(define_system!(
+                        |_, positions: &[TilePosition], stockpiles: &mut [Stockpile]| {
+                            for (position, stockpile) in positions.iter().zip(stockpiles) {
+                                if current_position
+                                    .can_interact(*position, InteractionKind::HaulPickUp)
+                                    && stockpile.has_non_reserved_resources(*resource)
+                                {
+                                    let stockpile_amount =
+                                        stockpile.get_resources_mut(*resource).unwrap();
+                                    let picked_up =
+                                        (wanted_amount - picked_up_thus_far).min(*stockpile_amount);
+                                    *stockpile_amount -= picked_up;
+                                    picked_up_thus_far += picked_up;
+                                    debug!("picked up {picked_up}x {resource:?}");
                                 }
-                                if current_amount >= *requested_amount {
-                                    resources_acquired = true;
+                                if picked_up_thus_far >= wanted_amount {
+                                    break;
                                 }
-                                break;
                             }
                         }
-                    }
-                ));
+                    ));
+                }
+
+                // Move the stuff we just picked up into our stockpile
+                if picked_up_thus_far > 0 {
+                    resource_index.invalidate();
+                    scene.run_system(define_system!(
+                        |_, characters: &[CharacterStatus], stockpiles: &mut [Stockpile]| {
+                            for (character, stockpile) in characters.iter().zip(stockpiles) {
+                                if character.brain_index == current_brain_index {
+                                    debug!(
+                                        "adding {picked_up_thus_far}x {resource:?} to my stockpile"
+                                    );
+                                    let overflow = stockpile
+                                        .add_resource(*resource, picked_up_thus_far)
+                                        .err()
+                                        .unwrap_or(0);
+                                    stockpile.mark_reserved(*resource, true);
+                                    stockpile.canonicalize();
+                                    current_amount += picked_up_thus_far - overflow;
+                                    picked_up_thus_far = overflow;
+                                    break;
+                                }
+                            }
+                        }
+                    ));
+                }
+                let mut resources_acquired = current_amount >= *requested_amount;
 
                 if picked_up_thus_far > 0 {
                     debug!(
@@ -532,12 +1215,11 @@ impl Brain {
                     debug!("looking for (more) {resource:?}");
 
                     // Find path
-                    let destinations =
-                        find_non_reserved_resources(scene, *resource, temp_arena, walls);
+                    let destinations = resource_index.destinations(scene, *resource);
                     let from = current_position;
-                    if let Some(path) = destinations
-                        .and_then(|dsts| find_path_to_any(from, &dsts, true, walls, temp_arena))
-                    {
+                    if let Some(path) = destinations.and_then(|dsts| {
+                        find_path_to_any(from, dsts, &paths_to_walls, temp_arena).ok()
+                    }) {
                         debug!("found path to resource: {path:?}");
                         new_instrumental_goal = Some(Goal::FollowPath { from, path });
                     } else if current_amount > 0 {
@@ -557,7 +1239,7 @@ impl Brain {
                 if resources_acquired {
                     debug!("I have {current_amount}x {resource:?} and am bringing them back");
                     let (from, to) = (current_position, destination.1);
-                    if let Some(path) = find_path_to(from, to, true, walls, temp_arena) {
+                    if let Ok(path) = find_path_to(from, to, &paths_to_walls, temp_arena) {
                         if path.is_empty() {
                             drop_off = true;
                             goal_finished = true;
@@ -567,12 +1249,14 @@ impl Brain {
                         }
                     } else {
                         debug!("could not find path from {from:?} to {to:?}");
+                        goal_not_acheivable = true;
                     }
                 }
 
                 // We have the stuff, and are at the destination, drop the resources off
                 if drop_off {
                     debug!("dropping off haul at {current_position:?}");
+                    resource_index.invalidate();
 
                     let (dst_job, dst_pos) = *destination;
 
@@ -588,13 +1272,15 @@ impl Brain {
                             {
                                 if job_station.variant == dst_job && *position == dst_pos {
                                     debug_assert!(
-                                        position.manhattan_distance(*current_position) < 2
+                                        current_position
+                                            .can_interact(*position, InteractionKind::HaulDropOff)
                                     );
                                     let overflow = stockpile
                                         .add_resource(*resource, current_amount)
                                         .err()
                                         .unwrap_or(0);
                                     dropped_off += current_amount - overflow;
+                                    stockpile.canonicalize();
                                     goal_finished = true;
                                     break;
                                 }
@@ -603,11 +1289,12 @@ impl Brain {
                     ));
 
                     // Remove the dropped off amount from the hauler's stockpile
-                    // and mark it as non-reserved
+                    // and mark it as non-reserved. Delivering is also how
+                    // haulers get better at hauling.
                     let mut left_over = 0;
                     scene.run_system(define_system!(
-                        |_, characters: &[CharacterStatus], stockpiles: &mut [Stockpile]| {
-                            for (character, stockpile) in characters.iter().zip(stockpiles) {
+                        |_, characters: &mut [CharacterStatus], stockpiles: &mut [Stockpile]| {
+                            for (character, stockpile) in characters.iter_mut().zip(stockpiles) {
                                 if character.brain_index == current_brain_index {
                                     if let Some(hauled_res) = stockpile.get_resources_mut(*resource)
                                     {
@@ -616,6 +1303,9 @@ impl Brain {
                                         *hauled_res -= left_over;
                                         stockpile.mark_reserved(*resource, false);
                                     }
+                                    leveled_up_hauling = character.gain_experience(
+                                        dropped_off as u16 * CharacterStatus::HAUL_EXPERIENCE,
+                                    );
                                     break;
                                 }
                             }
@@ -653,8 +1343,8 @@ impl Brain {
                 } else if current_position != *from {
                     let mut destination = *from;
                     let mut steps_progressed = None;
-                    for (i, step) in path.into_iter().enumerate() {
-                        destination = destination + step;
+                    for (i, position) in path.positions_from(*from).enumerate() {
+                        destination = position;
                         if destination == current_position {
                             steps_progressed = Some(i + 1);
                         }
@@ -669,8 +1359,8 @@ impl Brain {
                         *from = current_position;
                         *path = truncated_path;
                         trace!("moved {steps_progressed} steps");
-                    } else if let Some(new_path) =
-                        find_path_to(current_position, destination, true, walls, temp_arena)
+                    } else if let Ok(new_path) =
+                        find_path_to(current_position, destination, &paths_to_walls, temp_arena)
                     {
                         // Strayed off the path, make a new one.
                         *from = current_position;
@@ -687,112 +1377,1952 @@ impl Brain {
             Goal::Relax {
                 relax_start_tick,
                 walk_aabb,
+                relax_spot,
             } => {
                 debug!("relaxing!");
-                self.has_relaxed = true;
                 if *relax_start_tick != current_tick {
                     // Started relaxing earlier, and ended up back at this goal,
                     // so call it finished. If there's nothing useful to do (or
                     // morale is low), the next tick's goal will be relax again.
                     goal_finished = true;
+                    // Only a relaxing stroll counts, just standing around with
+                    // the goal doesn't.
+                    if *relax_spot == Some(current_position) {
+                        self.has_relaxed = true;
+                    }
                 } else {
                     // Try to find a spot to walk to:
                     let x = (rand & 0xFFFFFFFF) % walk_aabb.0.x.abs_diff(walk_aabb.1.x) as u64;
                     let y = (rand >> 32) % walk_aabb.0.y.abs_diff(walk_aabb.1.y) as u64;
                     let dst = TilePosition::new(walk_aabb.0.x + x as i16, walk_aabb.0.y + y as i16);
                     let from = current_position;
-                    if let Some(path) = find_path_to(from, dst, true, walls, temp_arena) {
+                    if let Ok(path) = find_path_to(from, dst, &paths, temp_arena) {
+                        *relax_spot = Some(dst);
                         new_instrumental_goal = Some(Goal::FollowPath { from, path });
                     }
                 }
             }
 
-            Goal::RefillOxygen => {
-                let mut oxygen_found = false;
-                scene.run_system(define_system!(
-                    |_, positions: &[TilePosition], stockpiles: &mut [Stockpile]| {
-                        for (position, stockpile) in positions.iter().zip(stockpiles) {
-                            if position.manhattan_distance(*current_position) < 2
-                                && stockpile.has_non_reserved_resources(ResourceVariant::OXYGEN)
-                            {
-                                let stockpile_amount = stockpile
-                                    .get_resources_mut(ResourceVariant::OXYGEN)
-                                    .unwrap();
-                                if *stockpile_amount > 0 {
-                                    *stockpile_amount -= 1;
-                                    oxygen_found = true;
-                                    debug!(
-                                        "found oxygen, left {} in the stockpile",
-                                        *stockpile_amount,
-                                    );
-                                    break;
+            Goal::Eat => {
+                if current_status.is_full() {
+                    goal_finished = true;
+                } else {
+                    let mut ate = false;
+                    scene.run_system(define_system!(
+                        |_, positions: &[TilePosition], stockpiles: &mut [Stockpile]| {
+                            for (position, stockpile) in positions.iter().zip(stockpiles) {
+                                if current_position.can_interact(*position, InteractionKind::Eat)
+                                    && stockpile.has_non_reserved_resources(ResourceVariant::FOOD)
+                                {
+                                    let stockpile_amount =
+                                        stockpile.get_resources_mut(ResourceVariant::FOOD).unwrap();
+                                    if *stockpile_amount > 0 {
+                                        *stockpile_amount -= 1;
+                                        ate = true;
+                                        debug!(
+                                            "ate food, left {} in the stockpile",
+                                            *stockpile_amount,
+                                        );
+                                        break;
+                                    }
                                 }
                             }
                         }
-                    }
-                ));
-
-                if !oxygen_found {
-                    goal_not_acheivable = true;
-                } else if current_status.oxygen + 1 >= CharacterStatus::MAX_OXYGEN {
-                    goal_finished = true;
-                }
+                    ));
 
-                scene.run_system(define_system!(|_, characters: &mut [CharacterStatus]| {
-                    for character in characters {
-                        if character.brain_index == current_brain_index {
-                            character.oxygen = character.oxygen.saturating_add(1);
-                            debug!(
-                                "breathed in oxygen, now at {}/{}",
-                                character.oxygen,
-                                CharacterStatus::MAX_OXYGEN,
-                            );
-                            break;
+                    if !ate {
+                        // The path here was cut short, or the food got eaten
+                        let from = current_position;
+                        let path_to_food = find_path_to_food(
+                            from,
+                            scene,
+                            resource_index,
+                            &paths_to_walls,
+                            temp_arena,
+                        )
+                        .filter(|(path, _)| !path.is_empty());
+                        if let Some((path, food_pos)) = path_to_food {
+                            debug!("no food here, heading for {food_pos:?} instead");
+                            new_instrumental_goal = Some(Goal::FollowPath { from, path });
+                        } else {
+                            goal_not_acheivable = true;
                         }
+                    } else {
+                        resource_index.invalidate();
+                        scene.run_system(define_system!(
+                            |_, characters: &mut [CharacterStatus]| {
+                                for character in characters {
+                                    if character.brain_index == current_brain_index {
+                                        character.eat();
+                                        debug!(
+                                            "ate, now at {}/{}",
+                                            character.food,
+                                            CharacterStatus::MAX_FOOD,
+                                        );
+                                        if character.is_full() {
+                                            goal_finished = true;
+                                        }
+                                        break;
+                                    }
+                                }
+                            }
+                        ));
                     }
-                }));
+                }
             }
-        }
-
-        temp_arena.reset();
 
-        if goal_not_acheivable {
-            debug!("giving up on {:?}", self.goal_stack.last());
-            self.goal_stack.pop();
-        } else if goal_finished {
+            Goal::Sleep { start_tick } => {
+                if current_status.morale >= CharacterStatus::MAX_MORALE {
+                    debug!("fully rested, waking up");
+                    goal_finished = true;
+                } else if let Some(start_tick) = start_tick {
+                    if current_tick.saturating_sub(*start_tick) >= SLEEP_TICKS {
+                        debug!("slept long enough, waking up");
+                        goal_finished = true;
+                    } else {
+                        trace!("sleeping");
+                        self.has_slept = true;
+                    }
+                } else if let Some(bed) = self.sleep_spot {
+                    if current_position.can_interact(bed, InteractionKind::Sleep) {
+                        debug!("got to bed at {current_position:?}");
+                        *start_tick = Some(current_tick);
+                        self.has_slept = true;
+                    } else if let Ok(path) = find_path_to(current_position, bed, &paths, temp_arena)
+                    {
+                        new_instrumental_goal = Some(Goal::FollowPath {
+                            from: current_position,
+                            path,
+                        });
+                    } else {
+                        debug!("can't find a path to bed at {bed:?}");
+                        goal_not_acheivable = true;
+                    }
+                } else {
+                    goal_not_acheivable = true;
+                }
+            }
+
+            Goal::RefillOxygen => {
+                if current_status.is_fully_oxygenated() {
+                    // Nothing to breathe in, leave the oxygen for others
+                    goal_finished = true;
+                } else {
+                    // Refill stations fill the tanks a few units at a time
+                    let mut oxygen_found = 0;
+                    scene.run_system(define_system!(
+                        |_,
+                         job_stations: &[JobStationStatus],
+                         stockpiles: &mut [Stockpile],
+                         positions: &[TilePosition]| {
+                            for ((job_station, stockpile), position) in
+                                job_stations.iter().zip(stockpiles).zip(positions)
+                            {
+                                if job_station.variant != JobStationVariant::REFILL_STATION
+                                    || !current_position
+                                        .can_interact(*position, InteractionKind::RefillOxygen)
+                                {
+                                    continue;
+                                }
+                                if let Some(stockpile_amount) =
+                                    stockpile.get_resources_mut(ResourceVariant::OXYGEN)
+                                {
+                                    oxygen_found = (*stockpile_amount)
+                                        .min(REFILL_STATION_OXYGEN_PER_TICK)
+                                        .min(current_status.oxygen_missing());
+                                    *stockpile_amount -= oxygen_found;
+                                    if oxygen_found > 0 {
+                                        debug!(
+                                            "refilled at a refill station, left {} in it",
+                                            *stockpile_amount,
+                                        );
+                                        break;
+                                    }
+                                }
+                            }
+                        }
+                    ));
+
+                    if oxygen_found == 0 {
+                        scene.run_system(define_system!(
+                            |_, positions: &[TilePosition], stockpiles: &mut [Stockpile]| {
+                                for (position, stockpile) in positions.iter().zip(stockpiles) {
+                                    if current_position
+                                        .can_interact(*position, InteractionKind::RefillOxygen)
+                                        && stockpile
+                                            .has_non_reserved_resources(ResourceVariant::OXYGEN)
+                                    {
+                                        let stockpile_amount = stockpile
+                                            .get_resources_mut(ResourceVariant::OXYGEN)
+                                            .unwrap();
+                                        if *stockpile_amount > 0 {
+                                            *stockpile_amount -= 1;
+                                            oxygen_found = 1;
+                                            debug!(
+                                                "found oxygen, left {} in the stockpile",
+                                                *stockpile_amount,
+                                            );
+                                            break;
+                                        }
+                                    }
+                                }
+                            }
+                        ));
+                    }
+
+                    if oxygen_found == 0 {
+                        // The path here was cut short, or the oxygen ran out
+                        let from = current_position;
+                        let path_to_oxygen = find_path_to_oxygen(
+                            from,
+                            scene,
+                            resource_index,
+                            &paths_to_walls,
+                            temp_arena,
+                        )
+                        .filter(|(path, _)| !path.is_empty());
+                        if let Some((path, oxygen_pos)) = path_to_oxygen {
+                            debug!("no oxygen here, heading for {oxygen_pos:?} instead");
+                            new_instrumental_goal = Some(Goal::FollowPath { from, path });
+                        } else {
+                            goal_not_acheivable = true;
+                        }
+                    } else {
+                        resource_index.invalidate();
+                        scene.run_system(define_system!(
+                            |_, characters: &mut [CharacterStatus]| {
+                                for character in characters {
+                                    if character.brain_index == current_brain_index {
+                                        for _ in 0..oxygen_found {
+                                            character.breathe_in();
+                                        }
+                                        debug!(
+                                            "breathed in oxygen, now at {}/{} (+{} in reserve)",
+                                            character.oxygen,
+                                            CharacterStatus::MAX_OXYGEN,
+                                            character.reserve_oxygen,
+                                        );
+                                        if character.is_fully_oxygenated() {
+                                            goal_finished = true;
+                                        }
+                                        break;
+                                    }
+                                }
+                            }
+                        ));
+                    }
+                }
+            }
+        }
+
+        temp_arena.reset();
+
+        if leveled_up_hauling {
+            self.set_max_haul_amount(self.max_haul_amount + 1);
+            debug!(
+                "leveled up, can now haul {} at a time",
+                self.max_haul_amount
+            );
+        }
+
+        if goal_not_acheivable {
+            debug!("giving up on {:?}", self.goal_stack.last());
+            if let Some(goal) = self.goal_stack.pop() {
+                if matches!(goal, Goal::Haul { .. })
+                    && drop_held_resources(scene, current_brain_index, current_position)
+                {
+                    resource_index.invalidate();
+                }
+                self.record_event(BrainEvent::GoalAbandoned(goal.kind()));
+            }
+        } else if goal_finished {
             debug!("finished {:?}", self.goal_stack.last());
-            self.goal_stack.pop();
+            if let Some(goal) = self.goal_stack.pop() {
+                if !matches!(goal, Goal::Relax { .. }) && !self.is_relaxing() {
+                    self.ticks_without_goal = 0;
+                }
+                self.record_event(BrainEvent::GoalFinished(goal.kind()));
+            }
         } else if let Some(new_instrumental_goal) = new_instrumental_goal {
             debug!(
                 "doing {new_instrumental_goal:?} first to be able to do {:?}",
                 self.goal_stack.last(),
             );
+            if !matches!(new_instrumental_goal, Goal::Relax { .. }) && !self.is_relaxing() {
+                self.ticks_without_goal = 0;
+            }
+            let kind = new_instrumental_goal.kind();
             if self.goal_stack.try_push(new_instrumental_goal).is_err() {
                 self.goal_stack.clear(); // reconsider everything
+                self.record_event(BrainEvent::GoalsCleared);
+            } else {
+                self.record_event(BrainEvent::GoalPushed(kind));
             }
         }
     }
+
+    /// Returns true if the character is relaxing, including walking to the
+    /// relaxing spot.
+    fn is_relaxing(&self) -> bool {
+        matches!(self.goal_stack.first(), Some(Goal::Relax { .. }))
+    }
+
+    /// Pushes the goal on top of the goal stack. Anything but relaxing counts
+    /// as something to do, so the idle counter is reset.
+    fn push_goal(&mut self, goal: Goal) {
+        if !matches!(goal, Goal::Relax { .. }) {
+            self.ticks_without_goal = 0;
+        }
+        self.record_event(BrainEvent::GoalPushed(goal.kind()));
+        self.goal_stack.push(goal);
+    }
 }
 
-fn find_non_reserved_resources<'a>(
+/// Orders haul jobs at the same distance differently for each hauler, so that
+/// haulers looking at the same jobs don't all go for the same one.
+fn haul_tie_breaker(brain_index: u8, haul: NotificationId) -> u64 {
+    let mut hasher = SeaHasher::new();
+    hasher.write_u8(brain_index);
+    haul.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Returns the haul priorities set for the job stations the `hauls` are
+/// going to, looked up in one go so that picking a haul doesn't go through
+/// the scene for every candidate.
+fn haul_destination_priorities<'a>(
+    scene: &mut Scene,
+    hauls: &NotificationSet<HaulDescription>,
+    temp_arena: &'a LinearAllocator,
+) -> Option<FixedVec<'a, ((JobStationVariant, TilePosition), u8)>> {
+    let mut priorities = FixedVec::new(temp_arena, hauls.len())?;
+    scene.run_system(define_system!(
+        |_, job_stations: &[JobStationStatus], positions: &[TilePosition]| {
+            for (job_station, position) in job_stations.iter().zip(positions) {
+                let destination = (job_station.variant, *position);
+                if hauls
+                    .iter()
+                    .any(|(_, haul)| haul.destination == destination)
+                {
+                    let priority = job_station.settings.haul_priority;
+                    let could_add = priorities.push((destination, priority));
+                    debug_assert!(could_add.is_ok());
+                }
+            }
+        }
+    ));
+    Some(priorities)
+}
+
+/// Returns true if the haul's destination job station still exists, is
+/// enabled, and is still below its [`JobStationStatus::input_target`].
+fn haul_destination_needs_resources(scene: &mut Scene, description: &HaulDescription) -> bool {
+    let (dst_job, dst_pos) = description.destination;
+    let mut needs_resources = false;
+    scene.run_system(define_system!(
+        |_,
+         job_stations: &[JobStationStatus],
+         positions: &[TilePosition],
+         stockpiles: &[Stockpile]| {
+            for ((job_station, position), stockpile) in
+                job_stations.iter().zip(positions).zip(stockpiles)
+            {
+                if job_station.variant == dst_job && *position == dst_pos {
+                    let current_amount = stockpile.get_resources(description.resource).unwrap_or(0);
+                    needs_resources = job_station.settings.is_enabled()
+                        && current_amount < job_station.input_target();
+                    break;
+                }
+            }
+        }
+    ));
+    needs_resources
+}
+
+/// Empties the character's stockpile onto the floor, unreserving the
+/// resources so that others can haul them.
+pub(crate) fn drop_held_resources(
+    scene: &mut Scene,
+    brain_index: u8,
+    position: TilePosition,
+) -> bool {
+    let mut dropped = Stockpile::zeroed();
+    scene.run_system(define_system!(
+        |_, characters: &[CharacterStatus], stockpiles: &mut [Stockpile]| {
+            for (character, stockpile) in characters.iter().zip(stockpiles) {
+                if character.brain_index == brain_index {
+                    dropped = *stockpile;
+                    *stockpile = Stockpile::zeroed();
+                    break;
+                }
+            }
+        }
+    ));
+    if dropped.is_empty() {
+        return false;
+    }
+
+    debug!("dropping {dropped:?} at {position:?}");
+    dropped.reserved = 0;
+    let dropped_resources = Resource {
+        position,
+        stockpile: dropped,
+        stockpile_reliant: StockpileReliantTag {},
+    };
+    if scene.spawn(dropped_resources).is_err() {
+        debug_assert!(false, "resource game object table is too small");
+    }
+    true
+}
+
+/// Finds a path to the nearest food. The position is where the food is, or
+/// where the path ends, if it was cut short.
+fn find_path_to_food(
+    from: TilePosition,
+    scene: &mut Scene,
+    resource_index: &mut ResourceIndex,
+    paths_to_walls: &PathOptions,
+    temp_arena: &LinearAllocator,
+) -> Option<(Path, TilePosition)> {
+    let food = resource_index.destinations(scene, ResourceVariant::FOOD)?;
+    find_path_to_nearest(from, food, paths_to_walls, temp_arena).ok()
+}
+
+/// Finds a path to the nearest refill station with oxygen in it, or if there
+/// are none, to the nearest loose oxygen. The position is where the oxygen is,
+/// or where the path ends, if it was cut short.
+fn find_path_to_oxygen(
+    from: TilePosition,
+    scene: &mut Scene,
+    resource_index: &mut ResourceIndex,
+    paths_to_walls: &PathOptions,
+    temp_arena: &LinearAllocator,
+) -> Option<(Path, TilePosition)> {
+    let refill_stations = find_refill_stations(scene, temp_arena, paths_to_walls.walls);
+    let find_path = |oxygen: &BitGrid| {
+        let path = find_path_to_nearest(from, oxygen, paths_to_walls, temp_arena);
+        path.ok()
+    };
+    // Refill stations are preferred, refilling is faster there
+    (refill_stations.as_ref().and_then(find_path)).or_else(|| {
+        let loose_oxygen = resource_index.destinations(scene, ResourceVariant::OXYGEN)?;
+        find_path(loose_oxygen)
+    })
+}
+
+/// Returns the refill stations with oxygen in them.
+fn find_refill_stations<'a>(
     scene: &mut Scene,
-    resource: ResourceVariant,
     temp_arena: &'a LinearAllocator,
     walls: &BitGrid,
 ) -> Option<BitGrid<'a>> {
     let Some(mut destinations) = BitGrid::new(temp_arena, walls.size()) else {
-        debug_assert!(false, "out of memory for pathfinding to resource :(");
+        debug_assert!(false, "out of memory for pathfinding to refill stations :(");
         return None;
     };
     scene.run_system(define_system!(
-        |_, positions: &[TilePosition], stockpiles: &[Stockpile]| {
-            for (pos, stockpile) in positions.iter().zip(stockpiles) {
-                if stockpile.has_non_reserved_resources(resource) {
+        |_,
+         job_stations: &[JobStationStatus],
+         stockpiles: &[Stockpile],
+         positions: &[TilePosition]| {
+            for ((job_station, stockpile), pos) in
+                job_stations.iter().zip(stockpiles).zip(positions)
+            {
+                if job_station.variant == JobStationVariant::REFILL_STATION
+                    && stockpile
+                        .get_resources(ResourceVariant::OXYGEN)
+                        .unwrap_or(0)
+                        > 0
+                {
                     destinations.set(*pos, true);
-                    trace!("found potential resource at: {pos:?}");
+                    trace!("found potential refill station at: {pos:?}");
                 }
             }
         }
     ));
     Some(destinations)
 }
+
+#[cfg(test)]
+mod tests {
+    use arrayvec::ArrayVec;
+    use bytemuck::Zeroable;
+    use engine::{
+        allocators::LinearAllocator,
+        collections::FixedVec,
+        define_system,
+        game_objects::{GameObjectHandle, Scene},
+        static_allocator,
+    };
+
+    use crate::{
+        GameTicks, Sprite,
+        game_object::{
+            Character, CharacterStatus, Collider, JobStation, JobStationSettings, JobStationStatus,
+            JobStationVariant, Personality, Resource, ResourceVariant, Stockpile,
+            StockpileReliantTag, TilePosition,
+        },
+        grid::{BitGrid, Grid},
+        notifications::NotificationSet,
+        pathfinding::Path,
+        resource_index::ResourceIndex,
+        tilemap::GAS_POCKET_PATH_COST,
+    };
+
+    use super::{
+        Brain, BrainEvent, Brains, Direction, Goal, GoalKind, HaulDescription, MAX_HAUL_AMOUNT,
+        Occupation, REFILL_STATION_OXYGEN_PER_TICK, SLEEP_AFTER_WAITS, haul_tie_breaker,
+    };
+
+    fn test_scene(arena: &'static LinearAllocator) -> Scene<'static> {
+        Scene::builder()
+            .with_game_object_type::<Character>(4)
+            .with_game_object_type::<JobStation>(4)
+            .with_game_object_type::<Resource>(16)
+            .build(arena, arena)
+            .unwrap()
+    }
+
+    /// A scene on an empty map, with everything else brains need for
+    /// thinking.
+    struct TestWorld {
+        scene: Scene<'static>,
+        haul_notifications: NotificationSet<'static, HaulDescription>,
+        resource_index: ResourceIndex<'static>,
+        walls: BitGrid<'static>,
+        characters: BitGrid<'static>,
+        tile_costs: Option<Grid<'static, u8>>,
+        temp_arena: LinearAllocator<'static>,
+        latest_tick: Option<GameTicks>,
+    }
+
+    impl TestWorld {
+        fn new(arena: &'static LinearAllocator, size: (usize, usize)) -> TestWorld {
+            TestWorld {
+                scene: test_scene(arena),
+                haul_notifications: NotificationSet::new(arena, 4).unwrap(),
+                resource_index: ResourceIndex::new(arena, size).unwrap(),
+                walls: BitGrid::new(arena, size).unwrap(),
+                characters: BitGrid::new(arena, size).unwrap(),
+                tile_costs: None,
+                temp_arena: LinearAllocator::new(arena, 64 * 1024).unwrap(),
+                latest_tick: None,
+            }
+        }
+
+        /// Updates the goals of the brain of the character at `position`. Like
+        /// in the game, the resources are looked up again on each new tick.
+        fn think(
+            &mut self,
+            brain: &mut Brain,
+            brain_index: u8,
+            position: TilePosition,
+            tick: GameTicks,
+        ) {
+            if self.latest_tick != Some(tick) {
+                self.resource_index.invalidate();
+                self.latest_tick = Some(tick);
+            }
+            self.temp_arena.reset();
+            brain.update_goals(
+                (brain_index, position, tick),
+                &mut self.scene,
+                &mut self.haul_notifications,
+                &mut self.resource_index,
+                &self.walls,
+                &self.characters,
+                self.tile_costs.as_ref(),
+                &mut self.temp_arena,
+            );
+        }
+    }
+
+    fn healthy_character(brain_index: u8) -> CharacterStatus {
+        CharacterStatus {
+            brain_index,
+            oxygen: CharacterStatus::MAX_OXYGEN,
+            reserve_oxygen: CharacterStatus::MAX_RESERVE_OXYGEN,
+            morale: CharacterStatus::MAX_MORALE,
+            food: CharacterStatus::MAX_FOOD,
+            low_oxygen_threshold: CharacterStatus::LOW_OXYGEN_THRESHOLD,
+            low_morale_threshold: CharacterStatus::LOW_MORALE_THRESHOLD,
+            ..CharacterStatus::zeroed()
+        }
+    }
+
+    fn spawn_character(scene: &mut Scene, status: CharacterStatus, position: TilePosition) {
+        let spawned = scene.spawn(Character {
+            status,
+            position,
+            held: Stockpile::zeroed(),
+            collider: Collider::NOT_WALKABLE,
+        });
+        assert!(spawned.is_ok());
+    }
+
+    fn spawn_job_station(scene: &mut Scene, variant: JobStationVariant, position: TilePosition) {
+        let spawned = scene.spawn(JobStation {
+            position,
+            stockpile: Stockpile::zeroed(),
+            status: JobStationStatus {
+                variant,
+                work_invested: 0,
+                input_work_left: 0,
+                settings: JobStationSettings::zeroed(),
+            },
+            collider: Collider::NOT_WALKABLE,
+        });
+        assert!(spawned.is_ok());
+    }
+
+    fn spawn_resource(scene: &mut Scene, variant: ResourceVariant, amount: u8, pos: TilePosition) {
+        let spawned = scene.spawn(Resource {
+            position: pos,
+            stockpile: Stockpile::zeroed().with_resource(variant, amount, false),
+            stockpile_reliant: StockpileReliantTag {},
+        });
+        assert!(spawned.is_ok());
+    }
+
+    #[test]
+    fn reserve_oxygen_drops_everything_for_refilling() {
+        static ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        let mut world = TestWorld::new(ARENA, (8, 3));
+
+        let position = TilePosition::new(1, 1);
+        let mut status = healthy_character(0);
+        status.oxygen = 0;
+        spawn_character(&mut world.scene, status, position);
+        spawn_resource(
+            &mut world.scene,
+            ResourceVariant::OXYGEN,
+            5,
+            TilePosition::new(6, 1),
+        );
+
+        let mut brain = Brain::new();
+        brain.job = Occupation::Hauler;
+        brain.goal_stack.push(Goal::Relax {
+            relax_start_tick: 0,
+            walk_aabb: (position, position),
+            relax_spot: None,
+        });
+        world.think(&mut brain, 0, position, 1);
+
+        assert!(matches!(brain.goal_stack.first(), Some(Goal::RefillOxygen)));
+        assert!(matches!(
+            brain.goal_stack.last(),
+            Some(Goal::FollowPath { .. })
+        ));
+        assert_eq!(brain.goal_stack.len(), 2);
+    }
+
+    #[test]
+    fn reserve_oxygen_lasts_exactly_its_amount_of_refill_attempts() {
+        static ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        let mut world = TestWorld::new(ARENA, (8, 3));
+
+        let position = TilePosition::new(1, 1);
+        let mut status = healthy_character(0);
+        status.oxygen = 0;
+        spawn_character(&mut world.scene, status, position);
+        // Too far away to reach before the reserve runs out
+        spawn_resource(
+            &mut world.scene,
+            ResourceVariant::OXYGEN,
+            5,
+            TilePosition::new(6, 1),
+        );
+
+        let mut brain = Brain::new();
+        brain.job = Occupation::Hauler;
+
+        // Each oxygen tick on the reserve leaves the character one more try
+        // at getting to the oxygen
+        for _ in 0..CharacterStatus::MAX_RESERVE_OXYGEN {
+            world.think(&mut brain, 0, position, 1);
+            assert!(matches!(brain.goal_stack.first(), Some(Goal::RefillOxygen)));
+            world
+                .scene
+                .run_system(define_system!(|_, characters: &mut [CharacterStatus]| {
+                    for character in characters {
+                        assert!(!character.is_out_of_oxygen());
+                        character.deplete_oxygen();
+                    }
+                }));
+        }
+
+        world
+            .scene
+            .run_system(define_system!(|_, characters: &[CharacterStatus]| {
+                assert!(characters[0].is_out_of_oxygen());
+            }));
+        world.think(&mut brain, 0, position, 1);
+        assert!(brain.goal_stack.is_empty());
+    }
+
+    #[test]
+    fn refill_stations_refill_faster_than_loose_oxygen() {
+        let refill_once = |arena: &'static LinearAllocator, at_station: bool| {
+            let mut world = TestWorld::new(arena, (8, 3));
+
+            let position = TilePosition::new(1, 1);
+            let mut status = healthy_character(0);
+            status.oxygen = 5;
+            spawn_character(&mut world.scene, status, position);
+            let oxygen_pos = TilePosition::new(2, 1);
+            if at_station {
+                let spawned = world.scene.spawn(JobStation {
+                    position: oxygen_pos,
+                    stockpile: Stockpile::zeroed().with_resource(ResourceVariant::OXYGEN, 10, true),
+                    status: JobStationStatus {
+                        variant: JobStationVariant::REFILL_STATION,
+                        work_invested: 0,
+                        input_work_left: 0,
+                        settings: JobStationSettings::zeroed(),
+                    },
+                    collider: Collider::NOT_WALKABLE,
+                });
+                assert!(spawned.is_ok());
+            } else {
+                spawn_resource(&mut world.scene, ResourceVariant::OXYGEN, 10, oxygen_pos);
+            }
+
+            let mut brain = Brain::new();
+            brain.goal_stack.push(Goal::RefillOxygen);
+            world.think(&mut brain, 0, position, 1);
+
+            let mut oxygen = 0;
+            world
+                .scene
+                .run_system(define_system!(|_, characters: &[CharacterStatus]| {
+                    oxygen = characters[0].oxygen;
+                }));
+            oxygen
+        };
+
+        static LOOSE_ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        static STATION_ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        let from_loose_oxygen = refill_once(LOOSE_ARENA, false);
+        let from_station = refill_once(STATION_ARENA, true);
+        assert_eq!(from_loose_oxygen, 6);
+        assert_eq!(from_station, 5 + REFILL_STATION_OXYGEN_PER_TICK);
+        assert!(from_station > from_loose_oxygen);
+    }
+
+    #[test]
+    fn idle_ticks_are_counted_until_a_goal_is_found() {
+        static ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        let mut world = TestWorld::new(ARENA, (8, 3));
+
+        let position = TilePosition::new(1, 1);
+        spawn_character(&mut world.scene, healthy_character(0), position);
+
+        let mut brain = Brain::new();
+        brain.wait_ticks = 10;
+        for tick in 1..=5 {
+            world.think(&mut brain, 0, position, tick);
+            assert_eq!(brain.stats().ticks_without_goal, tick);
+            assert_eq!(brain.stats().goal_stack_depth, 0);
+        }
+
+        brain.job = Occupation::Operator(JobStationVariant::ENERGY_GENERATOR);
+        world.think(&mut brain, 0, position, 6);
+        assert_eq!(brain.stats().ticks_without_goal, 0);
+    }
+
+    #[test]
+    fn finishing_work_resets_the_idle_counter() {
+        static ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        let mut world = TestWorld::new(ARENA, (8, 3));
+
+        let position = TilePosition::new(1, 1);
+        spawn_character(&mut world.scene, healthy_character(0), position);
+
+        spawn_job_station(
+            &mut world.scene,
+            JobStationVariant::ENERGY_GENERATOR,
+            TilePosition::new(2, 1),
+        );
+
+        let mut brain = Brain::new();
+        brain.ticks_without_goal = brain.wait_ticks + 5; // stale from a long idle
+        brain.goal_stack.push(Goal::Work {
+            haul_wait_timeout: None,
+            job: JobStationVariant::ENERGY_GENERATOR,
+        });
+        // The occupation is idle, so the work is finished right away
+        world.think(&mut brain, 0, position, 1);
+        assert!(brain.goal_stack.is_empty());
+        assert_eq!(brain.ticks_without_goal, 0);
+    }
+
+    #[test]
+    fn disabled_stations_do_not_request_inputs() {
+        static ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        let mut world = TestWorld::new(ARENA, (8, 3));
+
+        let position = TilePosition::new(1, 1);
+        spawn_character(&mut world.scene, healthy_character(0), position);
+        spawn_job_station(
+            &mut world.scene,
+            JobStationVariant::ENERGY_GENERATOR,
+            TilePosition::new(2, 1),
+        );
+        let set_enabled = |scene: &mut Scene, enabled: bool| {
+            scene.run_system(define_system!(|_, jobs: &mut [JobStationStatus]| {
+                for job in jobs {
+                    job.settings.set_enabled(enabled);
+                }
+            }));
+        };
+        set_enabled(&mut world.scene, false);
+
+        let mut brain = Brain::new();
+        brain.job = Occupation::Operator(JobStationVariant::ENERGY_GENERATOR);
+        brain.goal_stack.push(Goal::Work {
+            haul_wait_timeout: None,
+            job: JobStationVariant::ENERGY_GENERATOR,
+        });
+
+        world.think(&mut brain, 0, position, 1);
+        assert_eq!(world.haul_notifications.len(), 0);
+
+        set_enabled(&mut world.scene, true);
+        world.think(&mut brain, 0, position, 2);
+        assert_eq!(world.haul_notifications.len(), 1);
+    }
+
+    #[test]
+    fn stations_are_stocked_up_to_their_input_target() {
+        static ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        let mut world = TestWorld::new(ARENA, (8, 3));
+
+        let position = TilePosition::new(1, 1);
+        let station = TilePosition::new(2, 1);
+        spawn_character(&mut world.scene, healthy_character(0), position);
+        spawn_job_station(
+            &mut world.scene,
+            JobStationVariant::ENERGY_GENERATOR,
+            station,
+        );
+        let stock_station = |scene: &mut Scene, magma: u8| {
+            scene.run_system(define_system!(
+                |_, jobs: &mut [JobStationStatus], stockpiles: &mut [Stockpile]| {
+                    for (job, stockpile) in jobs.iter_mut().zip(stockpiles) {
+                        job.settings.input_target = 5;
+                        *stockpile =
+                            Stockpile::zeroed().with_resource(ResourceVariant::MAGMA, magma, true);
+                    }
+                }
+            ));
+        };
+
+        let mut brain = Brain::new();
+        brain.job = Occupation::Operator(JobStationVariant::ENERGY_GENERATOR);
+        brain.goal_stack.push(Goal::Work {
+            haul_wait_timeout: None,
+            job: JobStationVariant::ENERGY_GENERATOR,
+        });
+
+        // Enough to work, but below the target
+        stock_station(&mut world.scene, 3);
+        world.think(&mut brain, 0, position, 1);
+        let requested = (world.haul_notifications.iter())
+            .next()
+            .map(|(_, haul)| haul.amount);
+        assert_eq!(requested, Some(2));
+        let (haul_id, _) = world.haul_notifications.iter().next().unwrap();
+        world.haul_notifications.remove(haul_id);
+
+        stock_station(&mut world.scene, 5);
+        world.think(&mut brain, 0, position, 2);
+        assert_eq!(world.haul_notifications.len(), 0);
+    }
+
+    #[test]
+    fn operators_do_not_take_over_claimed_hauls() {
+        static ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        let mut world = TestWorld::new(ARENA, (8, 3));
+
+        let position = TilePosition::new(1, 1);
+        spawn_character(&mut world.scene, healthy_character(0), position);
+        spawn_job_station(
+            &mut world.scene,
+            JobStationVariant::ENERGY_GENERATOR,
+            TilePosition::new(2, 1),
+        );
+
+        let mut brain = Brain::new();
+        brain.job = Occupation::Operator(JobStationVariant::ENERGY_GENERATOR);
+        brain.wait_ticks = 1;
+        brain.goal_stack.push(Goal::Work {
+            haul_wait_timeout: None,
+            job: JobStationVariant::ENERGY_GENERATOR,
+        });
+        let is_hauling =
+            |brain: &Brain| (brain.goal_stack.iter()).any(|goal| matches!(goal, Goal::Haul { .. }));
+
+        world.think(&mut brain, 0, position, 1);
+        let (haul_id, _) = world.haul_notifications.iter().next().unwrap();
+        // A hauler takes the job before the operator runs out of patience
+        assert!(world.haul_notifications.claim(haul_id));
+        for tick in 2..5 {
+            world.think(&mut brain, 0, position, tick);
+            assert!(!is_hauling(&brain), "the hauler is already on it");
+            assert!(world.haul_notifications.check(haul_id));
+        }
+
+        // The hauler gives up, so the operator eventually does it instead
+        world.haul_notifications.release(haul_id);
+        for tick in 5..7 {
+            world.think(&mut brain, 0, position, tick);
+        }
+        assert!(is_hauling(&brain));
+        assert!(!world.haul_notifications.check(haul_id));
+    }
+
+    #[test]
+    fn only_relaxing_strolls_count_as_relaxing() {
+        static ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        let mut world = TestWorld::new(ARENA, (8, 8));
+        let position = TilePosition::new(3, 3);
+
+        let relax = |world: &mut TestWorld| {
+            let mut brain = Brain::new();
+            brain.ticks_without_goal = brain.wait_ticks;
+            let mut current_position = position;
+            for tick in 1..10 {
+                world.think(&mut brain, 0, current_position, tick);
+                if brain.has_relaxed {
+                    break;
+                }
+                // Teleport to the end of the path to skip the walking
+                if let Some(Goal::FollowPath { from, path }) = brain.goal_stack.last() {
+                    current_position = path.into_iter().fold(*from, |pos, step| pos + step);
+                }
+            }
+            brain.has_relaxed
+        };
+
+        // Walled in, so relaxing happens in place
+        spawn_character(&mut world.scene, healthy_character(0), position);
+        for dir in Direction::ALL {
+            world.walls.set(position + dir, true);
+        }
+        assert!(!relax(&mut world));
+
+        // Free to walk around
+        world.scene = test_scene(ARENA);
+        spawn_character(&mut world.scene, healthy_character(0), position);
+        world.walls.clear();
+        assert!(relax(&mut world));
+    }
+
+    #[test]
+    fn demoralized_characters_relax_until_morale_recovers() {
+        static ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        let mut world = TestWorld::new(ARENA, (8, 3));
+
+        let position = TilePosition::new(1, 1);
+        spawn_character(&mut world.scene, healthy_character(0), position);
+
+        let mut brain = Brain::new();
+        brain.job = Occupation::Operator(JobStationVariant::ENERGY_GENERATOR);
+        let mut wants_to_work_at = |morale: u8, tick| {
+            world
+                .scene
+                .run_system(define_system!(|_, characters: &mut [CharacterStatus]| {
+                    for character in characters {
+                        character.morale = morale;
+                    }
+                }));
+            brain.goal_stack.clear();
+            world.think(&mut brain, 0, position, tick);
+            !brain.recovering_morale
+                && !matches!(brain.goal_stack.first(), Some(Goal::Relax { .. }))
+        };
+
+        let between_thresholds = CharacterStatus::LOW_MORALE_THRESHOLD + 1;
+        assert!(between_thresholds <= CharacterStatus::RECOVERY_MORALE_THRESHOLD);
+        assert!(wants_to_work_at(between_thresholds, 1));
+        assert!(!wants_to_work_at(CharacterStatus::LOW_MORALE_THRESHOLD, 2));
+        assert!(!wants_to_work_at(between_thresholds, 3));
+        assert!(!wants_to_work_at(
+            CharacterStatus::RECOVERY_MORALE_THRESHOLD,
+            4
+        ));
+        assert!(wants_to_work_at(
+            CharacterStatus::RECOVERY_MORALE_THRESHOLD + 1,
+            5
+        ));
+    }
+
+    #[test]
+    fn cautious_characters_refill_oxygen_sooner() {
+        static ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        let mut world = TestWorld::new(ARENA, (8, 3));
+        let position = TilePosition::new(1, 1);
+
+        let mut goes_to_refill = |low_oxygen_threshold: u8, tick| {
+            world.scene = test_scene(ARENA);
+            let mut status = healthy_character(0);
+            status.oxygen = CharacterStatus::LOW_OXYGEN_THRESHOLD + 2;
+            status.low_oxygen_threshold = low_oxygen_threshold;
+            spawn_character(&mut world.scene, status, position);
+            spawn_resource(
+                &mut world.scene,
+                ResourceVariant::OXYGEN,
+                5,
+                TilePosition::new(6, 1),
+            );
+
+            let mut brain = Brain::new();
+            world.think(&mut brain, 0, position, tick);
+            matches!(brain.goal_stack.first(), Some(Goal::RefillOxygen))
+        };
+
+        assert!(!goes_to_refill(CharacterStatus::LOW_OXYGEN_THRESHOLD, 1));
+        assert!(goes_to_refill(CharacterStatus::LOW_OXYGEN_THRESHOLD + 3, 2));
+    }
+
+    #[test]
+    fn accepting_a_haul_is_recorded() {
+        static ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        let mut world = TestWorld::new(ARENA, (8, 3));
+
+        let position = TilePosition::new(1, 1);
+        spawn_character(&mut world.scene, healthy_character(0), position);
+        spawn_resource(
+            &mut world.scene,
+            ResourceVariant::MAGMA,
+            1,
+            TilePosition::new(4, 1),
+        );
+        let notified = world.haul_notifications.notify(HaulDescription {
+            resource: ResourceVariant::MAGMA,
+            amount: 1,
+            destination: (JobStationVariant::ENERGY_GENERATOR, TilePosition::new(6, 1)),
+        });
+        assert!(notified.is_ok());
+
+        let mut brain = Brain::new();
+        brain.job = Occupation::Hauler;
+        world.think(&mut brain, 0, position, 1);
+
+        let events = brain.recent_events();
+        assert!(events.contains(&BrainEvent::HaulAccepted {
+            resource: ResourceVariant::MAGMA,
+            amount: 1,
+            destination: JobStationVariant::ENERGY_GENERATOR,
+        }));
+        assert!(events.contains(&BrainEvent::GoalPushed(GoalKind::Haul)));
+    }
+
+    #[test]
+    fn hauls_are_split_by_the_max_haul_amount() {
+        static ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        let mut world = TestWorld::new(ARENA, (8, 3));
+
+        let position = TilePosition::new(1, 1);
+        spawn_character(&mut world.scene, healthy_character(0), position);
+        spawn_resource(
+            &mut world.scene,
+            ResourceVariant::MAGMA,
+            9,
+            TilePosition::new(4, 1),
+        );
+        let notified = world.haul_notifications.notify(HaulDescription {
+            resource: ResourceVariant::MAGMA,
+            amount: 9,
+            destination: (JobStationVariant::ENERGY_GENERATOR, TilePosition::new(6, 1)),
+        });
+        assert!(notified.is_ok());
+
+        let mut brain = Brain::new();
+        brain.job = Occupation::Hauler;
+        brain.set_max_haul_amount(0);
+        assert_eq!(brain.max_haul_amount, 1, "hauls carry at least one");
+        brain.set_max_haul_amount(u8::MAX);
+        assert_eq!(brain.max_haul_amount, MAX_HAUL_AMOUNT);
+        brain.set_max_haul_amount(3);
+        world.think(&mut brain, 0, position, 1);
+
+        assert!(brain.recent_events().contains(&BrainEvent::HaulAccepted {
+            resource: ResourceVariant::MAGMA,
+            amount: 3,
+            destination: JobStationVariant::ENERGY_GENERATOR,
+        }));
+        let (_, rest) = world.haul_notifications.iter().next().unwrap();
+        assert_eq!(rest.amount, 6, "the rest should be left for others");
+    }
+
+    #[test]
+    fn haulers_share_the_resource_lookups() {
+        static ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        let mut world = TestWorld::new(ARENA, (8, 3));
+
+        let destination = (JobStationVariant::ENERGY_GENERATOR, TilePosition::new(7, 1));
+        spawn_job_station(&mut world.scene, destination.0, destination.1);
+        for y in [1, 2] {
+            spawn_resource(
+                &mut world.scene,
+                ResourceVariant::MAGMA,
+                2,
+                TilePosition::new(4, y),
+            );
+        }
+        for brain_index in 0..2 {
+            spawn_character(
+                &mut world.scene,
+                healthy_character(brain_index),
+                TilePosition::new(0, brain_index as i16),
+            );
+            let notified = world.haul_notifications.notify(HaulDescription {
+                resource: ResourceVariant::MAGMA,
+                amount: 1,
+                destination,
+            });
+            assert!(notified.is_ok());
+        }
+
+        for brain_index in 0..2 {
+            let mut brain = Brain::new();
+            brain.job = Occupation::Hauler;
+            let position = TilePosition::new(0, brain_index as i16);
+            world.think(&mut brain, brain_index, position, 1);
+            assert!(matches!(
+                brain.goal_stack.last(),
+                Some(Goal::FollowPath { .. })
+            ));
+        }
+        assert_eq!(
+            world.resource_index.scans(),
+            1,
+            "both haulers should've used the same magma lookup",
+        );
+    }
+
+    #[test]
+    fn haulers_split_up_equidistant_hauls() {
+        static ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        let mut world = TestWorld::new(ARENA, (7, 5));
+
+        // Both haulers are three steps away from both job stations
+        let hauler_position = TilePosition::new(3, 2);
+        let destinations = [0, 6].map(|x| {
+            let destination = (JobStationVariant::ENERGY_GENERATOR, TilePosition::new(x, 2));
+            spawn_job_station(&mut world.scene, destination.0, destination.1);
+            spawn_resource(
+                &mut world.scene,
+                ResourceVariant::MAGMA,
+                1,
+                TilePosition::new(x, 0),
+            );
+            destination
+        });
+        let notify_both = |haul_notifications: &mut NotificationSet<HaulDescription>| {
+            destinations.map(|destination| {
+                let notified = haul_notifications.notify(HaulDescription {
+                    resource: ResourceVariant::MAGMA,
+                    amount: 1,
+                    destination,
+                });
+                notified.unwrap()
+            })
+        };
+
+        // The first hauler, and another one the tie-breaker orders the jobs
+        // the other way around for
+        let ids = notify_both(&mut world.haul_notifications);
+        let prefers_first = |brain_index| {
+            haul_tie_breaker(brain_index, ids[0]) < haul_tie_breaker(brain_index, ids[1])
+        };
+        let other_hauler = (1..u8::MAX).find(|&i| prefers_first(i) != prefers_first(0));
+        let haulers = [
+            0,
+            other_hauler.expect("the tie-breaker should depend on the hauler"),
+        ];
+
+        // Each hauler looks at its own copy of the same jobs, so that the
+        // claims can't tell the jobs apart, only the tie-breaker can
+        let mut picked = ArrayVec::<TilePosition, 2>::new();
+        for brain_index in haulers {
+            spawn_character(
+                &mut world.scene,
+                healthy_character(brain_index),
+                hauler_position,
+            );
+            world.haul_notifications = NotificationSet::new(ARENA, 4).unwrap();
+            notify_both(&mut world.haul_notifications);
+
+            let mut brain = Brain::new();
+            brain.job = Occupation::Hauler;
+            world.think(&mut brain, brain_index, hauler_position, 1);
+            let haul = brain.goal_stack.iter().find_map(|goal| match goal {
+                Goal::Haul { description } => Some(description.destination.1),
+                _ => None,
+            });
+            picked.push(haul.expect("every hauler should find a haul"));
+
+            let preferred = if prefers_first(brain_index) { 0 } else { 1 };
+            assert_eq!(picked.last(), Some(&destinations[preferred].1));
+        }
+        assert_ne!(picked[0], picked[1], "both haulers went for the same haul");
+    }
+
+    #[test]
+    fn higher_priority_hauls_are_taken_first() {
+        static ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        let mut world = TestWorld::new(ARENA, (7, 5));
+
+        let near = TilePosition::new(0, 2);
+        let far = TilePosition::new(6, 2);
+        for pos in [near, far] {
+            let destination = (JobStationVariant::ENERGY_GENERATOR, pos);
+            spawn_job_station(&mut world.scene, destination.0, destination.1);
+            spawn_resource(
+                &mut world.scene,
+                ResourceVariant::MAGMA,
+                1,
+                TilePosition::new(pos.x, 0),
+            );
+            let notified = world.haul_notifications.notify(HaulDescription {
+                resource: ResourceVariant::MAGMA,
+                amount: 1,
+                destination,
+            });
+            assert!(notified.is_ok());
+        }
+        world.scene.run_system(define_system!(
+            |_, job_stations: &mut [JobStationStatus], positions: &[TilePosition]| {
+                for (job_station, pos) in job_stations.iter_mut().zip(positions) {
+                    if *pos == far {
+                        job_station.settings.haul_priority = 2;
+                    }
+                }
+            }
+        ));
+        let hauler = TilePosition::new(1, 2);
+        spawn_character(&mut world.scene, healthy_character(0), hauler);
+
+        let mut brain = Brain::new();
+        brain.job = Occupation::Hauler;
+        world.think(&mut brain, 0, hauler, 1);
+        let haul = brain.goal_stack.iter().find_map(|goal| match goal {
+            Goal::Haul { description } => Some(description.destination.1),
+            _ => None,
+        });
+        assert_eq!(haul, Some(far));
+    }
+
+    #[test]
+    fn second_hauler_skips_a_claimed_haul() {
+        static ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        let mut world = TestWorld::new(ARENA, (7, 5));
+
+        // More than one hauler can carry, so the rest of it stays up for grabs
+        let destination = (JobStationVariant::ENERGY_GENERATOR, TilePosition::new(6, 2));
+        spawn_job_station(&mut world.scene, destination.0, destination.1);
+        spawn_resource(
+            &mut world.scene,
+            ResourceVariant::MAGMA,
+            4,
+            TilePosition::new(0, 2),
+        );
+        let notified = world.haul_notifications.notify(HaulDescription {
+            resource: ResourceVariant::MAGMA,
+            amount: 4,
+            destination,
+        });
+        assert!(notified.is_ok());
+
+        let haulers = [TilePosition::new(3, 1), TilePosition::new(3, 3)];
+        let mut brains = [Brain::new(), Brain::new()];
+        for (brain_index, (position, brain)) in haulers.iter().zip(&mut brains).enumerate() {
+            spawn_character(
+                &mut world.scene,
+                healthy_character(brain_index as u8),
+                *position,
+            );
+            brain.job = Occupation::Hauler;
+        }
+        let mut update = |brain_index: usize, brain: &mut Brain| {
+            world.think(brain, brain_index as u8, haulers[brain_index], 1);
+            let hauling = (brain.goal_stack.iter()).any(|goal| matches!(goal, Goal::Haul { .. }));
+            (hauling, world.haul_notifications.len())
+        };
+
+        let [first, second] = &mut brains;
+        assert_eq!(update(0, first), (true, 1));
+        assert!(
+            !update(1, second).0,
+            "the haul is already claimed by the first hauler",
+        );
+
+        // Once the first hauler gives up, the claim is released
+        first.goal_stack.clear();
+        first.job = Occupation::Idle;
+        assert!(!update(0, first).0);
+        assert!(update(1, second).0);
+    }
+
+    #[test]
+    fn haulers_claim_one_haul_at_a_time() {
+        static ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        let mut world = TestWorld::new(ARENA, (7, 5));
+
+        // Both jobs are bigger than one trip, so taking one only claims it
+        let haul_ids = [0, 6].map(|x| {
+            let destination = (JobStationVariant::ENERGY_GENERATOR, TilePosition::new(x, 2));
+            spawn_job_station(&mut world.scene, destination.0, destination.1);
+            spawn_resource(
+                &mut world.scene,
+                ResourceVariant::MAGMA,
+                4,
+                TilePosition::new(x, 0),
+            );
+            let notified = world.haul_notifications.notify(HaulDescription {
+                resource: ResourceVariant::MAGMA,
+                amount: 4,
+                destination,
+            });
+            notified.unwrap()
+        });
+        let position = TilePosition::new(3, 2);
+        spawn_character(&mut world.scene, healthy_character(0), position);
+        let claims = |haul_notifications: &NotificationSet<HaulDescription>| {
+            haul_ids.map(|id| haul_notifications.claims(id))
+        };
+
+        let mut brain = Brain::new();
+        brain.job = Occupation::Hauler;
+        world.think(&mut brain, 0, position, 1);
+        let hauls = brain.goal_stack.iter();
+        let hauls = hauls.filter(|goal| matches!(goal, Goal::Haul { .. }));
+        assert_eq!(hauls.count(), 1);
+        assert_eq!(claims(&world.haul_notifications).iter().sum::<u8>(), 1);
+
+        // Giving up leaves nothing claimed behind
+        brain.goal_stack.clear();
+        brain.job = Occupation::Idle;
+        world.think(&mut brain, 0, position, 1);
+        assert_eq!(claims(&world.haul_notifications), [0, 0]);
+    }
+
+    #[test]
+    fn full_characters_do_not_use_up_oxygen() {
+        static ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        let mut world = TestWorld::new(ARENA, (8, 3));
+
+        let position = TilePosition::new(1, 1);
+        spawn_character(&mut world.scene, healthy_character(0), position);
+        spawn_resource(
+            &mut world.scene,
+            ResourceVariant::OXYGEN,
+            3,
+            TilePosition::new(2, 1),
+        );
+
+        let mut brain = Brain::new();
+        brain.goal_stack.push(Goal::RefillOxygen);
+        world.think(&mut brain, 0, position, 1);
+        assert!(brain.goal_stack.is_empty());
+
+        let mut oxygen_left = 0;
+        world
+            .scene
+            .run_system(define_system!(|_, resources: &[Stockpile]| {
+                for stockpile in resources {
+                    oxygen_left += stockpile
+                        .get_resources(ResourceVariant::OXYGEN)
+                        .unwrap_or(0);
+                }
+            }));
+        assert_eq!(oxygen_left, 3);
+    }
+
+    #[test]
+    fn hauling_picks_up_exactly_what_is_needed() {
+        static ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        let mut world = TestWorld::new(ARENA, (8, 3));
+
+        let position = TilePosition::new(1, 1);
+        spawn_character(&mut world.scene, healthy_character(0), position);
+        spawn_resource(
+            &mut world.scene,
+            ResourceVariant::MAGMA,
+            5,
+            TilePosition::new(2, 1),
+        );
+        spawn_job_station(
+            &mut world.scene,
+            JobStationVariant::ENERGY_GENERATOR,
+            TilePosition::new(6, 1),
+        );
+
+        let mut brain = Brain::new();
+        brain.goal_stack.push(Goal::Haul {
+            description: HaulDescription {
+                resource: ResourceVariant::MAGMA,
+                amount: 2,
+                destination: (JobStationVariant::ENERGY_GENERATOR, TilePosition::new(6, 1)),
+            },
+        });
+        world.think(&mut brain, 0, position, 1);
+
+        let mut held = 0;
+        world.scene.run_system(define_system!(
+            |_, characters: &[CharacterStatus], stockpiles: &[Stockpile]| {
+                for (_, stockpile) in characters.iter().zip(stockpiles) {
+                    held += stockpile.get_resources(ResourceVariant::MAGMA).unwrap_or(0);
+                }
+            }
+        ));
+        let mut piles = ArrayVec::<u8, 4>::new();
+        world.scene.run_system(define_system!(
+            |_, _tags: &[StockpileReliantTag], stockpiles: &[Stockpile]| {
+                for stockpile in stockpiles {
+                    piles.push(stockpile.get_resources(ResourceVariant::MAGMA).unwrap_or(0));
+                }
+            }
+        ));
+        assert_eq!(held, 2);
+        assert_eq!(&piles[..], &[3]);
+    }
+
+    #[test]
+    fn hauls_are_carried_around_gas_pockets() {
+        static ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        let mut world = TestWorld::new(ARENA, (8, 3));
+        let gas_pocket = TilePosition::new(4, 1);
+        let mut tile_costs: Grid<u8> = Grid::new_zeroed(ARENA, (8, 3)).unwrap();
+        tile_costs[gas_pocket] = GAS_POCKET_PATH_COST;
+        world.tile_costs = Some(tile_costs);
+
+        let position = TilePosition::new(1, 1);
+        spawn_character(&mut world.scene, healthy_character(0), position);
+        spawn_resource(
+            &mut world.scene,
+            ResourceVariant::MAGMA,
+            2,
+            TilePosition::new(2, 1),
+        );
+        spawn_job_station(
+            &mut world.scene,
+            JobStationVariant::ENERGY_GENERATOR,
+            TilePosition::new(6, 1),
+        );
+
+        let mut brain = Brain::new();
+        brain.goal_stack.push(Goal::Haul {
+            description: HaulDescription {
+                resource: ResourceVariant::MAGMA,
+                amount: 2,
+                destination: (JobStationVariant::ENERGY_GENERATOR, TilePosition::new(6, 1)),
+            },
+        });
+        world.think(&mut brain, 0, position, 1);
+
+        // Picked up everything, and is now bringing it over
+        let Some((from, path)) = brain.active_path() else {
+            panic!("should be on the way to the destination");
+        };
+        assert!(path.positions_from(*from).all(|pos| pos != gas_pocket));
+        assert_eq!(
+            path.positions_from(*from).last(),
+            Some(TilePosition::new(6, 1))
+        );
+        assert_eq!(path.len(), 7, "should have detoured by one row");
+    }
+
+    #[test]
+    fn abandoned_hauls_drop_what_was_carried() {
+        static ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        let mut world = TestWorld::new(ARENA, (8, 3));
+
+        // The destination is on the other side of a wall
+        for y in 0..3 {
+            world.walls.set(TilePosition::new(4, y), true);
+        }
+
+        let position = TilePosition::new(1, 1);
+        let spawned = world.scene.spawn(Character {
+            status: healthy_character(0),
+            position,
+            held: Stockpile::zeroed().with_resource(ResourceVariant::MAGMA, 2, true),
+            collider: Collider::NOT_WALKABLE,
+        });
+        assert!(spawned.is_ok());
+        spawn_job_station(
+            &mut world.scene,
+            JobStationVariant::ENERGY_GENERATOR,
+            TilePosition::new(6, 1),
+        );
+
+        let mut brain = Brain::new();
+        brain.goal_stack.push(Goal::Haul {
+            description: HaulDescription {
+                resource: ResourceVariant::MAGMA,
+                amount: 2,
+                destination: (JobStationVariant::ENERGY_GENERATOR, TilePosition::new(6, 1)),
+            },
+        });
+        world.think(&mut brain, 0, position, 1);
+        assert!(brain.goal_stack.is_empty());
+
+        let mut held = Stockpile::zeroed();
+        world.scene.run_system(define_system!(
+            |_, _characters: &[CharacterStatus], stockpiles: &[Stockpile]| {
+                held = stockpiles[0];
+            }
+        ));
+        let mut dropped = ArrayVec::<Stockpile, 4>::new();
+        world.scene.run_system(define_system!(
+            |_, _tags: &[StockpileReliantTag], stockpiles: &[Stockpile]| {
+                dropped.extend(stockpiles.iter().copied());
+            }
+        ));
+        assert!(held.is_empty());
+        assert_eq!(dropped.len(), 1);
+        assert!(dropped[0].has_non_reserved_resources(ResourceVariant::MAGMA));
+        assert_eq!(dropped[0].get_resources(ResourceVariant::MAGMA), Some(2));
+    }
+
+    #[test]
+    fn hauls_to_removed_destinations_are_released() {
+        static ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        let mut world = TestWorld::new(ARENA, (8, 3));
+
+        let position = TilePosition::new(1, 1);
+        let spawned = world.scene.spawn(Character {
+            status: healthy_character(0),
+            position,
+            held: Stockpile::zeroed().with_resource(ResourceVariant::MAGMA, 2, true),
+            collider: Collider::NOT_WALKABLE,
+        });
+        assert!(spawned.is_ok());
+        spawn_job_station(
+            &mut world.scene,
+            JobStationVariant::ENERGY_GENERATOR,
+            TilePosition::new(6, 1),
+        );
+
+        let mut brain = Brain::new();
+        brain.goal_stack.push(Goal::Haul {
+            description: HaulDescription {
+                resource: ResourceVariant::MAGMA,
+                amount: 2,
+                destination: (JobStationVariant::ENERGY_GENERATOR, TilePosition::new(6, 1)),
+            },
+        });
+        world.think(&mut brain, 0, position, 1);
+        assert!(matches!(
+            brain.goal_stack.last(),
+            Some(Goal::FollowPath { .. })
+        ));
+
+        // The destination is removed while the hauler is on the way
+        let mut job_stations = FixedVec::<GameObjectHandle>::new(ARENA, 4).unwrap();
+        world.scene.run_system(define_system!(
+            |handles, _job_stations: &[JobStationStatus]| {
+                for handle in handles {
+                    assert!(job_stations.push(handle).is_ok());
+                }
+            }
+        ));
+        assert!(world.scene.delete(&mut job_stations).is_ok());
+        world.think(&mut brain, 0, position + Direction::Right, 2);
+        assert!(brain.goal_stack.is_empty());
+
+        let mut dropped = ArrayVec::<Stockpile, 4>::new();
+        world.scene.run_system(define_system!(
+            |_, _tags: &[StockpileReliantTag], stockpiles: &[Stockpile]| {
+                dropped.extend(stockpiles.iter().copied());
+            }
+        ));
+        assert_eq!(dropped.len(), 1);
+        assert!(dropped[0].has_non_reserved_resources(ResourceVariant::MAGMA));
+        assert_eq!(dropped[0].get_resources(ResourceVariant::MAGMA), Some(2));
+    }
+
+    #[test]
+    fn hauls_to_disabled_destinations_are_released() {
+        static ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        let mut world = TestWorld::new(ARENA, (8, 3));
+
+        let position = TilePosition::new(1, 1);
+        let spawned = world.scene.spawn(Character {
+            status: healthy_character(0),
+            position,
+            held: Stockpile::zeroed().with_resource(ResourceVariant::MAGMA, 2, true),
+            collider: Collider::NOT_WALKABLE,
+        });
+        assert!(spawned.is_ok());
+        spawn_job_station(
+            &mut world.scene,
+            JobStationVariant::ENERGY_GENERATOR,
+            TilePosition::new(6, 1),
+        );
+
+        let mut brain = Brain::new();
+        brain.goal_stack.push(Goal::Haul {
+            description: HaulDescription {
+                resource: ResourceVariant::MAGMA,
+                amount: 2,
+                destination: (JobStationVariant::ENERGY_GENERATOR, TilePosition::new(6, 1)),
+            },
+        });
+        let is_hauling =
+            |brain: &Brain| (brain.goal_stack.iter()).any(|goal| matches!(goal, Goal::Haul { .. }));
+        world.think(&mut brain, 0, position, 1);
+        assert!(is_hauling(&brain), "an enabled station should get its haul");
+
+        // The destination is disabled while the hauler is on the way
+        world
+            .scene
+            .run_system(define_system!(|_, jobs: &mut [JobStationStatus]| {
+                for job in jobs {
+                    job.settings.set_enabled(false);
+                }
+            }));
+        world.think(&mut brain, 0, position, 2);
+        assert!(!is_hauling(&brain), "a disabled station shouldn't");
+    }
+
+    #[test]
+    fn leveling_up_from_hauling_carries_more_per_haul() {
+        static ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        let mut world = TestWorld::new(ARENA, (8, 3));
+
+        // Right next to the destination, with just enough to level up
+        let station_pos = TilePosition::new(6, 1);
+        world.walls.set(station_pos, true);
+        let position = TilePosition::new(5, 1);
+        let mut status = healthy_character(0);
+        status.experience =
+            CharacterStatus::EXPERIENCE_PER_LEVEL - 2 * CharacterStatus::HAUL_EXPERIENCE;
+        let spawned = world.scene.spawn(Character {
+            status,
+            position,
+            held: Stockpile::zeroed().with_resource(ResourceVariant::MAGMA, 2, true),
+            collider: Collider::NOT_WALKABLE,
+        });
+        assert!(spawned.is_ok());
+        spawn_job_station(
+            &mut world.scene,
+            JobStationVariant::ENERGY_GENERATOR,
+            station_pos,
+        );
+
+        let mut brain = Brain::new();
+        let max_haul_amount = brain.max_haul_amount;
+        brain.goal_stack.push(Goal::Haul {
+            description: HaulDescription {
+                resource: ResourceVariant::MAGMA,
+                amount: 2,
+                destination: (JobStationVariant::ENERGY_GENERATOR, station_pos),
+            },
+        });
+        world.think(&mut brain, 0, position, 1);
+
+        assert!(
+            !(brain.goal_stack.iter()).any(|goal| matches!(goal, Goal::Haul { .. })),
+            "the haul should've been delivered",
+        );
+        let mut level = None;
+        world
+            .scene
+            .run_system(define_system!(|_, characters: &[CharacterStatus]| {
+                level = characters.first().map(|character| character.level);
+            }));
+        assert_eq!(level, Some(1));
+        assert_eq!(brain.max_haul_amount, max_haul_amount + 1);
+    }
+
+    #[test]
+    fn hungry_characters_go_eat() {
+        static ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        let mut world = TestWorld::new(ARENA, (8, 3));
+
+        let mut status = healthy_character(0);
+        status.food = CharacterStatus::LOW_FOOD_THRESHOLD;
+        spawn_character(&mut world.scene, status, TilePosition::new(1, 1));
+        spawn_resource(
+            &mut world.scene,
+            ResourceVariant::FOOD,
+            5,
+            TilePosition::new(6, 1),
+        );
+
+        let mut brain = Brain::new();
+        brain.job = Occupation::Operator(JobStationVariant::ENERGY_GENERATOR);
+        world.think(&mut brain, 0, TilePosition::new(1, 1), 1);
+        assert!(
+            matches!(brain.goal_stack.get(1), Some(Goal::Eat)),
+            "eating should come before work",
+        );
+        let Some(Goal::FollowPath { from, path }) = brain.goal_stack.pop() else {
+            panic!("should be walking to the food");
+        };
+        let position = path.into_iter().fold(from, |pos, step| pos + step);
+
+        world.think(&mut brain, 0, position, 2);
+        assert!(!(brain.goal_stack.iter()).any(|goal| matches!(goal, Goal::Eat)));
+
+        let mut food = 0;
+        world
+            .scene
+            .run_system(define_system!(|_, characters: &[CharacterStatus]| {
+                for character in characters {
+                    food = character.food;
+                }
+            }));
+        assert_eq!(
+            food,
+            CharacterStatus::LOW_FOOD_THRESHOLD + CharacterStatus::FOOD_PER_RATION,
+        );
+        let mut food_left = None;
+        world.scene.run_system(define_system!(
+            |_, _tags: &[StockpileReliantTag], stockpiles: &[Stockpile]| {
+                for stockpile in stockpiles {
+                    food_left = stockpile.get_resources(ResourceVariant::FOOD);
+                }
+            }
+        ));
+        assert_eq!(food_left, Some(4));
+    }
+
+    #[test]
+    fn active_path_shrinks_as_the_character_walks() {
+        static ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        let mut world = TestWorld::new(ARENA, (8, 3));
+
+        let from = TilePosition::new(1, 1);
+        let mut path = Path::default();
+        for _ in 0..3 {
+            path.add_step(Direction::Right);
+        }
+        let mut brain = Brain::new();
+        brain.goal_stack.push(Goal::FollowPath { from, path });
+        let (active_from, active_path) = brain.active_path().unwrap();
+        assert_eq!(*active_from, from);
+        assert_eq!(active_path.len(), 3);
+
+        // One step along the path
+        let walked_to = TilePosition::new(2, 1);
+        spawn_character(&mut world.scene, healthy_character(0), walked_to);
+        world.think(&mut brain, 0, walked_to, 1);
+        let (active_from, active_path) = brain.active_path().unwrap();
+        assert_eq!(*active_from, walked_to);
+        assert!(
+            (active_path.positions_from(*active_from))
+                .eq([TilePosition::new(3, 1), TilePosition::new(4, 1)])
+        );
+    }
+
+    #[test]
+    fn halted_characters_stand_still() {
+        static ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        let mut world = TestWorld::new(ARENA, (8, 3));
+
+        for (brain_index, y) in [(0, 1), (1, 2)] {
+            let mut status = healthy_character(brain_index);
+            status.food = CharacterStatus::LOW_FOOD_THRESHOLD;
+            spawn_character(&mut world.scene, status, TilePosition::new(1, y));
+        }
+        spawn_resource(
+            &mut world.scene,
+            ResourceVariant::FOOD,
+            5,
+            TilePosition::new(6, 1),
+        );
+
+        let mut halted = Brain::new();
+        halted.halted = true;
+        let mut working = Brain::new();
+        world.think(&mut halted, 0, TilePosition::new(1, 1), 1);
+        world.think(&mut working, 1, TilePosition::new(1, 2), 1);
+        assert!(halted.goal_stack.is_empty());
+        assert_eq!(halted.next_move_direction(), None);
+        assert!(working.next_move_direction().is_some());
+
+        // Halting a character on the move drops what it was doing
+        working.halted = true;
+        world.think(&mut working, 1, TilePosition::new(1, 2), 2);
+        assert!(working.goal_stack.is_empty());
+        assert_eq!(working.next_move_direction(), None);
+    }
+
+    #[test]
+    fn relaxing_strolls_count_toward_sleeping() {
+        static ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        let mut world = TestWorld::new(ARENA, (12, 12));
+
+        let mut status = healthy_character(0);
+        status.morale = CharacterStatus::MAX_MORALE - 5;
+        let mut current_position = TilePosition::new(6, 6);
+        spawn_character(&mut world.scene, status, current_position);
+
+        let mut brain = Brain::new();
+        brain.wait_ticks = 5;
+        brain.sleep_spot = Some(TilePosition::new(1, 1));
+
+        let mut strolls = 0;
+        let mut fell_asleep_on = None;
+        for tick in 1..200 {
+            world.think(&mut brain, 0, current_position, tick);
+            if matches!(brain.goal_stack.first(), Some(Goal::Sleep { .. })) {
+                fell_asleep_on = Some(tick);
+                break;
+            }
+            // Teleport to the end of the path to skip the walking
+            if let Some(Goal::FollowPath { from, path }) = brain.goal_stack.last() {
+                current_position = path.into_iter().fold(*from, |pos, step| pos + step);
+                strolls += 1;
+            }
+        }
+        assert!(strolls > 0, "should've strolled around before sleeping");
+        assert!(
+            fell_asleep_on.is_some(),
+            "strolling shouldn't keep the character from ever sleeping",
+        );
+    }
+
+    #[test]
+    fn long_idle_characters_sleep_until_rested() {
+        static ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        let mut world = TestWorld::new(ARENA, (8, 3));
+
+        let mut status = healthy_character(0);
+        status.morale = CharacterStatus::MAX_MORALE - 5;
+        spawn_character(&mut world.scene, status, TilePosition::new(1, 1));
+
+        let bed = TilePosition::new(6, 1);
+        let mut brain = Brain::new();
+        brain.job = Occupation::Hauler;
+        brain.sleep_spot = Some(bed);
+        brain.ticks_without_goal = brain.wait_ticks * SLEEP_AFTER_WAITS;
+
+        let mut current_position = TilePosition::new(1, 1);
+        let mut tick = 1;
+        while !brain.has_slept && tick < 10 {
+            world.think(&mut brain, 0, current_position, tick);
+            assert!(matches!(brain.goal_stack.first(), Some(Goal::Sleep { .. })));
+            // Teleport to the end of the path to skip the walking
+            if let Some(Goal::FollowPath { from, path }) = brain.goal_stack.last() {
+                current_position = path.into_iter().fold(*from, |pos, step| pos + step);
+            }
+            tick += 1;
+        }
+        assert!(brain.has_slept, "should've gotten to bed and slept");
+        assert_eq!(current_position, bed);
+
+        // Haul jobs don't wake sleeping haulers up
+        spawn_job_station(
+            &mut world.scene,
+            JobStationVariant::ENERGY_GENERATOR,
+            TilePosition::new(3, 2),
+        );
+        spawn_resource(
+            &mut world.scene,
+            ResourceVariant::MAGMA,
+            3,
+            TilePosition::new(3, 0),
+        );
+        let notified = world.haul_notifications.notify(HaulDescription {
+            resource: ResourceVariant::MAGMA,
+            amount: 1,
+            destination: (JobStationVariant::ENERGY_GENERATOR, TilePosition::new(3, 2)),
+        });
+        assert!(notified.is_ok());
+        world.think(&mut brain, 0, current_position, tick);
+        assert!(matches!(brain.goal_stack.last(), Some(Goal::Sleep { .. })));
+        assert_eq!(world.haul_notifications.len(), 1);
+
+        // Fully rested, so the sleep is over
+        world
+            .scene
+            .run_system(define_system!(|_, characters: &mut [CharacterStatus]| {
+                for character in characters {
+                    character.morale = CharacterStatus::MAX_MORALE;
+                }
+            }));
+        world.think(&mut brain, 0, current_position, tick + 1);
+        assert!(
+            !(brain.goal_stack.iter()).any(|goal| matches!(goal, Goal::Sleep { .. })),
+            "should've woken up",
+        );
+        assert_eq!(
+            brain.recent_events().last(),
+            Some(&BrainEvent::GoalFinished(GoalKind::Sleep)),
+        );
+    }
+
+    #[test]
+    fn thought_bubble_shows_the_current_goal() {
+        let mut brain = Brain::new();
+        assert_eq!(brain.thought_sprite(Personality::zeroed()), None);
+
+        brain.goal_stack.push(Goal::Work {
+            haul_wait_timeout: None,
+            job: JobStationVariant::ENERGY_GENERATOR,
+        });
+        brain.goal_stack.push(Goal::FollowPath {
+            from: TilePosition::new(0, 0),
+            path: Path::default(),
+        });
+        assert_eq!(
+            brain.thought_sprite(Personality::zeroed()),
+            Some(Sprite::GoalWork),
+            "paths should show the goal they lead to",
+        );
+
+        brain.goal_stack.push(Goal::RefillOxygen);
+        assert_eq!(
+            brain.thought_sprite(Personality::zeroed()),
+            Some(Sprite::GoalOxygen)
+        );
+
+        brain.goal_stack.clear();
+        brain.goal_stack.push(Goal::Relax {
+            relax_start_tick: 0,
+            walk_aabb: (TilePosition::new(0, 0), TilePosition::new(1, 1)),
+            relax_spot: None,
+        });
+        assert_eq!(
+            brain.thought_sprite(Personality::KAOMOJI),
+            Some(Sprite::GoalRelaxAlt)
+        );
+    }
+
+    #[test]
+    fn removing_brains_keeps_the_other_characters_brains() {
+        static ARENA: &LinearAllocator = static_allocator!(4 * 1024);
+        let mut brains = Brains::new(ARENA, 3).unwrap();
+        let mut characters = [CharacterStatus::zeroed(); 3];
+        for (i, character) in characters.iter_mut().enumerate() {
+            let id = brains.add(Brain::new()).unwrap();
+            brains[id].wait_ticks = i as u64;
+            character.brain_index = id.index;
+            character.brain_generation = id.generation;
+        }
+
+        let fired = characters[1].brain_id();
+        assert!(brains.remove(fired));
+        let hired = brains.add(Brain::new()).unwrap();
+        brains[hired].wait_ticks = 10;
+        assert!(
+            brains.add(Brain::new()).is_none(),
+            "all slots should be in use"
+        );
+
+        assert_ne!(hired, fired);
+        assert!(brains.get(fired).is_none());
+        assert!(!brains.remove(fired));
+        assert_eq!(brains[characters[0].brain_id()].wait_ticks, 0);
+        assert_eq!(brains[characters[2].brain_id()].wait_ticks, 2);
+        assert_eq!(brains[hired].wait_ticks, 10);
+        assert_eq!(brains.ids().count(), 3);
+    }
+}
@@ -1,20 +1,388 @@
 use engine::geom::Rect;
 use glam::Vec2;
+use libm::{cosf, expf, floorf, sinf};
+
+use crate::game_object::TilePosition;
+
+/// How many tiles fit in the view vertically before any zooming.
+pub const DEFAULT_VIEW_HEIGHT: f32 = 16.;
+/// How much one step of zooming scales the view by.
+pub const ZOOM_STEP: f32 = 1.25;
+const MIN_VIEW_HEIGHT: f32 = 6.;
+const MAX_VIEW_HEIGHT: f32 = 48.;
+/// Zooming out stops before tiles get smaller than this many output pixels.
+const MIN_TILE_OUTPUT_SIZE: f32 = 12.;
+/// How quickly the camera catches up when following something. The distance
+/// left shrinks to 1/e of what it was every `1 / FOLLOW_RATE` seconds.
+const FOLLOW_RATE: f32 = 4.;
 
 pub struct Camera {
+    /// Where the camera is looking, not counting any shaking.
     pub position: Vec2,
     pub size: Vec2,
     pub output_size: Vec2,
+    pub shake: CameraShake,
+}
+
+/// A shake of the camera, which only moves the drawn view. It fades out
+/// linearly over its duration.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CameraShake {
+    /// How far the view is offset at most, in world units.
+    intensity: f32,
+    /// The length of the whole shake in seconds.
+    duration: f32,
+    elapsed: f32,
+    offset: Vec2,
 }
 
 impl Camera {
     pub fn to_output(&self, rect: Rect) -> Rect {
         let scale = self.output_size / self.size;
+        let position = self.position + self.shake.offset;
         Rect {
-            x: (rect.x - self.position.x) * scale.x + self.output_size.x / 2.,
-            y: (rect.y - self.position.y) * scale.y + self.output_size.y / 2.,
+            x: (rect.x - position.x) * scale.x + self.output_size.x / 2.,
+            y: (rect.y - position.y) * scale.y + self.output_size.y / 2.,
             w: rect.w * scale.x,
             h: rect.h * scale.y,
         }
     }
+
+    /// The inverse of [`Camera::to_output`] for points: converts a point on
+    /// the output (e.g. a pointer position) into world coordinates.
+    pub fn to_world(&self, point: Vec2) -> Vec2 {
+        let scale = self.size / self.output_size;
+        (point - self.output_size / 2.) * scale + self.position + self.shake.offset
+    }
+
+    /// Returns the tile under a point on the output, e.g. the tile a pointer
+    /// is hovering over.
+    pub fn to_tile(&self, point: Vec2) -> TilePosition {
+        world_to_tile(self.to_world(point))
+    }
+
+    /// Resizes the view to fit `view_height` tiles vertically, with the width
+    /// following the aspect ratio of [`Camera::output_size`]. The height is
+    /// clamped to the zoom limits first, and the clamped height is returned.
+    /// The view stays centered on [`Camera::position`].
+    pub fn set_view_height(&mut self, view_height: f32) -> f32 {
+        let max_view_height = MAX_VIEW_HEIGHT
+            .min(self.output_size.y / MIN_TILE_OUTPUT_SIZE)
+            .max(MIN_VIEW_HEIGHT);
+        let view_height = view_height.clamp(MIN_VIEW_HEIGHT, max_view_height);
+        let aspect_ratio = self.output_size.x / self.output_size.y;
+        self.size = Vec2::new(aspect_ratio * view_height, view_height);
+        view_height
+    }
+
+    /// Moves the camera so that its view stays within a map of `map_size`,
+    /// which spans from the origin to `map_size`. If the map is smaller than
+    /// the view along an axis, the map is centered along that axis instead.
+    pub fn clamp_to_bounds(&mut self, map_size: Vec2) {
+        let half_size = self.size / 2.;
+        let min = half_size;
+        let max = map_size - half_size;
+        let center = map_size / 2.;
+        self.position = Vec2::new(
+            if min.x <= max.x {
+                self.position.x.clamp(min.x, max.x)
+            } else {
+                center.x
+            },
+            if min.y <= max.y {
+                self.position.y.clamp(min.y, max.y)
+            } else {
+                center.y
+            },
+        );
+    }
+
+    /// Moves the camera toward `target` over `dt_real` seconds, slowing down
+    /// as it gets closer. The easing is the same regardless of the frame rate.
+    pub fn ease_towards(&mut self, target: Vec2, dt_real: f32) {
+        let t = 1. - expf(-FOLLOW_RATE * dt_real);
+        self.position += (target - self.position) * t;
+    }
+
+    /// Starts shaking the view by up to `intensity` world units, fading out
+    /// over `duration` seconds. A weaker shake doesn't cut off a stronger one
+    /// that's still going.
+    pub fn shake(&mut self, intensity: f32, duration: f32) {
+        if intensity >= self.shake.current_intensity() {
+            self.shake = CameraShake {
+                intensity,
+                duration,
+                elapsed: 0.,
+                offset: self.shake.offset,
+            };
+        }
+    }
+
+    /// Advances the shake by `dt_real` seconds of real time, so that the
+    /// shake looks the same regardless of the game speed or pausing.
+    pub fn update_shake(&mut self, dt_real: f32) {
+        let shake = &mut self.shake;
+        shake.elapsed += dt_real;
+        let intensity = shake.current_intensity();
+        if intensity <= 0. {
+            *shake = CameraShake::default();
+            return;
+        }
+        // Sines at unrelated frequencies make for cheap, jittery noise
+        let t = shake.elapsed;
+        let noise = Vec2::new(
+            sinf(t * 71.) * 0.6 + sinf(t * 29.) * 0.4,
+            cosf(t * 83.) * 0.6 + sinf(t * 37.) * 0.4,
+        );
+        shake.offset = noise * intensity;
+    }
+
+    /// Returns true if any part of `rect` (in world coordinates) is in view.
+    /// The view is the unshaken one, widened by how far a shake could move it,
+    /// so that nothing pops in at the edges while shaking.
+    pub fn is_visible(&self, rect: Rect) -> bool {
+        let margin = Vec2::splat(self.shake.current_intensity());
+        let min = self.position - self.size / 2. - margin;
+        let max = self.position + self.size / 2. + margin;
+        rect.x < max.x && rect.x + rect.w > min.x && rect.y < max.y && rect.y + rect.h > min.y
+    }
+}
+
+impl CameraShake {
+    fn current_intensity(&self) -> f32 {
+        if self.elapsed >= self.duration {
+            0.
+        } else {
+            self.intensity * (1. - self.elapsed / self.duration)
+        }
+    }
+}
+
+/// Returns the tile which covers the world position. Tile (x, y) covers the
+/// world from (x, y) up to, but not including, (x + 1, y + 1).
+pub fn world_to_tile(world: Vec2) -> TilePosition {
+    TilePosition::new(floorf(world.x) as i16, floorf(world.y) as i16)
+}
+
+#[cfg(test)]
+mod tests {
+    use engine::geom::Rect;
+    use glam::Vec2;
+
+    use crate::game_object::TilePosition;
+
+    use super::{
+        Camera, CameraShake, DEFAULT_VIEW_HEIGHT, MAX_VIEW_HEIGHT, MIN_TILE_OUTPUT_SIZE,
+        MIN_VIEW_HEIGHT, world_to_tile,
+    };
+
+    #[test]
+    fn to_world_undoes_to_output() {
+        let cameras = [
+            (
+                Vec2::new(12.5, -3.0),
+                Vec2::new(16.0, 9.0),
+                Vec2::new(1280.0, 720.0),
+            ),
+            (
+                Vec2::new(0.0, 0.0),
+                Vec2::new(6.0, 6.0),
+                Vec2::new(300.0, 300.0),
+            ),
+            (
+                Vec2::new(64.0, 40.0),
+                Vec2::new(85.3, 48.0),
+                Vec2::new(1920.0, 1080.0),
+            ),
+            (
+                Vec2::new(-7.0, 3.5),
+                Vec2::new(10.0, 20.0),
+                Vec2::new(480.0, 800.0),
+            ),
+        ];
+        for (position, size, output_size) in cameras {
+            let camera = Camera {
+                position,
+                size,
+                output_size,
+                shake: CameraShake::default(),
+            };
+            for (x, y) in [(0.0, 0.0), (12.5, -3.0), (7.25, 1.5), (-40.0, 100.0)] {
+                let output = camera.to_output(Rect::xywh(x, y, 1.0, 1.0));
+                let center = Vec2::new(output.x + output.w / 2., output.y + output.h / 2.);
+                let world = camera.to_world(center);
+                let expected = Vec2::new(x + 0.5, y + 0.5);
+                assert!(
+                    world.distance(expected) < 0.001,
+                    "{world} should be {expected}",
+                );
+                assert_eq!(camera.to_tile(center), world_to_tile(expected));
+            }
+        }
+    }
+
+    #[test]
+    fn zooming_is_clamped_and_keeps_the_center() {
+        let position = Vec2::new(30.0, 20.0);
+        let mut camera = Camera {
+            position,
+            size: Vec2::ZERO,
+            output_size: Vec2::new(1280.0, 720.0),
+            shake: CameraShake::default(),
+        };
+        let center = camera.output_size / 2.;
+
+        assert_eq!(
+            camera.set_view_height(DEFAULT_VIEW_HEIGHT),
+            DEFAULT_VIEW_HEIGHT
+        );
+        assert!((camera.size.x - 16.0 * 16.0 / 9.0).abs() < 0.001);
+        assert_eq!(camera.size.y, DEFAULT_VIEW_HEIGHT);
+        assert_eq!(camera.to_world(center), position);
+
+        assert_eq!(camera.set_view_height(1.0), MIN_VIEW_HEIGHT);
+        assert_eq!(camera.to_world(center), position);
+        assert_eq!(camera.set_view_height(1000.0), MAX_VIEW_HEIGHT);
+        assert_eq!(camera.to_world(center), position);
+
+        // On small outputs, the tile size limits zooming out before the max
+        camera.output_size = Vec2::new(320.0, 240.0);
+        let view_height = camera.set_view_height(1000.0);
+        assert!(view_height < MAX_VIEW_HEIGHT);
+        let tile = camera.to_output(Rect::xywh(0.0, 0.0, 1.0, 1.0));
+        assert!(
+            tile.h >= MIN_TILE_OUTPUT_SIZE - 0.001,
+            "{tile:?} is too small"
+        );
+    }
+
+    #[test]
+    fn clamped_views_stay_within_the_map() {
+        let map_size = Vec2::new(128.0, 64.0);
+        let camera_at = |x, y, size| Camera {
+            position: Vec2::new(x, y),
+            size,
+            output_size: Vec2::new(1280.0, 720.0),
+            shake: CameraShake::default(),
+        };
+
+        let size = Vec2::new(16.0, 9.0);
+        for (position, expected) in [
+            (Vec2::new(-50.0, -50.0), Vec2::new(8.0, 4.5)),
+            (Vec2::new(500.0, -50.0), Vec2::new(120.0, 4.5)),
+            (Vec2::new(-50.0, 500.0), Vec2::new(8.0, 59.5)),
+            (Vec2::new(500.0, 500.0), Vec2::new(120.0, 59.5)),
+            (Vec2::new(60.0, 30.0), Vec2::new(60.0, 30.0)),
+        ] {
+            let mut camera = camera_at(position.x, position.y, size);
+            camera.clamp_to_bounds(map_size);
+            assert_eq!(camera.position, expected, "clamping {position}");
+        }
+
+        // Taller than the map, so only the x axis can move
+        let mut camera = camera_at(-50.0, 3.0, Vec2::new(32.0, 80.0));
+        camera.clamp_to_bounds(map_size);
+        assert_eq!(camera.position, Vec2::new(16.0, 32.0));
+
+        let mut camera = camera_at(100.0, 3.0, Vec2::new(200.0, 100.0));
+        camera.clamp_to_bounds(map_size);
+        assert_eq!(camera.position, map_size / 2.);
+    }
+
+    #[test]
+    fn easing_does_not_depend_on_the_frame_rate() {
+        let camera = || Camera {
+            position: Vec2::new(0.0, 0.0),
+            size: Vec2::new(16.0, 9.0),
+            output_size: Vec2::new(1280.0, 720.0),
+            shake: CameraShake::default(),
+        };
+        let target = Vec2::new(20.0, -10.0);
+
+        let mut slow = camera();
+        let mut fast = camera();
+        for _ in 0..30 {
+            slow.ease_towards(target, 1. / 30.);
+        }
+        for _ in 0..240 {
+            fast.ease_towards(target, 1. / 240.);
+        }
+        assert!(slow.position.distance(fast.position) < 0.01);
+        assert!(slow.position.distance(target) < target.length());
+
+        let mut paused = camera();
+        paused.ease_towards(target, 0.);
+        assert_eq!(paused.position, Vec2::ZERO);
+
+        for _ in 0..600 {
+            slow.ease_towards(target, 1. / 30.);
+        }
+        assert!(slow.position.distance(target) < 0.01);
+    }
+
+    #[test]
+    fn shaking_fades_out_without_moving_the_camera() {
+        let base_position = Vec2::new(4.0, 2.0);
+        let mut camera = Camera {
+            position: base_position,
+            size: Vec2::new(16.0, 9.0),
+            output_size: Vec2::new(1280.0, 720.0),
+            shake: CameraShake::default(),
+        };
+        let rect = Rect::xywh(4.0, 2.0, 1.0, 1.0);
+        let still_x = camera.to_output(rect).x;
+
+        camera.shake(0.5, 1.0);
+        let mut moved = false;
+        for _ in 0..20 {
+            camera.update_shake(1. / 60.);
+            moved |= camera.to_output(rect).x != still_x;
+            assert!(camera.shake.offset.length() <= 0.5 * 2f32.sqrt());
+        }
+        assert!(moved, "the view should've shaken");
+        assert_eq!(camera.position, base_position);
+
+        for _ in 0..60 {
+            camera.update_shake(1. / 60.);
+        }
+        assert_eq!(camera.shake.offset, Vec2::ZERO);
+        assert_eq!(camera.to_output(rect).x, still_x);
+        assert_eq!(camera.position, base_position);
+    }
+
+    #[test]
+    fn culling_ignores_the_shake_offset() {
+        let mut camera = Camera {
+            position: Vec2::new(8.0, 4.5),
+            size: Vec2::new(16.0, 9.0),
+            output_size: Vec2::new(1280.0, 720.0),
+            shake: CameraShake::default(),
+        };
+        // Just past the right edge of the view
+        let rect = Rect::xywh(16.1, 4.0, 1.0, 1.0);
+        assert!(!camera.is_visible(rect));
+
+        camera.shake.offset = Vec2::new(0.5, 0.0);
+        assert!(!camera.is_visible(rect));
+
+        // Kept around while shaking, whichever way the view ends up moving
+        camera.shake(0.5, 1.0);
+        for offset in [Vec2::new(0.5, 0.0), Vec2::new(-0.5, 0.0)] {
+            camera.shake.offset = offset;
+            assert!(camera.is_visible(rect));
+        }
+    }
+
+    #[test]
+    fn world_positions_map_to_the_tiles_covering_them() {
+        for (world, tile) in [
+            (Vec2::new(0.0, 0.0), TilePosition::new(0, 0)),
+            (Vec2::new(0.99, 0.5), TilePosition::new(0, 0)),
+            (Vec2::new(3.0, 7.999), TilePosition::new(3, 7)),
+            (Vec2::new(-0.01, 2.5), TilePosition::new(-1, 2)),
+            (Vec2::new(-1.0, -1.5), TilePosition::new(-1, -2)),
+        ] {
+            assert_eq!(world_to_tile(world), tile, "wrong tile for {world}");
+        }
+    }
 }
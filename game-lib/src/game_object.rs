@@ -7,7 +7,7 @@ use bytemuck::{Pod, Zeroable};
 use engine::impl_game_object;
 use glam::I16Vec2;
 
-use crate::Sprite;
+use crate::{Sprite, brain::BrainId};
 
 // Game objects
 
@@ -57,27 +57,179 @@ impl_game_object! {
     }
 }
 
+impl JobStation {
+    /// Creates an empty job station. The input resource's slot is reserved
+    /// from the start, so that haulers don't take the inputs back out.
+    pub fn new(variant: JobStationVariant, position: TilePosition) -> JobStation {
+        let mut stockpile = Stockpile::zeroed();
+        if let Some(details) = variant.details() {
+            stockpile = stockpile.with_resource(details.resource_variant, 0, true);
+        } else if variant == JobStationVariant::REFILL_STATION {
+            // Not operated by anyone: the oxygen is pulled in from adjacent
+            // stockpiles, and breathed in straight from the station's stockpile.
+            stockpile = stockpile.with_resource(ResourceVariant::OXYGEN, 0, true);
+        }
+        JobStation {
+            position,
+            stockpile,
+            status: JobStationStatus {
+                variant,
+                work_invested: 0,
+                input_work_left: 0,
+                settings: JobStationSettings::zeroed(),
+            },
+            collider: Collider::NOT_WALKABLE,
+        }
+    }
+}
+
 // Components
 
 #[derive(Clone, Copy, Debug, Zeroable, Pod)]
 #[repr(C)]
 pub struct CharacterStatus {
     pub brain_index: u8,
+    pub brain_generation: u8,
     pub oxygen: u8,
+    pub reserve_oxygen: u8,
     pub oxygen_depletion_amount: u8,
     pub morale: u8,
     pub morale_depletion_amount: u8,
     pub morale_relaxing_increment: u8,
+    /// The oxygen level at or below which the character goes to refill.
+    pub low_oxygen_threshold: u8,
+    /// The morale level at or below which the character stops working to relax.
+    pub low_morale_threshold: u8,
+    /// How many units of work the character does at a job station per work
+    /// tick.
+    pub work_speed: u8,
     pub personality: Personality,
+    /// How many times the character has leveled up from experience.
+    pub level: u8,
+    /// How full the character is. Goes down a bit every oxygen tick, and is
+    /// filled back up by eating [`ResourceVariant::FOOD`].
+    pub food: u8,
+    /// Progress toward the next level, reset (carrying over the excess) on
+    /// each level up.
+    pub experience: u16,
 }
 impl CharacterStatus {
     pub const MAX_OXYGEN: u8 = 24;
+    pub const MAX_RESERVE_OXYGEN: u8 = 3;
     pub const BASE_OXYGEN_DEPLETION_AMOUNT: u8 = 3;
     pub const LOW_OXYGEN_THRESHOLD: u8 = 9;
+    pub const OXYGEN_SHARING_THRESHOLD: u8 = 3;
     pub const MAX_MORALE: u8 = 24;
     pub const LOW_MORALE_THRESHOLD: u8 = 9;
+    pub const RECOVERY_MORALE_THRESHOLD: u8 = 18;
     pub const BASE_MORALE_DEPLETION_AMOUNT: u8 = 3;
     pub const BASE_MORALE_RELAXING_INCREMENT: u8 = 3;
+    /// How many times the relaxing increment is gained while sleeping.
+    pub const SLEEPING_MORALE_MULTIPLIER: u8 = 2;
+    pub const BASE_WORK_SPEED: u8 = 1;
+    pub const MAX_FOOD: u8 = 24;
+    pub const LOW_FOOD_THRESHOLD: u8 = 9;
+    pub const FOOD_DEPLETION_AMOUNT: u8 = 1;
+    /// How much food is gained by eating one unit of
+    /// [`ResourceVariant::FOOD`].
+    pub const FOOD_PER_RATION: u8 = 8;
+    pub const EXPERIENCE_PER_LEVEL: u16 = 100;
+    pub const MAX_LEVEL: u8 = 5;
+    /// Experience gained each time a job station produces something with the
+    /// character's work.
+    pub const PRODUCTION_EXPERIENCE: u16 = 10;
+    /// Experience gained per resource unit delivered by hauling.
+    pub const HAUL_EXPERIENCE: u16 = 5;
+
+    pub const fn brain_id(&self) -> BrainId {
+        BrainId {
+            index: self.brain_index,
+            generation: self.brain_generation,
+        }
+    }
+
+    /// Returns true if both the main tanks and the emergency reserve are
+    /// empty, i.e. the character can't go on.
+    pub const fn is_out_of_oxygen(&self) -> bool {
+        self.oxygen == 0 && self.reserve_oxygen == 0
+    }
+
+    /// Uses up oxygen for one oxygen tick. Once the main tanks are empty, the
+    /// reserve is used up one unit at a time.
+    pub fn deplete_oxygen(&mut self) {
+        if self.oxygen > 0 {
+            self.oxygen = self.oxygen.saturating_sub(self.oxygen_depletion_amount);
+        } else {
+            self.reserve_oxygen = self.reserve_oxygen.saturating_sub(1);
+        }
+    }
+
+    /// Adds one unit of oxygen to the main tanks, or to the reserve if the
+    /// main tanks are already full.
+    pub fn breathe_in(&mut self) {
+        if self.oxygen < CharacterStatus::MAX_OXYGEN {
+            self.oxygen += 1;
+        } else if self.reserve_oxygen < CharacterStatus::MAX_RESERVE_OXYGEN {
+            self.reserve_oxygen += 1;
+        }
+    }
+
+    /// Returns how much oxygen it'd take to fill both the main tanks and the
+    /// reserve.
+    pub const fn oxygen_missing(&self) -> u8 {
+        CharacterStatus::MAX_OXYGEN.saturating_sub(self.oxygen)
+            + CharacterStatus::MAX_RESERVE_OXYGEN.saturating_sub(self.reserve_oxygen)
+    }
+
+    pub const fn is_fully_oxygenated(&self) -> bool {
+        self.oxygen >= CharacterStatus::MAX_OXYGEN
+            && self.reserve_oxygen >= CharacterStatus::MAX_RESERVE_OXYGEN
+    }
+
+    pub const fn is_starving(&self) -> bool {
+        self.food == 0
+    }
+
+    /// Returns true if another ration wouldn't fit, i.e. eating it would be
+    /// a waste.
+    pub const fn is_full(&self) -> bool {
+        self.food > CharacterStatus::MAX_FOOD - CharacterStatus::FOOD_PER_RATION
+    }
+
+    /// Eats one unit of [`ResourceVariant::FOOD`].
+    pub fn eat(&mut self) {
+        self.food = (self.food.saturating_add(CharacterStatus::FOOD_PER_RATION))
+            .min(CharacterStatus::MAX_FOOD);
+    }
+
+    /// Adds experience, and returns true if it was enough for a level up.
+    /// Characters at [`CharacterStatus::MAX_LEVEL`] don't gain experience
+    /// anymore. What a level up improves is up to the caller, since working
+    /// and hauling train different things.
+    pub fn gain_experience(&mut self, amount: u16) -> bool {
+        if self.level >= CharacterStatus::MAX_LEVEL {
+            return false;
+        }
+        self.experience = self.experience.saturating_add(amount);
+        if self.experience >= CharacterStatus::EXPERIENCE_PER_LEVEL {
+            self.experience -= CharacterStatus::EXPERIENCE_PER_LEVEL;
+            self.level += 1;
+            if self.level >= CharacterStatus::MAX_LEVEL {
+                self.experience = 0;
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Adds experience from working at a job station, speeding up the
+    /// character's work on level ups.
+    pub fn gain_work_experience(&mut self, amount: u16) {
+        if self.gain_experience(amount) {
+            self.work_speed = self.work_speed.saturating_add(1);
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, Zeroable, Pod)]
@@ -95,16 +247,133 @@ impl Collider {
 pub struct JobStationStatus {
     pub variant: JobStationVariant,
     pub work_invested: u8,
+    /// How many more work ticks the already drained input resources last, for
+    /// job stations with [`JobStationDetails::continuous_input`].
+    pub input_work_left: u8,
+    pub settings: JobStationSettings,
 }
+impl JobStationStatus {
+    /// Does one tick of work at the job station, using up input resources from
+    /// the stockpile as needed. Returns true if the work was finished, and the
+    /// output should be produced. Disabled job stations don't get any work
+    /// done, but don't lose the work in progress either.
+    pub fn invest_work(&mut self, stockpile: &mut Stockpile) -> bool {
+        let Some(details) = self.variant.details() else {
+            return false;
+        };
+        if !self.settings.is_enabled() {
+            return false;
+        }
+        let resources = stockpile.get_resources_mut(details.resource_variant);
+
+        if details.continuous_input {
+            if self.input_work_left == 0 {
+                match resources {
+                    Some(resources) if *resources > 0 => {
+                        *resources -= 1;
+                        self.input_work_left =
+                            (details.work_amount / details.resource_amount).max(1);
+                    }
+                    _ => {
+                        // Stalled, the work in progress slowly goes to waste
+                        self.work_invested = self.work_invested.saturating_sub(1);
+                        return false;
+                    }
+                }
+            }
+            self.input_work_left -= 1;
+            self.work_invested += 1;
+            if self.work_invested >= details.work_amount {
+                self.work_invested -= details.work_amount;
+                return true;
+            }
+        } else {
+            let current_amount = resources.as_ref().map(|a| **a).unwrap_or(0);
+            if current_amount >= details.resource_amount {
+                self.work_invested += 1;
+                if self.work_invested >= details.work_amount {
+                    self.work_invested -= details.work_amount;
+                    if let Some(resources) = resources {
+                        *resources -= details.resource_amount;
+                    }
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+
+    /// How many input resources haulers are asked to bring until the job
+    /// station has. Never less than what the work needs, so that the station
+    /// can't be starved by setting the target too low.
+    pub fn input_target(&self) -> u8 {
+        let Some(details) = self.variant.details() else {
+            return 0;
+        };
+        (self.settings.input_target).max(details.resource_amount)
+    }
+}
+
+/// The settings the player can change for each job station. All zeroes is the
+/// default: enabled, with the lowest haul priority, and only enough inputs
+/// requested to do the work once.
+#[derive(Clone, Copy, Debug, Zeroable, Pod)]
+#[repr(C)]
+pub struct JobStationSettings {
+    disabled: u8,
+    /// Hauls to job stations with a higher priority are taken on first, up to
+    /// [`JobStationSettings::MAX_HAUL_PRIORITY`].
+    pub haul_priority: u8,
+    /// How many input resources to keep the job station stocked with, up to
+    /// [`JobStationSettings::MAX_INPUT_TARGET`]. See
+    /// [`JobStationStatus::input_target`].
+    pub input_target: u8,
+}
+impl JobStationSettings {
+    pub const MAX_HAUL_PRIORITY: u8 = 4;
+    pub const MAX_INPUT_TARGET: u8 = 5;
+
+    pub const fn is_enabled(self) -> bool {
+        self.disabled == 0
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.disabled = !enabled as u8;
+    }
+}
+
 impl JobStationVariant {
-    pub const fn sprite(self) -> Sprite {
+    pub(crate) const fn sprite(self) -> Sprite {
         match self {
             JobStationVariant::ENERGY_GENERATOR => Sprite::EnergyGenerator,
             JobStationVariant::OXYGEN_GENERATOR => Sprite::OxygenGenerator,
+            JobStationVariant::FOOD_SYNTHESIZER => Sprite::FoodSynthesizer,
             _ => Sprite::Placeholder,
         }
     }
 
+    pub const fn display_name(self) -> &'static str {
+        match self {
+            JobStationVariant::ENERGY_GENERATOR => "Energy Generator",
+            JobStationVariant::OXYGEN_GENERATOR => "Oxygen Generator",
+            JobStationVariant::REFILL_STATION => "Refill Station",
+            JobStationVariant::FOOD_SYNTHESIZER => "Food Synthesizer",
+            _ => "",
+        }
+    }
+
+    /// The resources it takes to build the job station.
+    pub const fn build_cost(self) -> &'static [(ResourceVariant, u8)] {
+        match self {
+            JobStationVariant::ENERGY_GENERATOR => &[(ResourceVariant::MAGMA, 6)],
+            JobStationVariant::OXYGEN_GENERATOR => &[(ResourceVariant::ENERGY, 4)],
+            JobStationVariant::REFILL_STATION => &[(ResourceVariant::ENERGY, 2)],
+            JobStationVariant::FOOD_SYNTHESIZER => &[(ResourceVariant::ENERGY, 3)],
+            _ => &[],
+        }
+    }
+
     pub const fn details(self) -> Option<JobStationDetails> {
         match self {
             JobStationVariant::ENERGY_GENERATOR => Some(JobStationDetails {
@@ -113,6 +382,7 @@ impl JobStationVariant {
                 work_amount: 10,
                 output_variant: ResourceVariant::ENERGY,
                 output_amount: 1,
+                continuous_input: false,
             }),
             JobStationVariant::OXYGEN_GENERATOR => Some(JobStationDetails {
                 resource_variant: ResourceVariant::ENERGY,
@@ -120,13 +390,22 @@ impl JobStationVariant {
                 work_amount: 5,
                 output_variant: ResourceVariant::OXYGEN,
                 output_amount: 15,
+                continuous_input: true,
+            }),
+            JobStationVariant::FOOD_SYNTHESIZER => Some(JobStationDetails {
+                resource_variant: ResourceVariant::ENERGY,
+                resource_amount: 2,
+                work_amount: 8,
+                output_variant: ResourceVariant::FOOD,
+                output_amount: 3,
+                continuous_input: false,
             }),
             _ => None,
         }
     }
 }
 
-#[derive(Clone, Copy, Debug, Zeroable, Pod)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Zeroable, Pod)]
 #[repr(C)]
 pub struct Stockpile {
     pub variant_count: u8,
@@ -215,6 +494,12 @@ impl Stockpile {
         None
     }
 
+    /// Returns true if the resources in the given slot (index into
+    /// `variants` and `amounts`) are reserved.
+    pub const fn is_slot_reserved(&self, slot: usize) -> bool {
+        ((self.reserved >> slot as u8) & 0b1) != 0
+    }
+
     pub fn has_non_reserved_resources(&self, variant: ResourceVariant) -> bool {
         let len = self.variant_count as usize;
         for (i, (variant_, amount)) in self.variants[..len]
@@ -244,6 +529,34 @@ impl Stockpile {
         }
     }
 
+    /// Returns true if both stockpiles hold the same amount of every
+    /// resource, regardless of which slots the resources are stored in.
+    pub fn same_contents(&self, other: &Stockpile) -> bool {
+        let contained_in = |a: &Stockpile, b: &Stockpile| {
+            let len = a.variant_count as usize;
+            (a.variants[..len].iter().zip(&a.amounts[..len]))
+                .all(|(variant, amount)| b.get_resources(*variant).unwrap_or(0) == *amount)
+        };
+        contained_in(self, other) && contained_in(other, self)
+    }
+
+    /// Sorts the slots by [`ResourceVariant`] (keeping the reserved flags
+    /// with their slots), so that stockpiles with the same contents also have
+    /// the same layout. Empty slots are kept, since e.g. an empty reserved
+    /// slot is how job stations mark which resources they accept.
+    pub fn canonicalize(&mut self) {
+        let len = self.variant_count as usize;
+        let mut order = [0, 1, 2];
+        order[..len].sort_unstable_by_key(|&i| self.variants[i].0);
+        let mut canonical = Stockpile::zeroed();
+        for &i in &order[..len] {
+            let reserved = ((self.reserved >> i as u8) & 0b1) != 0;
+            canonical = canonical.with_resource(self.variants[i], self.amounts[i], reserved);
+        }
+        debug_assert!(canonical.same_contents(self));
+        *self = canonical;
+    }
+
     pub fn is_empty(self) -> bool {
         for amount in &self.amounts[..self.variant_count as usize] {
             if *amount > 0 {
@@ -265,6 +578,12 @@ impl TilePosition {
     pub fn new(x: i16, y: i16) -> TilePosition {
         TilePosition(I16Vec2 { x, y })
     }
+
+    /// Returns true if a character standing here is close enough to
+    /// `target` for the interaction.
+    pub fn can_interact(self, target: TilePosition, kind: InteractionKind) -> bool {
+        self.manhattan_distance(*target) <= kind.range()
+    }
 }
 impl Deref for TilePosition {
     type Target = I16Vec2;
@@ -280,18 +599,81 @@ impl Debug for TilePosition {
 
 // Other
 
+/// The kinds of game objects, told apart by the components only they have.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ObjectKind {
+    Character,
+    JobStation,
+    Resource,
+}
+
+/// The ways characters interact with the things around them, each with its
+/// own reach.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InteractionKind {
+    /// Working at a job station.
+    Work,
+    /// Picking up resources from a stockpile for a haul.
+    HaulPickUp,
+    /// Dropping off hauled resources at a job station.
+    HaulDropOff,
+    /// Breathing in oxygen from a refill station or a stockpile.
+    RefillOxygen,
+    /// Eating food from a stockpile.
+    Eat,
+    /// Going to sleep in a bed.
+    Sleep,
+    /// Giving oxygen to another character.
+    ShareOxygen,
+}
+
+impl InteractionKind {
+    #[cfg(test)]
+    pub const ALL: [InteractionKind; 7] = [
+        InteractionKind::Work,
+        InteractionKind::HaulPickUp,
+        InteractionKind::HaulDropOff,
+        InteractionKind::RefillOxygen,
+        InteractionKind::Eat,
+        InteractionKind::Sleep,
+        InteractionKind::ShareOxygen,
+    ];
+
+    /// The farthest, in manhattan distance, that the interaction reaches. A
+    /// range of 1 covers the tile itself and the four tiles next to it.
+    pub const fn range(self) -> u16 {
+        match self {
+            InteractionKind::Work => 1,
+            InteractionKind::HaulPickUp => 1,
+            InteractionKind::HaulDropOff => 1,
+            InteractionKind::RefillOxygen => 1,
+            InteractionKind::Eat => 1,
+            InteractionKind::Sleep => 1,
+            InteractionKind::ShareOxygen => 1,
+        }
+    }
+}
+
 pub struct JobStationDetails {
     pub resource_variant: ResourceVariant,
     pub resource_amount: u8,
     pub work_amount: u8,
     pub output_variant: ResourceVariant,
     pub output_amount: u8,
+    /// If true, the input resources are drained over the course of the work
+    /// instead of all at once when the output is produced. Running out of the
+    /// input stalls the job station and slowly wastes the work invested.
+    pub continuous_input: bool,
 }
 
 macro_rules! define_consts_with_nice_debug {
     ([$const_type:tt] {$($variant:ident: $value:literal),*$(,)?}) => {
         impl $const_type {
             $(pub const $variant: $const_type = $const_type($value);)*
+
+            /// Every defined value, in definition order.
+            #[allow(dead_code)]
+            pub const ALL: &[$const_type] = &[$(<$const_type>::$variant),*];
         }
         impl Debug for $const_type {
             fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -310,6 +692,8 @@ pub struct JobStationVariant(u8);
 define_consts_with_nice_debug!([JobStationVariant] {
     ENERGY_GENERATOR: 1,
     OXYGEN_GENERATOR: 2,
+    REFILL_STATION: 3,
+    FOOD_SYNTHESIZER: 4,
 });
 
 #[derive(Clone, Copy, PartialEq, Eq, Zeroable, Pod)]
@@ -319,17 +703,29 @@ define_consts_with_nice_debug!([ResourceVariant] {
     MAGMA: 1,
     ENERGY: 2,
     OXYGEN: 3,
+    FOOD: 4,
 });
 
 impl ResourceVariant {
-    pub const fn sprite(self) -> Option<Sprite> {
+    pub(crate) const fn sprite(self) -> Option<Sprite> {
         match self {
             ResourceVariant::MAGMA => Some(Sprite::Magma),
             ResourceVariant::ENERGY => Some(Sprite::Energy),
             ResourceVariant::OXYGEN => Some(Sprite::Oxygen),
+            ResourceVariant::FOOD => Some(Sprite::Food),
             _ => None,
         }
     }
+
+    pub const fn display_name(self) -> &'static str {
+        match self {
+            ResourceVariant::MAGMA => "Magma",
+            ResourceVariant::ENERGY => "Energy",
+            ResourceVariant::OXYGEN => "Oxygen Tank",
+            ResourceVariant::FOOD => "Food Ration",
+            _ => "",
+        }
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Zeroable, Pod)]
@@ -344,3 +740,198 @@ impl Personality {
         (self.0 & other.0) == other.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bytemuck::Zeroable;
+
+    use super::{
+        CharacterStatus, InteractionKind, JobStationSettings, JobStationStatus, JobStationVariant,
+        ResourceVariant, Stockpile, TilePosition,
+    };
+
+    #[test]
+    fn every_resource_variant_has_a_sprite() {
+        for variant in ResourceVariant::ALL {
+            assert!(variant.sprite().is_some(), "{variant:?} has no sprite");
+        }
+    }
+
+    #[test]
+    fn interactions_reach_exactly_their_range() {
+        let from = TilePosition::new(10, 10);
+        for kind in InteractionKind::ALL {
+            let range = kind.range() as i16;
+            for (x, y) in [(range, 0), (0, -range), (-1, 1 - range), (range - 1, 1)] {
+                let target = TilePosition::new(10 + x, 10 + y);
+                assert!(from.can_interact(target, kind), "{kind:?} at {x}, {y}");
+            }
+            for (x, y) in [(range + 1, 0), (0, -range - 1), (-1, -range), (range, 1)] {
+                let target = TilePosition::new(10 + x, 10 + y);
+                assert!(!from.can_interact(target, kind), "{kind:?} at {x}, {y}");
+            }
+        }
+    }
+
+    #[test]
+    fn every_operated_job_station_variant_has_details() {
+        for variant in JobStationVariant::ALL {
+            if variant == JobStationVariant::REFILL_STATION {
+                assert!(
+                    variant.details().is_none(),
+                    "refill stations aren't operated"
+                );
+            } else {
+                assert!(variant.details().is_some(), "{variant:?} has no details");
+            }
+        }
+    }
+
+    #[test]
+    fn every_variant_has_a_display_name() {
+        for variant in ResourceVariant::ALL {
+            assert!(!variant.display_name().is_empty(), "{variant:?} has no name");
+        }
+        for variant in JobStationVariant::ALL {
+            assert!(!variant.display_name().is_empty(), "{variant:?} has no name");
+        }
+    }
+
+    #[test]
+    fn stockpile_contents_compare_regardless_of_slot_order() {
+        let a = Stockpile::zeroed()
+            .with_resource(ResourceVariant::MAGMA, 2, false)
+            .with_resource(ResourceVariant::OXYGEN, 5, false);
+        let b = Stockpile::zeroed()
+            .with_resource(ResourceVariant::OXYGEN, 5, false)
+            .with_resource(ResourceVariant::MAGMA, 2, false);
+        let c = Stockpile::zeroed()
+            .with_resource(ResourceVariant::OXYGEN, 5, false)
+            .with_resource(ResourceVariant::MAGMA, 1, false);
+        assert!(a.same_contents(&b));
+        assert!(b.same_contents(&a));
+        assert!(!a.same_contents(&c));
+        assert!(!a.same_contents(&Stockpile::zeroed()));
+    }
+
+    #[test]
+    fn canonicalized_stockpiles_have_identical_layouts() {
+        let mut a = Stockpile::zeroed()
+            .with_resource(ResourceVariant::OXYGEN, 5, true)
+            .with_resource(ResourceVariant::MAGMA, 0, false);
+        let mut b = Stockpile::zeroed().with_resource(ResourceVariant::MAGMA, 0, false);
+        b.add_resource(ResourceVariant::OXYGEN, 5).unwrap();
+        b.mark_reserved(ResourceVariant::OXYGEN, true);
+        assert_ne!(bytemuck::bytes_of(&a), bytemuck::bytes_of(&b));
+
+        a.canonicalize();
+        b.canonicalize();
+        assert_eq!(bytemuck::bytes_of(&a), bytemuck::bytes_of(&b));
+        assert_eq!(a, b);
+        assert_eq!(a.variants[..2], [ResourceVariant::MAGMA, ResourceVariant::OXYGEN]);
+        assert_eq!(a.reserved, 0b10, "the reserved flag should follow its slot");
+    }
+
+    #[test]
+    fn reserve_oxygen_lasts_one_depletion_per_unit() {
+        let mut status = CharacterStatus::zeroed();
+        status.oxygen_depletion_amount = CharacterStatus::BASE_OXYGEN_DEPLETION_AMOUNT;
+        status.reserve_oxygen = CharacterStatus::MAX_RESERVE_OXYGEN;
+        for _ in 0..CharacterStatus::MAX_RESERVE_OXYGEN {
+            assert!(!status.is_out_of_oxygen());
+            status.deplete_oxygen();
+        }
+        assert!(status.is_out_of_oxygen());
+    }
+
+    #[test]
+    fn breathing_in_refills_the_reserve_last() {
+        let mut status = CharacterStatus::zeroed();
+        status.oxygen = CharacterStatus::MAX_OXYGEN - 1;
+        status.breathe_in();
+        assert_eq!(status.oxygen, CharacterStatus::MAX_OXYGEN);
+        assert_eq!(status.reserve_oxygen, 0);
+        for _ in 0..CharacterStatus::MAX_RESERVE_OXYGEN {
+            assert!(!status.is_fully_oxygenated());
+            status.breathe_in();
+        }
+        assert!(status.is_fully_oxygenated());
+    }
+
+    #[test]
+    fn leveling_up_speeds_up_work_and_carries_over_experience() {
+        let mut status = CharacterStatus::zeroed();
+        status.work_speed = CharacterStatus::BASE_WORK_SPEED;
+
+        status.gain_work_experience(CharacterStatus::EXPERIENCE_PER_LEVEL - 1);
+        assert_eq!(status.level, 0);
+        assert_eq!(status.work_speed, CharacterStatus::BASE_WORK_SPEED);
+
+        status.gain_work_experience(3);
+        assert_eq!(status.level, 1);
+        assert_eq!(status.work_speed, CharacterStatus::BASE_WORK_SPEED + 1);
+        assert_eq!(
+            status.experience, 2,
+            "the excess should count toward the next level"
+        );
+
+        for _ in 1..CharacterStatus::MAX_LEVEL {
+            status.gain_work_experience(CharacterStatus::EXPERIENCE_PER_LEVEL);
+        }
+        assert_eq!(status.level, CharacterStatus::MAX_LEVEL);
+        assert!(!status.gain_experience(CharacterStatus::EXPERIENCE_PER_LEVEL));
+        assert_eq!(status.experience, 0);
+    }
+
+    #[test]
+    fn disabled_stations_keep_their_work_in_progress() {
+        let mut job = JobStationStatus {
+            variant: JobStationVariant::ENERGY_GENERATOR,
+            work_invested: 3,
+            input_work_left: 0,
+            settings: JobStationSettings::zeroed(),
+        };
+        job.settings.set_enabled(false);
+        let mut stockpile = Stockpile::zeroed().with_resource(ResourceVariant::MAGMA, 100, true);
+
+        for _ in 0..100 {
+            assert!(!job.invest_work(&mut stockpile));
+        }
+        assert_eq!(job.work_invested, 3);
+        assert_eq!(stockpile.get_resources(ResourceVariant::MAGMA), Some(100));
+
+        job.settings.set_enabled(true);
+        assert!(!job.invest_work(&mut stockpile));
+        assert_eq!(job.work_invested, 4);
+    }
+
+    #[test]
+    fn unpowered_oxygen_generators_stall_and_decay() {
+        let details = JobStationVariant::OXYGEN_GENERATOR.details().unwrap();
+        assert!(details.continuous_input);
+        let mut job = JobStationStatus {
+            variant: JobStationVariant::OXYGEN_GENERATOR,
+            work_invested: 3,
+            input_work_left: 0,
+            settings: JobStationSettings::zeroed(),
+        };
+        let mut stockpile = Stockpile::zeroed().with_resource(ResourceVariant::ENERGY, 0, false);
+
+        // Out of energy, so the work stalls and decays
+        assert!(!job.invest_work(&mut stockpile));
+        assert_eq!(job.work_invested, 2);
+        assert!(!job.invest_work(&mut stockpile));
+        assert_eq!(job.work_invested, 1);
+
+        // Powered again, the energy is drained as soon as the work continues
+        stockpile.insert_resource(ResourceVariant::ENERGY, 1);
+        assert!(!job.invest_work(&mut stockpile));
+        assert_eq!(job.work_invested, 2);
+        assert_eq!(stockpile.get_resources(ResourceVariant::ENERGY), Some(0));
+        for _ in 3..details.work_amount {
+            assert!(!job.invest_work(&mut stockpile));
+        }
+        assert!(job.invest_work(&mut stockpile), "should've finished");
+        assert_eq!(job.work_invested, 0);
+    }
+}
@@ -25,6 +25,15 @@ impl<T: Zeroable> Grid<'_, T> {
         })
     }
 
+    /// Sets every value in the grid to zero.
+    pub fn clear(&mut self) {
+        for value in self.values.iter_mut() {
+            *value = T::zeroed();
+        }
+    }
+}
+
+impl<T> Grid<'_, T> {
     pub const fn width(&self) -> usize {
         self.width
     }
@@ -43,18 +52,51 @@ impl<T: Zeroable> Grid<'_, T> {
             && (pos.0.x as usize) < self.width
             && (pos.0.y as usize) < self.height
     }
+
+    /// Returns all the values, row by row.
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+
+    /// Returns the value at `pos`, or `None` if it's out of bounds.
+    pub fn get(&self, pos: TilePosition) -> Option<&T> {
+        if !self.in_bounds(pos) {
+            return None;
+        }
+        Some(&self.values[pos.x as usize + pos.y as usize * self.width])
+    }
+
+    /// Returns the value at `pos`, or `None` if it's out of bounds.
+    pub fn get_mut(&mut self, pos: TilePosition) -> Option<&mut T> {
+        if !self.in_bounds(pos) {
+            return None;
+        }
+        Some(&mut self.values[pos.x as usize + pos.y as usize * self.width])
+    }
 }
 
 impl<T> Index<TilePosition> for Grid<'_, T> {
     type Output = T;
 
     fn index(&self, index: TilePosition) -> &Self::Output {
+        debug_assert!(
+            self.in_bounds(index),
+            "{index:?} is out of bounds of a {}x{} grid",
+            self.width,
+            self.height,
+        );
         &self.values[index.x as usize + index.y as usize * self.width]
     }
 }
 
 impl<T> IndexMut<TilePosition> for Grid<'_, T> {
     fn index_mut(&mut self, index: TilePosition) -> &mut Self::Output {
+        debug_assert!(
+            self.in_bounds(index),
+            "{index:?} is out of bounds of a {}x{} grid",
+            self.width,
+            self.height,
+        );
         &mut self.values[index.x as usize + index.y as usize * self.width]
     }
 }
@@ -130,8 +172,7 @@ impl BitGrid<'_> {
             self.values[bitfield_x + pos.y as usize * self.stride] |=
                 (new_value as u128) << x_bit_offset;
         } else {
-            self.values[bitfield_x + pos.y as usize * self.stride] &=
-                !((new_value as u128) << x_bit_offset);
+            self.values[bitfield_x + pos.y as usize * self.stride] &= !(1u128 << x_bit_offset);
         }
     }
 
@@ -147,6 +188,37 @@ impl BitGrid<'_> {
         let x_bit_offset = x % BIT_GRID_BITS;
         (bitfield & (1 << x_bit_offset)) != 0
     }
+
+    /// Like [`BitGrid::set`], but returns false instead of panicking if `pos`
+    /// is out of bounds.
+    pub fn set_checked(&mut self, pos: TilePosition, new_value: bool) -> bool {
+        if !self.in_bounds(pos) {
+            return false;
+        }
+        self.set(pos, new_value);
+        true
+    }
+
+    /// Like [`BitGrid::get`], but returns `None` instead of panicking if `pos`
+    /// is out of bounds.
+    pub fn get_checked(&self, pos: TilePosition) -> Option<bool> {
+        if !self.in_bounds(pos) {
+            return None;
+        }
+        Some(self.get(pos))
+    }
+
+    /// Sets every value in the grid to false.
+    pub fn clear(&mut self) {
+        self.values.fill(0);
+    }
+
+    /// Overwrites the contents of this grid with the contents of `other`,
+    /// without allocating. Both grids must be the same size.
+    pub fn clone_from(&mut self, other: &BitGrid) {
+        assert_eq!(self.size(), other.size());
+        self.values.copy_from_slice(&other.values);
+    }
 }
 
 #[cfg(test)]
@@ -155,7 +227,7 @@ mod tests {
 
     use crate::game_object::TilePosition;
 
-    use super::BitGrid;
+    use super::{BitGrid, Grid};
 
     #[test]
     fn bit_grid_works() {
@@ -180,5 +252,68 @@ mod tests {
                 );
             }
         }
+
+        grid.set(TilePosition::new(50, 30), false);
+        assert!(!grid.get(TilePosition::new(50, 30)));
+        assert!(grid.get(TilePosition::new(140, 30)));
+    }
+
+    #[test]
+    fn bit_grid_clone_from_overwrites_everything() {
+        static ARENA: &LinearAllocator = static_allocator!(100000);
+        let mut original = BitGrid::new(ARENA, (150, 3)).unwrap();
+        original.set(TilePosition::new(0, 0), true);
+        original.set(TilePosition::new(140, 2), true);
+        let mut copy = BitGrid::new(ARENA, (150, 3)).unwrap();
+        copy.set(TilePosition::new(1, 1), true);
+
+        copy.clone_from(&original);
+
+        for y in 0..3 {
+            for x in 0..150 {
+                let pos = TilePosition::new(x, y);
+                assert_eq!(copy.get(pos), original.get(pos), "mismatch at {x}, {y}");
+            }
+        }
+    }
+
+    #[test]
+    fn grid_get_returns_none_out_of_bounds() {
+        static ARENA: &LinearAllocator = static_allocator!(1000);
+        let mut grid = Grid::<u8>::new_zeroed(ARENA, (3, 2)).unwrap();
+
+        *grid.get_mut(TilePosition::new(2, 1)).unwrap() = 5;
+        assert_eq!(grid.get(TilePosition::new(2, 1)), Some(&5));
+        assert_eq!(grid[TilePosition::new(2, 1)], 5);
+
+        for pos in [
+            TilePosition::new(3, 0),
+            TilePosition::new(0, 2),
+            TilePosition::new(-1, 0),
+            TilePosition::new(0, -1),
+        ] {
+            assert_eq!(grid.get(pos), None);
+            assert!(grid.get_mut(pos).is_none());
+        }
+    }
+
+    #[test]
+    fn checked_bit_grid_access_handles_out_of_bounds() {
+        static ARENA: &LinearAllocator = static_allocator!(1000);
+        let mut grid = BitGrid::new(ARENA, (3, 2)).unwrap();
+
+        assert!(grid.set_checked(TilePosition::new(2, 1), true));
+        assert_eq!(grid.get_checked(TilePosition::new(2, 1)), Some(true));
+        assert_eq!(grid.get_checked(TilePosition::new(0, 0)), Some(false));
+
+        for pos in [
+            TilePosition::new(3, 0),
+            TilePosition::new(0, 2),
+            TilePosition::new(-1, 0),
+            TilePosition::new(0, -1),
+        ] {
+            assert!(!grid.set_checked(pos, true));
+            assert_eq!(grid.get_checked(pos), None);
+        }
     }
 }
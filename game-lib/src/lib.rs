@@ -7,14 +7,17 @@ mod grid;
 mod menu;
 mod notifications;
 mod pathfinding;
+mod resource_index;
+mod save;
 mod tilemap;
 
-use core::{fmt::Write, time::Duration};
+use core::{fmt::Write, hash::Hasher, ops::Add, time::Duration};
 
 use arrayvec::{ArrayString, ArrayVec};
-use brain::{Brain, HaulDescription};
+use brain::{Brain, Brains, HaulDescription, drop_held_resources};
+pub use brain::{BrainEvent, BrainId, BrainStats, GoalKind};
 use bytemuck::Zeroable;
-use camera::Camera;
+use camera::{Camera, CameraShake, DEFAULT_VIEW_HEIGHT, ZOOM_STEP, world_to_tile};
 use engine::{
     Engine,
     allocators::LinearAllocator,
@@ -31,41 +34,80 @@ use engine::{
     },
 };
 use game_object::{
-    Character, CharacterStatus, Collider, JobStation, JobStationStatus, JobStationVariant,
-    Personality, Resource, ResourceVariant, Stockpile, StockpileReliantTag, TilePosition,
+    Character, Collider, InteractionKind, JobStation, JobStationSettings, JobStationStatus,
+    JobStationVariant, Personality, Resource, ResourceVariant, Stockpile, StockpileReliantTag,
 };
+pub use game_object::{CharacterStatus, ObjectKind, TilePosition};
 use glam::Vec2;
-use grid::BitGrid;
-use menu::{Menu, MenuAction, MenuEntry, MenuMode};
+use grid::{BitGrid, Grid};
+pub use menu::MenuEntry;
+use menu::{BuildPlacement, Menu, MenuCommand, MenuKind, MenuMode};
 use notifications::NotificationSet;
-use pathfinding::Direction;
+pub use pathfinding::{Direction, Path, PathError, find_path_generic};
+use pathfinding::{PathOptions, find_path_to, flood_fill, nearest_free_tile};
 use platform::{ActionCategory, Event, InputDevice, Instant, Platform};
-use tilemap::{Tile, Tilemap};
-use tracing::debug;
+use resource_index::ResourceIndex;
+pub use save::SaveStorage;
+use save::{SAVE_MAGIC, SAVE_VERSION, SaveReader, SaveWriter, save_file_name};
+use seahash::SeaHasher;
+pub use tilemap::WorldSummary;
+use tilemap::{TerrainParams, Tile, Tilemap, VEIN_RESOURCE_AMOUNT};
+use tracing::{debug, trace};
 
 const MAX_CHARACTERS: usize = 10;
+const MAX_JOB_STATIONS: usize = 100;
+const MAX_RESOURCES: usize = 2000;
 
 pub type GameTicks = u64;
 pub const MILLIS_PER_TICK: u64 = 100;
 const STOCKPILE_VISUALIZED_COUNT: u8 = 5;
+/// How much oxygen the base's life support can hold.
+const MAX_BASE_OXYGEN: u16 = 30;
+/// How far from oxygen generators the base is pressurized, in tiles walked.
+const PRESSURIZED_RADIUS: u8 = 6;
+/// Vents stop adding magma to stockpiles which already have this much magma.
+const VENT_MAGMA_CAP: u8 = 20;
+/// Characters standing on a gas pocket use up oxygen this many times as fast.
+const GAS_POCKET_OXYGEN_DRAIN_MULTIPLIER: u8 = 4;
+/// How much oxygen a refill station can hold.
+const REFILL_STATION_CAPACITY: u8 = 24;
+/// How much oxygen a refill station pulls from each adjacent stockpile per
+/// work tick.
+const REFILL_STATION_PULL_AMOUNT: u8 = 3;
+/// How far from a new job station the resources for building it can be taken
+/// from, in tiles.
+const BUILD_RESOURCE_RADIUS: u16 = 5;
+/// How many of the latest frames the frame rate is averaged over.
+const FPS_AVERAGED_FRAMES: usize = 30;
+/// How big of a save file can be written or read, in bytes. Fits the whole
+/// tilemap and a full scene.
+const SAVE_BUFFER_SIZE: usize = 128 * 1024;
 
 #[derive(Clone, Copy)]
 #[repr(u8)]
 enum DrawLayer {
     // The map
     Tilemap,
+    TileEdges,
     // Game objects
+    SelectionHighlight,
+    SelectedPath,
     LooseStockpiles,
     _ReserveFiveLooseStockpiles = DrawLayer::LooseStockpiles as u8 + STOCKPILE_VISUALIZED_COUNT,
     CharacterSuits,
     CharacterHelmets,
     CharacterAccessories,
+    CharacterThoughts,
     JobStations,
     JobStationStockpiles,
     _ReserveFiveJobStationStockpiles =
         DrawLayer::JobStationStockpiles as u8 + STOCKPILE_VISUALIZED_COUNT,
     CarriedStockpiles,
     _ReserveFiveCarriedStockpiles = DrawLayer::CarriedStockpiles as u8 + STOCKPILE_VISUALIZED_COUNT,
+    // Build placement
+    BuildGhost,
+    BuildGhostTint,
+    BuildCursor,
     // UI
     Passes,
     PassInformation,
@@ -84,7 +126,7 @@ enum AudioChannel {
     Music,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[repr(usize)]
 enum Sprite {
     Placeholder,
@@ -125,6 +167,35 @@ enum Sprite {
     AccessoryCap,
     AccessoryPaint,
     AccessoryShine,
+    BuildCursor,
+    BuildCursorBlocked,
+    ReservedMarker,
+    BuildGhostUnaffordable,
+    GoalSleep,
+    Food,
+    FoodSynthesizer,
+    GoalEat,
+    OccupationWorkFood,
+    MenuItemSave,
+    MenuItemLoad,
+    MenuItemSlotEmpty,
+    MenuItemSlotSaved,
+    TileEdgeUp,
+    TileEdgeDown,
+    TileEdgeLeft,
+    TileEdgeRight,
+    TileCornerUpLeft,
+    TileCornerUpRight,
+    TileCornerDownLeft,
+    TileCornerDownRight,
+    SelectionHighlight,
+    Halted,
+    PathDot,
+    MenuItemStationEnabled,
+    MenuItemStationDisabled,
+    MenuItemHaulPriority,
+    MenuItemInputTarget,
+    BuildGhostTint,
     _Count,
 }
 
@@ -210,115 +281,194 @@ fn create_action_bindings(
     }
 }
 
+/// Settings for starting a new game.
+pub struct GameConfig {
+    /// The width and height of the area cleared of walls around the starting
+    /// position.
+    pub start_area_size: u8,
+    /// How much magma in total is spread around the geothermal vents at the
+    /// start, regardless of how many vents the map has.
+    pub initial_magma: u16,
+    /// Whether the soundtrack is loaded and played.
+    pub music: bool,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig {
+            start_area_size: 8,
+            initial_magma: 120,
+            music: true,
+        }
+    }
+}
+
 pub struct Game {
     tilemap: Tilemap<'static>,
+    /// The walls of [`Game::tilemap`], cached since the tiles don't change
+    /// during play. Must be updated if the tiles do change.
+    tile_walls: BitGrid<'static>,
+    /// The pathfinding costs of the hazards in [`Game::tilemap`], cached like
+    /// [`Game::tile_walls`].
+    hazard_costs: Grid<'static, u8>,
+    /// The walls and the other unwalkable objects as of the latest tick, for
+    /// [`Game::is_walkable`] and [`Game::can_reach`].
+    walls: BitGrid<'static>,
+    /// The characters' positions as of the latest tick.
+    characters_grid: BitGrid<'static>,
+    /// The characters as of the latest tick, for [`Game::for_each_character`].
+    characters: ArrayVec<(GameObjectHandle, CharacterStatus, TilePosition), MAX_CHARACTERS>,
+    start_position: TilePosition,
     camera: Camera,
+    /// How many tiles fit vertically in [`Game::camera`]'s view, changed by
+    /// zooming in and out.
+    view_height: f32,
     ui_camera: Camera,
     scene: Scene<'static>,
-    brains: FixedVec<'static, Brain>,
+    brains: Brains,
     accessories: FixedVec<'static, Sprite>,
     haul_notifications: NotificationSet<'static, HaulDescription>,
+    base_oxygen: u16,
     current_tick: u64,
     next_tick_time: Instant,
     sprites: ArrayVec<SpriteHandle, { Sprite::_Count as usize }>,
     number_sprites: ArrayVec<SpriteHandle, 5>,
     music_clips: ArrayVec<AudioClipHandle, 4>,
     last_music_clip_start: Instant,
+    last_update_time: Instant,
+    frame_times: FrameTimes,
     flip_confirm_cancel: bool,
     input: Option<InputDeviceState<{ Button::_Count as usize }>>,
     paused: bool,
     menu: Option<MenuMode>,
+    /// The character or job station picked with [`Game::select_at`], by
+    /// accepting while no menu is open.
+    selected: Option<GameObjectHandle>,
+    /// Set when a character is selected, and cleared when the camera is
+    /// moved by hand. While set, the camera follows the selected character.
+    following_selected: bool,
+    save_storage: Option<&'static dyn SaveStorage>,
 }
 
 impl Game {
+    /// Creates a new game, with `start_time` as the time base for the ticks,
+    /// which advance based on the timestamps passed into
+    /// [`Game::iterate`].
     pub fn new(
         arena: &'static LinearAllocator,
         engine: &Engine,
-        platform: &dyn Platform,
+        start_time: Instant,
         seed: u64,
+        config: &GameConfig,
     ) -> Game {
-        let mut brains = FixedVec::new(arena, MAX_CHARACTERS).unwrap();
-        brains.push(Brain::new()).unwrap();
-        brains.push(Brain::new()).unwrap();
-        brains.push(Brain::new()).unwrap();
-        brains.push(Brain::new()).unwrap();
-        brains[0].wait_ticks = 50;
-        brains[1].wait_ticks = 20;
-        brains[2].wait_ticks = 40;
-        brains[3].wait_ticks = 30;
-
-        let mut accessories = FixedVec::new(arena, MAX_CHARACTERS).unwrap();
-        accessories.push(Sprite::AccessoryShine).unwrap();
-        accessories.push(Sprite::AccessoryBowtie).unwrap();
-        accessories.push(Sprite::AccessoryCap).unwrap();
-        accessories.push(Sprite::AccessoryPaint).unwrap();
+        let mut brains = Brains::new(arena, MAX_CHARACTERS).unwrap();
+        let brain_ids = [50, 20, 40, 30].map(|wait_ticks| {
+            let id = brains.add(Brain::new()).unwrap();
+            brains[id].wait_ticks = wait_ticks;
+            id
+        });
 
         let characters = [
             CharacterStatus {
-                brain_index: 0,
+                brain_index: brain_ids[0].index,
+                brain_generation: brain_ids[0].generation,
                 oxygen: CharacterStatus::MAX_OXYGEN,
+                reserve_oxygen: CharacterStatus::MAX_RESERVE_OXYGEN,
                 morale: CharacterStatus::MAX_MORALE - 3,
                 oxygen_depletion_amount: CharacterStatus::BASE_OXYGEN_DEPLETION_AMOUNT,
                 morale_depletion_amount: CharacterStatus::BASE_MORALE_DEPLETION_AMOUNT,
                 morale_relaxing_increment: CharacterStatus::BASE_MORALE_RELAXING_INCREMENT,
+                low_oxygen_threshold: CharacterStatus::LOW_OXYGEN_THRESHOLD,
+                low_morale_threshold: CharacterStatus::LOW_MORALE_THRESHOLD,
+                work_speed: CharacterStatus::BASE_WORK_SPEED,
                 personality: Personality::zeroed(),
+                level: 0,
+                food: CharacterStatus::MAX_FOOD,
+                experience: 0,
             },
             CharacterStatus {
-                brain_index: 1,
+                brain_index: brain_ids[1].index,
+                brain_generation: brain_ids[1].generation,
                 oxygen: CharacterStatus::MAX_OXYGEN - 3,
+                reserve_oxygen: CharacterStatus::MAX_RESERVE_OXYGEN,
                 morale: CharacterStatus::MAX_MORALE,
                 oxygen_depletion_amount: CharacterStatus::BASE_OXYGEN_DEPLETION_AMOUNT,
                 morale_depletion_amount: CharacterStatus::BASE_MORALE_DEPLETION_AMOUNT + 2,
                 morale_relaxing_increment: CharacterStatus::BASE_MORALE_RELAXING_INCREMENT + 2,
+                low_oxygen_threshold: CharacterStatus::LOW_OXYGEN_THRESHOLD,
+                low_morale_threshold: CharacterStatus::LOW_MORALE_THRESHOLD,
+                work_speed: CharacterStatus::BASE_WORK_SPEED,
                 personality: Personality::KAOMOJI,
+                level: 0,
+                food: CharacterStatus::MAX_FOOD,
+                experience: 0,
             },
             CharacterStatus {
-                brain_index: 2,
+                brain_index: brain_ids[2].index,
+                brain_generation: brain_ids[2].generation,
                 oxygen: CharacterStatus::MAX_OXYGEN - 1,
+                reserve_oxygen: CharacterStatus::MAX_RESERVE_OXYGEN,
                 morale: CharacterStatus::MAX_MORALE - 2,
                 oxygen_depletion_amount: CharacterStatus::BASE_OXYGEN_DEPLETION_AMOUNT,
                 morale_depletion_amount: CharacterStatus::BASE_MORALE_DEPLETION_AMOUNT - 1,
                 morale_relaxing_increment: CharacterStatus::BASE_MORALE_RELAXING_INCREMENT - 1,
+                low_oxygen_threshold: CharacterStatus::LOW_OXYGEN_THRESHOLD,
+                low_morale_threshold: CharacterStatus::LOW_MORALE_THRESHOLD,
+                work_speed: CharacterStatus::BASE_WORK_SPEED,
                 personality: Personality::zeroed(),
+                level: 0,
+                food: CharacterStatus::MAX_FOOD,
+                experience: 0,
             },
             CharacterStatus {
-                brain_index: 3,
+                brain_index: brain_ids[3].index,
+                brain_generation: brain_ids[3].generation,
                 oxygen: CharacterStatus::MAX_OXYGEN - 2,
+                reserve_oxygen: CharacterStatus::MAX_RESERVE_OXYGEN,
                 morale: CharacterStatus::MAX_MORALE - 1,
                 oxygen_depletion_amount: CharacterStatus::BASE_OXYGEN_DEPLETION_AMOUNT + 1,
                 morale_depletion_amount: CharacterStatus::BASE_MORALE_DEPLETION_AMOUNT,
                 morale_relaxing_increment: CharacterStatus::BASE_MORALE_RELAXING_INCREMENT + 2,
+                low_oxygen_threshold: CharacterStatus::LOW_OXYGEN_THRESHOLD + 3,
+                low_morale_threshold: CharacterStatus::LOW_MORALE_THRESHOLD,
+                work_speed: CharacterStatus::BASE_WORK_SPEED,
                 personality: Personality::KAOMOJI,
+                level: 0,
+                food: CharacterStatus::MAX_FOOD,
+                experience: 0,
             },
         ];
 
-        let haul_notifications = NotificationSet::new(arena, 128).unwrap();
-
-        let mut scene = Scene::builder()
-            .with_game_object_type::<Character>(MAX_CHARACTERS)
-            .with_game_object_type::<JobStation>(100)
-            .with_game_object_type::<Resource>(2000)
-            .build(arena, &engine.frame_arena)
-            .unwrap();
+        let mut scene = new_scene(arena, &engine.frame_arena);
 
-        let mut tilemap = Tilemap::new(arena, &engine.resource_db, seed);
-        let start_pos = 'pick_start_pos: {
-            let (w, h) = tilemap.tiles.size();
-            for y in h / 2 - 8..h / 2 + 8 {
-                for x in w / 2 - 8..w / 2 + 8 {
-                    if matches!(tilemap.tiles[(x, y)], Tile::Seafloor) {
-                        break 'pick_start_pos TilePosition::new(x as i16, y as i16);
-                    }
-                }
-            }
-            TilePosition::new(64, 64)
-        };
+        let mut tilemap = Tilemap::new(arena, &engine.resource_db, seed, &TerrainParams::default());
+        let start_area =
+            prepare_start_area(&mut tilemap, config.start_area_size, |pos, resource| {
+                let res_spawned = scene.spawn(Resource {
+                    position: pos,
+                    stockpile: Stockpile::zeroed().with_resource(
+                        resource,
+                        VEIN_RESOURCE_AMOUNT,
+                        false,
+                    ),
+                    stockpile_reliant: StockpileReliantTag {},
+                });
+                debug_assert!(res_spawned.is_ok());
+            });
+        let start_pos = start_area.center;
 
-        // Spawn characters around start position
-        for (i, character) in characters.into_iter().enumerate() {
-            let x = start_pos.x - 1 + i as i16;
-            let y = start_pos.y - 1 + ((i as i16 * 3 + 3) % 5);
-            let position = TilePosition::new(x, y);
+        // Spawn characters around start position, which is also where they
+        // go to sleep
+        let character_positions =
+            character_start_positions(&tilemap.tiles, &start_area, characters.len());
+        debug_assert_eq!(character_positions.len(), characters.len());
+        for (i, (character, position)) in characters
+            .iter()
+            .copied()
+            .zip(character_positions.iter().copied())
+            .enumerate()
+        {
+            brains[brain_ids[i]].sleep_spot = Some(position);
             let char_spawned = scene.spawn(Character {
                 status: character,
                 position,
@@ -328,86 +478,153 @@ impl Game {
             debug_assert!(char_spawned.is_ok());
         }
 
-        // Clear a start area
-        for y in start_pos.y - 4..start_pos.y + 4 {
-            for x in start_pos.x - 4..start_pos.x + 4 {
-                let pos = TilePosition::new(x, y);
-                tilemap.tiles[pos] = Tile::Seafloor;
-            }
-        }
-        tilemap.tiles[start_pos + Direction::Left + Direction::Up + Direction::Up] =
-            Tile::GeothermalVent;
-
         // Place the machines (TODO: remove after building is possible)
-        let job_station_spawned = scene.spawn(JobStation {
-            position: TilePosition::new(start_pos.x - 4, start_pos.y + 2),
-            stockpile: Stockpile::zeroed().with_resource(ResourceVariant::MAGMA, 0, true),
-            status: JobStationStatus {
-                variant: JobStationVariant::ENERGY_GENERATOR,
-                work_invested: 0,
-            },
-            collider: Collider::NOT_WALKABLE,
-        });
+        let mut taken_positions = ArrayVec::<TilePosition, { MAX_CHARACTERS + 3 }>::new();
+        taken_positions.extend(character_positions);
+        let energy_generator_pos = find_seafloor_in_start_area(
+            &tilemap.tiles,
+            &start_area,
+            start_area.energy_generator,
+            &taken_positions,
+        )
+        .unwrap_or(start_area.energy_generator);
+        taken_positions.push(energy_generator_pos);
+        let oxygen_generator_pos = find_seafloor_in_start_area(
+            &tilemap.tiles,
+            &start_area,
+            start_area.oxygen_generator,
+            &taken_positions,
+        )
+        .unwrap_or(start_area.oxygen_generator);
+        taken_positions.push(oxygen_generator_pos);
+        // Next to the oxygen generator, to pull the oxygen out of it
+        let refill_station_pos = find_seafloor_in_start_area(
+            &tilemap.tiles,
+            &start_area,
+            oxygen_generator_pos + Direction::Right,
+            &taken_positions,
+        )
+        .unwrap_or(oxygen_generator_pos + Direction::Right);
+
+        let job_station_spawned = scene.spawn(JobStation::new(
+            JobStationVariant::ENERGY_GENERATOR,
+            energy_generator_pos,
+        ));
         debug_assert!(job_station_spawned.is_ok());
 
-        let job_station_spawned = scene.spawn(JobStation {
-            position: TilePosition::new(start_pos.x, start_pos.y - 4),
-            stockpile: Stockpile::zeroed().with_resource(ResourceVariant::ENERGY, 0, true),
-            status: JobStationStatus {
-                variant: JobStationVariant::OXYGEN_GENERATOR,
-                work_invested: 0,
-            },
-            collider: Collider::NOT_WALKABLE,
-        });
+        let job_station_spawned = scene.spawn(JobStation::new(
+            JobStationVariant::OXYGEN_GENERATOR,
+            oxygen_generator_pos,
+        ));
+        debug_assert!(job_station_spawned.is_ok());
+
+        let job_station_spawned = scene.spawn(JobStation::new(
+            JobStationVariant::REFILL_STATION,
+            refill_station_pos,
+        ));
         debug_assert!(job_station_spawned.is_ok());
 
         // Spawn magma resources
-        for y in 0..tilemap.tiles.height() as i16 {
-            for x in 0..tilemap.tiles.width() as i16 {
-                let position = TilePosition::new(x, y);
-                if matches!(tilemap.tiles[position], Tile::GeothermalVent) {
-                    for dir in Direction::ALL {
-                        let position = position + dir;
-                        if tilemap.tiles.in_bounds(position)
-                            && matches!(tilemap.tiles[position], Tile::Seafloor)
-                        {
-                            let res_spawned = scene.spawn(Resource {
-                                position,
-                                stockpile: Stockpile::zeroed().with_resource(
-                                    ResourceVariant::MAGMA,
-                                    2,
-                                    false,
-                                ),
-                                stockpile_reliant: StockpileReliantTag {},
-                            });
-                            debug_assert!(res_spawned.is_ok());
-                        }
-                    }
-                }
-            }
+        let magma_piles = plan_initial_magma(&tilemap, config.initial_magma, &engine.frame_arena);
+        for (position, amount) in magma_piles.iter().flat_map(|piles| piles.iter()) {
+            let res_spawned = scene.spawn(Resource {
+                position: *position,
+                stockpile: Stockpile::zeroed().with_resource(
+                    ResourceVariant::MAGMA,
+                    *amount,
+                    false,
+                ),
+                stockpile_reliant: StockpileReliantTag {},
+            });
+            debug_assert!(res_spawned.is_ok());
         }
 
+        let state = GameState {
+            tilemap,
+            scene,
+            brains,
+            start_position: start_pos,
+            camera_position: Vec2::new(start_pos.x as f32, start_pos.y as f32),
+            current_tick: 0,
+            base_oxygen: 0,
+        };
+        Game::from_state(arena, engine, start_time, state, config.music)
+    }
+
+    /// Loads a game saved with [`Game::save`], with `start_time` as the time
+    /// base for the ticks like in [`Game::new`]. Returns `None` if the save
+    /// is from another version of the game, or is otherwise unreadable.
+    ///
+    /// Like [`Game::new`], this allocates a whole game's worth from `arena`.
+    pub fn load(
+        arena: &'static LinearAllocator,
+        engine: &Engine,
+        start_time: Instant,
+        save: &[u8],
+    ) -> Option<Game> {
+        let mut state = GameState::new(arena, &engine.frame_arena)?;
+        state.load(&engine.frame_arena, &mut SaveReader::new(save))?;
+        state.tilemap.load_sprites(arena, &engine.resource_db);
+        Some(Game::from_state(arena, engine, start_time, state, true))
+    }
+
+    /// Sets up everything other than the simulation itself, which is either
+    /// freshly generated or loaded from a save. Without `music`, no music
+    /// clips are loaded.
+    fn from_state(
+        arena: &'static LinearAllocator,
+        engine: &Engine,
+        start_time: Instant,
+        mut state: GameState,
+        music: bool,
+    ) -> Game {
+        let mut tile_walls = BitGrid::new(arena, state.tilemap.tiles.size()).unwrap();
+        state.tilemap.mark_walls(&mut tile_walls);
+        let mut walls = BitGrid::new(arena, state.tilemap.tiles.size()).unwrap();
+        walls.clone_from(&tile_walls);
+        let mut hazard_costs = Grid::new_zeroed(arena, state.tilemap.tiles.size()).unwrap();
+        state.tilemap.mark_hazard_costs(&mut hazard_costs);
+        let characters_grid = BitGrid::new(arena, state.tilemap.tiles.size()).unwrap();
+        let mut characters = ArrayVec::new();
+        collect_characters(&mut state.scene, &mut characters);
+
+        let mut accessories = FixedVec::new(arena, MAX_CHARACTERS).unwrap();
+        accessories.push(Sprite::AccessoryShine).unwrap();
+        accessories.push(Sprite::AccessoryBowtie).unwrap();
+        accessories.push(Sprite::AccessoryCap).unwrap();
+        accessories.push(Sprite::AccessoryPaint).unwrap();
+
         let mut main_menu = ArrayVec::new();
         main_menu.push(Menu::main_menu());
 
         Game {
-            tilemap,
+            tilemap: state.tilemap,
+            tile_walls,
+            hazard_costs,
+            walls,
+            characters_grid,
+            characters,
+            start_position: state.start_position,
             camera: Camera {
-                position: Vec2::new(start_pos.x as f32, start_pos.y as f32),
+                position: state.camera_position,
                 size: Vec2::ZERO,
                 output_size: Vec2::ZERO,
+                shake: CameraShake::default(),
             },
+            view_height: DEFAULT_VIEW_HEIGHT,
             ui_camera: Camera {
                 position: Vec2::ZERO,
                 size: Vec2::ZERO,
                 output_size: Vec2::ZERO,
+                shake: CameraShake::default(),
             },
-            scene,
-            brains,
+            scene: state.scene,
+            brains: state.brains,
             accessories,
-            haul_notifications,
-            current_tick: 0,
-            next_tick_time: platform.now(),
+            haul_notifications: NotificationSet::new(arena, 128).unwrap(),
+            base_oxygen: state.base_oxygen,
+            current_tick: state.current_tick,
+            next_tick_time: start_time,
             sprites: {
                 use Sprite::*;
                 let sprite_enums: [Sprite; Sprite::_Count as usize] = [
@@ -449,12 +666,44 @@ impl Game {
                     AccessoryCap,
                     AccessoryPaint,
                     AccessoryShine,
+                    BuildCursor,
+                    BuildCursorBlocked,
+                    ReservedMarker,
+                    BuildGhostUnaffordable,
+                    GoalSleep,
+                    Food,
+                    FoodSynthesizer,
+                    GoalEat,
+                    OccupationWorkFood,
+                    MenuItemSave,
+                    MenuItemLoad,
+                    MenuItemSlotEmpty,
+                    MenuItemSlotSaved,
+                    TileEdgeUp,
+                    TileEdgeDown,
+                    TileEdgeLeft,
+                    TileEdgeRight,
+                    TileCornerUpLeft,
+                    TileCornerUpRight,
+                    TileCornerDownLeft,
+                    TileCornerDownRight,
+                    SelectionHighlight,
+                    Halted,
+                    PathDot,
+                    MenuItemStationEnabled,
+                    MenuItemStationDisabled,
+                    MenuItemHaulPriority,
+                    MenuItemInputTarget,
+                    BuildGhostTint,
                 ];
                 let mut sprites = ArrayVec::new();
                 for sprite in sprite_enums {
                     let mut name = ArrayString::<27>::new();
                     let _ = write!(&mut name, "{sprite:?}");
-                    sprites.push(engine.resource_db.find_sprite(&name).unwrap());
+                    let Some(sprite) = engine.resource_db.find_sprite(&name) else {
+                        panic!("sprite {name} is missing from resources.db");
+                    };
+                    sprites.push(sprite);
                 }
                 sprites
             },
@@ -469,7 +718,8 @@ impl Game {
             },
             music_clips: {
                 let mut music_clips = ArrayVec::new();
-                for i in 0..music_clips.capacity() {
+                let clip_count = if music { music_clips.capacity() } else { 0 };
+                for i in 0..clip_count {
                     let mut name = ArrayString::<27>::new();
                     let _ = write!(&mut name, "Soundtrack{i:02}");
                     if let Some(clip) = engine.resource_db.find_audio_clip(&name) {
@@ -478,15 +728,276 @@ impl Game {
                 }
                 music_clips
             },
-            last_music_clip_start: platform.now() - Duration::from_secs(10000),
+            last_music_clip_start: start_time - Duration::from_secs(10000),
+            last_update_time: start_time,
+            frame_times: FrameTimes::new(),
             flip_confirm_cancel: false,
             input: None,
             paused: true,
             menu: Some(MenuMode::MenuStack(main_menu)),
+            selected: None,
+            following_selected: false,
+            save_storage: None,
+        }
+    }
+
+    /// Sets where the save slots in the menu are saved to and loaded from.
+    /// Without a storage, saving and loading from the menu does nothing.
+    pub fn set_save_storage(&mut self, storage: &'static dyn SaveStorage) {
+        self.save_storage = Some(storage);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// How many ticks the simulation has advanced since the game started.
+    pub fn current_tick(&self) -> GameTicks {
+        self.current_tick
+    }
+
+    /// Pauses the game and opens the main menu, or closes the menus and
+    /// unpauses the game.
+    pub fn set_paused(&mut self, paused: bool) {
+        set_pause_state(&mut self.paused, &mut self.menu, paused);
+    }
+
+    /// How many menus are open on top of each other, e.g. 2 in the options
+    /// opened from the main menu. Zero when no menu is shown.
+    pub fn current_menu_depth(&self) -> usize {
+        menu_depth(&self.menu)
+    }
+
+    /// The hovered entry of the topmost open menu, if a menu is shown.
+    pub fn current_menu_top_entry(&self) -> Option<MenuEntry> {
+        menu_top_entry(&self.menu)
+    }
+
+    /// Calls `f` with the handle, status, and position of each character as
+    /// of the latest tick.
+    pub fn for_each_character(
+        &self,
+        mut f: impl FnMut(GameObjectHandle, &CharacterStatus, TilePosition),
+    ) {
+        for (handle, character, position) in &self.characters {
+            f(*handle, character, *position);
+        }
+    }
+
+    /// Calls `f` with the handle and position of each object of the given
+    /// kind within `radius` tiles of `center`, in manhattan distance.
+    pub fn for_each_object_within(
+        &mut self,
+        center: TilePosition,
+        radius: u16,
+        kind: ObjectKind,
+        f: impl FnMut(GameObjectHandle, TilePosition),
+    ) {
+        for_each_object_within(&mut self.scene, center, radius, kind, f);
+    }
+
+    /// Selects the character or job station under the point on the output,
+    /// or clears the selection if there's nothing there, in which case this
+    /// returns false. Characters are picked over job stations, and the camera
+    /// follows selected characters until it's moved by hand. Selecting a job
+    /// station outside of the menus opens its settings.
+    fn select_at(&mut self, point: Vec2) -> bool {
+        let tile = self.camera.to_tile(point);
+        let picked = pick_object_at(&mut self.scene, tile);
+        self.selected = picked.map(|(handle, _)| handle);
+        self.following_selected = matches!(picked, Some((_, ObjectKind::Character)));
+
+        if let Some((_, ObjectKind::JobStation)) = picked {
+            if self.menu.is_none() {
+                if let Some(status) = update_job_station_settings(&mut self.scene, tile, |_| {}) {
+                    let mut menus = ArrayVec::new();
+                    menus.push(Menu::station_settings(tile, status));
+                    self.menu = Some(MenuMode::MenuStack(menus));
+                    self.paused = true;
+                }
+            }
+        }
+        picked.is_some()
+    }
+
+    /// Returns true if a character could step onto the tile, as of the latest
+    /// tick. Tiles with characters on them don't count as walkable.
+    pub fn is_walkable(&self, pos: TilePosition) -> bool {
+        is_walkable(&self.walls, &self.characters_grid, pos)
+    }
+
+    /// Returns true if a character at `from` could find a path to `to`, as of
+    /// the latest tick. Other characters are walked past, like when the
+    /// characters pathfind themselves. Returns false if `temp_arena` doesn't
+    /// have room for the search.
+    pub fn can_reach(
+        &self,
+        from: TilePosition,
+        to: TilePosition,
+        temp_arena: &LinearAllocator,
+    ) -> bool {
+        let options = PathOptions::new(&self.walls, &self.characters_grid);
+        find_path_to(from, to, &options, temp_arena).is_ok()
+    }
+
+    /// Returns statistics about the map, for checking that it's playable.
+    /// Returns `None` if `arena` doesn't have enough memory for the flood
+    /// fills.
+    pub fn world_summary(&self, arena: &LinearAllocator) -> Option<WorldSummary> {
+        self.tilemap.summary(self.start_position, arena)
+    }
+
+    /// Returns the idling-related state of the brain with the given id, if
+    /// there is such a brain.
+    pub fn brain_stats(&self, brain: BrainId) -> Option<BrainStats> {
+        self.brains.get(brain).map(Brain::stats)
+    }
+
+    /// The frame rate averaged over the latest frames, or `None` before any
+    /// time has passed.
+    pub fn fps(&self) -> Option<f32> {
+        self.frame_times.average_fps()
+    }
+
+    /// The latest decisions made by the brain, oldest first.
+    pub fn brain_events(&self, brain: BrainId) -> &[BrainEvent] {
+        self.brains
+            .get(brain)
+            .map(Brain::recent_events)
+            .unwrap_or(&[])
+    }
+
+    /// Returns a hash of the simulation state, which is equal between games
+    /// that have played out identically. For catching replays or other
+    /// platforms diverging from each other.
+    pub fn state_fingerprint(&mut self) -> u64 {
+        state_fingerprint(
+            &self.tilemap,
+            &mut self.scene,
+            &self.brains,
+            self.current_tick,
+            self.base_oxygen,
+        )
+    }
+
+    /// Writes the game into `buffer` in a format [`Game::load`] can read.
+    /// Returns the length of the save, or `None` if it didn't fit in
+    /// `buffer`.
+    pub fn save(&mut self, buffer: &mut [u8]) -> Option<usize> {
+        let mut writer = SaveWriter::new(buffer);
+        save_state(
+            &mut writer,
+            &self.tilemap,
+            &mut self.scene,
+            &self.brains,
+            (self.current_tick, self.base_oxygen),
+            (self.start_position, self.camera.position),
+        );
+        writer.finish()
+    }
+
+    /// Saves the game into the save slot's file. Returns false if there's no
+    /// save storage, or the save couldn't be written.
+    fn save_to_slot(&mut self, slot: u8, temp_arena: &LinearAllocator) -> bool {
+        let Some(storage) = self.save_storage else {
+            return false;
+        };
+        write_save_slot(storage, slot, temp_arena, |buffer| self.save(buffer))
+    }
+
+    /// Loads the game saved in the save slot's file, if there's one and it's
+    /// loadable. Returns false if the game was left as it was.
+    fn load_from_slot(&mut self, slot: u8, engine: &Engine, timestamp: Instant) -> bool {
+        let Some(storage) = self.save_storage else {
+            return false;
+        };
+        let temp_arena = &engine.frame_arena;
+        let Some((save, len)) = read_save_slot(storage, slot, temp_arena) else {
+            return false;
+        };
+
+        // The save is loaded over the game's own state, so the game is saved
+        // first to put it back as it was if the save turns out to be broken
+        let Some(mut backup) = FixedVec::new(temp_arena, SAVE_BUFFER_SIZE) else {
+            debug_assert!(false, "not enough memory for the save buffer");
+            return false;
+        };
+        backup.fill_with_zeroes();
+        let Some(backup_len) = self.save(&mut backup) else {
+            debug_assert!(false, "the save buffer is too small");
+            return false;
+        };
+
+        // The game objects are respawned either way, so the handles are stale
+        self.selected = None;
+        self.following_selected = false;
+
+        let reader = &mut SaveReader::new(&save[..len]);
+        let loaded = self.load_in_place(temp_arena, reader).is_some();
+        debug!("loading from {} (success: {loaded})", save_file_name(slot));
+        if !loaded {
+            let backup = &mut SaveReader::new(&backup[..backup_len]);
+            let restored = self.load_in_place(temp_arena, backup);
+            debug_assert!(restored.is_some(), "the game's own save should load back");
+            return false;
         }
+
+        self.camera.shake = CameraShake::default();
+        self.view_height = DEFAULT_VIEW_HEIGHT;
+
+        self.tile_walls.clear();
+        self.tilemap.mark_walls(&mut self.tile_walls);
+        self.walls.clone_from(&self.tile_walls);
+        self.hazard_costs.clear();
+        self.tilemap.mark_hazard_costs(&mut self.hazard_costs);
+        self.characters_grid.clear();
+        collect_characters(&mut self.scene, &mut self.characters);
+        self.haul_notifications.clear();
+        self.next_tick_time = timestamp;
+        self.last_update_time = timestamp;
+        self.frame_times = FrameTimes::new();
+        true
+    }
+
+    /// Loads a save over the game's simulation state with [`load_state`].
+    /// Returns `None` if the save couldn't be read, which can leave the game
+    /// partially loaded.
+    fn load_in_place(
+        &mut self,
+        temp_arena: &LinearAllocator,
+        reader: &mut SaveReader,
+    ) -> Option<()> {
+        let ((current_tick, base_oxygen), (start_position, camera_position)) = load_state(
+            reader,
+            &mut self.tilemap,
+            &mut self.scene,
+            &mut self.brains,
+            temp_arena,
+        )?;
+        self.current_tick = current_tick;
+        self.base_oxygen = base_oxygen;
+        self.start_position = start_position;
+        self.camera.position = camera_position;
+        Some(())
     }
 
     pub fn iterate(&mut self, engine: &mut Engine, platform: &dyn Platform, timestamp: Instant) {
+        self.update(engine, platform, timestamp);
+        self.render(engine, platform);
+    }
+
+    /// Handles input and runs the game logic up until `timestamp`, without
+    /// drawing anything, so this can be used to step the game headless.
+    pub fn update(&mut self, engine: &mut Engine, platform: &dyn Platform, timestamp: Instant) {
+        // Effects that run on real time, regardless of ticks or pausing:
+
+        let dt_real = (timestamp.duration_since(self.last_update_time))
+            .map(|dt| dt.as_secs_f32())
+            .unwrap_or(0.);
+        self.last_update_time = timestamp;
+        self.frame_times.record(dt_real);
+        self.camera.update_shake(dt_real);
+
         // Handle input:
 
         if let Some(event) = engine.event_queue.last() {
@@ -501,72 +1012,177 @@ impl Game {
             }
         }
 
+        let mut save_to_slot = None;
+        let mut load_from_slot = None;
+        let mut select_at_center = false;
+
         if let Some(input) = &mut self.input {
             input.update(&mut engine.event_queue);
 
-            if input.actions[Button::OpenMenu as usize].pressed && !self.paused {
-                self.paused = true;
-                let mut menus = ArrayVec::new();
-                menus.push(Menu::main_menu());
-                self.menu = Some(MenuMode::MenuStack(menus));
-            }
+            // Checked before the menu is handled, so that the presses which
+            // close the menu don't also zoom
+            let free_camera = self.menu.is_none() && !self.paused;
 
-            if input.actions[Button::Cancel as usize].pressed {
-                if let Some(MenuMode::MenuStack(menus)) = &mut self.menu {
-                    menus.pop();
-                    if menus.is_empty() {
-                        self.menu = None;
-                        self.paused = false;
+            let pressed = |button: Button| input.actions[button as usize].pressed;
+            let menu_command = update_menu_stack(&mut self.menu, &mut self.paused, pressed);
+
+            // Opened after the menus are updated, so that the same press doesn't
+            // also select the first entry
+            let mut open_station_settings = None;
+            if let Some(MenuMode::BuildPlacement(placement)) = &mut self.menu {
+                let dx = input.actions[Button::Right as usize].pressed as i16
+                    - input.actions[Button::Left as usize].pressed as i16;
+                let dy = input.actions[Button::Down as usize].pressed as i16
+                    - input.actions[Button::Up as usize].pressed as i16;
+                let (width, height) = self.tilemap.tiles.size();
+                placement.cursor = TilePosition::new(
+                    (placement.cursor.x + dx).clamp(0, width as i16 - 1),
+                    (placement.cursor.y + dy).clamp(0, height as i16 - 1),
+                );
+                self.camera.position = camera_following_cursor(&self.camera, placement.cursor);
+
+                if input.actions[Button::Accept as usize].pressed {
+                    let cost = placement.variant.build_cost();
+                    let existing_station =
+                        update_job_station_settings(&mut self.scene, placement.cursor, |_| {});
+                    if let Some(status) = existing_station {
+                        open_station_settings =
+                            Some(Menu::station_settings(placement.cursor, status));
+                    } else if !can_build_at(&self.tilemap, &mut self.scene, placement.cursor) {
+                        debug!("can't build at {:?}", placement.cursor);
+                    } else if !can_afford_build(
+                        &mut self.scene,
+                        cost,
+                        placement.cursor,
+                        &engine.frame_arena,
+                    ) {
+                        debug!(
+                            "not enough resources near {:?} for {cost:?}",
+                            placement.cursor
+                        );
+                    } else {
+                        debug!(
+                            "building a {} at {:?}",
+                            placement.variant.display_name(),
+                            placement.cursor,
+                        );
+                        pay_build_cost(
+                            &mut self.scene,
+                            cost,
+                            placement.cursor,
+                            &engine.frame_arena,
+                        );
+                        let station = JobStation::new(placement.variant, placement.cursor);
+                        if self.scene.spawn(station).is_err() {
+                            debug_assert!(false, "job station game object table is too small");
+                        }
                     }
                 }
             }
 
-            if let Some(menus) = self.menu.as_mut().and_then(|menus| {
-                if let MenuMode::MenuStack(menus) = menus {
-                    Some(menus)
-                } else {
-                    None
-                }
-            }) {
-                if let Some(top_menu) = menus.last_mut() {
-                    if let Some(selected) = top_menu.update(input) {
-                        match selected {
-                            (MenuEntry::Quit, MenuAction::Select) => platform.exit(true),
-                            (MenuEntry::Continue, MenuAction::Select) => {
-                                self.paused = false;
-                                self.menu = None;
-                            }
-                            (MenuEntry::Options, MenuAction::Select) => {
-                                menus.push(Menu::options(self.flip_confirm_cancel));
-                            }
-                            (MenuEntry::Build, MenuAction::Select) => {} // TODO
-                            (MenuEntry::BuildSelect(_), MenuAction::Select) => {} // TODO
-                            (MenuEntry::ManageCharacters, MenuAction::Select) => {
-                                menus.push(Menu::manage_characters(self.brains.len()));
-                            }
-                            (MenuEntry::ManageCharacter { brain_index }, MenuAction::Previous) => {
-                                let job = &mut self.brains[*brain_index].job;
-                                *job = job.previous();
-                            }
-                            (MenuEntry::ManageCharacter { brain_index }, MenuAction::Next) => {
-                                let job = &mut self.brains[*brain_index].job;
-                                *job = job.next();
-                            }
-                            (MenuEntry::FlipAcceptCancel(flip), _) => {
-                                *flip = !*flip;
-                                self.flip_confirm_cancel = *flip;
-                            }
-                            (MenuEntry::Volume, MenuAction::Next) => {
-                                let vol = &mut engine.audio_mixer.channels[0].volume;
-                                *vol = vol.saturating_add(32);
-                            }
-                            (MenuEntry::Volume, MenuAction::Previous) => {
-                                let vol = &mut engine.audio_mixer.channels[0].volume;
-                                *vol = vol.saturating_sub(32);
-                            }
-                            _ => {}
-                        }
+            let mut start_placement = None;
+            let mut open_submenu = None;
+            match menu_command {
+                Some(MenuCommand::Quit) => platform.exit(true),
+                Some(MenuCommand::Continue) => {
+                    set_pause_state(&mut self.paused, &mut self.menu, false);
+                }
+                Some(MenuCommand::OpenSubmenu(kind)) => open_submenu = Some(kind),
+                Some(MenuCommand::Build(variant)) => start_placement = Some(variant),
+                // Only shown on the entry, the next press fires
+                Some(MenuCommand::ConfirmFire(_)) => {}
+                Some(MenuCommand::Fire(brain)) => {
+                    if let Some(fired) = self.brains.get_mut(brain) {
+                        fired.release_haul_claim(&mut self.haul_notifications);
+                    }
+                    fire_character(
+                        &mut self.scene,
+                        &mut self.brains,
+                        brain,
+                        &engine.frame_arena,
+                    );
+                    // The handle might be reused for someone else
+                    self.selected = None;
+                    self.following_selected = false;
+                    // Reopened to leave out the fired character
+                    if let Some(MenuMode::MenuStack(menus)) = &mut self.menu {
+                        menus.pop();
                     }
+                    open_submenu = Some(MenuKind::ManageCharacters);
+                }
+                Some(MenuCommand::ChangeOccupation { brain, delta }) => {
+                    let job = &mut self.brains[brain].job;
+                    *job = if delta < 0 {
+                        job.previous()
+                    } else {
+                        job.next()
+                    };
+                }
+                Some(MenuCommand::ChangeMaxHaulAmount { brain, delta }) => {
+                    let brain = &mut self.brains[brain];
+                    brain.set_max_haul_amount(brain.max_haul_amount.saturating_add_signed(delta));
+                }
+                Some(MenuCommand::ToggleHalt(brain)) => {
+                    let brain = &mut self.brains[brain];
+                    brain.halted = !brain.halted;
+                }
+                Some(MenuCommand::SetStationEnabled { station, enabled }) => {
+                    update_job_station_settings(&mut self.scene, station, |settings| {
+                        settings.set_enabled(enabled);
+                    });
+                }
+                Some(MenuCommand::SetHaulPriority { station, priority }) => {
+                    update_job_station_settings(&mut self.scene, station, |settings| {
+                        settings.haul_priority = priority;
+                    });
+                }
+                Some(MenuCommand::SetInputTarget { station, target }) => {
+                    update_job_station_settings(&mut self.scene, station, |settings| {
+                        settings.input_target = target;
+                    });
+                }
+                Some(MenuCommand::SaveToSlot(slot)) => save_to_slot = Some(slot),
+                Some(MenuCommand::LoadFromSlot(slot)) => load_from_slot = Some(slot),
+                Some(MenuCommand::ChangeVolume(delta)) => {
+                    let vol = &mut engine.audio_mixer.channels[0].volume;
+                    *vol = vol.saturating_add_signed(delta);
+                }
+                Some(MenuCommand::SetFlipAcceptCancel(flip)) => self.flip_confirm_cancel = flip,
+                None => {}
+            }
+
+            let submenu = open_submenu.and_then(|kind| {
+                submenu(
+                    kind,
+                    self.flip_confirm_cancel,
+                    &self.brains,
+                    self.save_storage,
+                )
+            });
+            if let Some(menu) = submenu {
+                if let Some(MenuMode::MenuStack(menus)) = &mut self.menu {
+                    menus.push(menu);
+                }
+            }
+
+            if let Some(menu) = open_station_settings {
+                if let Some(MenuMode::BuildPlacement(placement)) = self.menu.take() {
+                    let mut menus = placement.menus;
+                    menus.push(menu);
+                    self.menu = Some(MenuMode::MenuStack(menus));
+                }
+            }
+
+            if let Some(variant) = start_placement {
+                if let Some(MenuMode::MenuStack(menus)) = self.menu.take() {
+                    // The camera follows the placement cursor instead
+                    self.following_selected = false;
+                    let cursor = world_to_tile(self.camera.position);
+                    self.menu = Some(MenuMode::BuildPlacement(BuildPlacement {
+                        variant,
+                        cursor,
+                        menus,
+                    }));
                 }
             }
 
@@ -575,22 +1191,66 @@ impl Game {
                     - (input.actions[Button::Left as usize].pressed as i32 as f32);
                 let dy = (input.actions[Button::Down as usize].pressed as i32 as f32)
                     - (input.actions[Button::Up as usize].pressed as i32 as f32);
+                if dx != 0. || dy != 0. {
+                    self.following_selected = false;
+                }
                 self.camera.position += Vec2::new(dx, dy);
+                self.camera.clamp_to_bounds(map_size(&self.tilemap));
+            }
+
+            // With no menu to use them in, accept selects what's in the middle
+            // of the screen (or zooms in if there's nothing), and cancel zooms
+            // out. The zoom is clamped when the view is resized for rendering.
+            if free_camera {
+                select_at_center = input.actions[Button::Accept as usize].pressed;
+                if input.actions[Button::Cancel as usize].pressed {
+                    self.view_height *= ZOOM_STEP;
+                }
+            }
+        }
+
+        if select_at_center && !self.select_at(self.camera.output_size / 2.) {
+            self.view_height /= ZOOM_STEP;
+        }
+
+        if let Some(slot) = save_to_slot {
+            if self.save_to_slot(slot, &engine.frame_arena) {
+                // Back to the main menu to show that it's done
+                if let Some(MenuMode::MenuStack(menus)) = &mut self.menu {
+                    menus.pop();
+                }
+            }
+        }
+
+        if let Some(slot) = load_from_slot {
+            if self.load_from_slot(slot, engine, timestamp) {
+                self.set_paused(false);
+            }
+        }
+
+        if self.following_selected {
+            let followed =
+                (self.selected).and_then(|handle| object_position(&mut self.scene, handle));
+            if let Some(position) = followed {
+                let target = Vec2::new(position.x as f32, position.y as f32) + Vec2::splat(0.5);
+                self.camera.ease_towards(target, dt_real);
+                self.camera.clamp_to_bounds(map_size(&self.tilemap));
+            } else {
+                self.following_selected = false;
             }
         }
 
         // Game logic:
 
-        while timestamp >= self.next_tick_time {
-            self.next_tick_time = self.next_tick_time + Duration::from_millis(MILLIS_PER_TICK);
-            if self.paused {
+        for _ in 0..take_due_ticks(&mut self.next_tick_time, timestamp, self.paused) {
+            if !advance_tick(&mut self.current_tick, self.paused) {
                 continue;
             }
-            self.current_tick += 1;
 
             let on_move_tick = self.current_tick % 3 == 0;
             let on_work_tick = self.current_tick % 2 == 0;
             let on_oxygen_and_morale_tick = self.current_tick % 100 == 0;
+            let on_oxygen_sharing_tick = self.current_tick % 10 == 0;
             let on_magma_spawn_tick = self.current_tick % 120 == 0;
 
             // Each tick can reuse the entire frame arena, since it's such a top level thing
@@ -601,71 +1261,65 @@ impl Game {
 
             // Set up this tick's collision information
             let mut walls = BitGrid::new(&engine.frame_arena, self.tilemap.tiles.size()).unwrap();
-            self.scene.run_system(define_system!(
-                |_, colliders: &[Collider], positions: &[TilePosition]| {
-                    for (collider, pos) in colliders.iter().zip(positions) {
-                        if collider.is_not_walkable() {
-                            walls.set(*pos, true);
-                        }
-                    }
-                }
-            ));
-            for y in 0..self.tilemap.tiles.height() {
-                for x in 0..self.tilemap.tiles.width() {
-                    match self.tilemap.tiles[(x, y)] {
-                        Tile::Wall | Tile::GeothermalVent => {
-                            walls.set(TilePosition::new(x as i16, y as i16), true)
-                        }
-                        Tile::Seafloor => {}
-                        Tile::_Count => debug_assert!(false, "Tile::_Count in the tilemap?"),
-                    }
-                }
-            }
+            let mut characters_grid =
+                BitGrid::new(&engine.frame_arena, self.tilemap.tiles.size()).unwrap();
+            mark_obstacles(
+                &mut self.scene,
+                &self.tile_walls,
+                &mut walls,
+                &mut characters_grid,
+            );
 
             // Move all characters who are currently following a path
-            // (specifically before the think tick, and updating the walls, so
-            // that other characters can reroute based on the new position).
+            // (specifically before the think tick, and updating the character
+            // grid, so that other characters can reroute based on the new
+            // position).
             if on_move_tick {
                 self.scene.run_system(define_system!(
                     |_, characters: &[CharacterStatus], positions: &mut [TilePosition]| {
-                        'next_char: for (character, pos) in characters.iter().zip(positions) {
-                            let brain = &mut self.brains[character.brain_index as usize];
-                            if let Some(dir) = brain.next_move_direction() {
-                                let mut new_pos = *pos + dir;
-                                let mut backup_dir = dir.next_clockwise();
-                                while walls.get(new_pos) {
-                                    new_pos = *pos + backup_dir;
-                                    backup_dir = backup_dir.next_clockwise();
-                                    if backup_dir == dir {
-                                        // Walls in all directions, can't do much about that.
-                                        continue 'next_char;
-                                    }
-                                }
-                                walls.set(*pos, false);
-                                walls.set(new_pos, true);
-                                *pos = new_pos;
-                            }
-                        }
+                        move_characters(
+                            self.brains.slots_mut(),
+                            characters,
+                            positions,
+                            &walls,
+                            &mut characters_grid,
+                        );
                     }
                 ));
             }
+            self.walls.clone_from(&walls);
+            self.characters_grid.clone_from(&characters_grid);
+
+            let expired_hauls = self.haul_notifications.tick_expirations(self.current_tick);
+            if expired_hauls > 0 {
+                debug!("{expired_hauls} haul requests expired without a hauler");
+            }
 
             // Run the think tick for the brains
             if let Some(mut brains_to_think) = FixedVec::new(&engine.frame_arena, MAX_CHARACTERS) {
                 self.scene.run_system(define_system!(
                     |_, characters: &[CharacterStatus], positions: &[TilePosition]| {
                         for (character, pos) in characters.iter().zip(positions) {
-                            let _ = brains_to_think.push((character.brain_index, *pos));
+                            let _ = brains_to_think.push((character.brain_id(), *pos));
                         }
                     }
                 ));
 
-                for (brain_idx, pos) in &mut *brains_to_think {
-                    self.brains[*brain_idx as usize].update_goals(
-                        (*brain_idx, *pos, self.current_tick),
+                // Shared between the brains, so that every brain looking for
+                // resources doesn't need to scan all the stockpiles
+                let mut resource_index =
+                    ResourceIndex::new(&engine.frame_arena, self.tilemap.tiles.size()).unwrap();
+
+                for (brain_id, pos) in &mut *brains_to_think {
+                    self.brains[*brain_id].update_goals(
+                        (brain_id.index, *pos, self.current_tick),
                         &mut self.scene,
                         &mut self.haul_notifications,
+                        &mut resource_index,
                         &walls,
+                        &characters_grid,
+                        // Hazards are walked around if the detour isn't too long
+                        Some(&self.hazard_costs),
                         &mut temp_arena,
                     );
                     temp_arena.reset();
@@ -677,9 +1331,14 @@ impl Game {
             self.scene.run_system(define_system!(
                 |_, characters: &[CharacterStatus], positions: &[TilePosition]| {
                     for (character, pos) in characters.iter().zip(positions) {
-                        if let Some(job) = self.brains[character.brain_index as usize].current_job()
-                        {
-                            let could_record_worker = workers.push((job, *pos));
+                        if let Some(job) = self.brains[character.brain_id()].current_job() {
+                            let could_record_worker = workers.push((
+                                job,
+                                *pos,
+                                character.work_speed,
+                                character.brain_id(),
+                                0,
+                            ));
                             debug_assert!(could_record_worker.is_ok());
                         }
                     }
@@ -688,13 +1347,33 @@ impl Game {
 
             // Update oxygen and morale for all characters
             if on_oxygen_and_morale_tick {
-                self.scene
-                    .run_system(define_system!(|_, characters: &mut [CharacterStatus]| {
-                        for character in characters {
-                            let brain = &mut self.brains[character.brain_index as usize];
-                            character.oxygen = (character.oxygen)
-                                .saturating_sub(character.oxygen_depletion_amount);
-                            if brain.has_relaxed {
+                let pressurized = self.pressurized_region(&engine.frame_arena);
+                let mut someone_ran_out_of_oxygen = false;
+                self.scene.run_system(define_system!(
+                    |_, characters: &mut [CharacterStatus], positions: &[TilePosition]| {
+                        for (character, pos) in characters.iter_mut().zip(positions) {
+                            let brain = &mut self.brains[character.brain_id()];
+                            let was_out_of_oxygen = character.is_out_of_oxygen();
+                            use_oxygen(
+                                character,
+                                *pos,
+                                self.tilemap.is_gas_pocket(*pos),
+                                pressurized.as_ref(),
+                                &mut self.base_oxygen,
+                            );
+                            someone_ran_out_of_oxygen |=
+                                !was_out_of_oxygen && character.is_out_of_oxygen();
+                            if brain.has_slept {
+                                character.morale = (character.morale)
+                                    .saturating_add(
+                                        (character.morale_relaxing_increment).saturating_mul(
+                                            CharacterStatus::SLEEPING_MORALE_MULTIPLIER,
+                                        ),
+                                    )
+                                    .min(CharacterStatus::MAX_MORALE);
+                                brain.has_slept = false;
+                                brain.has_relaxed = false;
+                            } else if brain.has_relaxed {
                                 character.morale = (character.morale)
                                     .saturating_add(character.morale_relaxing_increment)
                                     .min(CharacterStatus::MAX_MORALE);
@@ -703,8 +1382,29 @@ impl Game {
                                 character.morale = (character.morale)
                                     .saturating_sub(character.morale_depletion_amount);
                             }
+                            // Going hungry is hard on morale too
+                            if character.is_starving() {
+                                character.morale = (character.morale)
+                                    .saturating_sub(character.morale_depletion_amount);
+                            }
+                            character.food = (character.food)
+                                .saturating_sub(CharacterStatus::FOOD_DEPLETION_AMOUNT);
                         }
-                    }));
+                    }
+                ));
+                // Running out of oxygen is as bad as it gets, so make it felt
+                if someone_ran_out_of_oxygen {
+                    self.camera.shake(0.3, 0.8);
+                }
+            }
+
+            // Let characters share oxygen with their neighbors
+            if on_oxygen_sharing_tick {
+                self.scene.run_system(define_system!(
+                    |_, characters: &mut [CharacterStatus], positions: &[TilePosition]| {
+                        share_oxygen(characters, positions);
+                    }
+                ));
             }
 
             // Produce at all job stations with a worker next to it
@@ -717,56 +1417,58 @@ impl Game {
                         for ((job, stockpile), pos) in
                             jobs.iter_mut().zip(stockpiles).zip(positions)
                         {
-                            for (worker_job, worker_position) in workers.iter() {
+                            for (worker_job, worker_position, work_speed, _, worker_produced) in
+                                workers.iter_mut()
+                            {
                                 if job.variant == *worker_job
-                                    && worker_position.manhattan_distance(**pos) < 2
+                                    && worker_position.can_interact(*pos, InteractionKind::Work)
                                 {
-                                    if let Some(details) = job.variant.details() {
-                                        let resources =
-                                            stockpile.get_resources_mut(details.resource_variant);
-                                        let current_amount =
-                                            resources.as_ref().map(|a| **a).unwrap_or(0);
-                                        if current_amount >= details.resource_amount {
-                                            job.work_invested += 1;
-                                            if job.work_invested >= details.work_amount {
-                                                job.work_invested -= details.work_amount;
-                                                if let Some(resources) = resources {
-                                                    *resources -= details.resource_amount;
-                                                }
-                                                stockpile.insert_resource(
-                                                    details.output_variant,
-                                                    details.output_amount,
-                                                );
-                                                debug!(
-                                                    "produced {}x {:?} at {pos:?}",
-                                                    details.output_amount, details.output_variant
-                                                );
-                                            }
-                                        }
+                                    let produced = work_at_job_station(
+                                        job,
+                                        stockpile,
+                                        *work_speed,
+                                        &mut self.base_oxygen,
+                                    );
+                                    *worker_produced += produced;
+                                    if produced > 0 {
+                                        debug!(
+                                            "{} produced {produced} times at {pos:?}",
+                                            job.variant.display_name(),
+                                        );
                                     }
                                 }
                             }
                         }
                     }
                 ));
-            }
 
-            // Spawn magma
-            if on_magma_spawn_tick {
-                self.scene.run_system(define_system!(
-                    |_, stockpiles: &mut [Stockpile], positions: &[TilePosition]| {
-                        for (pos, stockpile) in positions.iter().zip(stockpiles) {
-                            for dir in Direction::ALL {
-                                let pos = *pos + dir;
-                                if self.tilemap.tiles.in_bounds(pos)
-                                    && matches!(self.tilemap.tiles[pos], Tile::GeothermalVent)
-                                {
-                                    let _ = stockpile.add_resource(ResourceVariant::MAGMA, 2);
-                                }
+                // Working at the stations is how workers get better at it
+                self.scene
+                    .run_system(define_system!(|_, characters: &mut [CharacterStatus]| {
+                        for character in characters {
+                            for (_, _, _, brain_id, produced) in workers.iter() {
+                                if *brain_id == character.brain_id() && *produced > 0 {
+                                    character.gain_work_experience(
+                                        *produced as u16 * CharacterStatus::PRODUCTION_EXPERIENCE,
+                                    );
+                                }
                             }
                         }
+                    }));
+
+                pull_oxygen_into_refill_stations(&mut self.scene, &temp_arena);
+                temp_arena.reset();
+            }
+
+            // Spawn magma
+            if on_magma_spawn_tick {
+                self.scene.run_system(define_system!(
+                    |_, stockpiles: &mut [Stockpile], positions: &[TilePosition]| {
+                        spawn_vent_magma(&self.tilemap, positions, stockpiles);
                     }
                 ));
+                spawn_vent_magma_piles(&mut self.scene, &self.tilemap, &temp_arena);
+                temp_arena.reset();
             }
 
             // Clean up empty stockpiles
@@ -791,34 +1493,38 @@ impl Game {
                 debug_assert!(false, "not enough memory to collect the garbage stockpiles");
             }
             temp_arena.reset();
+
+            collect_characters(&mut self.scene, &mut self.characters);
         }
 
         // Music:
 
         if let Some(duration) = timestamp.duration_since(self.last_music_clip_start) {
             if duration > Duration::from_secs(45) && !self.paused {
-                let time_ms = timestamp
-                    .duration_since(Instant::reference())
-                    .unwrap_or_else(|| Instant::reference().duration_since(timestamp).unwrap())
-                    .as_micros();
-                let hash = seahash::hash(&time_ms.to_le_bytes()) as usize;
-                self.last_music_clip_start = timestamp;
-                engine.audio_mixer.play_clip(
-                    AudioChannel::Music as usize,
-                    self.music_clips[hash % self.music_clips.len()],
-                    false,
-                    &engine.resource_db,
-                );
+                // No music if none of the clips could be loaded
+                if let Some(clip_index) = pick_music_clip(self.current_tick, self.music_clips.len())
+                {
+                    self.last_music_clip_start = timestamp;
+                    engine.audio_mixer.play_clip(
+                        AudioChannel::Music as usize,
+                        self.music_clips[clip_index],
+                        false,
+                        &engine.resource_db,
+                    );
+                }
             }
         }
+    }
 
-        // Render:
-
+    /// Draws the current state of the game.
+    pub fn render(&mut self, engine: &mut Engine, platform: &dyn Platform) {
         let (draw_width, draw_height) = platform.draw_area();
         let draw_scale = platform.draw_scale_factor();
         let aspect_ratio = draw_width / draw_height;
         self.camera.output_size = Vec2::new(draw_width, draw_height);
-        self.camera.size = Vec2::new(aspect_ratio * 16., 16.);
+        self.view_height = self.camera.set_view_height(self.view_height);
+        // Zooming out can reveal past the map's edges
+        self.camera.clamp_to_bounds(map_size(&self.tilemap));
         self.ui_camera.output_size = Vec2::new(draw_width, draw_height);
         self.ui_camera.size = Vec2::new(aspect_ratio * 16., 16.);
 
@@ -829,16 +1535,27 @@ impl Game {
             &engine.resource_db,
             &mut engine.resource_loader,
             &self.camera,
+            &self.sprites,
             &engine.frame_arena,
         );
 
         // Non-specific stockpiles
+        let mut piles_on_tile: Option<Grid<u8>> =
+            Grid::new_zeroed(&engine.frame_arena, self.tilemap.tiles.size());
         self.scene.run_system(define_system!(
             |_,
              tile_positions: &[TilePosition],
              stockpiles: &[Stockpile],
              _tags: &[StockpileReliantTag]| {
                 for (tile_pos, stockpile) in tile_positions.iter().zip(stockpiles) {
+                    let mut nth_pile_on_tile = 0;
+                    if let Some(pile_count) = piles_on_tile
+                        .as_mut()
+                        .and_then(|piles_on_tile| piles_on_tile.get_mut(*tile_pos))
+                    {
+                        nth_pile_on_tile = *pile_count;
+                        *pile_count = nth_pile_on_tile.saturating_add(1);
+                    }
                     draw_stockpile(
                         &engine.resource_db,
                         &mut engine.resource_loader,
@@ -847,6 +1564,7 @@ impl Game {
                         &self.sprites,
                         &self.camera,
                         tile_pos,
+                        pile_offset(*tile_pos, nth_pile_on_tile),
                         stockpile,
                     );
                 }
@@ -868,6 +1586,7 @@ impl Game {
                         &self.sprites,
                         &self.camera,
                         tile_pos,
+                        Vec2::ZERO,
                         stockpile,
                     );
                 }
@@ -889,6 +1608,7 @@ impl Game {
                         &self.sprites,
                         &self.camera,
                         tile_pos,
+                        Vec2::ZERO,
                         stockpile,
                     );
                 }
@@ -918,6 +1638,54 @@ impl Game {
             }
         ));
 
+        // Highlight under the selected character or job station
+        let selected = (self.selected).and_then(|handle| object_position(&mut self.scene, handle));
+        if let Some(position) = selected {
+            let sprite = engine
+                .resource_db
+                .get_sprite(self.sprites[Sprite::SelectionHighlight as usize]);
+            let dst = Rect::xywh(position.x as f32, position.y as f32, 1., 1.);
+            let draw_success = sprite.draw(
+                self.camera.to_output(dst),
+                DrawLayer::SelectionHighlight as u8,
+                &mut draw_queue,
+                &engine.resource_db,
+                &mut engine.resource_loader,
+            );
+            debug_assert!(draw_success);
+        }
+
+        // The path the selected character is walking along
+        let mut selected_brain = None;
+        if let Some(selected) = self.selected {
+            self.scene
+                .run_system(define_system!(|handles, characters: &[CharacterStatus]| {
+                    for (handle, character) in handles.zip(characters) {
+                        if handle == selected {
+                            selected_brain = Some(character.brain_id());
+                        }
+                    }
+                }));
+        }
+        let selected_path =
+            (selected_brain.and_then(|brain| self.brains.get(brain))).and_then(Brain::active_path);
+        if let Some((from, path)) = selected_path {
+            let sprite = engine
+                .resource_db
+                .get_sprite(self.sprites[Sprite::PathDot as usize]);
+            for pos in path.positions_from(*from) {
+                let dst = Rect::xywh(pos.x as f32 + 0.35, pos.y as f32 + 0.35, 0.3, 0.3);
+                let draw_success = sprite.draw(
+                    self.camera.to_output(dst),
+                    DrawLayer::SelectedPath as u8,
+                    &mut draw_queue,
+                    &engine.resource_db,
+                    &mut engine.resource_loader,
+                );
+                debug_assert!(draw_success);
+            }
+        }
+
         // Characters on the map
         let helmet_sprite = engine
             .resource_db
@@ -962,6 +1730,26 @@ impl Game {
                         );
                         debug_assert!(draw_success);
                     }
+
+                    // Thought bubble for what the character is up to
+                    let brain = &self.brains[character.brain_id()];
+                    let thought_rect =
+                        Rect::xywh(tile_pos.x as f32 + 0.3, tile_pos.y as f32 - 0.55, 0.4, 0.4);
+                    if let Some(thought) = brain.thought_sprite(character.personality) {
+                        if self.camera.is_visible(thought_rect) {
+                            let sprite = engine
+                                .resource_db
+                                .get_sprite(self.sprites[thought as usize]);
+                            let draw_success = sprite.draw(
+                                self.camera.to_output(thought_rect),
+                                DrawLayer::CharacterThoughts as u8,
+                                &mut draw_queue,
+                                &engine.resource_db,
+                                &mut engine.resource_loader,
+                            );
+                            debug_assert!(draw_success);
+                        }
+                    }
                 }
             }
         ));
@@ -973,32 +1761,62 @@ impl Game {
         let pass_selection_overlay_sprite = engine
             .resource_db
             .get_sprite(self.sprites[Sprite::PassSelectionOverlay as usize]);
+        let food_sprite = engine
+            .resource_db
+            .get_sprite(self.sprites[Sprite::Food as usize]);
+        let haul_sprite = engine
+            .resource_db
+            .get_sprite(self.sprites[Sprite::GoalHaul as usize]);
+        let haul_underscore_sprite = engine
+            .resource_db
+            .get_sprite(self.sprites[Sprite::MenuUnderscore as usize]);
+        let halted_sprite = engine
+            .resource_db
+            .get_sprite(self.sprites[Sprite::Halted as usize]);
         self.scene
-            .run_system(define_system!(|_, characters: &[CharacterStatus]| {
-                for (i, character) in characters.iter().enumerate() {
-                    let brain = &self.brains[character.brain_index as usize];
+            .run_system(define_system!(|handles, characters: &[CharacterStatus]| {
+                for (i, (handle, character)) in handles.zip(characters).enumerate() {
+                    let brain = &self.brains[character.brain_id()];
 
-                    let selected_in_management = 'is_managed: {
+                    let hovered_management_entry = 'is_managed: {
                         if let Some(MenuMode::MenuStack(menus)) = &self.menu {
                             if let Some(top_menu) = menus.last() {
-                                if let MenuEntry::ManageCharacter { brain_index } =
-                                    top_menu.hover_entry()
-                                {
-                                    if brain_index == character.brain_index as usize {
-                                        break 'is_managed true;
-                                    }
+                                let entry = top_menu.hover_entry();
+                                if entry.managed_brain() == Some(character.brain_id()) {
+                                    break 'is_managed Some(entry);
                                 }
                             }
                         }
-                        false
+                        None
                     };
+                    let selected_in_management = hovered_management_entry.is_some();
+                    let editing_haul_amount = matches!(
+                        hovered_management_entry,
+                        Some(MenuEntry::ManageCharacterHaul { .. }),
+                    );
+                    let editing_halt = matches!(
+                        hovered_management_entry,
+                        Some(MenuEntry::ManageCharacterHalt { .. }),
+                    );
+                    let confirming_fire = matches!(
+                        hovered_management_entry,
+                        Some(MenuEntry::ManageCharacter {
+                            confirming_fire: true,
+                            ..
+                        }),
+                    );
 
                     const MAX_DRAWS: usize = 2 // The pass background and overlay
                         + 2 // Picture and accessory
                         + 1 // Occupation field
                         + brain::MAX_GOALS
                         + CharacterStatus::MAX_MORALE.div_ceil(5) as usize
-                        + CharacterStatus::MAX_OXYGEN.div_ceil(5) as usize;
+                        + CharacterStatus::MAX_OXYGEN.div_ceil(5) as usize
+                        + 1 // Food icon
+                        + CharacterStatus::MAX_FOOD.div_ceil(5) as usize
+                        + 2 // Haul amount icon and underscore
+                        + 2 // Halt icon and underscore
+                        + brain::MAX_HAUL_AMOUNT.div_ceil(5) as usize;
                     let mut draws = ArrayVec::<_, MAX_DRAWS>::new();
 
                     let mut pass_x = self.ui_camera.size.x / 2. - 5.7;
@@ -1007,6 +1825,10 @@ impl Game {
                     if selected_in_management {
                         pass_x -= 1.0;
                     }
+                    // Slid further out, about to be handed back for good
+                    if confirming_fire {
+                        pass_x -= 1.0;
+                    }
 
                     draws.push((
                         DrawLayer::Passes,
@@ -1015,7 +1837,7 @@ impl Game {
                             .to_output(Rect::xywh(pass_x, pass_y, 5.5, 3.5)),
                     ));
 
-                    if selected_in_management {
+                    if selected_in_management || self.selected == Some(handle) {
                         draws.push((
                             DrawLayer::PassInformation,
                             pass_selection_overlay_sprite,
@@ -1042,6 +1864,78 @@ impl Game {
                         pass_y + 1.18,
                     ));
 
+                    // The pass doesn't have a label for food, so it gets an icon
+                    draws.push((
+                        DrawLayer::PassInformation,
+                        food_sprite,
+                        self.ui_camera
+                            .to_output(Rect::xywh(pass_x + 2.0, pass_y + 1.63, 0.4, 0.4)),
+                    ));
+                    draws.extend(draw_counter(
+                        &self.ui_camera,
+                        &engine.resource_db,
+                        &self.number_sprites,
+                        character.food,
+                        pass_x + 2.4,
+                        pass_y + 1.68,
+                    ));
+
+                    // How much the character carries per haul, adjustable
+                    // from the character management menu
+                    draws.push((
+                        DrawLayer::PassInformation,
+                        haul_sprite,
+                        self.ui_camera
+                            .to_output(Rect::xywh(pass_x + 1.9, pass_y + 2.13, 0.5, 0.4)),
+                    ));
+                    draws.extend(draw_counter(
+                        &self.ui_camera,
+                        &engine.resource_db,
+                        &self.number_sprites,
+                        brain.max_haul_amount,
+                        pass_x + 2.4,
+                        pass_y + 2.18,
+                    ));
+                    if editing_haul_amount {
+                        draws.push((
+                            DrawLayer::PassInformation,
+                            haul_underscore_sprite,
+                            self.ui_camera.to_output(Rect::xywh(
+                                pass_x + 2.4,
+                                pass_y + 2.48,
+                                0.8,
+                                0.1,
+                            )),
+                        ));
+                    }
+
+                    // Whether the character has been halted, toggled from the
+                    // character management menu
+                    if brain.halted {
+                        draws.push((
+                            DrawLayer::PassInformation,
+                            halted_sprite,
+                            self.ui_camera.to_output(Rect::xywh(
+                                pass_x + 4.6,
+                                pass_y + 2.1,
+                                0.6,
+                                0.6,
+                            )),
+                        ));
+                    }
+                    if editing_halt {
+                        draws.push((
+                            DrawLayer::PassInformation,
+                            haul_underscore_sprite,
+                            self.ui_camera.to_output(Rect::xywh(
+                                pass_x + 4.6,
+                                pass_y + 2.78,
+                                0.6,
+                                0.1,
+                            )),
+                        ));
+                    }
+
                     for (i, goal) in brain.goal_stack.iter().enumerate() {
                         if let Some(sprite) = goal.sprite(character.personality) {
                             let sprite =
@@ -1132,6 +2026,7 @@ impl Game {
                             - Vec2::new(2.0, 2.0) * (rendered_idx as f32),
                         size: self.ui_camera.size,
                         output_size: self.ui_camera.output_size,
+                        shake: CameraShake::default(),
                     };
                     let draw_layer_offset = rendered_idx as u8 * 3;
 
@@ -1187,6 +2082,59 @@ impl Game {
                             debug_assert!(draw_success);
                         }
 
+                        if let MenuEntry::SaveSlot { slot, .. } | MenuEntry::LoadSlot { slot, .. } =
+                            *menu.entry(entry_idx)
+                        {
+                            let number = engine
+                                .resource_db
+                                .get_sprite(self.number_sprites[slot as usize]);
+                            let draw_success = number.draw(
+                                menu_camera.to_output(Rect::xywh(0.25, i as f32 + 0.2, 0.8, 0.6)),
+                                DrawLayer::MenuItems as u8 + draw_layer_offset,
+                                &mut draw_queue,
+                                &engine.resource_db,
+                                &mut engine.resource_loader,
+                            );
+                            debug_assert!(draw_success);
+                        }
+
+                        // The number sprites start from one, so the lowest
+                        // priority is shown as a one
+                        let station_setting_number = match *menu.entry(entry_idx) {
+                            MenuEntry::StationHaulPriority { priority, .. } => {
+                                self.number_sprites.get(priority as usize)
+                            }
+                            MenuEntry::StationInputTarget { target, .. } => (target as usize)
+                                .checked_sub(1)
+                                .and_then(|index| self.number_sprites.get(index)),
+                            _ => None,
+                        };
+                        if let Some(number) = station_setting_number {
+                            let number = engine.resource_db.get_sprite(*number);
+                            let draw_success = number.draw(
+                                menu_camera.to_output(Rect::xywh(4.35, i as f32 + 0.2, 0.8, 0.6)),
+                                DrawLayer::MenuItems as u8 + draw_layer_offset,
+                                &mut draw_queue,
+                                &engine.resource_db,
+                                &mut engine.resource_loader,
+                            );
+                            debug_assert!(draw_success);
+                        }
+
+                        if let MenuEntry::BuildSelect(variant) = *menu.entry(entry_idx) {
+                            let sprite = engine
+                                .resource_db
+                                .get_sprite(self.sprites[variant.sprite() as usize]);
+                            let draw_success = sprite.draw(
+                                menu_camera.to_output(Rect::xywh(0.25, i as f32 + 0.1, 0.8, 0.8)),
+                                DrawLayer::MenuItems as u8 + draw_layer_offset,
+                                &mut draw_queue,
+                                &engine.resource_db,
+                                &mut engine.resource_loader,
+                            );
+                            debug_assert!(draw_success);
+                        }
+
                         if entry_idx == menu.hover_index() && menu_idx == last_menu_idx {
                             let draw_success = menu_underscore.draw(
                                 menu_camera.to_output(Rect::xywh(0.25, i as f32 + 0.8, 5.0, 0.1)),
@@ -1200,7 +2148,45 @@ impl Game {
                     }
                 }
             }
-            Some(MenuMode::BuildPlacement) => todo!("build placement rendering"),
+            Some(MenuMode::BuildPlacement(placement)) => {
+                let cost = placement.variant.build_cost();
+                let cursor_sprite =
+                    if can_build_at(&self.tilemap, &mut self.scene, placement.cursor) {
+                        Sprite::BuildCursor
+                    } else {
+                        Sprite::BuildCursorBlocked
+                    };
+                let affordable =
+                    can_afford_build(&mut self.scene, cost, placement.cursor, &engine.frame_arena);
+                let ghost_tint = if affordable {
+                    Sprite::BuildGhostTint
+                } else {
+                    Sprite::BuildGhostUnaffordable
+                };
+                let dst = self.camera.to_output(Rect::xywh(
+                    placement.cursor.x as f32,
+                    placement.cursor.y as f32,
+                    1.,
+                    1.,
+                ));
+                // The tint sets the ghost apart from the built job stations,
+                // and greys it out if the job station can't be afforded
+                for (layer, sprite) in [
+                    (DrawLayer::BuildGhost, placement.variant.sprite()),
+                    (DrawLayer::BuildGhostTint, ghost_tint),
+                    (DrawLayer::BuildCursor, cursor_sprite),
+                ] {
+                    let sprite = engine.resource_db.get_sprite(self.sprites[sprite as usize]);
+                    let draw_success = sprite.draw(
+                        dst,
+                        layer as u8,
+                        &mut draw_queue,
+                        &engine.resource_db,
+                        &mut engine.resource_loader,
+                    );
+                    debug_assert!(draw_success);
+                }
+            }
             None => {}
         }
 
@@ -1226,71 +2212,3194 @@ impl Game {
 
         draw_queue.dispatch_draw(&engine.frame_arena, platform);
     }
+
+    /// Returns the tiles near oxygen generators, and the rooms with oxygen
+    /// generators in them, where characters breathe from the base's life
+    /// support instead of their own tanks.
+    fn pressurized_region<'a>(&mut self, arena: &'a LinearAllocator) -> Option<BitGrid<'a>> {
+        let mut generators = FixedVec::new(arena, MAX_JOB_STATIONS)?;
+        self.scene.run_system(define_system!(
+            |_, positions: &[TilePosition], job_stations: &[JobStationStatus]| {
+                for (pos, job_station) in positions.iter().zip(job_stations) {
+                    if job_station.variant == JobStationVariant::OXYGEN_GENERATOR {
+                        let _ = generators.push(*pos);
+                    }
+                }
+            }
+        ));
+        let mut pressurized = flood_fill(&generators, PRESSURIZED_RADIUS, &self.tile_walls, arena)?;
+        for generator in &*generators {
+            if let Some(room) = self.tilemap.room_at(*generator, arena) {
+                if room.enclosed {
+                    for y in 0..room.tiles.height() {
+                        for x in 0..room.tiles.width() {
+                            let pos = TilePosition::new(x as i16, y as i16);
+                            if room.tiles.get(pos) {
+                                pressurized.set(pos, true);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Some(pressurized)
+    }
 }
 
-#[allow(clippy::too_many_arguments)]
-fn draw_stockpile(
-    resources: &ResourceDatabase,
-    resource_loader: &mut ResourceLoader,
-    draw_queue: &mut DrawQueue,
-    layer: DrawLayer,
-    sprites: &[SpriteHandle],
-    camera: &Camera,
-    tile_pos: &TilePosition,
-    stockpile: &Stockpile,
-) {
-    for i in 0..stockpile.variant_count as usize {
-        let stockpile_pos = [
-            Vec2::new(0.3, 0.75),
-            Vec2::new(0.6, 0.5),
-            Vec2::new(0.2, 0.25),
-        ][i];
-        for j in 0..stockpile.amounts[i].min(STOCKPILE_VISUALIZED_COUNT) as usize {
-            let individual_offset = [
-                Vec2::new(-0.1, -0.07),
-                Vec2::new(0.1, 0.02),
-                Vec2::new(0.0, -0.08),
-                Vec2::new(-0.05, 0.02),
-                Vec2::new(0.05, -0.03),
-            ][j];
-            let off = stockpile_pos + individual_offset;
-            let dst = camera.to_output(Rect::xywh(
-                tile_pos.x as f32 + off.x,
-                tile_pos.y as f32 + off.y,
-                1. / 4.,
-                1. / 4.,
-            ));
-            let sprite = stockpile.variants[i]
-                .sprite()
-                .unwrap_or(Sprite::Placeholder);
-            let sprite = resources.get_sprite(sprites[sprite as usize]);
-            let draw_success = sprite.draw(
-                dst,
-                layer as u8 + j as u8,
-                draw_queue,
-                resources,
-                resource_loader,
-            );
-            debug_assert!(draw_success);
+/// The real time it took to run each of the latest
+/// [`FPS_AVERAGED_FRAMES`] frames, for a rolling average of the frame rate.
+struct FrameTimes {
+    dts: ArrayVec<f32, FPS_AVERAGED_FRAMES>,
+    /// Where the next frame time is written, once `dts` is full.
+    next: usize,
+}
+
+impl FrameTimes {
+    fn new() -> FrameTimes {
+        FrameTimes {
+            dts: ArrayVec::new(),
+            next: 0,
+        }
+    }
+
+    /// Records a frame which took `dt` seconds, replacing the oldest one if
+    /// there's no room.
+    fn record(&mut self, dt: f32) {
+        if self.dts.is_full() {
+            self.dts[self.next] = dt;
+            self.next = (self.next + 1) % self.dts.len();
+        } else {
+            self.dts.push(dt);
         }
     }
+
+    fn average_fps(&self) -> Option<f32> {
+        let total: f32 = self.dts.iter().sum();
+        (total > 0.).then(|| self.dts.len() as f32 / total)
+    }
 }
 
-fn draw_counter<'a>(
-    ui_camera: &Camera,
-    resources: &'a ResourceDatabase,
-    number_sprites: &[SpriteHandle],
-    count: u8,
-    x: f32,
-    y: f32,
-) -> impl Iterator<Item = (DrawLayer, &'a SpriteAsset, Rect)> {
-    (0..count.div_ceil(5)).map(move |oxygen_i| {
-        let count = (count - oxygen_i * 5).min(5) - 1;
-        let number_sprite = resources.get_sprite(number_sprites[count as usize]);
-        (
-            DrawLayer::PassInformation,
-            number_sprite,
-            ui_camera.to_output(Rect::xywh(x + 0.4 * oxygen_i as f32, y, 0.4, 0.3)),
-        )
-    })
+/// The area cleared of walls at the start of the game, and the positions of
+/// the starting machines in it.
+struct StartArea {
+    center: TilePosition,
+    top_left: TilePosition,
+    /// Exclusive, i.e. just outside the area.
+    bottom_right: TilePosition,
+    vent: TilePosition,
+    energy_generator: TilePosition,
+    oxygen_generator: TilePosition,
+}
+
+/// Picks the start position near the middle of the map, and clears out the
+/// start area around it, with a vent inside. This keeps every seed playable:
+/// the characters can always reach a vent, and the start area has room for
+/// the starting stations. Resources mined out of the cleared walls are passed
+/// to `on_mined`.
+fn prepare_start_area(
+    tilemap: &mut Tilemap,
+    start_area_size: u8,
+    mut on_mined: impl FnMut(TilePosition, ResourceVariant),
+) -> StartArea {
+    let start_pos = 'pick_start_pos: {
+        let (w, h) = tilemap.tiles.size();
+        for y in h / 2 - 8..h / 2 + 8 {
+            for x in w / 2 - 8..w / 2 + 8 {
+                if matches!(tilemap.tiles[(x, y)], Tile::Seafloor) {
+                    break 'pick_start_pos TilePosition::new(x as i16, y as i16);
+                }
+            }
+        }
+        TilePosition::new(64, 64)
+    };
+    debug!("starting in biome: {:?}", tilemap.biome_at(start_pos));
+    let start_area = plan_start_area(start_pos, start_area_size, tilemap.tiles.size());
+
+    for y in start_area.top_left.y..start_area.bottom_right.y {
+        for x in start_area.top_left.x..start_area.bottom_right.x {
+            let pos = TilePosition::new(x, y);
+            if let Some(resource) = tilemap.mine(pos) {
+                on_mined(pos, resource);
+            }
+            tilemap.tiles[pos] = Tile::Seafloor;
+        }
+    }
+    tilemap.tiles[start_area.vent] = Tile::GeothermalVent;
+
+    start_area
+}
+
+/// Lays out a start area of `size`×`size` tiles around `start_pos`, moving it
+/// as needed to keep the whole area inside a map of `map_size`.
+fn plan_start_area(start_pos: TilePosition, size: u8, map_size: (usize, usize)) -> StartArea {
+    let (map_width, map_height) = (map_size.0 as i16, map_size.1 as i16);
+    let size = (size as i16).max(4).min(map_width).min(map_height);
+    let half = size / 2;
+    let center = TilePosition::new(
+        start_pos.x.clamp(half, map_width - (size - half)),
+        start_pos.y.clamp(half, map_height - (size - half)),
+    );
+    StartArea {
+        center,
+        top_left: TilePosition::new(center.x - half, center.y - half),
+        bottom_right: TilePosition::new(center.x - half + size, center.y - half + size),
+        vent: TilePosition::new(center.x - 1, center.y - half / 2),
+        energy_generator: TilePosition::new(center.x - half, center.y + half / 2),
+        oxygen_generator: TilePosition::new(center.x, center.y - half),
+    }
+}
+
+/// Adds magma to the stockpiles next to geothermal vents, depending on the
+/// vents' richness, up to [`VENT_MAGMA_CAP`].
+fn spawn_vent_magma(tilemap: &Tilemap, positions: &[TilePosition], stockpiles: &mut [Stockpile]) {
+    for (pos, stockpile) in positions.iter().zip(stockpiles) {
+        for dir in Direction::ALL {
+            let richness = tilemap.vent_richness(*pos + dir);
+            if richness > 0 {
+                let magma = stockpile.get_resources(ResourceVariant::MAGMA).unwrap_or(0);
+                let amount = richness.min(VENT_MAGMA_CAP.saturating_sub(magma));
+                if amount > 0 {
+                    let _ = stockpile.add_resource(ResourceVariant::MAGMA, amount);
+                }
+            }
+        }
+        stockpile.canonicalize();
+    }
+}
+
+/// Does `work_speed` units of work at the job station, putting whatever is
+/// produced into its stockpile (oxygen goes to the base's life support first).
+/// Returns how many times the output was produced.
+fn work_at_job_station(
+    job: &mut JobStationStatus,
+    stockpile: &mut Stockpile,
+    work_speed: u8,
+    base_oxygen: &mut u16,
+) -> u8 {
+    let Some(details) = job.variant.details() else {
+        return 0;
+    };
+    let mut produced = 0;
+    for _ in 0..work_speed {
+        if job.invest_work(stockpile) {
+            let mut output_amount = details.output_amount;
+            if details.output_variant == ResourceVariant::OXYGEN {
+                // Pressurize the base first
+                let pumped = (output_amount as u16).min(MAX_BASE_OXYGEN - *base_oxygen);
+                *base_oxygen += pumped;
+                output_amount -= pumped as u8;
+            }
+            stockpile.insert_resource(details.output_variant, output_amount);
+            stockpile.canonicalize();
+            produced += 1;
+        }
+    }
+    produced
+}
+
+/// Moves oxygen from the stockpiles next to refill stations into the
+/// stations, up to [`REFILL_STATION_CAPACITY`].
+fn pull_oxygen_into_refill_stations(scene: &mut Scene, temp_arena: &LinearAllocator) {
+    // Each entry is (position, room left, amount pulled in)
+    let Some(mut stations) = FixedVec::<(TilePosition, u8, u8)>::new(temp_arena, MAX_JOB_STATIONS)
+    else {
+        debug_assert!(false, "not enough memory to find the refill stations");
+        return;
+    };
+    scene.run_system(define_system!(
+        |_,
+         job_stations: &[JobStationStatus],
+         stockpiles: &[Stockpile],
+         positions: &[TilePosition]| {
+            for ((job_station, stockpile), pos) in
+                job_stations.iter().zip(stockpiles).zip(positions)
+            {
+                if job_station.variant == JobStationVariant::REFILL_STATION {
+                    let stored = stockpile
+                        .get_resources(ResourceVariant::OXYGEN)
+                        .unwrap_or(0);
+                    let room_left = REFILL_STATION_CAPACITY.saturating_sub(stored);
+                    let could_add_station = stations.push((*pos, room_left, 0));
+                    debug_assert!(could_add_station.is_ok());
+                }
+            }
+        }
+    ));
+    if stations.is_empty() {
+        return;
+    }
+
+    scene.run_system(define_system!(
+        |_, positions: &[TilePosition], stockpiles: &mut [Stockpile]| {
+            for (pos, stockpile) in positions.iter().zip(stockpiles) {
+                if !stockpile.has_non_reserved_resources(ResourceVariant::OXYGEN) {
+                    continue;
+                }
+                for (station_pos, room_left, pulled) in stations.iter_mut() {
+                    // The station's own stockpile is at its position
+                    if *pos == *station_pos
+                        || !pos.can_interact(*station_pos, InteractionKind::HaulDropOff)
+                    {
+                        continue;
+                    }
+                    let Some(amount) = stockpile.get_resources_mut(ResourceVariant::OXYGEN) else {
+                        break;
+                    };
+                    let moved = (*amount).min(*room_left).min(REFILL_STATION_PULL_AMOUNT);
+                    *amount -= moved;
+                    *room_left -= moved;
+                    *pulled += moved;
+                }
+            }
+        }
+    ));
+
+    scene.run_system(define_system!(
+        |_,
+         job_stations: &[JobStationStatus],
+         stockpiles: &mut [Stockpile],
+         positions: &[TilePosition]| {
+            for ((job_station, stockpile), pos) in
+                job_stations.iter().zip(stockpiles).zip(positions)
+            {
+                if job_station.variant != JobStationVariant::REFILL_STATION {
+                    continue;
+                }
+                if let Some((_, _, pulled)) = stations.iter().find(|(p, ..)| p == pos) {
+                    if *pulled > 0 {
+                        stockpile.insert_resource(ResourceVariant::OXYGEN, *pulled);
+                        trace!("refill station at {pos:?} pulled in {pulled} oxygen");
+                    }
+                }
+            }
+        }
+    ));
+}
+
+/// Spawns a new magma pile on each seafloor tile next to a geothermal vent
+/// which doesn't have a stockpile on it, so that vents keep producing even
+/// after all of their piles have been hauled away.
+fn spawn_vent_magma_piles(scene: &mut Scene, tilemap: &Tilemap, temp_arena: &LinearAllocator) {
+    let tiles = &tilemap.tiles;
+    let Some(mut occupied) = BitGrid::new(temp_arena, tiles.size()) else {
+        debug_assert!(false, "not enough memory to find the tiles with stockpiles");
+        return;
+    };
+    scene.run_system(define_system!(
+        |_, positions: &[TilePosition], _stockpiles: &[Stockpile]| {
+            for pos in positions {
+                occupied.set_checked(*pos, true);
+            }
+        }
+    ));
+
+    for y in 0..tiles.height() as i16 {
+        for x in 0..tiles.width() as i16 {
+            let vent = TilePosition::new(x, y);
+            let richness = tilemap.vent_richness(vent);
+            if richness == 0 {
+                continue;
+            }
+            for dir in Direction::ALL {
+                let pos = vent + dir;
+                if !matches!(tiles.get(pos), Some(Tile::Seafloor)) || occupied.get(pos) {
+                    continue;
+                }
+                occupied.set(pos, true);
+                let magma_spawned = scene.spawn(Resource {
+                    position: pos,
+                    stockpile: Stockpile::zeroed().with_resource(
+                        ResourceVariant::MAGMA,
+                        richness,
+                        false,
+                    ),
+                    stockpile_reliant: StockpileReliantTag {},
+                });
+                if magma_spawned.is_err() {
+                    debug!("resource game object table is full, can't spawn more magma");
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Picks the seafloor tiles next to geothermal vents for the initial magma
+/// piles, and splits `total_magma` between them as evenly as possible, with
+/// piles next to richer vents getting proportionally more. Magma that doesn't
+/// fit in a pile goes to the piles with room left, so the total only falls
+/// short if every pile is full.
+fn plan_initial_magma<'a>(
+    tilemap: &Tilemap,
+    total_magma: u16,
+    arena: &'a LinearAllocator,
+) -> Option<FixedVec<'a, (TilePosition, u8)>> {
+    let tiles = &tilemap.tiles;
+    // The richness of the richest vent next to each pile spot, 0 elsewhere
+    let mut pile_weights: Grid<u8> = Grid::new_zeroed(arena, tiles.size())?;
+    let mut pile_count = 0;
+    let mut total_weight = 0;
+    for y in 0..tiles.height() as i16 {
+        for x in 0..tiles.width() as i16 {
+            let richness = tilemap.vent_richness(TilePosition::new(x, y));
+            if richness == 0 {
+                continue;
+            }
+            for dir in Direction::ALL {
+                let position = TilePosition::new(x, y) + dir;
+                if !matches!(tiles.get(position), Some(Tile::Seafloor)) {
+                    continue;
+                }
+                let weight = &mut pile_weights[position];
+                if *weight == 0 {
+                    pile_count += 1;
+                }
+                if richness > *weight {
+                    total_weight += (richness - *weight) as u32;
+                    *weight = richness;
+                }
+            }
+        }
+    }
+
+    let mut piles = FixedVec::new(arena, pile_count)?;
+    if pile_count == 0 {
+        return Some(piles);
+    }
+    let magma_for_weight = |weight: u8| (total_magma as u32 * weight as u32 / total_weight) as u16;
+    let mut leftovers = total_magma;
+    for y in 0..tiles.height() as i16 {
+        for x in 0..tiles.width() as i16 {
+            leftovers -= magma_for_weight(pile_weights[TilePosition::new(x, y)]);
+        }
+    }
+    for y in 0..tiles.height() as i16 {
+        for x in 0..tiles.width() as i16 {
+            let position = TilePosition::new(x, y);
+            let weight = pile_weights[position];
+            if weight == 0 {
+                continue;
+            }
+            let mut amount = magma_for_weight(weight);
+            if leftovers > 0 {
+                amount += 1;
+                leftovers -= 1;
+            }
+            if amount > 0 {
+                let clamped = amount.min(u8::MAX as u16);
+                leftovers += amount - clamped;
+                let _ = piles.push((position, clamped as u8));
+            }
+        }
+    }
+    for (_, amount) in piles.iter_mut() {
+        let added = leftovers.min((u8::MAX - *amount) as u16);
+        *amount += added as u8;
+        leftovers -= added;
+    }
+    Some(piles)
+}
+
+/// Picks a seafloor tile in the start area for each of the `count`
+/// characters, scattered around the center. Returns fewer positions if the
+/// start area runs out of room.
+fn character_start_positions(
+    tiles: &Grid<Tile>,
+    start_area: &StartArea,
+    count: usize,
+) -> ArrayVec<TilePosition, MAX_CHARACTERS> {
+    let mut positions = ArrayVec::new();
+    for i in 0..count.min(MAX_CHARACTERS) as i16 {
+        let preferred = TilePosition::new(
+            start_area.center.x - 1 + i,
+            start_area.center.y - 1 + ((i * 3 + 3) % 5),
+        );
+        if let Some(pos) = find_seafloor_in_start_area(tiles, start_area, preferred, &positions) {
+            positions.push(pos);
+        }
+    }
+    positions
+}
+
+/// Returns the seafloor tile in the start area closest to `preferred`, which
+/// isn't in `taken`.
+fn find_seafloor_in_start_area(
+    tiles: &Grid<Tile>,
+    start_area: &StartArea,
+    preferred: TilePosition,
+    taken: &[TilePosition],
+) -> Option<TilePosition> {
+    let is_valid = |pos: TilePosition| {
+        (start_area.top_left.x..start_area.bottom_right.x).contains(&pos.x)
+            && (start_area.top_left.y..start_area.bottom_right.y).contains(&pos.y)
+            && tiles.get(pos).is_some_and(|tile| !tile.blocks_build())
+            && !taken.contains(&pos)
+    };
+    if is_valid(preferred) {
+        return Some(preferred);
+    }
+    let max_distance = start_area.bottom_right.x - start_area.top_left.x
+        + start_area.bottom_right.y
+        - start_area.top_left.y;
+    for distance in 1..=max_distance {
+        for dx in -distance..=distance {
+            let dy = distance - dx.abs();
+            for pos in [
+                TilePosition::new(preferred.x + dx, preferred.y + dy),
+                TilePosition::new(preferred.x + dx, preferred.y - dy),
+            ] {
+                if is_valid(pos) {
+                    return Some(pos);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Moves each character one step along their path, if possible, and nudges
+/// out any characters who have been stuck for too long. Characters walking
+/// into each other swap places instead of blocking each other. Characters move
+/// in brain index order, so the results don't depend on the scene's ordering.
+fn move_characters(
+    brains: &mut [Brain],
+    characters: &[CharacterStatus],
+    positions: &mut [TilePosition],
+    walls: &BitGrid,
+    characters_grid: &mut BitGrid,
+) {
+    debug_assert!(characters.len() <= MAX_CHARACTERS);
+    let mut moved = [false; MAX_CHARACTERS];
+    let mut move_order = (0..characters.len()).collect::<ArrayVec<usize, MAX_CHARACTERS>>();
+    move_order.sort_unstable_by_key(|i| characters[*i].brain_index);
+    'next_char: for i in move_order {
+        if moved[i] {
+            continue;
+        }
+        let brain_index = characters[i].brain_index as usize;
+        let Some(dir) = brains[brain_index].next_move_direction() else {
+            continue;
+        };
+        let pos = positions[i];
+
+        // Swap places with a character who wants to move to this one's tile
+        let wanted_pos = pos + dir;
+        if let Some(j) = positions.iter().position(|other| *other == wanted_pos) {
+            let other_brain_index = characters[j].brain_index as usize;
+            let other_dir = brains[other_brain_index].next_move_direction();
+            if !moved[j] && other_dir.is_some_and(|other_dir| wanted_pos + other_dir == pos) {
+                trace!("characters at {pos:?} and {wanted_pos:?} swapped places");
+                positions.swap(i, j);
+                moved[i] = true;
+                moved[j] = true;
+                brains[brain_index].update_stuckness(true);
+                brains[other_brain_index].update_stuckness(true);
+                continue;
+            }
+        }
+
+        let brain = &mut brains[brain_index];
+        let mut new_pos = wanted_pos;
+        let mut backup_dir = dir.next_clockwise();
+        // Tiles off the map are treated like walls
+        while walls.get_checked(new_pos).unwrap_or(true) || characters_grid.get(new_pos) {
+            new_pos = pos + backup_dir;
+            backup_dir = backup_dir.next_clockwise();
+            if backup_dir == dir {
+                // Walls in all directions, can't do much about that,
+                // except nudge the character out if it goes on for long.
+                let needs_nudge = brain.update_stuckness(false);
+                if needs_nudge {
+                    if let Some(free_pos) = nearest_free_tile(pos, walls, characters_grid) {
+                        debug!("nudged character from {pos:?} to {free_pos:?}");
+                        characters_grid.set(pos, false);
+                        characters_grid.set(free_pos, true);
+                        positions[i] = free_pos;
+                    }
+                }
+                continue 'next_char;
+            }
+        }
+        brain.update_stuckness(true);
+        characters_grid.set(pos, false);
+        characters_grid.set(new_pos, true);
+        positions[i] = new_pos;
+        moved[i] = true;
+    }
+}
+
+/// Uses up a character's oxygen for one oxygen tick. Inside the pressurized
+/// region, the oxygen comes from the base's life support while it lasts. Gas
+/// pockets drain the character's own tanks, pressurized or not.
+fn use_oxygen(
+    character: &mut CharacterStatus,
+    position: TilePosition,
+    on_gas_pocket: bool,
+    pressurized: Option<&BitGrid>,
+    base_oxygen: &mut u16,
+) {
+    if on_gas_pocket {
+        for _ in 0..GAS_POCKET_OXYGEN_DRAIN_MULTIPLIER {
+            character.deplete_oxygen();
+        }
+        return;
+    }
+    let drain = character.oxygen_depletion_amount as u16;
+    let is_pressurized =
+        pressurized.is_some_and(|region| region.get_checked(position) == Some(true));
+    if is_pressurized && *base_oxygen >= drain {
+        *base_oxygen -= drain;
+    } else {
+        character.deplete_oxygen();
+    }
+}
+
+/// Moves oxygen, one unit per pair, from characters to adjacent characters
+/// who are low on it, i.e. at or below their low oxygen threshold. Characters
+/// that have already run their tanks dry aren't given any, and characters
+/// low on oxygen themselves don't give any. The difference has to be at least
+/// [`CharacterStatus::OXYGEN_SHARING_THRESHOLD`] so that the amounts settle
+/// instead of bouncing back and forth.
+fn share_oxygen(characters: &mut [CharacterStatus], positions: &[TilePosition]) {
+    for giver in 0..characters.len() {
+        for receiver in 0..characters.len() {
+            if giver == receiver
+                || !positions[giver].can_interact(positions[receiver], InteractionKind::ShareOxygen)
+            {
+                continue;
+            }
+            let is_low =
+                |character: &CharacterStatus| character.oxygen <= character.low_oxygen_threshold;
+            if characters[receiver].oxygen == 0
+                || !is_low(&characters[receiver])
+                || is_low(&characters[giver])
+            {
+                continue;
+            }
+            let needed_oxygen = (characters[receiver].oxygen)
+                .saturating_add(CharacterStatus::OXYGEN_SHARING_THRESHOLD);
+            if characters[giver].oxygen >= needed_oxygen {
+                characters[giver].oxygen -= 1;
+                characters[receiver].oxygen += 1;
+                debug!(
+                    "brain #{} shared oxygen with brain #{}",
+                    characters[giver].brain_index, characters[receiver].brain_index,
+                );
+            }
+        }
+    }
+}
+
+fn for_each_character(
+    scene: &mut Scene,
+    mut f: impl FnMut(GameObjectHandle, &CharacterStatus, TilePosition),
+) {
+    scene.run_system(define_system!(
+        |handles, characters: &[CharacterStatus], positions: &[TilePosition]| {
+            for ((handle, character), position) in handles.zip(characters).zip(positions) {
+                f(handle, character, *position);
+            }
+        }
+    ));
+}
+
+/// Replaces the contents of `characters` with the characters in `scene`.
+fn collect_characters(
+    scene: &mut Scene,
+    characters: &mut ArrayVec<(GameObjectHandle, CharacterStatus, TilePosition), MAX_CHARACTERS>,
+) {
+    characters.clear();
+    for_each_character(scene, |handle, character, position| {
+        let pushed = characters.try_push((handle, *character, position));
+        debug_assert!(
+            pushed.is_ok(),
+            "there should be at most MAX_CHARACTERS characters"
+        );
+    });
+}
+
+/// Returns the character or job station on `tile` for [`Game::select_at`], and
+/// which kind it is. Characters are picked over job stations, since they can
+/// stand on them.
+fn pick_object_at(scene: &mut Scene, tile: TilePosition) -> Option<(GameObjectHandle, ObjectKind)> {
+    let mut picked = None;
+    for kind in [ObjectKind::Character, ObjectKind::JobStation] {
+        if picked.is_none() {
+            for_each_object_within(scene, tile, 0, kind, |handle, _| {
+                picked = Some((handle, kind));
+            });
+        }
+    }
+    picked
+}
+
+fn for_each_object_within(
+    scene: &mut Scene,
+    center: TilePosition,
+    radius: u16,
+    kind: ObjectKind,
+    mut f: impl FnMut(GameObjectHandle, TilePosition),
+) {
+    let mut visit = |handle, position: &TilePosition| {
+        if position.manhattan_distance(*center) <= radius {
+            f(handle, *position);
+        }
+    };
+    match kind {
+        ObjectKind::Character => {
+            scene.run_system(define_system!(
+                |handles, _characters: &[CharacterStatus], positions: &[TilePosition]| {
+                    for (handle, position) in handles.zip(positions) {
+                        visit(handle, position);
+                    }
+                }
+            ));
+        }
+        ObjectKind::JobStation => {
+            scene.run_system(define_system!(
+                |handles, _job_stations: &[JobStationStatus], positions: &[TilePosition]| {
+                    for (handle, position) in handles.zip(positions) {
+                        visit(handle, position);
+                    }
+                }
+            ));
+        }
+        ObjectKind::Resource => {
+            scene.run_system(define_system!(
+                |handles, _tags: &[StockpileReliantTag], positions: &[TilePosition]| {
+                    for (handle, position) in handles.zip(positions) {
+                        visit(handle, position);
+                    }
+                }
+            ));
+        }
+    }
+}
+
+/// Marks the tiles that can't be walked on into `walls`, and the characters'
+/// positions into `characters_grid`. Characters are only soft obstacles for
+/// pathfinding, so they're tracked separately from the walls.
+fn mark_obstacles(
+    scene: &mut Scene,
+    tile_walls: &BitGrid,
+    walls: &mut BitGrid,
+    characters_grid: &mut BitGrid,
+) {
+    walls.clone_from(tile_walls);
+    characters_grid.clear();
+    scene.run_system(define_system!(
+        |_, colliders: &[Collider], positions: &[TilePosition]| {
+            for (collider, pos) in colliders.iter().zip(positions) {
+                // Anything off the map can't get in anyone's way
+                if collider.is_not_walkable() {
+                    walls.set_checked(*pos, true);
+                }
+            }
+        }
+    ));
+    scene.run_system(define_system!(
+        |_, _characters: &[CharacterStatus], positions: &[TilePosition]| {
+            for pos in positions {
+                // The tile under a character might still be a wall
+                let tile_wall = tile_walls.get_checked(*pos).unwrap_or(false);
+                walls.set_checked(*pos, tile_wall);
+                characters_grid.set_checked(*pos, true);
+            }
+        }
+    ));
+}
+
+/// Returns true if the tile is on the map, and there's no wall, unwalkable
+/// object, or character on it.
+fn is_walkable(walls: &BitGrid, characters_grid: &BitGrid, pos: TilePosition) -> bool {
+    walls.get_checked(pos) == Some(false) && characters_grid.get_checked(pos) == Some(false)
+}
+
+/// The menu opened from another, or `None` if it'd be empty.
+fn submenu(
+    kind: MenuKind,
+    flip_confirm_cancel: bool,
+    brains: &Brains,
+    save_storage: Option<&dyn SaveStorage>,
+) -> Option<Menu> {
+    let menu = match kind {
+        MenuKind::Options => Menu::options(flip_confirm_cancel),
+        MenuKind::Build => Menu::build(),
+        MenuKind::ManageCharacters => Menu::manage_characters(brains.ids()),
+        MenuKind::SaveSlots | MenuKind::LoadSlots => {
+            let storage = save_storage?;
+            Menu::save_slots(kind == MenuKind::LoadSlots, |slot| {
+                storage.exists(&save_file_name(slot))
+            })
+        }
+    };
+    (menu.len() > 0).then_some(menu)
+}
+
+/// Returns the position of the character or job station, or `None` if it's
+/// not in the scene anymore.
+fn object_position(scene: &mut Scene, object: GameObjectHandle) -> Option<TilePosition> {
+    let mut object_position = None;
+    scene.run_system(define_system!(
+        |handles, positions: &[TilePosition], _colliders: &[Collider]| {
+            for (handle, position) in handles.zip(positions) {
+                if handle == object {
+                    object_position = Some(*position);
+                }
+            }
+        }
+    ));
+    object_position
+}
+
+/// Runs `update` on the settings of the job station at `station`, returning
+/// the station's updated status, or `None` if there's no job station there.
+fn update_job_station_settings(
+    scene: &mut Scene,
+    station: TilePosition,
+    update: impl FnOnce(&mut JobStationSettings),
+) -> Option<JobStationStatus> {
+    let mut update = Some(update);
+    let mut updated = None;
+    scene.run_system(define_system!(
+        |_, job_stations: &mut [JobStationStatus], positions: &[TilePosition]| {
+            for (job, position) in job_stations.iter_mut().zip(positions) {
+                if *position == station {
+                    if let Some(update) = update.take() {
+                        update(&mut job.settings);
+                        updated = Some(*job);
+                    }
+                }
+            }
+        }
+    ));
+    updated
+}
+
+/// Despawns the character using the given brain, leaving anything they were
+/// carrying on the floor, and removes the brain.
+fn fire_character(
+    scene: &mut Scene,
+    brains: &mut Brains,
+    brain: BrainId,
+    temp_arena: &LinearAllocator,
+) {
+    let mut fired = None;
+    for_each_character(scene, |handle, character, position| {
+        if character.brain_id() == brain {
+            fired = Some((handle, position));
+        }
+    });
+    let Some((handle, position)) = fired else {
+        return;
+    };
+
+    drop_held_resources(scene, brain.index, position);
+    let Some(mut handles) = FixedVec::new(temp_arena, 1) else {
+        debug_assert!(false, "not enough memory to fire a character");
+        return;
+    };
+    let _ = handles.push(handle);
+    if scene.delete(&mut handles).is_err() {
+        debug_assert!(false, "could not despawn the fired character");
+        return;
+    }
+    brains.remove(brain);
+}
+
+/// Returns true if a job station can be built at `pos`, i.e. the tile allows
+/// building and there's nothing with a collider on it.
+fn can_build_at(tilemap: &Tilemap, scene: &mut Scene, pos: TilePosition) -> bool {
+    if !tilemap
+        .tiles
+        .get(pos)
+        .is_some_and(|tile| !tile.blocks_build())
+    {
+        return false;
+    }
+    let mut occupied = false;
+    scene.run_system(define_system!(
+        |_, _colliders: &[Collider], positions: &[TilePosition]| {
+            occupied |= positions.contains(&pos);
+        }
+    ));
+    !occupied
+}
+
+/// Returns the handles of the loose stockpiles within [`BUILD_RESOURCE_RADIUS`]
+/// of `center`, which are what job stations are built out of.
+fn build_resources_near<'a>(
+    scene: &mut Scene,
+    center: TilePosition,
+    temp_arena: &'a LinearAllocator,
+) -> Option<FixedVec<'a, GameObjectHandle>> {
+    let mut nearby = FixedVec::new(temp_arena, MAX_RESOURCES)?;
+    let radius = BUILD_RESOURCE_RADIUS;
+    for_each_object_within(scene, center, radius, ObjectKind::Resource, |handle, _| {
+        let _ = nearby.push(handle);
+    });
+    Some(nearby)
+}
+
+/// Returns true if the non-reserved resources in the loose stockpiles within
+/// [`BUILD_RESOURCE_RADIUS`] of `center` add up to at least `cost`. Job
+/// stations' and characters' stockpiles aren't counted. Returns false if
+/// `temp_arena` doesn't have room to look for the resources.
+fn can_afford_build(
+    scene: &mut Scene,
+    cost: &[(ResourceVariant, u8)],
+    center: TilePosition,
+    temp_arena: &LinearAllocator,
+) -> bool {
+    let Some(nearby) = build_resources_near(scene, center, temp_arena) else {
+        debug_assert!(false, "not enough memory to look for build resources");
+        return false;
+    };
+    cost.iter().all(|(variant, amount)| {
+        let mut available = 0;
+        scene.run_system(define_system!(
+            |handles, _tags: &[StockpileReliantTag], stockpiles: &[Stockpile]| {
+                for (handle, stockpile) in handles.zip(stockpiles) {
+                    if nearby.contains(&handle) && stockpile.has_non_reserved_resources(*variant) {
+                        available += stockpile.get_resources(*variant).unwrap_or(0) as u16;
+                    }
+                }
+            }
+        ));
+        available >= *amount as u16
+    })
+}
+
+/// Takes `cost` out of the non-reserved resources in the loose stockpiles within
+/// [`BUILD_RESOURCE_RADIUS`] of `center`. Affordability should be checked with
+/// [`can_afford_build`] first, this takes what it can.
+fn pay_build_cost(
+    scene: &mut Scene,
+    cost: &[(ResourceVariant, u8)],
+    center: TilePosition,
+    temp_arena: &LinearAllocator,
+) {
+    let Some(nearby) = build_resources_near(scene, center, temp_arena) else {
+        debug_assert!(false, "not enough memory to look for build resources");
+        return;
+    };
+    for (variant, amount) in cost {
+        let mut left_to_pay = *amount;
+        scene.run_system(define_system!(
+            |handles, _tags: &[StockpileReliantTag], stockpiles: &mut [Stockpile]| {
+                for (handle, stockpile) in handles.zip(stockpiles) {
+                    if left_to_pay == 0 {
+                        break;
+                    }
+                    if nearby.contains(&handle) && stockpile.has_non_reserved_resources(*variant) {
+                        let available = stockpile.get_resources_mut(*variant).unwrap();
+                        let paid = left_to_pay.min(*available);
+                        *available -= paid;
+                        left_to_pay -= paid;
+                    }
+                }
+            }
+        ));
+        debug_assert_eq!(left_to_pay, 0, "could not afford {cost:?}");
+    }
+}
+
+/// Returns the camera position closest to the current one which has the build
+/// cursor in view.
+fn camera_following_cursor(camera: &Camera, cursor: TilePosition) -> Vec2 {
+    let half_size = camera.size / 2.;
+    let cursor_min = Vec2::new(cursor.x as f32, cursor.y as f32);
+    let cursor_max = cursor_min + Vec2::ONE;
+    (camera.position)
+        .max(cursor_max - half_size)
+        .min(cursor_min + half_size)
+}
+
+/// The size of the map in world units.
+fn map_size(tilemap: &Tilemap) -> Vec2 {
+    let (width, height) = tilemap.tiles.size();
+    Vec2::new(width as f32, height as f32)
+}
+
+/// Opens the main menu when pausing, and closes all menus when unpausing.
+fn set_pause_state(paused: &mut bool, menu: &mut Option<MenuMode>, new_paused: bool) {
+    if new_paused && !*paused {
+        let mut menus = ArrayVec::new();
+        menus.push(Menu::main_menu());
+        *menu = Some(MenuMode::MenuStack(menus));
+    } else if !new_paused {
+        *menu = None;
+    }
+    *paused = new_paused;
+}
+
+/// Opens, closes, and navigates the menu stack based on which buttons were
+/// `pressed`, and returns the command of the menu entry that was used. Build
+/// placement's cursor is left for the caller, since it needs the map.
+fn update_menu_stack(
+    menu: &mut Option<MenuMode>,
+    paused: &mut bool,
+    pressed: impl Fn(Button) -> bool,
+) -> Option<MenuCommand> {
+    if pressed(Button::OpenMenu) && !*paused {
+        set_pause_state(paused, menu, true);
+    }
+
+    if pressed(Button::Cancel) {
+        match menu.take() {
+            Some(MenuMode::MenuStack(mut menus)) => {
+                menus.pop();
+                if menus.is_empty() {
+                    *paused = false;
+                } else {
+                    *menu = Some(MenuMode::MenuStack(menus));
+                }
+            }
+            Some(MenuMode::BuildPlacement(placement)) => {
+                *menu = Some(MenuMode::MenuStack(placement.menus));
+            }
+            None => {}
+        }
+    }
+
+    let Some(MenuMode::MenuStack(menus)) = menu else {
+        return None;
+    };
+    let (entry, action) = menus.last_mut()?.update(pressed)?;
+    let menu_command = entry.command(action);
+    // The entry shows the current setting
+    match menu_command {
+        Some(MenuCommand::SetFlipAcceptCancel(flip)) => {
+            *entry = MenuEntry::FlipAcceptCancel(flip);
+        }
+        Some(MenuCommand::ConfirmFire(brain)) => {
+            *entry = MenuEntry::ManageCharacter {
+                brain,
+                confirming_fire: true,
+            };
+        }
+        Some(MenuCommand::SetStationEnabled { station, enabled }) => {
+            *entry = MenuEntry::StationEnabled { station, enabled };
+        }
+        Some(MenuCommand::SetHaulPriority { station, priority }) => {
+            *entry = MenuEntry::StationHaulPriority { station, priority };
+        }
+        Some(MenuCommand::SetInputTarget { target, .. }) => {
+            if let MenuEntry::StationInputTarget { target: shown, .. } = entry {
+                *shown = target;
+            }
+        }
+        _ => {}
+    }
+    menu_command
+}
+
+/// How many menus are open on top of each other, zero when no menu is shown.
+fn menu_depth(menu: &Option<MenuMode>) -> usize {
+    menu.as_ref().map_or(0, MenuMode::depth)
+}
+
+/// The hovered entry of the topmost open menu, if a menu is shown.
+fn menu_top_entry(menu: &Option<MenuMode>) -> Option<MenuEntry> {
+    menu.as_ref().and_then(MenuMode::top_entry)
+}
+
+/// Moves `next_tick_time` past the ticks due by `timestamp`, returning how
+/// many there were. Paused time is skipped over instead of piling up, so that
+/// unpausing doesn't start with a burst of ticks to catch up on. Generic over
+/// the time type to allow testing with [`Duration`]s.
+fn take_due_ticks<T>(next_tick_time: &mut T, timestamp: T, paused: bool) -> u64
+where
+    T: Copy + PartialOrd + Add<Duration, Output = T>,
+{
+    if paused {
+        if timestamp > *next_tick_time {
+            *next_tick_time = timestamp;
+        }
+        return 0;
+    }
+    let mut ticks = 0;
+    while timestamp >= *next_tick_time {
+        *next_tick_time = *next_tick_time + Duration::from_millis(MILLIS_PER_TICK);
+        ticks += 1;
+    }
+    ticks
+}
+
+/// Moves on to the next tick, unless paused. Returns false if paused.
+fn advance_tick(current_tick: &mut u64, paused: bool) -> bool {
+    if paused {
+        return false;
+    }
+    *current_tick += 1;
+    true
+}
+
+/// Hashes everything the simulation depends on. Sprites, the camera, and the
+/// menus don't affect the simulation, so they're left out.
+fn state_fingerprint(
+    tilemap: &Tilemap,
+    scene: &mut Scene,
+    brains: &Brains,
+    current_tick: GameTicks,
+    base_oxygen: u16,
+) -> u64 {
+    let mut hasher = SeaHasher::new();
+    hasher.write_u64(current_tick);
+    hasher.write_u16(base_oxygen);
+    tilemap.hash_state(&mut hasher);
+    scene.run_system(define_system!(|_, positions: &[TilePosition]| {
+        hasher.write(bytemuck::cast_slice(positions));
+    }));
+    scene.run_system(define_system!(|_, characters: &[CharacterStatus]| {
+        hasher.write(bytemuck::cast_slice(characters));
+    }));
+    scene.run_system(define_system!(|_, stockpiles: &[Stockpile]| {
+        hasher.write(bytemuck::cast_slice(stockpiles));
+    }));
+    scene.run_system(define_system!(|_, job_stations: &[JobStationStatus]| {
+        hasher.write(bytemuck::cast_slice(job_stations));
+    }));
+    scene.run_system(define_system!(|_, colliders: &[Collider]| {
+        hasher.write(bytemuck::cast_slice(colliders));
+    }));
+    brains.hash_state(&mut hasher);
+    hasher.finish()
+}
+
+/// The parts of [`Game`] that make up the simulation, and the camera position
+/// to show it from. Everything else is set up the same way whether the game
+/// is new or loaded.
+struct GameState {
+    tilemap: Tilemap<'static>,
+    scene: Scene<'static>,
+    brains: Brains,
+    start_position: TilePosition,
+    camera_position: Vec2,
+    current_tick: GameTicks,
+    base_oxygen: u16,
+}
+
+impl GameState {
+    /// Allocates an empty game state, to be filled in by [`GameState::load`].
+    fn new(arena: &'static LinearAllocator, temp_arena: &LinearAllocator) -> Option<GameState> {
+        Some(GameState {
+            tilemap: Tilemap::new_zeroed(arena)?,
+            scene: new_scene(arena, temp_arena),
+            brains: Brains::new(arena, MAX_CHARACTERS)?,
+            start_position: TilePosition::new(0, 0),
+            camera_position: Vec2::ZERO,
+            current_tick: 0,
+            base_oxygen: 0,
+        })
+    }
+
+    /// Loads a save over the state with [`load_state`].
+    fn load(&mut self, temp_arena: &LinearAllocator, reader: &mut SaveReader) -> Option<()> {
+        let ((current_tick, base_oxygen), (start_position, camera_position)) = load_state(
+            reader,
+            &mut self.tilemap,
+            &mut self.scene,
+            &mut self.brains,
+            temp_arena,
+        )?;
+        self.current_tick = current_tick;
+        self.base_oxygen = base_oxygen;
+        self.start_position = start_position;
+        self.camera_position = camera_position;
+        Some(())
+    }
+}
+
+fn new_scene(arena: &'static LinearAllocator, temp_arena: &LinearAllocator) -> Scene<'static> {
+    Scene::builder()
+        .with_game_object_type::<Character>(MAX_CHARACTERS)
+        .with_game_object_type::<JobStation>(MAX_JOB_STATIONS)
+        .with_game_object_type::<Resource>(MAX_RESOURCES)
+        .build(arena, temp_arena)
+        .unwrap()
+}
+
+/// Saves into the save slot's file in `storage`, with `save` writing the save
+/// into the buffer it's given, like [`Game::save`]. Returns false if the save
+/// couldn't be written.
+fn write_save_slot(
+    storage: &dyn SaveStorage,
+    slot: u8,
+    temp_arena: &LinearAllocator,
+    save: impl FnOnce(&mut [u8]) -> Option<usize>,
+) -> bool {
+    let Some(mut buffer) = FixedVec::new(temp_arena, SAVE_BUFFER_SIZE) else {
+        debug_assert!(false, "not enough memory for the save buffer");
+        return false;
+    };
+    buffer.fill_with_zeroes();
+    let Some(len) = save(&mut buffer) else {
+        debug_assert!(false, "the save buffer is too small");
+        return false;
+    };
+    let name = save_file_name(slot);
+    let written = storage.write(&name, &buffer[..len]);
+    debug!("saving into {name} (success: {written})");
+    written
+}
+
+/// Reads the save slot's file from `storage` into a buffer allocated from
+/// `temp_arena`, returning the buffer and the length of the save. Returns
+/// `None` if the slot is empty.
+fn read_save_slot<'a>(
+    storage: &dyn SaveStorage,
+    slot: u8,
+    temp_arena: &'a LinearAllocator,
+) -> Option<(FixedVec<'a, u8>, usize)> {
+    let Some(mut buffer) = FixedVec::new(temp_arena, SAVE_BUFFER_SIZE) else {
+        debug_assert!(false, "not enough memory for the save buffer");
+        return None;
+    };
+    buffer.fill_with_zeroes();
+    let len = storage.read(&save_file_name(slot), &mut buffer)?;
+    Some((buffer, len))
+}
+
+/// Writes the save header and everything in [`GameState`] into `writer`.
+fn save_state(
+    writer: &mut SaveWriter,
+    tilemap: &Tilemap,
+    scene: &mut Scene,
+    brains: &Brains,
+    (current_tick, base_oxygen): (GameTicks, u16),
+    (start_position, camera_position): (TilePosition, Vec2),
+) {
+    writer.write_bytes(&SAVE_MAGIC);
+    writer.write_u8(SAVE_VERSION);
+    writer.write_u64(current_tick);
+    writer.write_u16(base_oxygen);
+    writer.write_pod(&start_position);
+    writer.write_f32(camera_position.x);
+    writer.write_f32(camera_position.y);
+    tilemap.save(writer);
+    brains.save(writer);
+
+    // Each game object type is written as a count followed by the objects
+    let mut count = 0;
+    scene.run_system(define_system!(|_, characters: &[CharacterStatus]| {
+        count += characters.len();
+    }));
+    writer.write_u16(count as u16);
+    scene.run_system(define_system!(
+        |_,
+         characters: &[CharacterStatus],
+         positions: &[TilePosition],
+         held: &[Stockpile],
+         colliders: &[Collider]| {
+            for (((character, position), held), collider) in
+                characters.iter().zip(positions).zip(held).zip(colliders)
+            {
+                writer.write_pod(character);
+                writer.write_pod(position);
+                writer.write_pod(held);
+                writer.write_pod(collider);
+            }
+        }
+    ));
+
+    let mut count = 0;
+    scene.run_system(define_system!(|_, job_stations: &[JobStationStatus]| {
+        count += job_stations.len();
+    }));
+    writer.write_u16(count as u16);
+    scene.run_system(define_system!(
+        |_,
+         job_stations: &[JobStationStatus],
+         positions: &[TilePosition],
+         stockpiles: &[Stockpile],
+         colliders: &[Collider]| {
+            for (((job_station, position), stockpile), collider) in job_stations
+                .iter()
+                .zip(positions)
+                .zip(stockpiles)
+                .zip(colliders)
+            {
+                writer.write_pod(job_station);
+                writer.write_pod(position);
+                writer.write_pod(stockpile);
+                writer.write_pod(collider);
+            }
+        }
+    ));
+
+    let mut count = 0;
+    scene.run_system(define_system!(|_, tags: &[StockpileReliantTag]| {
+        count += tags.len();
+    }));
+    writer.write_u16(count as u16);
+    scene.run_system(define_system!(
+        |_, _tags: &[StockpileReliantTag], positions: &[TilePosition], stockpiles: &[Stockpile]| {
+            for (position, stockpile) in positions.iter().zip(stockpiles) {
+                writer.write_pod(position);
+                writer.write_pod(stockpile);
+            }
+        }
+    ));
+}
+
+/// Reads a save written by [`save_state`] over the tilemap, scene, and brains,
+/// reusing their storage, and returns the rest of the state grouped like
+/// [`save_state`] takes it. Returns `None` if the save is from another
+/// version, cut short, has more game objects than fit in the scene, has
+/// characters without a brain, or has positions outside the tilemap, in which
+/// case the parts may have been partially overwritten.
+fn load_state(
+    reader: &mut SaveReader,
+    tilemap: &mut Tilemap,
+    scene: &mut Scene,
+    brains: &mut Brains,
+    temp_arena: &LinearAllocator,
+) -> Option<((GameTicks, u16), (TilePosition, Vec2))> {
+    if reader.read_bytes(SAVE_MAGIC.len())? != SAVE_MAGIC {
+        debug!("not a save file");
+        return None;
+    }
+    let version = reader.read_u8()?;
+    if version != SAVE_VERSION {
+        debug!("save is from version {version}, only {SAVE_VERSION} is supported");
+        return None;
+    }
+    let current_tick = reader.read_u64()?;
+    let base_oxygen = reader.read_u16()?;
+    let start_position = reader.read_pod()?;
+    let camera_position = Vec2::new(reader.read_f32()?, reader.read_f32()?);
+    tilemap.load(reader)?;
+    brains.load(reader)?;
+
+    // The rest of the game indexes grids with these without checking
+    let in_bounds = |position: TilePosition| {
+        let inside = tilemap.tiles.get(position).is_some();
+        if !inside {
+            debug!("save has a position outside the map: {position:?}");
+        }
+        inside
+    };
+    if !in_bounds(start_position) {
+        return None;
+    }
+
+    despawn_everything(scene, temp_arena)?;
+    for _ in 0..reader.read_u16()? {
+        let character = Character {
+            status: reader.read_pod()?,
+            position: reader.read_pod()?,
+            held: reader.read_pod()?,
+            collider: reader.read_pod()?,
+        };
+        if brains.get(character.status.brain_id()).is_none() {
+            debug!("save has a character without a brain");
+            return None;
+        }
+        if !in_bounds(character.position) {
+            return None;
+        }
+        scene.spawn(character).ok()?;
+    }
+    for _ in 0..reader.read_u16()? {
+        let job_station = JobStation {
+            status: reader.read_pod()?,
+            position: reader.read_pod()?,
+            stockpile: reader.read_pod()?,
+            collider: reader.read_pod()?,
+        };
+        if !in_bounds(job_station.position) {
+            return None;
+        }
+        scene.spawn(job_station).ok()?;
+    }
+    for _ in 0..reader.read_u16()? {
+        let resource = Resource {
+            position: reader.read_pod()?,
+            stockpile: reader.read_pod()?,
+            stockpile_reliant: StockpileReliantTag {},
+        };
+        if !in_bounds(resource.position) {
+            return None;
+        }
+        scene.spawn(resource).ok()?;
+    }
+
+    if !reader.is_empty() {
+        debug!("save has extra data at the end");
+        return None;
+    }
+
+    Some((
+        (current_tick, base_oxygen),
+        (start_position, camera_position),
+    ))
+}
+
+/// Deletes every game object in the scene. Returns `None` if there wasn't
+/// enough memory to collect them all.
+fn despawn_everything(scene: &mut Scene, temp_arena: &LinearAllocator) -> Option<()> {
+    let capacity = MAX_CHARACTERS + MAX_JOB_STATIONS + MAX_RESOURCES;
+    let mut all_handles = FixedVec::<GameObjectHandle>::new(temp_arena, capacity)?;
+    scene.run_system(define_system!(|handles, _positions: &[TilePosition]| {
+        for handle in handles {
+            let _ = all_handles.push(handle);
+        }
+    }));
+    scene.delete(&mut all_handles).ok()
+}
+
+/// Picks which of the music clips to play next, if there are any. Based on the
+/// game tick instead of the time, so that replays play the same music.
+fn pick_music_clip(current_tick: u64, clip_count: usize) -> Option<usize> {
+    if clip_count == 0 {
+        return None;
+    }
+    Some(seahash::hash(&current_tick.to_le_bytes()) as usize % clip_count)
+}
+
+/// Returns an offset for drawing the `nth_pile` on the tile, so that multiple
+/// piles on the same tile don't get drawn exactly on top of each other.
+fn pile_offset(tile_pos: TilePosition, nth_pile: u8) -> Vec2 {
+    if nth_pile == 0 {
+        return Vec2::ZERO;
+    }
+    let mut hashed_bytes = ArrayVec::<u8, 5>::new();
+    hashed_bytes.extend(tile_pos.x.to_le_bytes());
+    hashed_bytes.extend(tile_pos.y.to_le_bytes());
+    hashed_bytes.push(nth_pile);
+    let hash = seahash::hash(&hashed_bytes);
+    let x = (hash & 0xFF) as f32 / 0xFF as f32;
+    let y = ((hash >> 8) & 0xFF) as f32 / 0xFF as f32;
+    (Vec2::new(x, y) - 0.5) * 0.3
+}
+
+#[allow(clippy::too_many_arguments)]
+fn draw_stockpile(
+    resources: &ResourceDatabase,
+    resource_loader: &mut ResourceLoader,
+    draw_queue: &mut DrawQueue,
+    layer: DrawLayer,
+    sprites: &[SpriteHandle],
+    camera: &Camera,
+    tile_pos: &TilePosition,
+    pile_offset: Vec2,
+    stockpile: &Stockpile,
+) {
+    for i in 0..stockpile.variant_count as usize {
+        let stockpile_pos = [
+            Vec2::new(0.3, 0.75),
+            Vec2::new(0.6, 0.5),
+            Vec2::new(0.2, 0.25),
+        ][i];
+        for j in 0..stockpile.amounts[i].min(STOCKPILE_VISUALIZED_COUNT) as usize {
+            let individual_offset = [
+                Vec2::new(-0.1, -0.07),
+                Vec2::new(0.1, 0.02),
+                Vec2::new(0.0, -0.08),
+                Vec2::new(-0.05, 0.02),
+                Vec2::new(0.05, -0.03),
+            ][j];
+            let off = stockpile_pos + individual_offset + pile_offset;
+            let dst = camera.to_output(Rect::xywh(
+                tile_pos.x as f32 + off.x,
+                tile_pos.y as f32 + off.y,
+                1. / 4.,
+                1. / 4.,
+            ));
+            let sprite = stockpile.variants[i]
+                .sprite()
+                .unwrap_or(Sprite::Placeholder);
+            let sprite = resources.get_sprite(sprites[sprite as usize]);
+            let draw_success = sprite.draw(
+                dst,
+                layer as u8 + j as u8,
+                draw_queue,
+                resources,
+                resource_loader,
+            );
+            debug_assert!(draw_success);
+
+            // Drawn on the layer reserved after the items, to be on top of
+            // all of them
+            if let Some(overlay) = stockpile_slot_overlay(stockpile, i) {
+                let overlay = resources.get_sprite(sprites[overlay as usize]);
+                let draw_success = overlay.draw(
+                    dst,
+                    layer as u8 + STOCKPILE_VISUALIZED_COUNT,
+                    draw_queue,
+                    resources,
+                    resource_loader,
+                );
+                debug_assert!(draw_success);
+            }
+        }
+    }
+}
+
+/// Returns the sprite drawn over the items in the stockpile's slot, to tell
+/// apart the resources committed to a haul or a job from the free ones.
+fn stockpile_slot_overlay(stockpile: &Stockpile, slot: usize) -> Option<Sprite> {
+    if stockpile.is_slot_reserved(slot) {
+        Some(Sprite::ReservedMarker)
+    } else {
+        None
+    }
+}
+
+fn draw_counter<'a>(
+    ui_camera: &Camera,
+    resources: &'a ResourceDatabase,
+    number_sprites: &[SpriteHandle],
+    count: u8,
+    x: f32,
+    y: f32,
+) -> impl Iterator<Item = (DrawLayer, &'a SpriteAsset, Rect)> {
+    (0..count.div_ceil(5)).map(move |oxygen_i| {
+        let count = (count - oxygen_i * 5).min(5) - 1;
+        let number_sprite = resources.get_sprite(number_sprites[count as usize]);
+        (
+            DrawLayer::PassInformation,
+            number_sprite,
+            ui_camera.to_output(Rect::xywh(x + 0.4 * oxygen_i as f32, y, 0.4, 0.3)),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use core::{cell::RefCell, hash::Hasher, time::Duration};
+
+    use arrayvec::{ArrayString, ArrayVec};
+    use bytemuck::Zeroable;
+
+    use engine::{
+        allocators::LinearAllocator, collections::FixedVec, define_system, game_objects::Scene,
+        static_allocator,
+    };
+    use glam::Vec2;
+    use seahash::SeaHasher;
+
+    use crate::{
+        brain::{
+            Brain, BrainId, Brains, Goal, Occupation, STUCK_MOVE_TICKS_BEFORE_NUDGE,
+            STUCK_MOVE_TICKS_BEFORE_REPLAN,
+        },
+        camera::{Camera, CameraShake},
+        game_object::{
+            Character, CharacterStatus, Collider, JobStation, JobStationSettings, JobStationStatus,
+            JobStationVariant, ObjectKind, Resource, ResourceVariant, Stockpile,
+            StockpileReliantTag, TilePosition,
+        },
+        grid::{BitGrid, Grid},
+        menu::{MenuCommand, MenuEntry, MenuMode},
+        notifications::NotificationSet,
+        pathfinding::{Direction, Path, flood_fill},
+        resource_index::ResourceIndex,
+        save::{SAVE_MAGIC, SAVE_VERSION, SaveReader, SaveStorage, SaveWriter},
+        tilemap::{TerrainParams, Tile, Tilemap},
+    };
+
+    use super::{
+        Button, FPS_AVERAGED_FRAMES, FrameTimes, GAS_POCKET_OXYGEN_DRAIN_MULTIPLIER, GameConfig,
+        GameState, MAX_CHARACTERS, MILLIS_PER_TICK, REFILL_STATION_CAPACITY,
+        REFILL_STATION_PULL_AMOUNT, Sprite, VENT_MAGMA_CAP, advance_tick, camera_following_cursor,
+        can_afford_build, can_build_at, character_start_positions, find_seafloor_in_start_area,
+        fire_character, for_each_character, for_each_object_within, is_walkable, mark_obstacles,
+        menu_depth, menu_top_entry, move_characters, new_scene, pay_build_cost, pick_music_clip,
+        pick_object_at, pile_offset, plan_initial_magma, plan_start_area, prepare_start_area,
+        pull_oxygen_into_refill_stations, read_save_slot, save_state, set_pause_state,
+        share_oxygen, spawn_vent_magma, spawn_vent_magma_piles, state_fingerprint,
+        stockpile_slot_overlay, submenu, take_due_ticks, update_menu_stack, use_oxygen,
+        work_at_job_station, write_save_slot,
+    };
+
+    /// A full character and one that's nearly out of oxygen, side by side.
+    fn oxygen_sharing_pair(low_oxygen: u8) -> [CharacterStatus; 2] {
+        let mut characters = [CharacterStatus::zeroed(); 2];
+        for character in &mut characters {
+            character.low_oxygen_threshold = CharacterStatus::LOW_OXYGEN_THRESHOLD;
+        }
+        characters[0].oxygen = CharacterStatus::MAX_OXYGEN;
+        characters[1].oxygen = low_oxygen;
+        characters[1].brain_index = 1;
+        characters
+    }
+
+    #[test]
+    fn adjacent_characters_equalize_oxygen() {
+        let mut characters = oxygen_sharing_pair(1);
+        let positions = [TilePosition::new(3, 3), TilePosition::new(4, 3)];
+
+        let mut previous_gap = CharacterStatus::MAX_OXYGEN;
+        for _ in 0..CharacterStatus::MAX_OXYGEN {
+            share_oxygen(&mut characters, &positions);
+            let gap = characters[0].oxygen.abs_diff(characters[1].oxygen);
+            assert!(gap <= previous_gap, "sharing should not oscillate");
+            previous_gap = gap;
+        }
+
+        assert_eq!(
+            characters[0].oxygen + characters[1].oxygen,
+            CharacterStatus::MAX_OXYGEN + 1
+        );
+        assert!(characters[0].oxygen >= characters[1].oxygen);
+        // Sharing stops once the receiver is no longer low on oxygen
+        assert_eq!(
+            characters[1].oxygen,
+            CharacterStatus::LOW_OXYGEN_THRESHOLD + 1
+        );
+    }
+
+    #[test]
+    fn only_characters_low_on_oxygen_are_shared_with() {
+        let positions = [TilePosition::new(3, 3), TilePosition::new(4, 3)];
+        let mut characters = oxygen_sharing_pair(CharacterStatus::LOW_OXYGEN_THRESHOLD + 1);
+        share_oxygen(&mut characters, &positions);
+        assert_eq!(characters[0].oxygen, CharacterStatus::MAX_OXYGEN);
+        assert_eq!(
+            characters[1].oxygen,
+            CharacterStatus::LOW_OXYGEN_THRESHOLD + 1
+        );
+    }
+
+    #[test]
+    fn characters_out_of_oxygen_are_not_revived() {
+        let positions = [TilePosition::new(3, 3), TilePosition::new(4, 3)];
+        let mut characters = oxygen_sharing_pair(0);
+        share_oxygen(&mut characters, &positions);
+        assert_eq!(characters[0].oxygen, CharacterStatus::MAX_OXYGEN);
+        assert_eq!(characters[1].oxygen, 0);
+    }
+
+    #[test]
+    fn distant_characters_do_not_share_oxygen() {
+        let mut characters = oxygen_sharing_pair(1);
+        let positions = [TilePosition::new(3, 3), TilePosition::new(5, 3)];
+        share_oxygen(&mut characters, &positions);
+        assert_eq!(characters[0].oxygen, CharacterStatus::MAX_OXYGEN);
+        assert_eq!(characters[1].oxygen, 1);
+    }
+
+    #[test]
+    fn pressurized_characters_breathe_from_the_base() {
+        static ARENA: &LinearAllocator = static_allocator!(1000);
+        let mut pressurized = BitGrid::new(ARENA, (4, 4)).unwrap();
+        pressurized.set(TilePosition::new(1, 1), true);
+
+        let mut character = CharacterStatus::zeroed();
+        character.oxygen = CharacterStatus::MAX_OXYGEN;
+        character.oxygen_depletion_amount = CharacterStatus::BASE_OXYGEN_DEPLETION_AMOUNT;
+        let drain = CharacterStatus::BASE_OXYGEN_DEPLETION_AMOUNT as u16;
+        let mut base_oxygen = 10;
+
+        use_oxygen(
+            &mut character,
+            TilePosition::new(1, 1),
+            false,
+            Some(&pressurized),
+            &mut base_oxygen,
+        );
+        assert_eq!(character.oxygen, CharacterStatus::MAX_OXYGEN);
+        assert_eq!(base_oxygen, 10 - drain);
+
+        use_oxygen(
+            &mut character,
+            TilePosition::new(2, 1),
+            false,
+            Some(&pressurized),
+            &mut base_oxygen,
+        );
+        assert_eq!(character.oxygen, CharacterStatus::MAX_OXYGEN - drain as u8);
+        assert_eq!(base_oxygen, 10 - drain);
+    }
+
+    #[test]
+    fn gas_pockets_drain_oxygen_faster() {
+        let mut character = CharacterStatus::zeroed();
+        character.oxygen = CharacterStatus::MAX_OXYGEN;
+        character.oxygen_depletion_amount = CharacterStatus::BASE_OXYGEN_DEPLETION_AMOUNT;
+        let mut gassed = character;
+        let mut base_oxygen = 10;
+
+        let position = TilePosition::new(1, 1);
+        use_oxygen(&mut character, position, false, None, &mut base_oxygen);
+        use_oxygen(&mut gassed, position, true, None, &mut base_oxygen);
+        let used = |status: &CharacterStatus| CharacterStatus::MAX_OXYGEN - status.oxygen;
+        assert!(used(&gassed) > used(&character));
+        assert_eq!(
+            used(&gassed),
+            used(&character) * GAS_POCKET_OXYGEN_DRAIN_MULTIPLIER
+        );
+        assert_eq!(base_oxygen, 10, "gas pockets don't touch the base's oxygen");
+    }
+
+    #[test]
+    fn boxed_in_characters_replan_and_get_nudged_out() {
+        // The map (the character is @, others are C, . is walkable):
+        // . C .
+        // C @ C
+        // . C .
+        static ARENA: &LinearAllocator = static_allocator!(1000);
+        let walls = BitGrid::new(ARENA, (3, 3)).unwrap();
+        let mut characters_grid = BitGrid::new(ARENA, (3, 3)).unwrap();
+        for (x, y) in [(1, 0), (0, 1), (2, 1), (1, 2), (1, 1)] {
+            characters_grid.set(TilePosition::new(x, y), true);
+        }
+
+        let from = TilePosition::new(1, 1);
+        let mut path = Path::default();
+        path.add_step(Direction::Right);
+        let mut brains = [Brain::new()];
+        brains[0].goal_stack.push(Goal::FollowPath { from, path });
+        let characters = [CharacterStatus::zeroed()];
+        let mut positions = [from];
+
+        for _ in 0..STUCK_MOVE_TICKS_BEFORE_REPLAN {
+            move_characters(
+                &mut brains,
+                &characters,
+                &mut positions,
+                &walls,
+                &mut characters_grid,
+            );
+        }
+        assert!(brains[0].goal_stack.is_empty(), "goals should be cleared");
+        assert_eq!(positions[0], from);
+
+        // Still stuck after re-planning
+        let mut path = Path::default();
+        path.add_step(Direction::Right);
+        brains[0].goal_stack.push(Goal::FollowPath { from, path });
+        for _ in STUCK_MOVE_TICKS_BEFORE_REPLAN..STUCK_MOVE_TICKS_BEFORE_NUDGE {
+            move_characters(
+                &mut brains,
+                &characters,
+                &mut positions,
+                &walls,
+                &mut characters_grid,
+            );
+        }
+        assert_ne!(positions[0], from, "should have been nudged out");
+        assert!(!walls.get(positions[0]));
+        assert!(characters_grid.get(positions[0]));
+        assert!(!characters_grid.get(from));
+    }
+
+    #[test]
+    fn characters_pushing_against_the_map_edge_back_up_inside_it() {
+        // The map (the character is @, walls are #, . is walkable), with the
+        // character trying to walk up, off the map:
+        // @ # .
+        // . . .
+        static ARENA: &LinearAllocator = static_allocator!(1000);
+        let mut walls = BitGrid::new(ARENA, (3, 2)).unwrap();
+        walls.set(TilePosition::new(1, 0), true);
+        let mut characters_grid = BitGrid::new(ARENA, (3, 2)).unwrap();
+        let from = TilePosition::new(0, 0);
+        characters_grid.set(from, true);
+
+        let mut path = Path::default();
+        path.add_step(Direction::Up);
+        let mut brains = [Brain::new()];
+        brains[0].goal_stack.push(Goal::FollowPath { from, path });
+        let characters = [CharacterStatus::zeroed()];
+        let mut positions = [from];
+
+        move_characters(
+            &mut brains,
+            &characters,
+            &mut positions,
+            &walls,
+            &mut characters_grid,
+        );
+        assert_eq!(positions[0], TilePosition::new(0, 1));
+        assert!(characters_grid.get(positions[0]));
+    }
+
+    #[test]
+    fn characters_walking_into_each_other_swap_places() {
+        // The map (the characters are A and B, . is walkable):
+        // # # # #
+        // . A B .
+        // # # # #
+        static ARENA: &LinearAllocator = static_allocator!(1000);
+        let mut walls = BitGrid::new(ARENA, (4, 3)).unwrap();
+        for x in 0..4 {
+            walls.set(TilePosition::new(x, 0), true);
+            walls.set(TilePosition::new(x, 2), true);
+        }
+        let mut characters_grid = BitGrid::new(ARENA, (4, 3)).unwrap();
+        let a = TilePosition::new(1, 1);
+        let b = TilePosition::new(2, 1);
+        characters_grid.set(a, true);
+        characters_grid.set(b, true);
+
+        let mut brains = [Brain::new(), Brain::new()];
+        for (brain, (from, dir)) in brains
+            .iter_mut()
+            .zip([(a, Direction::Right), (b, Direction::Left)])
+        {
+            let mut path = Path::default();
+            path.add_step(dir);
+            path.add_step(dir);
+            brain.goal_stack.push(Goal::FollowPath { from, path });
+        }
+        let mut characters = [CharacterStatus::zeroed(); 2];
+        characters[1].brain_index = 1;
+        let mut positions = [a, b];
+
+        move_characters(
+            &mut brains,
+            &characters,
+            &mut positions,
+            &walls,
+            &mut characters_grid,
+        );
+        assert_eq!(positions, [b, a]);
+        assert!(characters_grid.get(a) && characters_grid.get(b));
+    }
+
+    #[test]
+    fn movement_does_not_depend_on_scene_order() {
+        // The map (the characters are A and B, . is walkable):
+        // # # # # #
+        // . A . B .
+        // # # # # #
+        static ARENA: &LinearAllocator = static_allocator!(1000);
+        let mut walls = BitGrid::new(ARENA, (5, 3)).unwrap();
+        for x in 0..5 {
+            walls.set(TilePosition::new(x, 0), true);
+            walls.set(TilePosition::new(x, 2), true);
+        }
+        let a = TilePosition::new(1, 1);
+        let b = TilePosition::new(3, 1);
+
+        let run = |scene_order: [usize; 2]| {
+            let mut characters_grid = BitGrid::new(ARENA, (5, 3)).unwrap();
+            characters_grid.set(a, true);
+            characters_grid.set(b, true);
+            let mut brains = [Brain::new(), Brain::new()];
+            for (brain, (from, dir)) in brains
+                .iter_mut()
+                .zip([(a, Direction::Right), (b, Direction::Left)])
+            {
+                let mut path = Path::default();
+                path.add_step(dir);
+                brain.goal_stack.push(Goal::FollowPath { from, path });
+            }
+
+            let mut characters = [CharacterStatus::zeroed(); 2];
+            let mut positions = [a; 2];
+            for (i, brain_index) in scene_order.into_iter().enumerate() {
+                characters[i].brain_index = brain_index as u8;
+                positions[i] = [a, b][brain_index];
+            }
+            move_characters(
+                &mut brains,
+                &characters,
+                &mut positions,
+                &walls,
+                &mut characters_grid,
+            );
+
+            let mut positions_by_brain = [a; 2];
+            for (character, pos) in characters.iter().zip(positions) {
+                positions_by_brain[character.brain_index as usize] = pos;
+            }
+            positions_by_brain
+        };
+
+        assert_eq!(run([0, 1]), run([1, 0]));
+    }
+
+    #[test]
+    fn piles_on_the_same_tile_are_drawn_apart() {
+        let pos = TilePosition::new(5, 7);
+        let first = pile_offset(pos, 0);
+        let second = pile_offset(pos, 1);
+        let third = pile_offset(pos, 2);
+        assert_ne!(first, second);
+        assert_ne!(second, third);
+        assert_ne!(first, third);
+        assert_eq!(second, pile_offset(pos, 1), "offsets should be stable");
+    }
+
+    #[test]
+    fn start_area_matches_the_configured_size_and_fits_the_map() {
+        let map_size = (20, 20);
+        for (start_pos, size) in [
+            (TilePosition::new(10, 10), 8),
+            (TilePosition::new(0, 0), 6),
+            (TilePosition::new(19, 19), 12),
+            (TilePosition::new(3, 17), 40),
+        ] {
+            let area = plan_start_area(start_pos, size, map_size);
+            let expected_size = (size as i16).min(20);
+            assert_eq!(area.bottom_right.x - area.top_left.x, expected_size);
+            assert_eq!(area.bottom_right.y - area.top_left.y, expected_size);
+            assert!(area.top_left.x >= 0 && area.top_left.y >= 0);
+            assert!(area.bottom_right.x <= 20 && area.bottom_right.y <= 20);
+            for pos in [
+                area.center,
+                area.vent,
+                area.energy_generator,
+                area.oxygen_generator,
+            ] {
+                assert!(
+                    (area.top_left.x..area.bottom_right.x).contains(&pos.x)
+                        && (area.top_left.y..area.bottom_right.y).contains(&pos.y),
+                    "{pos:?} is outside the start area"
+                );
+            }
+        }
+
+        let area = plan_start_area(TilePosition::new(10, 10), 8, map_size);
+        assert_eq!(area.vent, TilePosition::new(9, 8));
+        assert_eq!(area.energy_generator, TilePosition::new(6, 12));
+        assert_eq!(area.oxygen_generator, TilePosition::new(10, 6));
+    }
+
+    #[test]
+    fn starting_stations_are_placed_on_seafloor() {
+        static ARENA: &LinearAllocator = static_allocator!(1000);
+        let mut tiles: Grid<Tile> = Grid::new_zeroed(ARENA, (12, 12)).unwrap();
+        let area = plan_start_area(TilePosition::new(6, 6), 8, tiles.size());
+        // Block the preferred spots with a wall, a vent, and a character
+        tiles[area.energy_generator] = Tile::Wall;
+        tiles[area.oxygen_generator] = Tile::GeothermalVent;
+        let taken = [area.oxygen_generator + Direction::Down];
+
+        for preferred in [area.energy_generator, area.oxygen_generator] {
+            let pos = find_seafloor_in_start_area(&tiles, &area, preferred, &taken).unwrap();
+            assert!(matches!(tiles[pos], Tile::Seafloor));
+            assert!(!taken.contains(&pos));
+            assert!((area.top_left.x..area.bottom_right.x).contains(&pos.x));
+            assert!((area.top_left.y..area.bottom_right.y).contains(&pos.y));
+            assert_eq!(pos.manhattan_distance(*preferred), 1);
+        }
+    }
+
+    #[test]
+    fn every_character_spawns_on_a_walkable_tile_in_the_smallest_start_area() {
+        static ARENA: &LinearAllocator = static_allocator!(1_000_000);
+        let mut tilemap = Tilemap::generate(ARENA, 1, &TerrainParams::default()).unwrap();
+        let area = prepare_start_area(&mut tilemap, 4, |_, _| {});
+        let positions = character_start_positions(&tilemap.tiles, &area, MAX_CHARACTERS);
+        assert_eq!(positions.len(), MAX_CHARACTERS);
+        for (i, pos) in positions.iter().enumerate() {
+            assert!(tilemap.tiles[*pos].is_walkable(), "{pos:?} isn't walkable");
+            assert!((area.top_left.x..area.bottom_right.x).contains(&pos.x));
+            assert!((area.top_left.y..area.bottom_right.y).contains(&pos.y));
+            assert!(!positions[..i].contains(pos), "two characters at {pos:?}");
+        }
+    }
+
+    #[test]
+    fn fps_is_averaged_over_the_latest_frames() {
+        let mut frame_times = FrameTimes::new();
+        assert_eq!(frame_times.average_fps(), None);
+
+        for dt in [1. / 60., 1. / 30.] {
+            frame_times.record(dt);
+        }
+        let fps = frame_times.average_fps().unwrap();
+        assert!((fps - 40.).abs() < 0.01, "{fps} should be 40");
+
+        // A slow start is forgotten once there's enough new frames
+        for _ in 0..FPS_AVERAGED_FRAMES {
+            frame_times.record(1. / 120.);
+        }
+        let fps = frame_times.average_fps().unwrap();
+        assert!((fps - 120.).abs() < 0.01, "{fps} should be 120");
+
+        frame_times.record(1. / 60.);
+        let expected =
+            FPS_AVERAGED_FRAMES as f32 / ((FPS_AVERAGED_FRAMES - 1) as f32 / 120. + 1. / 60.);
+        let fps = frame_times.average_fps().unwrap();
+        assert!((fps - expected).abs() < 0.01, "{fps} should be {expected}");
+    }
+
+    /// A base played out from the start of a game, without the player doing
+    /// anything.
+    struct SimulatedBase {
+        tilemap: Tilemap<'static>,
+        scene: Scene<'static>,
+        brains: Brains,
+        start_area: StartArea,
+    }
+
+    /// Sets up a base on the map generated from `seed`, with a few haulers
+    /// and an energy generator, and lets the haulers think and move for
+    /// `ticks` ticks.
+    fn simulate_base(arena: &'static LinearAllocator, seed: u64, ticks: u64) -> SimulatedBase {
+        let mut tilemap = Tilemap::generate(arena, seed, &TerrainParams::default()).unwrap();
+        let mut scene = new_scene(arena, arena);
+        let start_area = prepare_start_area(&mut tilemap, 8, |pos, resource| {
+            let spawned = scene.spawn(Resource {
+                position: pos,
+                stockpile: Stockpile::zeroed().with_resource(resource, 3, false),
+                stockpile_reliant: StockpileReliantTag {},
+            });
+            assert!(spawned.is_ok());
+        });
+        let mut walls = BitGrid::new(arena, tilemap.tiles.size()).unwrap();
+        tilemap.mark_walls(&mut walls);
+        let mut characters_grid = BitGrid::new(arena, tilemap.tiles.size()).unwrap();
+
+        let mut brains = Brains::new(arena, MAX_CHARACTERS).unwrap();
+        let positions = character_start_positions(&tilemap.tiles, &start_area, 3);
+        for (i, position) in positions.into_iter().enumerate() {
+            let id = brains.add(Brain::new()).unwrap();
+            brains[id].wait_ticks = 2 + i as u64;
+            brains[id].job = Occupation::Hauler;
+            brains[id].sleep_spot = Some(position);
+            characters_grid.set(position, true);
+            let spawned = scene.spawn(Character {
+                status: CharacterStatus {
+                    brain_index: id.index,
+                    brain_generation: id.generation,
+                    oxygen: CharacterStatus::MAX_OXYGEN,
+                    morale: CharacterStatus::MAX_MORALE,
+                    food: CharacterStatus::MAX_FOOD,
+                    ..CharacterStatus::zeroed()
+                },
+                position,
+                held: Stockpile::zeroed(),
+                collider: Collider::NOT_WALKABLE,
+            });
+            assert!(spawned.is_ok());
+        }
+        let spawned = scene.spawn(JobStation::new(
+            JobStationVariant::ENERGY_GENERATOR,
+            start_area.energy_generator,
+        ));
+        assert!(spawned.is_ok());
+
+        let mut haul_notifications = NotificationSet::new(arena, 4).unwrap();
+        let mut resource_index = ResourceIndex::new(arena, tilemap.tiles.size()).unwrap();
+        let mut temp_arena = LinearAllocator::new(arena, 256 * 1024).unwrap();
+        for current_tick in 1..=ticks {
+            let mut thinkers = ArrayVec::<_, MAX_CHARACTERS>::new();
+            for_each_character(&mut scene, |_, character, position| {
+                thinkers.push((character.brain_id(), position));
+            });
+            resource_index.invalidate();
+            for (brain_id, position) in thinkers {
+                brains[brain_id].update_goals(
+                    (brain_id.index, position, current_tick),
+                    &mut scene,
+                    &mut haul_notifications,
+                    &mut resource_index,
+                    &walls,
+                    &characters_grid,
+                    None,
+                    &mut temp_arena,
+                );
+                temp_arena.reset();
+            }
+            scene.run_system(define_system!(
+                |_, characters: &[CharacterStatus], positions: &mut [TilePosition]| {
+                    move_characters(
+                        brains.slots_mut(),
+                        characters,
+                        positions,
+                        &walls,
+                        &mut characters_grid,
+                    );
+                }
+            ));
+        }
+
+        SimulatedBase {
+            tilemap,
+            scene,
+            brains,
+            start_area,
+        }
+    }
+
+    #[test]
+    fn identical_simulations_have_identical_fingerprints() {
+        // Whole games are fingerprinted as they update in the headless test
+        // games_with_the_same_start_step_identically, this just checks that
+        // the fingerprint and the shared simulation are deterministic
+        fn fingerprint(arena: &'static LinearAllocator, seed: u64, ticks: u64) -> u64 {
+            let mut base = simulate_base(arena, seed, ticks);
+            state_fingerprint(&base.tilemap, &mut base.scene, &base.brains, ticks, 0)
+        }
+
+        static FIRST_ARENA: &LinearAllocator = static_allocator!(4 * 1024 * 1024);
+        static SECOND_ARENA: &LinearAllocator = static_allocator!(4 * 1024 * 1024);
+        static OTHER_SEED_ARENA: &LinearAllocator = static_allocator!(4 * 1024 * 1024);
+        let first = fingerprint(FIRST_ARENA, 1, 30);
+        let second = fingerprint(SECOND_ARENA, 1, 30);
+        let other_seed = fingerprint(OTHER_SEED_ARENA, 2, 30);
+        assert_eq!(first, second, "same seed should play out the same");
+        assert_ne!(first, other_seed, "different seeds should diverge");
+    }
+
+    #[test]
+    fn saved_games_load_back_the_same() {
+        static ARENA: &LinearAllocator = static_allocator!(4 * 1024 * 1024);
+        static LOAD_ARENA: &LinearAllocator = static_allocator!(4 * 1024 * 1024);
+        let ticks = 20;
+        let SimulatedBase {
+            tilemap,
+            mut scene,
+            brains,
+            start_area,
+        } = simulate_base(ARENA, 1, ticks);
+
+        let mut buffer = FixedVec::new(ARENA, 256 * 1024).unwrap();
+        buffer.fill_with_zeroes();
+        let mut writer = SaveWriter::new(&mut buffer);
+        let camera_position = Vec2::new(12.5, 34.0);
+        save_state(
+            &mut writer,
+            &tilemap,
+            &mut scene,
+            &brains,
+            (ticks, 7),
+            (start_area.center, camera_position),
+        );
+        let len = writer.finish().expect("the save should fit in the buffer");
+        let save = &buffer[..len];
+
+        let mut loaded = GameState::new(LOAD_ARENA, ARENA).unwrap();
+        assert!(loaded.load(ARENA, &mut SaveReader::new(save)).is_some());
+        assert_eq!(loaded.current_tick, ticks);
+        assert_eq!(loaded.base_oxygen, 7);
+        assert_eq!(loaded.start_position, start_area.center);
+        assert_eq!(loaded.camera_position, camera_position);
+
+        let hash_tilemap = |tilemap: &Tilemap| {
+            let mut hasher = SeaHasher::new();
+            tilemap.hash_state(&mut hasher);
+            hasher.finish()
+        };
+        assert_eq!(hash_tilemap(&loaded.tilemap), hash_tilemap(&tilemap));
+
+        // The positions and stockpiles of every game object, in spawn order
+        let objects = |scene: &mut Scene| {
+            let mut objects = ArrayVec::<(TilePosition, Stockpile), 512>::new();
+            scene.run_system(define_system!(
+                |_, positions: &[TilePosition], stockpiles: &[Stockpile]| {
+                    for (position, stockpile) in positions.iter().zip(stockpiles) {
+                        objects.push((*position, *stockpile));
+                    }
+                }
+            ));
+            objects
+        };
+        assert_eq!(objects(&mut loaded.scene), objects(&mut scene));
+
+        let mut original_characters = ArrayVec::<_, MAX_CHARACTERS>::new();
+        for_each_character(&mut scene, |_, character, _| {
+            original_characters.push(*character);
+        });
+        let mut loaded_characters = ArrayVec::<_, MAX_CHARACTERS>::new();
+        for_each_character(&mut loaded.scene, |_, character, _| {
+            loaded_characters.push(*character);
+        });
+        assert_eq!(
+            bytemuck::cast_slice::<_, u8>(&loaded_characters[..]),
+            bytemuck::cast_slice::<_, u8>(&original_characters[..]),
+        );
+
+        assert!(loaded.brains.ids().eq(brains.ids()));
+        for id in brains.ids() {
+            let (brain, loaded_brain) = (&brains[id], &loaded.brains[id]);
+            assert_eq!(loaded_brain.job, brain.job);
+            assert_eq!(loaded_brain.sleep_spot, brain.sleep_spot);
+            assert_eq!(loaded_brain.stats(), brain.stats());
+            assert!(
+                (loaded_brain.goal_stack.iter().map(Goal::kind))
+                    .eq(brain.goal_stack.iter().map(Goal::kind)),
+                "goals should be loaded as they were: {:?}",
+                loaded_brain.goal_stack,
+            );
+        }
+
+        // Cut short and out of date saves shouldn't load
+        let cut_short = &mut SaveReader::new(&save[..len / 2]);
+        assert!(loaded.load(ARENA, cut_short).is_none());
+        let mut old_save = FixedVec::new(ARENA, len).unwrap();
+        for byte in save {
+            assert!(old_save.push(*byte).is_ok());
+        }
+        old_save[SAVE_MAGIC.len()] = SAVE_VERSION - 1;
+        let old = &mut SaveReader::new(&old_save);
+        assert!(loaded.load(ARENA, old).is_none());
+
+        // Loading over a used state replaces everything that was in it
+        assert!(loaded.load(ARENA, &mut SaveReader::new(save)).is_some());
+        assert_eq!(objects(&mut loaded.scene), objects(&mut scene));
+        assert!(loaded.brains.ids().eq(brains.ids()));
+    }
+
+    #[test]
+    fn saves_with_broken_references_do_not_load() {
+        static ARENA: &LinearAllocator = static_allocator!(8 * 1024 * 1024);
+        static LOAD_ARENA: &LinearAllocator = static_allocator!(4 * 1024 * 1024);
+        let tilemap = Tilemap::generate(ARENA, 1, &TerrainParams::default()).unwrap();
+        let mut brains = Brains::new(ARENA, MAX_CHARACTERS).unwrap();
+        let id = brains.add(Brain::new()).unwrap();
+        let (width, height) = tilemap.tiles.size();
+        let inside = TilePosition::new(width as i16 / 2, height as i16 / 2);
+        let outside = TilePosition::new(width as i16, height as i16 / 2);
+        let character = |brain_index, brain_generation, position| Character {
+            status: CharacterStatus {
+                brain_index,
+                brain_generation,
+                ..CharacterStatus::zeroed()
+            },
+            position,
+            held: Stockpile::zeroed(),
+            collider: Collider::NOT_WALKABLE,
+        };
+
+        let mut buffer = FixedVec::new(ARENA, 256 * 1024).unwrap();
+        buffer.fill_with_zeroes();
+        let mut loaded = GameState::new(LOAD_ARENA, ARENA).unwrap();
+        let mut loads = |spawn: &dyn Fn(&mut Scene)| {
+            let mut scene = new_scene(ARENA, ARENA);
+            spawn(&mut scene);
+            let mut writer = SaveWriter::new(&mut buffer);
+            save_state(
+                &mut writer,
+                &tilemap,
+                &mut scene,
+                &brains,
+                (1, 0),
+                (inside, Vec2::ZERO),
+            );
+            let len = writer.finish().unwrap();
+            let save = &mut SaveReader::new(&buffer[..len]);
+            loaded.load(ARENA, save).is_some()
+        };
+
+        assert!(loads(&|scene| {
+            assert!(
+                scene
+                    .spawn(character(id.index, id.generation, inside))
+                    .is_ok()
+            );
+        }));
+        assert!(!loads(&|scene| {
+            let stale = character(id.index, id.generation.wrapping_add(1), inside);
+            assert!(scene.spawn(stale).is_ok());
+        }));
+        assert!(!loads(&|scene| {
+            assert!(
+                scene
+                    .spawn(character(id.index + 1, id.generation, inside))
+                    .is_ok()
+            );
+        }));
+        assert!(!loads(&|scene| {
+            assert!(
+                scene
+                    .spawn(character(id.index, id.generation, outside))
+                    .is_ok()
+            );
+        }));
+        assert!(!loads(&|scene| {
+            let station = JobStation::new(JobStationVariant::ENERGY_GENERATOR, outside);
+            assert!(scene.spawn(station).is_ok());
+        }));
+    }
+
+    /// Keeps the saves in memory, allocating each one from `arena`.
+    struct MemorySaveStorage {
+        arena: &'static LinearAllocator,
+        saves: RefCell<ArrayVec<(ArrayString<16>, FixedVec<'static, u8>), 4>>,
+    }
+
+    impl SaveStorage for MemorySaveStorage {
+        fn exists(&self, name: &str) -> bool {
+            self.saves
+                .borrow()
+                .iter()
+                .any(|(saved, _)| saved.as_str() == name)
+        }
+
+        fn write(&self, name: &str, save: &[u8]) -> bool {
+            let Some(mut file) = FixedVec::new(self.arena, save.len()) else {
+                return false;
+            };
+            for byte in save {
+                assert!(file.push(*byte).is_ok());
+            }
+            let mut saves = self.saves.borrow_mut();
+            saves.retain(|(saved, _)| saved.as_str() != name);
+            let name = ArrayString::from(name).unwrap();
+            saves.try_push((name, file)).is_ok()
+        }
+
+        fn read(&self, name: &str, buffer: &mut [u8]) -> Option<usize> {
+            let saves = self.saves.borrow();
+            let (_, save) = saves.iter().find(|(saved, _)| saved.as_str() == name)?;
+            buffer.get_mut(..save.len())?.copy_from_slice(save);
+            Some(save.len())
+        }
+    }
+
+    #[test]
+    fn games_saved_from_the_menu_load_back_from_the_menu() {
+        static ARENA: &LinearAllocator = static_allocator!(8 * 1024 * 1024);
+        static LOAD_ARENA: &LinearAllocator = static_allocator!(4 * 1024 * 1024);
+        let storage = MemorySaveStorage {
+            arena: ARENA,
+            saves: RefCell::new(ArrayVec::new()),
+        };
+        let mut tilemap = Tilemap::generate(ARENA, 1, &TerrainParams::default()).unwrap();
+        let mut scene = new_scene(ARENA, ARENA);
+        let start_area = prepare_start_area(&mut tilemap, 8, |pos, resource| {
+            let spawned = scene.spawn(Resource {
+                position: pos,
+                stockpile: Stockpile::zeroed().with_resource(resource, 3, false),
+                stockpile_reliant: StockpileReliantTag {},
+            });
+            assert!(spawned.is_ok());
+        });
+        let brains = Brains::new(ARENA, MAX_CHARACTERS).unwrap();
+        let ticks = 123;
+
+        // Moves down to the first entry matching `is_entry` in the topmost
+        // menu and accepts it, like a player would
+        let pick =
+            |menu: &mut Option<MenuMode>, paused: &mut bool, is_entry: fn(MenuEntry) -> bool| {
+                for _ in 0..16 {
+                    if menu_top_entry(menu).is_some_and(is_entry) {
+                        return update_menu_stack(menu, paused, |button| {
+                            matches!(button, Button::Accept)
+                        });
+                    }
+                    let command =
+                        update_menu_stack(menu, paused, |button| matches!(button, Button::Down));
+                    assert!(command.is_none());
+                }
+                panic!("the entry should be in the menu");
+            };
+        let open_submenu = |menu: &mut Option<MenuMode>, command: Option<MenuCommand>| {
+            let Some(MenuCommand::OpenSubmenu(kind)) = command else {
+                panic!("expected a submenu to open, got {command:?}");
+            };
+            let Some(MenuMode::MenuStack(menus)) = menu else {
+                panic!("the main menu should still be open");
+            };
+            menus.push(submenu(kind, false, &brains, Some(&storage)).unwrap());
+        };
+
+        let (mut paused, mut menu) = (false, None);
+        let command = update_menu_stack(&mut menu, &mut paused, |button| {
+            matches!(button, Button::OpenMenu)
+        });
+        assert!(command.is_none());
+
+        let command = pick(&mut menu, &mut paused, |entry| {
+            matches!(entry, MenuEntry::SaveGame)
+        });
+        open_submenu(&mut menu, command);
+        let command = pick(&mut menu, &mut paused, |entry| {
+            matches!(entry, MenuEntry::SaveSlot { slot: 0, .. })
+        });
+        assert_eq!(command, Some(MenuCommand::SaveToSlot(0)));
+        let saved = write_save_slot(&storage, 0, ARENA, |buffer| {
+            let mut writer = SaveWriter::new(buffer);
+            save_state(
+                &mut writer,
+                &tilemap,
+                &mut scene,
+                &brains,
+                (ticks, 7),
+                (start_area.center, Vec2::ZERO),
+            );
+            writer.finish()
+        });
+        assert!(saved);
+        let command = update_menu_stack(&mut menu, &mut paused, |button| {
+            matches!(button, Button::Cancel)
+        });
+        assert!(command.is_none());
+
+        let command = pick(&mut menu, &mut paused, |entry| {
+            matches!(entry, MenuEntry::LoadGame)
+        });
+        open_submenu(&mut menu, command);
+        assert_eq!(menu_depth(&menu), 2);
+
+        // Nothing happens when picking an empty slot, and the menu stays open
+        let command = pick(&mut menu, &mut paused, |entry| {
+            matches!(
+                entry,
+                MenuEntry::LoadSlot {
+                    slot: 1,
+                    occupied: false
+                }
+            )
+        });
+        assert!(command.is_none());
+        assert_eq!(menu_depth(&menu), 2);
+        assert!(read_save_slot(&storage, 1, ARENA).is_none());
+
+        // Back up to the slot that was saved into
+        let command = update_menu_stack(&mut menu, &mut paused, |button| {
+            matches!(button, Button::Up)
+        });
+        assert!(command.is_none());
+        let command = pick(&mut menu, &mut paused, |entry| {
+            matches!(
+                entry,
+                MenuEntry::LoadSlot {
+                    slot: 0,
+                    occupied: true
+                }
+            )
+        });
+        assert_eq!(command, Some(MenuCommand::LoadFromSlot(0)));
+        let (save, len) = read_save_slot(&storage, 0, ARENA).unwrap();
+        let mut loaded = GameState::new(LOAD_ARENA, ARENA).unwrap();
+        assert!(
+            loaded
+                .load(ARENA, &mut SaveReader::new(&save[..len]))
+                .is_some()
+        );
+        assert_eq!(loaded.current_tick, ticks);
+        assert_eq!(
+            state_fingerprint(
+                &loaded.tilemap,
+                &mut loaded.scene,
+                &loaded.brains,
+                loaded.current_tick,
+                loaded.base_oxygen,
+            ),
+            state_fingerprint(&tilemap, &mut scene, &brains, ticks, 7),
+        );
+    }
+
+    #[test]
+    fn every_seed_is_playable() {
+        static ARENA: &LinearAllocator = static_allocator!(1_000_000);
+        let mut arena = LinearAllocator::new(ARENA, 900_000).unwrap();
+        let config = GameConfig::default();
+        for seed in 0..100 {
+            arena.reset();
+            let mut tilemap = Tilemap::generate(&arena, seed, &TerrainParams::default()).unwrap();
+            let start_area = prepare_start_area(&mut tilemap, config.start_area_size, |_, _| {});
+            let start_pos = start_area.center;
+
+            let summary = tilemap.summary(start_pos, &arena).unwrap();
+            assert!(
+                summary.start_connects_to_vent,
+                "no vent to reach with seed {seed}"
+            );
+
+            let mut walls = BitGrid::new(&arena, tilemap.tiles.size()).unwrap();
+            tilemap.mark_walls(&mut walls);
+            let reachable = flood_fill(&[start_pos], u8::MAX, &walls, &arena).unwrap();
+            let mut taken = ArrayVec::<TilePosition, { MAX_CHARACTERS + 2 }>::new();
+            taken.extend(character_start_positions(&tilemap.tiles, &start_area, 4));
+            for preferred in [start_area.energy_generator, start_area.oxygen_generator] {
+                let station =
+                    find_seafloor_in_start_area(&tilemap.tiles, &start_area, preferred, &taken)
+                        .unwrap_or_else(|| panic!("no room for the stations with seed {seed}"));
+                assert!(
+                    reachable.get(station),
+                    "unreachable station with seed {seed}"
+                );
+                taken.push(station);
+            }
+        }
+    }
+
+    #[test]
+    fn initial_magma_adds_up_to_the_target() {
+        static ARENA: &LinearAllocator = static_allocator!(200_000);
+        for seed in 0..8u64 {
+            let mut tiles: Grid<Tile> = Grid::new_zeroed(ARENA, (32, 32)).unwrap();
+            let vent_count = 1 + seed % 5;
+            for i in 0..vent_count {
+                let rand = seahash::hash(&(seed * 100 + i).to_le_bytes());
+                let x = (rand % 32) as usize;
+                let y = ((rand >> 8) % 32) as usize;
+                tiles[(x, y)] = Tile::GeothermalVent;
+            }
+            let tilemap = Tilemap::from_tiles(ARENA, tiles);
+
+            // Enough for the richer vents' piles to overflow, but not all
+            let pile_count = plan_initial_magma(&tilemap, u16::MAX, ARENA).unwrap().len() as u16;
+            let nearly_full = (pile_count * u8::MAX as u16).saturating_sub(1);
+
+            for target in [7, 120, 500, nearly_full] {
+                let piles = plan_initial_magma(&tilemap, target, ARENA).unwrap();
+                let total: u16 = piles.iter().map(|(_, amount)| *amount as u16).sum();
+                assert_eq!(total, target, "seed {seed} with {vent_count} vents");
+                for (pos, _) in piles.iter() {
+                    assert!(matches!(tilemap.tiles[*pos], Tile::Seafloor));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn richer_vents_get_more_initial_magma() {
+        static ARENA: &LinearAllocator = static_allocator!(10_000);
+        // Vents three tiles apart, so that their piles don't touch
+        let mut tiles: Grid<Tile> = Grid::new_zeroed(ARENA, (12, 3)).unwrap();
+        for x in [1, 4, 7, 10] {
+            tiles[(x, 1)] = Tile::GeothermalVent;
+        }
+        let tilemap = Tilemap::from_tiles(ARENA, tiles);
+        let richness_near = |pos: TilePosition| {
+            Direction::ALL
+                .iter()
+                .map(|dir| tilemap.vent_richness(pos + *dir))
+                .max()
+                .unwrap()
+        };
+        let total_weight: u16 = [1, 4, 7, 10]
+            .map(|x| tilemap.vent_richness(TilePosition::new(x, 1)) as u16 * 4)
+            .iter()
+            .sum();
+
+        let piles = plan_initial_magma(&tilemap, total_weight * 10, ARENA).unwrap();
+        assert_eq!(piles.len(), 16);
+        for (pos, amount) in piles.iter() {
+            assert_eq!(*amount, richness_near(*pos) * 10, "pile at {pos:?}");
+        }
+    }
+
+    #[test]
+    fn vents_stop_filling_full_magma_piles() {
+        static ARENA: &LinearAllocator = static_allocator!(10_000);
+        let mut tiles: Grid<Tile> = Grid::new_zeroed(ARENA, (3, 3)).unwrap();
+        tiles[(1, 1)] = Tile::GeothermalVent;
+        let tilemap = Tilemap::from_tiles(ARENA, tiles);
+        let positions = [TilePosition::new(0, 1), TilePosition::new(2, 1)];
+        let mut stockpiles = [
+            Stockpile::zeroed().with_resource(ResourceVariant::MAGMA, VENT_MAGMA_CAP, false),
+            Stockpile::zeroed().with_resource(ResourceVariant::MAGMA, VENT_MAGMA_CAP - 1, false),
+        ];
+
+        spawn_vent_magma(&tilemap, &positions, &mut stockpiles);
+        let magma = stockpiles.map(|pile| pile.get_resources(ResourceVariant::MAGMA));
+        assert_eq!(magma, [Some(VENT_MAGMA_CAP); 2]);
+
+        spawn_vent_magma(&tilemap, &positions, &mut stockpiles);
+        let magma = stockpiles.map(|pile| pile.get_resources(ResourceVariant::MAGMA));
+        assert_eq!(magma, [Some(VENT_MAGMA_CAP); 2]);
+    }
+
+    #[test]
+    fn faster_workers_finish_production_in_fewer_ticks() {
+        let ticks_to_produce = |work_speed: u8| {
+            let mut job = JobStationStatus {
+                variant: JobStationVariant::ENERGY_GENERATOR,
+                work_invested: 0,
+                input_work_left: 0,
+                settings: JobStationSettings::zeroed(),
+            };
+            let mut stockpile =
+                Stockpile::zeroed().with_resource(ResourceVariant::MAGMA, 100, true);
+            let mut base_oxygen = 0;
+            let mut ticks = 0;
+            while work_at_job_station(&mut job, &mut stockpile, work_speed, &mut base_oxygen) == 0 {
+                ticks += 1;
+                assert!(ticks < 100, "never finished at work speed {work_speed}");
+            }
+            assert_eq!(stockpile.get_resources(ResourceVariant::ENERGY), Some(1));
+            ticks + 1
+        };
+
+        let work_amount = JobStationVariant::ENERGY_GENERATOR
+            .details()
+            .unwrap()
+            .work_amount;
+        assert_eq!(ticks_to_produce(1), work_amount);
+        assert_eq!(ticks_to_produce(2), work_amount / 2);
+    }
+
+    #[test]
+    fn disabled_job_stations_do_not_produce() {
+        static ARENA: &LinearAllocator = static_allocator!(64 * 1024);
+        let mut scene = Scene::builder()
+            .with_game_object_type::<JobStation>(1)
+            .build(ARENA, ARENA)
+            .unwrap();
+        let station_pos = TilePosition::new(2, 3);
+        let spawned = scene.spawn(JobStation {
+            position: station_pos,
+            stockpile: Stockpile::zeroed().with_resource(ResourceVariant::MAGMA, 100, true),
+            status: JobStationStatus {
+                variant: JobStationVariant::ENERGY_GENERATOR,
+                work_invested: 0,
+                input_work_left: 0,
+                settings: JobStationSettings::zeroed(),
+            },
+            collider: Collider::NOT_WALKABLE,
+        });
+        assert!(spawned.is_ok());
+
+        let status = update_job_station_settings(&mut scene, station_pos, |settings| {
+            settings.set_enabled(false);
+        });
+        assert!(status.is_some_and(|status| !status.settings.is_enabled()));
+
+        let work_amount = JobStationVariant::ENERGY_GENERATOR
+            .details()
+            .unwrap()
+            .work_amount;
+        let work = |scene: &mut Scene| {
+            let mut produced = 0;
+            scene.run_system(define_system!(
+                |_, jobs: &mut [JobStationStatus], stockpiles: &mut [Stockpile]| {
+                    for (job, stockpile) in jobs.iter_mut().zip(stockpiles) {
+                        for _ in 0..work_amount {
+                            let mut base_oxygen = 0;
+                            produced += work_at_job_station(job, stockpile, 1, &mut base_oxygen);
+                        }
+                    }
+                }
+            ));
+            produced
+        };
+        assert_eq!(work(&mut scene), 0);
+        let mut invested = None;
+        scene.run_system(define_system!(|_, jobs: &[JobStationStatus]| {
+            invested = jobs.first().map(|job| job.work_invested);
+        }));
+        assert_eq!(invested, Some(0));
+
+        update_job_station_settings(&mut scene, station_pos, |settings| {
+            settings.set_enabled(true);
+        });
+        assert_eq!(work(&mut scene), 1);
+    }
+
+    #[test]
+    fn refill_stations_pull_oxygen_from_adjacent_stockpiles() {
+        static ARENA: &LinearAllocator = static_allocator!(64 * 1024);
+        let mut scene = Scene::builder()
+            .with_game_object_type::<JobStation>(2)
+            .with_game_object_type::<Resource>(4)
+            .build(ARENA, ARENA)
+            .unwrap();
+        let station_pos = TilePosition::new(1, 1);
+        let spawned = scene.spawn(JobStation {
+            position: station_pos,
+            stockpile: Stockpile::zeroed().with_resource(ResourceVariant::OXYGEN, 0, true),
+            status: JobStationStatus {
+                variant: JobStationVariant::REFILL_STATION,
+                work_invested: 0,
+                input_work_left: 0,
+                settings: JobStationSettings::zeroed(),
+            },
+            collider: Collider::NOT_WALKABLE,
+        });
+        assert!(spawned.is_ok());
+        for (pos, amount) in [
+            (station_pos + Direction::Left, 100),
+            (TilePosition::new(3, 1), 5),
+        ] {
+            let spawned = scene.spawn(Resource {
+                position: pos,
+                stockpile: Stockpile::zeroed().with_resource(
+                    ResourceVariant::OXYGEN,
+                    amount,
+                    false,
+                ),
+                stockpile_reliant: StockpileReliantTag {},
+            });
+            assert!(spawned.is_ok());
+        }
+
+        let mut temp_arena = LinearAllocator::new(ARENA, 4 * 1024).unwrap();
+        let station_oxygen = |scene: &mut Scene| {
+            let mut oxygen = None;
+            scene.run_system(define_system!(
+                |_, _stations: &[JobStationStatus], stockpiles: &[Stockpile]| {
+                    oxygen = stockpiles[0].get_resources(ResourceVariant::OXYGEN);
+                }
+            ));
+            oxygen
+        };
+        let pile_oxygen = |scene: &mut Scene| {
+            let mut oxygen = ArrayVec::<Option<u8>, 2>::new();
+            scene.run_system(define_system!(
+                |_, _tags: &[StockpileReliantTag], stockpiles: &[Stockpile]| {
+                    oxygen.extend(
+                        stockpiles
+                            .iter()
+                            .map(|s| s.get_resources(ResourceVariant::OXYGEN)),
+                    );
+                }
+            ));
+            oxygen
+        };
+
+        pull_oxygen_into_refill_stations(&mut scene, &temp_arena);
+        temp_arena.reset();
+        assert_eq!(station_oxygen(&mut scene), Some(REFILL_STATION_PULL_AMOUNT));
+        assert_eq!(
+            pile_oxygen(&mut scene).as_slice(),
+            [Some(100 - REFILL_STATION_PULL_AMOUNT), Some(5)],
+            "only the adjacent pile should be pulled from"
+        );
+
+        for _ in 0..100 {
+            pull_oxygen_into_refill_stations(&mut scene, &temp_arena);
+            temp_arena.reset();
+        }
+        assert_eq!(station_oxygen(&mut scene), Some(REFILL_STATION_CAPACITY));
+        assert_eq!(
+            pile_oxygen(&mut scene)[0],
+            Some(100 - REFILL_STATION_CAPACITY)
+        );
+    }
+
+    #[test]
+    fn vents_without_piles_spawn_new_ones() {
+        static ARENA: &LinearAllocator = static_allocator!(64 * 1024);
+        let mut scene = Scene::builder()
+            .with_game_object_type::<Resource>(8)
+            .build(ARENA, ARENA)
+            .unwrap();
+        let mut tiles: Grid<Tile> = Grid::new_zeroed(ARENA, (3, 3)).unwrap();
+        tiles[(1, 1)] = Tile::GeothermalVent;
+        tiles[(1, 0)] = Tile::Wall;
+        let tilemap = Tilemap::from_tiles(ARENA, tiles);
+        let existing = TilePosition::new(0, 1);
+        let spawned = scene.spawn(Resource {
+            position: existing,
+            stockpile: Stockpile::zeroed(),
+            stockpile_reliant: StockpileReliantTag {},
+        });
+        assert!(spawned.is_ok());
+
+        let temp_arena = LinearAllocator::new(ARENA, 1024).unwrap();
+        spawn_vent_magma_piles(&mut scene, &tilemap, &temp_arena);
+
+        let mut piles = ArrayVec::<(TilePosition, Stockpile), 8>::new();
+        scene.run_system(define_system!(
+            |_,
+             _tags: &[StockpileReliantTag],
+             positions: &[TilePosition],
+             stockpiles: &[Stockpile]| {
+                piles.extend(positions.iter().copied().zip(stockpiles.iter().copied()));
+            }
+        ));
+        assert_eq!(
+            piles.len(),
+            3,
+            "the wall and the existing pile should be skipped"
+        );
+        for (pos, stockpile) in &piles[1..] {
+            assert!([TilePosition::new(2, 1), TilePosition::new(1, 2)].contains(pos));
+            assert_eq!(
+                stockpile.get_resources(ResourceVariant::MAGMA),
+                Some(tilemap.vent_richness(TilePosition::new(1, 1)))
+            );
+        }
+    }
+
+    #[test]
+    fn music_selection_only_depends_on_the_tick() {
+        let ticks = [0, 450, 1350, 1800, 3150, 9000];
+        let expected_picks = [3, 1, 3, 0, 3, 2].map(Some);
+        for (tick, expected) in ticks.into_iter().zip(expected_picks) {
+            assert_eq!(pick_music_clip(tick, 4), expected, "tick {tick}");
+        }
+
+        // Picking in another order, with picks for other ticks and clip counts
+        // in between, doesn't change what gets picked for a tick
+        let next_tick_picks = [3, 1, 1, 0, 0, 1].map(Some);
+        for ((tick, expected), next_expected) in ticks
+            .into_iter()
+            .zip(expected_picks)
+            .zip(next_tick_picks)
+            .rev()
+        {
+            assert_eq!(
+                pick_music_clip(tick + 1, 5),
+                next_expected,
+                "tick {}",
+                tick + 1
+            );
+            assert_eq!(pick_music_clip(tick, 4), expected, "tick {tick}");
+        }
+        assert_eq!(
+            ticks.map(|tick| pick_music_clip(tick, 3)),
+            [0, 1, 0, 1, 0, 1].map(Some)
+        );
+    }
+
+    #[test]
+    fn no_music_is_picked_without_music_clips() {
+        for tick in [0, 450, 1350] {
+            assert_eq!(pick_music_clip(tick, 0), None);
+        }
+    }
+
+    #[test]
+    fn pausing_stops_ticks_until_unpaused() {
+        let (mut paused, mut menu, mut current_tick) = (false, None, 0);
+        assert!(advance_tick(&mut current_tick, paused));
+        assert_eq!(current_tick, 1);
+
+        set_pause_state(&mut paused, &mut menu, true);
+        assert!(menu.is_some());
+        for _ in 0..5 {
+            assert!(!advance_tick(&mut current_tick, paused));
+        }
+        assert_eq!(current_tick, 1);
+
+        set_pause_state(&mut paused, &mut menu, false);
+        assert!(menu.is_none());
+        assert!(advance_tick(&mut current_tick, paused));
+        assert_eq!(current_tick, 2);
+    }
+
+    #[test]
+    fn opening_the_options_goes_one_menu_deeper() {
+        static ARENA: &LinearAllocator = static_allocator!(10_000);
+        let brains = Brains::new(ARENA, MAX_CHARACTERS).unwrap();
+        let (mut paused, mut menu) = (false, None);
+        let open_menu = update_menu_stack(&mut menu, &mut paused, |button| {
+            matches!(button, Button::OpenMenu)
+        });
+        assert!(open_menu.is_none());
+        assert!(paused);
+        assert_eq!(menu_depth(&menu), 1);
+        assert!(matches!(menu_top_entry(&menu), Some(MenuEntry::Continue)));
+
+        while !matches!(menu_top_entry(&menu), Some(MenuEntry::Options)) {
+            let command = update_menu_stack(&mut menu, &mut paused, |button| {
+                matches!(button, Button::Down)
+            });
+            assert!(command.is_none());
+        }
+        let command = update_menu_stack(&mut menu, &mut paused, |button| {
+            matches!(button, Button::Accept)
+        });
+        let Some(MenuCommand::OpenSubmenu(kind)) = command else {
+            panic!("the options entry should open a submenu, got {command:?}");
+        };
+        let Some(MenuMode::MenuStack(menus)) = &mut menu else {
+            panic!("the main menu should still be open");
+        };
+        menus.push(submenu(kind, false, &brains, None).unwrap());
+
+        assert_eq!(menu_depth(&menu), 2);
+        assert!(matches!(
+            menu_top_entry(&menu),
+            Some(MenuEntry::Volume | MenuEntry::FlipAcceptCancel(_)),
+        ));
+
+        let command = update_menu_stack(&mut menu, &mut paused, |button| {
+            matches!(button, Button::Cancel)
+        });
+        assert!(command.is_none());
+        assert_eq!(menu_depth(&menu), 1);
+    }
+
+    #[test]
+    fn long_pauses_do_not_cause_a_burst_of_ticks() {
+        let tick = Duration::from_millis(MILLIS_PER_TICK);
+        let mut next_tick_time = Duration::ZERO;
+        let mut timestamp = tick * 3;
+        assert_eq!(take_due_ticks(&mut next_tick_time, timestamp, false), 4);
+
+        // Paused for an hour, with a frame every second
+        for _ in 0..60 * 60 {
+            timestamp += Duration::from_secs(1);
+            assert_eq!(take_due_ticks(&mut next_tick_time, timestamp, true), 0);
+        }
+
+        // Only the time since the last paused frame is ticked through
+        timestamp += tick / 2;
+        assert_eq!(take_due_ticks(&mut next_tick_time, timestamp, false), 1);
+        timestamp += tick / 4;
+        assert_eq!(take_due_ticks(&mut next_tick_time, timestamp, false), 0);
+    }
+
+    #[test]
+    fn every_character_is_visited_once() {
+        static ARENA: &LinearAllocator = static_allocator!(64 * 1024);
+        let mut scene = Scene::builder()
+            .with_game_object_type::<Character>(4)
+            .build(ARENA, ARENA)
+            .unwrap();
+        for brain_index in 0..3 {
+            let spawned = scene.spawn(Character {
+                status: CharacterStatus {
+                    brain_index,
+                    ..CharacterStatus::zeroed()
+                },
+                position: TilePosition::new(brain_index as i16, 5),
+                held: Stockpile::zeroed(),
+                collider: Collider::NOT_WALKABLE,
+            });
+            assert!(spawned.is_ok());
+        }
+
+        let mut visited = [0; 3];
+        for_each_character(&mut scene, |_, character, position| {
+            let i = character.brain_index as usize;
+            assert_eq!(position, TilePosition::new(i as i16, 5));
+            visited[i] += 1;
+        });
+        assert_eq!(visited, [1, 1, 1]);
+    }
+
+    #[test]
+    fn objects_within_a_radius_are_found_by_kind() {
+        static ARENA: &LinearAllocator = static_allocator!(64 * 1024);
+        let mut scene = Scene::builder()
+            .with_game_object_type::<Character>(8)
+            .with_game_object_type::<JobStation>(8)
+            .with_game_object_type::<Resource>(8)
+            .build(ARENA, ARENA)
+            .unwrap();
+
+        let center = TilePosition::new(10, 10);
+        let offsets = [(0, 0), (2, 0), (-1, 1), (0, -3), (2, 1), (-5, 5)];
+        for (x, y) in offsets {
+            let position = TilePosition::new(10 + x, 10 + y);
+            let spawned = scene.spawn(Character {
+                status: CharacterStatus::zeroed(),
+                position,
+                held: Stockpile::zeroed(),
+                collider: Collider::NOT_WALKABLE,
+            });
+            assert!(spawned.is_ok());
+            let spawned = scene.spawn(Resource {
+                position,
+                stockpile: Stockpile::zeroed(),
+                stockpile_reliant: StockpileReliantTag {},
+            });
+            assert!(spawned.is_ok());
+        }
+        let spawned = scene.spawn(JobStation::new(JobStationVariant::ENERGY_GENERATOR, center));
+        assert!(spawned.is_ok());
+
+        for kind in [ObjectKind::Character, ObjectKind::Resource] {
+            let mut found = ArrayVec::<TilePosition, 8>::new();
+            for_each_object_within(&mut scene, center, 2, kind, |_, position| {
+                found.push(position);
+            });
+            for (x, y) in offsets {
+                let position = TilePosition::new(10 + x, 10 + y);
+                let within = x.abs() + y.abs() <= 2;
+                assert_eq!(
+                    found.contains(&position),
+                    within,
+                    "{kind:?} at {position:?}",
+                );
+            }
+            assert_eq!(found.len(), 3, "{kind:?} was found more than once");
+        }
+
+        let mut job_stations = 0;
+        for_each_object_within(&mut scene, center, 0, ObjectKind::JobStation, |_, _| {
+            job_stations += 1;
+        });
+        assert_eq!(job_stations, 1);
+    }
+
+    #[test]
+    fn characters_are_picked_over_job_stations() {
+        static ARENA: &LinearAllocator = static_allocator!(64 * 1024);
+        let mut scene = Scene::builder()
+            .with_game_object_type::<Character>(4)
+            .with_game_object_type::<JobStation>(4)
+            .with_game_object_type::<Resource>(4)
+            .build(ARENA, ARENA)
+            .unwrap();
+
+        let shared_tile = TilePosition::new(3, 3);
+        let station_tile = TilePosition::new(5, 3);
+        let resource_tile = TilePosition::new(7, 3);
+        let spawned = scene.spawn(Character {
+            status: CharacterStatus::zeroed(),
+            position: shared_tile,
+            held: Stockpile::zeroed(),
+            collider: Collider::NOT_WALKABLE,
+        });
+        assert!(spawned.is_ok());
+        for position in [shared_tile, station_tile] {
+            let spawned = scene.spawn(JobStation::new(
+                JobStationVariant::ENERGY_GENERATOR,
+                position,
+            ));
+            assert!(spawned.is_ok());
+        }
+        let spawned = scene.spawn(Resource {
+            position: resource_tile,
+            stockpile: Stockpile::zeroed(),
+            stockpile_reliant: StockpileReliantTag {},
+        });
+        assert!(spawned.is_ok());
+
+        let mut character = None;
+        for_each_character(&mut scene, |handle, _, _| character = Some(handle));
+        let mut station = None;
+        for_each_object_within(
+            &mut scene,
+            station_tile,
+            0,
+            ObjectKind::JobStation,
+            |handle, _| {
+                station = Some(handle);
+            },
+        );
+
+        let picked = pick_object_at(&mut scene, shared_tile);
+        assert!(picked.map(|(_, kind)| kind) == Some(ObjectKind::Character));
+        assert!(picked.map(|(handle, _)| handle) == character);
+        let picked = pick_object_at(&mut scene, station_tile);
+        assert!(picked.map(|(_, kind)| kind) == Some(ObjectKind::JobStation));
+        assert!(picked.map(|(handle, _)| handle) == station);
+        assert!(pick_object_at(&mut scene, resource_tile).is_none());
+        assert!(pick_object_at(&mut scene, TilePosition::new(0, 0)).is_none());
+    }
+
+    #[test]
+    fn walls_and_characters_are_not_walkable() {
+        static ARENA: &LinearAllocator = static_allocator!(64 * 1024);
+        let mut scene = Scene::builder()
+            .with_game_object_type::<Character>(4)
+            .with_game_object_type::<JobStation>(4)
+            .build(ARENA, ARENA)
+            .unwrap();
+        let size = (6, 4);
+        let mut tile_walls = BitGrid::new(ARENA, size).unwrap();
+        tile_walls.set(TilePosition::new(0, 0), true);
+
+        let character = TilePosition::new(2, 2);
+        let spawned = scene.spawn(Character {
+            status: CharacterStatus::zeroed(),
+            position: character,
+            held: Stockpile::zeroed(),
+            collider: Collider::NOT_WALKABLE,
+        });
+        assert!(spawned.is_ok());
+        let job_station = TilePosition::new(4, 1);
+        let spawned = scene.spawn(JobStation::new(
+            JobStationVariant::ENERGY_GENERATOR,
+            job_station,
+        ));
+        assert!(spawned.is_ok());
+
+        let mut walls = BitGrid::new(ARENA, size).unwrap();
+        let mut characters_grid = BitGrid::new(ARENA, size).unwrap();
+        mark_obstacles(&mut scene, &tile_walls, &mut walls, &mut characters_grid);
+
+        let walkable = |x, y| is_walkable(&walls, &characters_grid, TilePosition::new(x, y));
+        assert!(!walkable(0, 0), "walls aren't walkable");
+        assert!(
+            !walkable(character.x, character.y),
+            "characters aren't walkable"
+        );
+        assert!(
+            !walkable(job_station.x, job_station.y),
+            "job stations aren't walkable"
+        );
+        assert!(!walkable(-1, 2), "off the map isn't walkable");
+        assert!(walkable(1, 0));
+        assert!(walkable(5, 3));
+    }
+
+    #[test]
+    fn firing_a_character_keeps_the_others_brains() {
+        static ARENA: &LinearAllocator = static_allocator!(64 * 1024);
+        let mut scene = Scene::builder()
+            .with_game_object_type::<Character>(4)
+            .with_game_object_type::<Resource>(4)
+            .build(ARENA, ARENA)
+            .unwrap();
+        let mut brains = Brains::new(ARENA, 3).unwrap();
+        let mut ids = [BrainId::zeroed(); 3];
+        for (i, id) in ids.iter_mut().enumerate() {
+            *id = brains.add(Brain::new()).unwrap();
+            brains[*id].wait_ticks = 10 * i as u64;
+            let spawned = scene.spawn(Character {
+                status: CharacterStatus {
+                    brain_index: id.index,
+                    brain_generation: id.generation,
+                    ..CharacterStatus::zeroed()
+                },
+                position: TilePosition::new(i as i16, 5),
+                held: Stockpile::zeroed().with_resource(ResourceVariant::MAGMA, 2, true),
+                collider: Collider::NOT_WALKABLE,
+            });
+            assert!(spawned.is_ok());
+        }
+
+        let temp_arena = LinearAllocator::new(ARENA, 1024).unwrap();
+        fire_character(&mut scene, &mut brains, ids[1], &temp_arena);
+
+        assert!(brains.get(ids[1]).is_none());
+        let mut remaining = 0;
+        for_each_character(&mut scene, |_, character, position| {
+            let i = ids
+                .iter()
+                .position(|id| *id == character.brain_id())
+                .unwrap();
+            assert_eq!(brains[character.brain_id()].wait_ticks, 10 * i as u64);
+            assert_eq!(position, TilePosition::new(i as i16, 5));
+            remaining += 1;
+        });
+        assert_eq!(remaining, 2);
+
+        let mut dropped = None;
+        scene.run_system(define_system!(
+            |_,
+             _tags: &[StockpileReliantTag],
+             positions: &[TilePosition],
+             stockpiles: &[Stockpile]| {
+                dropped = positions
+                    .iter()
+                    .copied()
+                    .zip(stockpiles.iter().copied())
+                    .next();
+            }
+        ));
+        let (position, stockpile) =
+            dropped.expect("the fired character's resources should be dropped");
+        assert_eq!(position, TilePosition::new(1, 5));
+        assert!(stockpile.has_non_reserved_resources(ResourceVariant::MAGMA));
+    }
+
+    #[test]
+    fn building_is_blocked_by_walls_vents_and_colliders() {
+        static ARENA: &LinearAllocator = static_allocator!(100_000);
+        let mut tiles: Grid<Tile> = Grid::new_zeroed(ARENA, (4, 1)).unwrap();
+        tiles[(1, 0)] = Tile::Wall;
+        tiles[(2, 0)] = Tile::GeothermalVent;
+        let tilemap = Tilemap::from_tiles(ARENA, tiles);
+        let mut scene = Scene::builder()
+            .with_game_object_type::<JobStation>(4)
+            .build(ARENA, ARENA)
+            .unwrap();
+
+        assert!(can_build_at(&tilemap, &mut scene, TilePosition::new(0, 0)));
+        assert!(!can_build_at(&tilemap, &mut scene, TilePosition::new(1, 0)));
+        assert!(!can_build_at(&tilemap, &mut scene, TilePosition::new(2, 0)));
+        assert!(!can_build_at(&tilemap, &mut scene, TilePosition::new(4, 0)));
+
+        let station = JobStation::new(JobStationVariant::ENERGY_GENERATOR, TilePosition::new(0, 0));
+        assert!(scene.spawn(station).is_ok());
+        assert!(!can_build_at(&tilemap, &mut scene, TilePosition::new(0, 0)));
+        assert!(can_build_at(&tilemap, &mut scene, TilePosition::new(3, 0)));
+    }
+
+    #[test]
+    fn camera_follows_the_build_cursor_out_of_view() {
+        let camera = Camera {
+            position: Vec2::new(10., 10.),
+            size: Vec2::new(8., 6.),
+            output_size: Vec2::new(800., 600.),
+            shake: CameraShake::default(),
+        };
+        let in_view = TilePosition::new(8, 11);
+        assert_eq!(camera_following_cursor(&camera, in_view), camera.position);
+
+        let off_right = TilePosition::new(16, 10);
+        assert_eq!(
+            camera_following_cursor(&camera, off_right),
+            Vec2::new(13., 10.)
+        );
+        let off_top_left = TilePosition::new(2, 3);
+        assert_eq!(
+            camera_following_cursor(&camera, off_top_left),
+            Vec2::new(6., 6.)
+        );
+    }
+
+    #[test]
+    fn reserved_stockpile_items_are_drawn_differently() {
+        let mut stockpile = Stockpile::zeroed()
+            .with_resource(ResourceVariant::MAGMA, 2, true)
+            .with_resource(ResourceVariant::ENERGY, 3, false);
+        stockpile.canonicalize();
+        for slot in 0..stockpile.variant_count as usize {
+            let reserved = stockpile.variants[slot] == ResourceVariant::MAGMA;
+            assert_eq!(
+                stockpile_slot_overlay(&stockpile, slot),
+                reserved.then_some(Sprite::ReservedMarker),
+                "unexpected overlay for {:?}",
+                stockpile.variants[slot],
+            );
+        }
+
+        stockpile.mark_reserved(ResourceVariant::MAGMA, false);
+        for slot in 0..stockpile.variant_count as usize {
+            assert_eq!(stockpile_slot_overlay(&stockpile, slot), None);
+        }
+    }
+
+    #[test]
+    fn build_costs_are_paid_from_nearby_stockpiles() {
+        static ARENA: &LinearAllocator = static_allocator!(200_000);
+        let mut scene = Scene::builder()
+            .with_game_object_type::<Resource>(8)
+            .with_game_object_type::<JobStation>(1)
+            .build(ARENA, ARENA)
+            .unwrap();
+        let center = TilePosition::new(10, 10);
+        let cost = [(ResourceVariant::ENERGY, 4)];
+        // Job stations' outputs aren't loose resources
+        let spawned = scene.spawn(JobStation {
+            position: TilePosition::new(10, 11),
+            stockpile: Stockpile::zeroed().with_resource(ResourceVariant::ENERGY, 10, false),
+            status: JobStationStatus {
+                variant: JobStationVariant::ENERGY_GENERATOR,
+                work_invested: 0,
+                input_work_left: 0,
+                settings: JobStationSettings::zeroed(),
+            },
+            collider: Collider::NOT_WALKABLE,
+        });
+        assert!(spawned.is_ok());
+        let spawn_energy = |scene: &mut Scene, pos: TilePosition, amount: u8, reserved: bool| {
+            let spawned = scene.spawn(Resource {
+                position: pos,
+                stockpile: Stockpile::zeroed().with_resource(
+                    ResourceVariant::ENERGY,
+                    amount,
+                    reserved,
+                ),
+                stockpile_reliant: StockpileReliantTag {},
+            });
+            assert!(spawned.is_ok());
+        };
+        let mut temp_arena = LinearAllocator::new(ARENA, 64 * 1024).unwrap();
+        let mut can_afford = |scene: &mut Scene, cost: &[(ResourceVariant, u8)]| {
+            let affordable = can_afford_build(scene, cost, center, &temp_arena);
+            temp_arena.reset();
+            affordable
+        };
+        spawn_energy(&mut scene, TilePosition::new(10, 12), 2, false);
+        // Too far away, and reserved, respectively
+        spawn_energy(&mut scene, TilePosition::new(10, 16), 5, false);
+        spawn_energy(&mut scene, TilePosition::new(11, 10), 5, true);
+        // Right at the edge of the radius
+        spawn_energy(&mut scene, TilePosition::new(13, 8), 1, false);
+        assert!(!can_afford(&mut scene, &cost));
+
+        spawn_energy(&mut scene, TilePosition::new(9, 9), 2, false);
+        assert!(can_afford(&mut scene, &cost));
+        assert!(can_afford(&mut scene, &[]));
+        assert!(!can_afford(
+            &mut scene,
+            &[(ResourceVariant::ENERGY, 4), (ResourceVariant::MAGMA, 1)],
+        ));
+
+        let paying_arena = LinearAllocator::new(ARENA, 64 * 1024).unwrap();
+        pay_build_cost(&mut scene, &cost, center, &paying_arena);
+        assert!(!can_afford(&mut scene, &[(ResourceVariant::ENERGY, 2)]));
+        assert!(can_afford(&mut scene, &[(ResourceVariant::ENERGY, 1)]));
+    }
 }
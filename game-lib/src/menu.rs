@@ -1,22 +1,106 @@
 use arrayvec::ArrayVec;
-use engine::input::InputDeviceState;
 
-use crate::{Button, Sprite, game_object::JobStationVariant};
+use crate::{
+    Button, MAX_CHARACTERS, Sprite,
+    brain::BrainId,
+    game_object::{JobStationSettings, JobStationStatus, JobStationVariant, TilePosition},
+    save::SAVE_SLOTS,
+};
 
 pub enum MenuMode {
     MenuStack(ArrayVec<Menu, 3>),
-    BuildPlacement,
+    BuildPlacement(BuildPlacement),
 }
 
-#[derive(Clone, Copy)]
+impl MenuMode {
+    /// How many menus are open. The menus hidden during build placement don't
+    /// count.
+    pub fn depth(&self) -> usize {
+        match self {
+            MenuMode::MenuStack(menus) => menus.len(),
+            MenuMode::BuildPlacement(_) => 0,
+        }
+    }
+
+    /// The hovered entry of the topmost open menu.
+    pub fn top_entry(&self) -> Option<MenuEntry> {
+        match self {
+            MenuMode::MenuStack(menus) => menus.last().map(Menu::hover_entry),
+            MenuMode::BuildPlacement(_) => None,
+        }
+    }
+}
+
+/// A job station being placed on the map with a tile cursor.
+pub struct BuildPlacement {
+    pub variant: JobStationVariant,
+    pub cursor: TilePosition,
+    /// The menus to go back to when done placing.
+    pub menus: ArrayVec<Menu, 3>,
+}
+
+/// How much the volume changes per press in the options.
+const VOLUME_STEP: i8 = 32;
+
+#[derive(Clone, Copy, Debug)]
 pub enum MenuAction {
     Select,
     Next,
     Previous,
 }
 
+/// The menus which are opened from other menus.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MenuKind {
+    Options,
+    Build,
+    ManageCharacters,
+    SaveSlots,
+    LoadSlots,
+}
+
+/// What using a [`MenuEntry`] does, carried out by the game.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MenuCommand {
+    Quit,
+    Continue,
+    OpenSubmenu(MenuKind),
+    /// Starts placing a job station on the map.
+    Build(JobStationVariant),
+    /// Asks for a second press before firing the character.
+    ConfirmFire(BrainId),
+    Fire(BrainId),
+    /// Moves forward (or backward, if negative) in the list of occupations.
+    ChangeOccupation {
+        brain: BrainId,
+        delta: i8,
+    },
+    ChangeMaxHaulAmount {
+        brain: BrainId,
+        delta: i8,
+    },
+    ToggleHalt(BrainId),
+    SetStationEnabled {
+        station: TilePosition,
+        enabled: bool,
+    },
+    SetHaulPriority {
+        station: TilePosition,
+        priority: u8,
+    },
+    SetInputTarget {
+        station: TilePosition,
+        target: u8,
+    },
+    SaveToSlot(u8),
+    LoadFromSlot(u8),
+    ChangeVolume(i8),
+    SetFlipAcceptCancel(bool),
+}
+
 pub struct Menu {
-    entries: ArrayVec<MenuEntry, 8>,
+    /// Sized for managing every character, which takes three entries each.
+    entries: ArrayVec<MenuEntry, { 3 * MAX_CHARACTERS }>,
     selected_index: usize,
     pub rendered: bool,
 }
@@ -27,6 +111,8 @@ impl Menu {
         entries.push(MenuEntry::Continue);
         entries.push(MenuEntry::Build);
         entries.push(MenuEntry::ManageCharacters);
+        entries.push(MenuEntry::SaveGame);
+        entries.push(MenuEntry::LoadGame);
         entries.push(MenuEntry::Options);
         entries.push(MenuEntry::Quit);
         Menu {
@@ -47,10 +133,46 @@ impl Menu {
         }
     }
 
-    pub fn manage_characters(character_count: usize) -> Menu {
+    pub fn build() -> Menu {
+        let mut entries = ArrayVec::new();
+        for variant in JobStationVariant::ALL.iter().take(entries.capacity()) {
+            entries.push(MenuEntry::BuildSelect(*variant));
+        }
+        Menu {
+            entries,
+            selected_index: 0,
+            rendered: true,
+        }
+    }
+
+    /// The save slots to save into, or to load from if `loading`.
+    /// `occupied` tells if there's a save in the slot.
+    pub fn save_slots(loading: bool, occupied: impl Fn(u8) -> bool) -> Menu {
+        let mut entries = ArrayVec::new();
+        for slot in 0..SAVE_SLOTS {
+            let occupied = occupied(slot);
+            entries.push(if loading {
+                MenuEntry::LoadSlot { slot, occupied }
+            } else {
+                MenuEntry::SaveSlot { slot, occupied }
+            });
+        }
+        Menu {
+            entries,
+            selected_index: 0,
+            rendered: true,
+        }
+    }
+
+    pub fn manage_characters(brains: impl Iterator<Item = BrainId>) -> Menu {
         let mut entries = ArrayVec::new();
-        for brain_index in 0..character_count.min(entries.capacity()) {
-            entries.push(MenuEntry::ManageCharacter { brain_index });
+        for brain in brains.take(entries.capacity() / 3) {
+            entries.push(MenuEntry::ManageCharacter {
+                brain,
+                confirming_fire: false,
+            });
+            entries.push(MenuEntry::ManageCharacterHaul { brain });
+            entries.push(MenuEntry::ManageCharacterHalt { brain });
         }
         Menu {
             entries,
@@ -59,6 +181,31 @@ impl Menu {
         }
     }
 
+    /// The settings of the job station at `station`.
+    pub fn station_settings(station: TilePosition, status: JobStationStatus) -> Menu {
+        let mut entries = ArrayVec::new();
+        entries.push(MenuEntry::StationEnabled {
+            station,
+            enabled: status.settings.is_enabled(),
+        });
+        entries.push(MenuEntry::StationHaulPriority {
+            station,
+            priority: status.settings.haul_priority,
+        });
+        if let Some(details) = status.variant.details() {
+            entries.push(MenuEntry::StationInputTarget {
+                station,
+                target: status.input_target(),
+                min: details.resource_amount,
+            });
+        }
+        Menu {
+            entries,
+            selected_index: 0,
+            rendered: true,
+        }
+    }
+
     pub fn len(&self) -> usize {
         self.entries.len()
     }
@@ -80,30 +227,34 @@ impl Menu {
         entry.sprite()
     }
 
-    /// Updates the selection based on input, and returns a [`MenuEntry`] if one
-    /// was selected.
+    /// Updates the selection based on which buttons were `pressed`, and
+    /// returns a [`MenuEntry`] if one was selected.
     pub fn update(
         &mut self,
-        input: &InputDeviceState<{ Button::_Count as usize }>,
+        pressed: impl Fn(Button) -> bool,
     ) -> Option<(&mut MenuEntry, MenuAction)> {
-        if input.actions[Button::Up as usize].pressed {
+        let previous_index = self.selected_index;
+        if pressed(Button::Up) {
             self.selected_index = self.selected_index.saturating_sub(1);
         }
-        if input.actions[Button::Down as usize].pressed {
+        if pressed(Button::Down) {
             self.selected_index = (self.selected_index + 1).min(self.entries.len() - 1);
         }
-        if input.actions[Button::Accept as usize].pressed {
+        if self.selected_index != previous_index {
+            self.entries[previous_index].cancel_confirmation();
+        }
+        if pressed(Button::Accept) {
             return Some((&mut self.entries[self.selected_index], MenuAction::Select));
-        } else if input.actions[Button::Left as usize].pressed {
+        } else if pressed(Button::Left) {
             return Some((&mut self.entries[self.selected_index], MenuAction::Previous));
-        } else if input.actions[Button::Right as usize].pressed {
+        } else if pressed(Button::Right) {
             return Some((&mut self.entries[self.selected_index], MenuAction::Next));
         }
         None
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub enum MenuEntry {
     Quit,
     Continue,
@@ -111,12 +262,176 @@ pub enum MenuEntry {
     Build,
     BuildSelect(JobStationVariant),
     ManageCharacters,
-    ManageCharacter { brain_index: usize },
+    /// Selecting fires the character, once confirmed with a second press.
+    ManageCharacter {
+        brain: BrainId,
+        confirming_fire: bool,
+    },
+    /// Adjusts how much the character can carry per haul.
+    ManageCharacterHaul {
+        brain: BrainId,
+    },
+    /// Stops the character where it stands, or lets it go again.
+    ManageCharacterHalt {
+        brain: BrainId,
+    },
+    /// Toggles whether the job station does any work.
+    StationEnabled {
+        station: TilePosition,
+        enabled: bool,
+    },
+    StationHaulPriority {
+        station: TilePosition,
+        priority: u8,
+    },
+    /// Sets how many inputs the job station is kept stocked with, from `min`,
+    /// the amount the work needs, up to the maximum.
+    StationInputTarget {
+        station: TilePosition,
+        target: u8,
+        min: u8,
+    },
+    SaveGame,
+    LoadGame,
+    SaveSlot {
+        slot: u8,
+        occupied: bool,
+    },
+    LoadSlot {
+        slot: u8,
+        occupied: bool,
+    },
     Volume,
     FlipAcceptCancel(bool),
 }
 
 impl MenuEntry {
+    /// The brain of the character this entry manages, if any.
+    pub fn managed_brain(self) -> Option<BrainId> {
+        match self {
+            MenuEntry::ManageCharacter { brain, .. }
+            | MenuEntry::ManageCharacterHaul { brain }
+            | MenuEntry::ManageCharacterHalt { brain } => Some(brain),
+            _ => None,
+        }
+    }
+
+    /// Goes back to asking for confirmation from the start, for when the entry
+    /// isn't hovered anymore.
+    pub fn cancel_confirmation(&mut self) {
+        if let MenuEntry::ManageCharacter {
+            confirming_fire, ..
+        } = self
+        {
+            *confirming_fire = false;
+        }
+    }
+
+    /// What doing `action` on this entry should do, if anything.
+    pub fn command(self, action: MenuAction) -> Option<MenuCommand> {
+        match (self, action) {
+            (MenuEntry::Quit, MenuAction::Select) => Some(MenuCommand::Quit),
+            (MenuEntry::Continue, MenuAction::Select) => Some(MenuCommand::Continue),
+            (MenuEntry::Options, MenuAction::Select) => {
+                Some(MenuCommand::OpenSubmenu(MenuKind::Options))
+            }
+            (MenuEntry::Build, MenuAction::Select) => {
+                Some(MenuCommand::OpenSubmenu(MenuKind::Build))
+            }
+            (MenuEntry::BuildSelect(variant), MenuAction::Select) => {
+                Some(MenuCommand::Build(variant))
+            }
+            (MenuEntry::ManageCharacters, MenuAction::Select) => {
+                Some(MenuCommand::OpenSubmenu(MenuKind::ManageCharacters))
+            }
+            (
+                MenuEntry::ManageCharacter {
+                    brain,
+                    confirming_fire,
+                },
+                MenuAction::Select,
+            ) => {
+                if confirming_fire {
+                    Some(MenuCommand::Fire(brain))
+                } else {
+                    Some(MenuCommand::ConfirmFire(brain))
+                }
+            }
+            (MenuEntry::ManageCharacter { brain, .. }, MenuAction::Previous) => {
+                Some(MenuCommand::ChangeOccupation { brain, delta: -1 })
+            }
+            (MenuEntry::ManageCharacter { brain, .. }, MenuAction::Next) => {
+                Some(MenuCommand::ChangeOccupation { brain, delta: 1 })
+            }
+            (MenuEntry::ManageCharacterHaul { brain }, MenuAction::Previous) => {
+                Some(MenuCommand::ChangeMaxHaulAmount { brain, delta: -1 })
+            }
+            (MenuEntry::ManageCharacterHaul { brain }, MenuAction::Next) => {
+                Some(MenuCommand::ChangeMaxHaulAmount { brain, delta: 1 })
+            }
+            (MenuEntry::ManageCharacterHalt { brain }, MenuAction::Select) => {
+                Some(MenuCommand::ToggleHalt(brain))
+            }
+            (MenuEntry::StationEnabled { station, enabled }, MenuAction::Select) => {
+                Some(MenuCommand::SetStationEnabled {
+                    station,
+                    enabled: !enabled,
+                })
+            }
+            (MenuEntry::StationHaulPriority { station, priority }, MenuAction::Previous) => {
+                Some(MenuCommand::SetHaulPriority {
+                    station,
+                    priority: priority.saturating_sub(1),
+                })
+            }
+            (MenuEntry::StationHaulPriority { station, priority }, MenuAction::Next) => {
+                Some(MenuCommand::SetHaulPriority {
+                    station,
+                    priority: (priority + 1).min(JobStationSettings::MAX_HAUL_PRIORITY),
+                })
+            }
+            (
+                MenuEntry::StationInputTarget {
+                    station,
+                    target,
+                    min,
+                },
+                MenuAction::Previous,
+            ) => Some(MenuCommand::SetInputTarget {
+                station,
+                target: target.saturating_sub(1).max(min),
+            }),
+            (
+                MenuEntry::StationInputTarget {
+                    station, target, ..
+                },
+                MenuAction::Next,
+            ) => Some(MenuCommand::SetInputTarget {
+                station,
+                target: (target + 1).min(JobStationSettings::MAX_INPUT_TARGET),
+            }),
+            (MenuEntry::SaveGame, MenuAction::Select) => {
+                Some(MenuCommand::OpenSubmenu(MenuKind::SaveSlots))
+            }
+            (MenuEntry::LoadGame, MenuAction::Select) => {
+                Some(MenuCommand::OpenSubmenu(MenuKind::LoadSlots))
+            }
+            (MenuEntry::SaveSlot { slot, .. }, MenuAction::Select) => {
+                Some(MenuCommand::SaveToSlot(slot))
+            }
+            // Nothing to load from an empty slot
+            (MenuEntry::LoadSlot { slot, occupied }, MenuAction::Select) => {
+                occupied.then_some(MenuCommand::LoadFromSlot(slot))
+            }
+            (MenuEntry::Volume, MenuAction::Previous) => {
+                Some(MenuCommand::ChangeVolume(-VOLUME_STEP))
+            }
+            (MenuEntry::Volume, MenuAction::Next) => Some(MenuCommand::ChangeVolume(VOLUME_STEP)),
+            (MenuEntry::FlipAcceptCancel(flip), _) => Some(MenuCommand::SetFlipAcceptCancel(!flip)),
+            _ => None,
+        }
+    }
+
     fn sprite(self) -> Option<Sprite> {
         match self {
             MenuEntry::Quit => Some(Sprite::MenuItemQuit),
@@ -126,9 +441,292 @@ impl MenuEntry {
             MenuEntry::BuildSelect(_) => None,
             MenuEntry::ManageCharacters => Some(Sprite::MenuItemManageChars),
             MenuEntry::ManageCharacter { .. } => None,
+            MenuEntry::ManageCharacterHaul { .. } => None,
+            MenuEntry::ManageCharacterHalt { .. } => None,
+            MenuEntry::StationEnabled { enabled: true, .. } => Some(Sprite::MenuItemStationEnabled),
+            MenuEntry::StationEnabled { enabled: false, .. } => {
+                Some(Sprite::MenuItemStationDisabled)
+            }
+            MenuEntry::StationHaulPriority { .. } => Some(Sprite::MenuItemHaulPriority),
+            MenuEntry::StationInputTarget { .. } => Some(Sprite::MenuItemInputTarget),
+            MenuEntry::SaveGame => Some(Sprite::MenuItemSave),
+            MenuEntry::LoadGame => Some(Sprite::MenuItemLoad),
+            MenuEntry::SaveSlot { occupied, .. } | MenuEntry::LoadSlot { occupied, .. } => {
+                if occupied {
+                    Some(Sprite::MenuItemSlotSaved)
+                } else {
+                    Some(Sprite::MenuItemSlotEmpty)
+                }
+            }
             MenuEntry::Volume => Some(Sprite::MenuItemVolume),
             MenuEntry::FlipAcceptCancel(true) => Some(Sprite::MenuItemFlipACtrue),
             MenuEntry::FlipAcceptCancel(false) => Some(Sprite::MenuItemFlipACfalse),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Button,
+        brain::BrainId,
+        game_object::{JobStationSettings, JobStationVariant, TilePosition},
+    };
+
+    use super::{Menu, MenuAction, MenuCommand, MenuEntry, MenuKind, VOLUME_STEP};
+
+    #[test]
+    fn entries_map_to_their_commands() {
+        let brain = BrainId {
+            index: 2,
+            generation: 1,
+        };
+        let variant = JobStationVariant::ENERGY_GENERATOR;
+        let station = TilePosition::new(3, 4);
+
+        // The commands for selecting the entry, and for going to the previous
+        // and next values on it
+        let cases = [
+            (MenuEntry::Quit, [Some(MenuCommand::Quit), None, None]),
+            (
+                MenuEntry::Continue,
+                [Some(MenuCommand::Continue), None, None],
+            ),
+            (
+                MenuEntry::Options,
+                [
+                    Some(MenuCommand::OpenSubmenu(MenuKind::Options)),
+                    None,
+                    None,
+                ],
+            ),
+            (
+                MenuEntry::Build,
+                [Some(MenuCommand::OpenSubmenu(MenuKind::Build)), None, None],
+            ),
+            (
+                MenuEntry::BuildSelect(variant),
+                [Some(MenuCommand::Build(variant)), None, None],
+            ),
+            (
+                MenuEntry::ManageCharacters,
+                [
+                    Some(MenuCommand::OpenSubmenu(MenuKind::ManageCharacters)),
+                    None,
+                    None,
+                ],
+            ),
+            (
+                MenuEntry::ManageCharacter {
+                    brain,
+                    confirming_fire: false,
+                },
+                [
+                    Some(MenuCommand::ConfirmFire(brain)),
+                    Some(MenuCommand::ChangeOccupation { brain, delta: -1 }),
+                    Some(MenuCommand::ChangeOccupation { brain, delta: 1 }),
+                ],
+            ),
+            (
+                MenuEntry::ManageCharacter {
+                    brain,
+                    confirming_fire: true,
+                },
+                [
+                    Some(MenuCommand::Fire(brain)),
+                    Some(MenuCommand::ChangeOccupation { brain, delta: -1 }),
+                    Some(MenuCommand::ChangeOccupation { brain, delta: 1 }),
+                ],
+            ),
+            (
+                MenuEntry::ManageCharacterHaul { brain },
+                [
+                    None,
+                    Some(MenuCommand::ChangeMaxHaulAmount { brain, delta: -1 }),
+                    Some(MenuCommand::ChangeMaxHaulAmount { brain, delta: 1 }),
+                ],
+            ),
+            (
+                MenuEntry::ManageCharacterHalt { brain },
+                [Some(MenuCommand::ToggleHalt(brain)), None, None],
+            ),
+            (
+                MenuEntry::StationEnabled {
+                    station,
+                    enabled: true,
+                },
+                [
+                    Some(MenuCommand::SetStationEnabled {
+                        station,
+                        enabled: false,
+                    }),
+                    None,
+                    None,
+                ],
+            ),
+            (
+                MenuEntry::StationHaulPriority {
+                    station,
+                    priority: 0,
+                },
+                [
+                    None,
+                    Some(MenuCommand::SetHaulPriority {
+                        station,
+                        priority: 0,
+                    }),
+                    Some(MenuCommand::SetHaulPriority {
+                        station,
+                        priority: 1,
+                    }),
+                ],
+            ),
+            (
+                MenuEntry::StationHaulPriority {
+                    station,
+                    priority: JobStationSettings::MAX_HAUL_PRIORITY,
+                },
+                [
+                    None,
+                    Some(MenuCommand::SetHaulPriority {
+                        station,
+                        priority: JobStationSettings::MAX_HAUL_PRIORITY - 1,
+                    }),
+                    Some(MenuCommand::SetHaulPriority {
+                        station,
+                        priority: JobStationSettings::MAX_HAUL_PRIORITY,
+                    }),
+                ],
+            ),
+            (
+                MenuEntry::StationInputTarget {
+                    station,
+                    target: 3,
+                    min: 3,
+                },
+                [
+                    None,
+                    Some(MenuCommand::SetInputTarget { station, target: 3 }),
+                    Some(MenuCommand::SetInputTarget { station, target: 4 }),
+                ],
+            ),
+            (
+                MenuEntry::StationInputTarget {
+                    station,
+                    target: JobStationSettings::MAX_INPUT_TARGET,
+                    min: 1,
+                },
+                [
+                    None,
+                    Some(MenuCommand::SetInputTarget {
+                        station,
+                        target: JobStationSettings::MAX_INPUT_TARGET - 1,
+                    }),
+                    Some(MenuCommand::SetInputTarget {
+                        station,
+                        target: JobStationSettings::MAX_INPUT_TARGET,
+                    }),
+                ],
+            ),
+            (
+                MenuEntry::SaveGame,
+                [
+                    Some(MenuCommand::OpenSubmenu(MenuKind::SaveSlots)),
+                    None,
+                    None,
+                ],
+            ),
+            (
+                MenuEntry::LoadGame,
+                [
+                    Some(MenuCommand::OpenSubmenu(MenuKind::LoadSlots)),
+                    None,
+                    None,
+                ],
+            ),
+            (
+                MenuEntry::SaveSlot {
+                    slot: 1,
+                    occupied: false,
+                },
+                [Some(MenuCommand::SaveToSlot(1)), None, None],
+            ),
+            (
+                MenuEntry::SaveSlot {
+                    slot: 1,
+                    occupied: true,
+                },
+                [Some(MenuCommand::SaveToSlot(1)), None, None],
+            ),
+            (
+                MenuEntry::LoadSlot {
+                    slot: 2,
+                    occupied: false,
+                },
+                [None, None, None],
+            ),
+            (
+                MenuEntry::LoadSlot {
+                    slot: 2,
+                    occupied: true,
+                },
+                [Some(MenuCommand::LoadFromSlot(2)), None, None],
+            ),
+            (
+                MenuEntry::Volume,
+                [
+                    None,
+                    Some(MenuCommand::ChangeVolume(-VOLUME_STEP)),
+                    Some(MenuCommand::ChangeVolume(VOLUME_STEP)),
+                ],
+            ),
+            (
+                MenuEntry::FlipAcceptCancel(false),
+                [Some(MenuCommand::SetFlipAcceptCancel(true)); 3],
+            ),
+            (
+                MenuEntry::FlipAcceptCancel(true),
+                [Some(MenuCommand::SetFlipAcceptCancel(false)); 3],
+            ),
+        ];
+
+        for (entry, expected) in cases {
+            let actions = [MenuAction::Select, MenuAction::Previous, MenuAction::Next];
+            for (action, expected) in actions.into_iter().zip(expected) {
+                assert_eq!(
+                    entry.command(action),
+                    expected,
+                    "wrong command for {action:?} on {entry:?}",
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn firing_is_cancelled_by_moving_away() {
+        let brain = BrainId {
+            index: 0,
+            generation: 0,
+        };
+        let mut menu = Menu::manage_characters([brain].into_iter());
+        let (entry, action) = menu
+            .update(|button| matches!(button, Button::Accept))
+            .unwrap();
+        let command = entry.command(action);
+        assert_eq!(command, Some(MenuCommand::ConfirmFire(brain)));
+        *entry = MenuEntry::ManageCharacter {
+            brain,
+            confirming_fire: true,
+        };
+        let command = menu.hover_entry().command(MenuAction::Select);
+        assert_eq!(command, Some(MenuCommand::Fire(brain)));
+
+        // Going down and back up asks for the confirmation again
+        let _ = menu.update(|button| matches!(button, Button::Down));
+        let _ = menu.update(|button| matches!(button, Button::Up));
+        let (entry, action) = menu
+            .update(|button| matches!(button, Button::Accept))
+            .unwrap();
+        assert_eq!(entry.command(action), Some(MenuCommand::ConfirmFire(brain)));
+    }
+}
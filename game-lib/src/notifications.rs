@@ -1,11 +1,27 @@
 use engine::{allocators::LinearAllocator, collections::FixedVec};
 
-#[derive(Clone, Copy, Debug)]
+use crate::GameTicks;
+
+#[derive(Clone, Copy, Debug, Hash)]
 pub struct NotificationId(u32);
 
 pub struct NotificationSet<'a, T> {
-    notifications: FixedVec<'a, (u32, T)>,
+    notifications: FixedVec<'a, Notification<T>>,
     id_counter: u32,
+    /// The tick of the latest [`NotificationSet::tick_expirations`], which
+    /// the expiry times are counted from.
+    current_tick: GameTicks,
+}
+
+struct Notification<T> {
+    id: u32,
+    /// How many are currently taking care of the notification, see
+    /// [`NotificationSet::claim`].
+    claims: u8,
+    /// The tick on which the notification is dropped, if it's not claimed by
+    /// then.
+    expires_at: Option<GameTicks>,
+    data: T,
 }
 
 impl<T> NotificationSet<'_, T> {
@@ -13,53 +29,214 @@ impl<T> NotificationSet<'_, T> {
         Some(NotificationSet {
             notifications: FixedVec::new(arena, capacity)?,
             id_counter: 0,
+            current_tick: 0,
         })
     }
 
+    /// A notification that doesn't expire. The game's own notifications all
+    /// have a time to live, so this is just for tests.
+    #[cfg(test)]
     pub fn notify(&mut self, data: T) -> Result<NotificationId, T> {
+        self.push(data, None)
+    }
+
+    /// Like [`NotificationSet::notify`], but the notification is dropped by
+    /// [`NotificationSet::tick_expirations`] if no one has claimed it within
+    /// `ticks`.
+    pub fn notify_with_ttl(&mut self, data: T, ticks: GameTicks) -> Result<NotificationId, T> {
+        self.push(data, Some(self.current_tick.saturating_add(ticks)))
+    }
+
+    /// Like [`NotificationSet::notify`], but if there's already a notification
+    /// with the same `key`, its id is returned instead of adding a duplicate.
+    /// The existing notification is left as it was.
+    #[cfg(test)]
+    pub fn notify_unique<K: PartialEq>(
+        &mut self,
+        data: T,
+        key: impl Fn(&T) -> K,
+    ) -> Result<NotificationId, T> {
+        if let Some(existing) = self.find_by_key(&data, key) {
+            return Ok(existing);
+        }
+        self.notify(data)
+    }
+
+    /// Like [`NotificationSet::notify_unique`], but with a time to live like
+    /// [`NotificationSet::notify_with_ttl`]. The time to live of an existing
+    /// notification isn't extended.
+    pub fn notify_unique_with_ttl<K: PartialEq>(
+        &mut self,
+        data: T,
+        ticks: GameTicks,
+        key: impl Fn(&T) -> K,
+    ) -> Result<NotificationId, T> {
+        if let Some(existing) = self.find_by_key(&data, key) {
+            return Ok(existing);
+        }
+        self.notify_with_ttl(data, ticks)
+    }
+
+    fn find_by_key<K: PartialEq>(&self, data: &T, key: impl Fn(&T) -> K) -> Option<NotificationId> {
+        let data_key = key(data);
+        let existing =
+            (self.notifications.iter()).find(|notification| key(&notification.data) == data_key)?;
+        Some(NotificationId(existing.id))
+    }
+
+    fn push(&mut self, data: T, expires_at: Option<GameTicks>) -> Result<NotificationId, T> {
         let id = self.id_counter;
         self.notifications
-            .push((id, data))
-            .map_err(|(_, data)| data)?;
+            .push(Notification {
+                id,
+                claims: 0,
+                expires_at,
+                data,
+            })
+            .map_err(|notification| notification.data)?;
         self.id_counter += 1;
         Ok(NotificationId(id))
     }
 
     pub fn check(&self, id: NotificationId) -> bool {
-        for (id_, _) in &*self.notifications {
-            if id.0 == *id_ {
-                return true;
-            }
-        }
-        false
+        self.find(id).is_some()
     }
 
     pub fn iter(&self) -> impl Iterator<Item = (NotificationId, &T)> {
         self.notifications
             .iter()
-            .map(|(id, t)| (NotificationId(*id), t))
+            .map(|notification| (NotificationId(notification.id), &notification.data))
     }
 
     pub fn remove(&mut self, id: NotificationId) -> Option<T> {
-        let index = self
-            .notifications
-            .iter()
-            .position(|(id_, _)| *id_ == id.0)?;
+        let index = self.find(id)?;
         let last_index = self.notifications.len() - 1;
         self.notifications.swap(index, last_index);
-        let (_, t) = self.notifications.pop().unwrap();
-        Some(t)
+        let notification = self.notifications.pop().unwrap();
+        Some(notification.data)
     }
 
     pub fn get_mut(&mut self, id: NotificationId) -> Option<&mut T> {
-        let index = self
-            .notifications
-            .iter()
-            .position(|(id_, _)| *id_ == id.0)?;
-        Some(&mut self.notifications[index].1)
+        let index = self.find(id)?;
+        Some(&mut self.notifications[index].data)
+    }
+
+    /// Marks one more claimant as taking care of the notification, so that
+    /// others can skip it. Returns false if there's no such notification.
+    pub fn claim(&mut self, id: NotificationId) -> bool {
+        let Some(index) = self.find(id) else {
+            return false;
+        };
+        let claims = &mut self.notifications[index].claims;
+        *claims = claims.saturating_add(1);
+        true
+    }
+
+    /// Undoes a [`NotificationSet::claim`]. Removed notifications don't need
+    /// releasing.
+    pub fn release(&mut self, id: NotificationId) {
+        if let Some(index) = self.find(id) {
+            let claims = &mut self.notifications[index].claims;
+            *claims = claims.saturating_sub(1);
+        }
+    }
+
+    /// How many claimants are currently taking care of the notification.
+    pub fn claims(&self, id: NotificationId) -> u8 {
+        self.find(id)
+            .map(|index| self.notifications[index].claims)
+            .unwrap_or(0)
+    }
+
+    /// Removes the unclaimed notifications which have expired by
+    /// `current_tick`, returning how many were removed.
+    pub fn tick_expirations(&mut self, current_tick: GameTicks) -> usize {
+        self.current_tick = current_tick;
+        let mut removed = 0;
+        let mut i = 0;
+        while i < self.notifications.len() {
+            let notification = &self.notifications[i];
+            let expired =
+                (notification.expires_at).is_some_and(|expires_at| expires_at <= current_tick);
+            if expired && notification.claims == 0 {
+                let last_index = self.notifications.len() - 1;
+                self.notifications.swap(i, last_index);
+                self.notifications.pop();
+                removed += 1;
+            } else {
+                i += 1;
+            }
+        }
+        removed
+    }
+
+    /// Removes every notification, as if the set was just created.
+    pub fn clear(&mut self) {
+        while self.notifications.pop().is_some() {}
+        self.id_counter = 0;
+        self.current_tick = 0;
     }
 
     pub fn len(&self) -> usize {
         self.notifications.len()
     }
+
+    fn find(&self, id: NotificationId) -> Option<usize> {
+        (self.notifications.iter()).position(|notification| notification.id == id.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use engine::{allocators::LinearAllocator, static_allocator};
+
+    use super::NotificationSet;
+
+    #[test]
+    fn notifications_expire_unless_claimed() {
+        static ARENA: &LinearAllocator = static_allocator!(1024);
+        let mut notifications = NotificationSet::new(ARENA, 4).unwrap();
+        let expiring = notifications.notify_with_ttl('a', 0).unwrap();
+        let permanent = notifications.notify('b').unwrap();
+        let claimed = notifications.notify_with_ttl('c', 0).unwrap();
+        assert!(notifications.claim(claimed));
+
+        assert_eq!(notifications.tick_expirations(1), 1);
+        assert!(!notifications.check(expiring));
+        assert!(notifications.check(permanent));
+        assert!(notifications.check(claimed));
+        assert_eq!(notifications.len(), 2);
+
+        // The time to live is counted from the latest tick
+        let later = notifications.notify_with_ttl('d', 5).unwrap();
+        assert_eq!(notifications.tick_expirations(5), 0);
+        assert!(notifications.check(later));
+        assert_eq!(notifications.tick_expirations(6), 1);
+        assert!(!notifications.check(later));
+    }
+
+    #[test]
+    fn unique_notifications_are_not_duplicated() {
+        static ARENA: &LinearAllocator = static_allocator!(1024);
+        let mut notifications = NotificationSet::new(ARENA, 4).unwrap();
+        let first_letter = |word: &&str| word.chars().next();
+        let first = notifications.notify_unique("bubble", first_letter);
+        let second = notifications.notify_unique("bubble", first_letter);
+        assert_eq!(first.unwrap().0, second.unwrap().0);
+        assert_eq!(notifications.len(), 1);
+
+        // Expiring ones are deduplicated the same way
+        let expiring = notifications.notify_unique_with_ttl("bubble", 10, first_letter);
+        assert_eq!(first.unwrap().0, expiring.unwrap().0);
+        assert_eq!(notifications.len(), 1);
+
+        // Only the key needs to match
+        let third = notifications.notify_unique_with_ttl("bathysphere", 10, first_letter);
+        assert_eq!(first.unwrap().0, third.unwrap().0);
+        assert_eq!(notifications.len(), 1);
+
+        let other = notifications.notify_unique_with_ttl("current", 10, first_letter);
+        assert_ne!(first.unwrap().0, other.unwrap().0);
+        assert_eq!(notifications.len(), 2);
+    }
 }
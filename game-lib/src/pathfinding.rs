@@ -5,91 +5,543 @@ use core::{
 
 use arrayvec::ArrayVec;
 use bytemuck::Zeroable;
-use engine::{allocators::LinearAllocator, collections::Queue};
+use engine::{
+    allocators::LinearAllocator,
+    collections::{FixedVec, Queue},
+};
 use glam::I16Vec2;
 
 use crate::{
     game_object::TilePosition,
     grid::{BitGrid, Grid},
+    save::{SaveReader, SaveWriter},
 };
 
+/// How many steps' worth walking through a soft obstacle (i.e. another
+/// character) costs. Makes paths prefer walking around others, if the detour
+/// isn't too long.
+pub const SOFT_OBSTACLE_COST: u8 = 4;
+
+/// Why a path couldn't be found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathError {
+    /// None of the destinations can be walked to.
+    Unreachable,
+    /// The temporary arena didn't have room for the search.
+    OutOfMemory,
+    /// The search frontier filled up. The search is stopped instead of
+    /// skipping tiles, which could result in a wrong path.
+    FrontierFull,
+}
+
+/// The map a path is searched on, and the rules for walking on it.
+#[derive(Clone, Copy)]
+pub struct PathOptions<'a> {
+    /// The tiles that can't be walked on.
+    pub walls: &'a BitGrid<'a>,
+    /// The tiles that can be walked on, but cost [`SOFT_OBSTACLE_COST`] steps
+    /// (i.e. other characters).
+    pub soft_obstacles: &'a BitGrid<'a>,
+    /// The cost of stepping on each tile, if it varies. Soft obstacles still
+    /// cost at least [`SOFT_OBSTACLE_COST`]. The least costly path is found.
+    pub tile_costs: Option<&'a Grid<'a, u8>>,
+    /// Allows the destination to be a wall, in which case the path ends next
+    /// to it.
+    pub allow_wall_destination: bool,
+    /// Allows diagonal steps, but not between two walls.
+    pub diagonal_movement: bool,
+}
+
+impl<'a> PathOptions<'a> {
+    /// Options for paths around `walls` and `soft_obstacles`, with only
+    /// cardinal steps, to destinations that can be walked on.
+    pub fn new(walls: &'a BitGrid<'a>, soft_obstacles: &'a BitGrid<'a>) -> PathOptions<'a> {
+        PathOptions {
+            walls,
+            soft_obstacles,
+            tile_costs: None,
+            allow_wall_destination: false,
+            diagonal_movement: false,
+        }
+    }
+
+    pub fn with_tile_costs(self, tile_costs: Option<&'a Grid<'a, u8>>) -> PathOptions<'a> {
+        PathOptions { tile_costs, ..self }
+    }
+
+    pub fn with_wall_destination(self) -> PathOptions<'a> {
+        PathOptions {
+            allow_wall_destination: true,
+            ..self
+        }
+    }
+
+    /// Returns how many steps' worth walking onto `pos` costs, or None if it
+    /// can't be walked on.
+    fn step_cost(&self, pos: TilePosition) -> Option<u8> {
+        let cost = step_cost_on_bit_grids(pos, self.walls, self.soft_obstacles)?;
+        match self.tile_costs {
+            Some(tile_costs) => Some(cost.max(tile_costs[pos])),
+            None => Some(cost),
+        }
+    }
+
+    fn search_rules(&self) -> SearchRules {
+        SearchRules {
+            allow_impassable_destination: self.allow_wall_destination,
+            diagonal_movement: self.diagonal_movement,
+        }
+    }
+}
+
+/// The rules of [`PathOptions`] that apply to searches on any kind of map.
+#[derive(Clone, Copy, Default)]
+struct SearchRules {
+    allow_impassable_destination: bool,
+    diagonal_movement: bool,
+}
+
+/// Finds the least costly path to `to`.
 pub fn find_path_to(
     from: TilePosition,
     to: TilePosition,
-    allow_wall_destination: bool,
-    walls: &BitGrid,
+    options: &PathOptions,
     temp_arena: &LinearAllocator,
-) -> Option<Path> {
-    let mut destinations = BitGrid::new(temp_arena, walls.size())?;
-    destinations.set(to, true);
-    find_path_to_any(
+) -> Result<Path, PathError> {
+    find_path_a_star(
         from,
-        &destinations,
-        allow_wall_destination,
-        walls,
+        to,
+        options.walls.size(),
+        |pos| options.step_cost(pos),
+        options.search_rules(),
         temp_arena,
     )
 }
 
+/// Finds the least costly path to any of the destinations.
 pub fn find_path_to_any(
     from: TilePosition,
     destinations: &BitGrid,
-    allow_wall_destination: bool,
+    options: &PathOptions,
+    temp_arena: &LinearAllocator,
+) -> Result<Path, PathError> {
+    find_path_to_nearest(from, destinations, options, temp_arena).map(|(path, _)| path)
+}
+
+/// Like [`find_path_to_any`], but also returns which of the destinations the
+/// path leads to. Note that if the destination is a wall, the path ends next
+/// to it. If the path was cut short at [`Path::MAX_STEPS`], the position
+/// returned is where the path ends instead, which isn't one of the
+/// destinations, so callers can tell that there's more walking to do.
+pub fn find_path_to_nearest(
+    from: TilePosition,
+    destinations: &BitGrid,
+    options: &PathOptions,
+    temp_arena: &LinearAllocator,
+) -> Result<(Path, TilePosition), PathError> {
+    let walls = options.walls;
+    find_path_where(
+        from,
+        destinations.size(),
+        |pos| destinations.get(pos),
+        |pos| options.step_cost(pos),
+        options.search_rules(),
+        walls.width() * walls.height() * 2,
+        temp_arena,
+    )
+}
+
+/// Finds the shortest path from `from` to `to` on any map of the given size,
+/// with `is_passable` deciding which tiles can be walked on. Meant for tools
+/// that don't have their maps in [`BitGrid`]s, and searches the same way as
+/// [`find_path_to`] does.
+pub fn find_path_generic(
+    from: TilePosition,
+    to: TilePosition,
+    is_passable: impl Fn(TilePosition) -> bool,
+    size: (usize, usize),
+    temp_arena: &LinearAllocator,
+) -> Result<Path, PathError> {
+    find_path_a_star(
+        from,
+        to,
+        size,
+        |pos| if is_passable(pos) { Some(1) } else { None },
+        SearchRules::default(),
+        temp_arena,
+    )
+}
+
+fn step_cost_on_bit_grids(
+    pos: TilePosition,
     walls: &BitGrid,
+    soft_obstacles: &BitGrid,
+) -> Option<u8> {
+    if walls.get(pos) {
+        None
+    } else if soft_obstacles.get(pos) {
+        Some(SOFT_OBSTACLE_COST)
+    } else {
+        Some(1)
+    }
+}
+
+/// The pathfinding shared by all the `find_path_*` functions, returning the
+/// path and the destination it leads to, or where the path ends if it was cut
+/// short. `step_cost` returns how many steps' worth walking onto the tile
+/// costs, or None if it can't be walked on. The closures are only called with
+/// in-bounds positions. With diagonal movement, diagonal steps cost as much as
+/// cardinal ones, and can't squeeze between two impassable tiles.
+///
+/// Tiles that cost 1 step are only queued once, but costlier tiles are queued
+/// once per neighbor that reaches them, so `queue_capacity` needs to be over
+/// `width * height` if there are costlier tiles. Twice that is plenty for a few
+/// characters standing around and the odd hazard, and if the queue does fill
+/// up, the search fails with [`PathError::FrontierFull`].
+fn find_path_where(
+    from: TilePosition,
+    (width, height): (usize, usize),
+    is_destination: impl Fn(TilePosition) -> bool,
+    step_cost: impl Fn(TilePosition) -> Option<u8>,
+    rules: SearchRules,
+    queue_capacity: usize,
     temp_arena: &LinearAllocator,
-) -> Option<Path> {
-    if !destinations.in_bounds(from) {
-        return None;
-    } else if destinations.get(from) {
-        return Some(Path::default());
+) -> Result<(Path, TilePosition), PathError> {
+    let in_bounds = |pos: TilePosition| {
+        pos.x >= 0 && pos.y >= 0 && (pos.x as usize) < width && (pos.y as usize) < height
+    };
+    if !in_bounds(from) {
+        return Err(PathError::Unreachable);
+    } else if is_destination(from) {
+        return Ok((Path::default(), from));
     }
 
-    let mut try_positions: Queue<TilePosition> =
-        Queue::new(temp_arena, walls.width() * walls.height())?;
-    let mut shortest_distance_to_pos: Grid<u8> = Grid::new_zeroed(temp_arena, walls.size())?;
-    let mut step_to_previous_in_path: Grid<Direction> = Grid::new_zeroed(temp_arena, walls.size())?;
+    // Each entry is (position, step back towards the previous position, steps
+    // left until arrival).
+    let mut try_positions: Queue<(TilePosition, Direction, u8)> =
+        Queue::new(temp_arena, queue_capacity).ok_or(PathError::OutOfMemory)?;
+    let mut shortest_distance_to_pos: Grid<u8> =
+        Grid::new_zeroed(temp_arena, (width, height)).ok_or(PathError::OutOfMemory)?;
+    let mut step_to_previous_in_path: Grid<Direction> =
+        Grid::new_zeroed(temp_arena, (width, height)).ok_or(PathError::OutOfMemory)?;
 
-    let _ = try_positions.push_back(from);
+    (try_positions.push_back((from, Direction::Up, 0))).map_err(|_| PathError::FrontierFull)?;
     shortest_distance_to_pos[from] = 1;
 
     // The first one at the front of the queue should always be one of the
     // shortest paths, since every step only costs 1, and longer paths are
-    // always pushed to the back of the queue.
-    while let Some(try_pos) = try_positions.pop_front() {
+    // always pushed to the back of the queue. Costlier tiles (e.g. soft
+    // obstacles) cost more by cycling through the queue a few times before
+    // they're actually arrived at, which keeps the queue in order of distance.
+    while let Some((try_pos, step_back, steps_left)) = try_positions.pop_front() {
+        if steps_left > 1 {
+            (try_positions.push_back((try_pos, step_back, steps_left - 1)))
+                .map_err(|_| PathError::FrontierFull)?;
+            continue;
+        } else if steps_left == 1 {
+            if shortest_distance_to_pos[try_pos] != 0 {
+                continue; // Already arrived here via a shorter path
+            }
+            let previous = try_pos + step_back;
+            let cost = step_cost(try_pos).unwrap_or(1);
+            shortest_distance_to_pos[try_pos] =
+                shortest_distance_to_pos[previous].saturating_add(cost);
+            step_to_previous_in_path[try_pos] = step_back;
+            if is_destination(try_pos) {
+                let (path, reachable_end) = backtrack(from, try_pos, &step_to_previous_in_path);
+                return Ok((path, reachable_end));
+            }
+        }
+
         // Try neighbors
-        for dir in Direction::ALL {
+        for &dir in Direction::neighbors(rules.diagonal_movement) {
             let neighbor = try_pos + dir;
-            if !walls.in_bounds(neighbor) || shortest_distance_to_pos[neighbor] != 0 {
+            if !in_bounds(neighbor) || shortest_distance_to_pos[neighbor] != 0 {
                 continue; // Oout of bounds or already been there
             }
+            if cuts_corner(try_pos, dir, in_bounds, &step_cost) {
+                continue;
+            }
+
+            let cost = step_cost(neighbor);
+            let can_walk = cost.is_some();
+            if let Some(cost @ 2..) = cost {
+                // Arrival (and the destination check) is delayed until the
+                // entry has gone through the queue `cost` times.
+                (try_positions.push_back((neighbor, -dir, cost)))
+                    .map_err(|_| PathError::FrontierFull)?;
+                continue;
+            }
 
-            let can_walk = !walls.get(neighbor);
             if can_walk {
-                let could_add_neighbor = try_positions.push_back(neighbor);
-                debug_assert!(could_add_neighbor.is_ok());
-                shortest_distance_to_pos[neighbor] = shortest_distance_to_pos[try_pos] + 1;
+                (try_positions.push_back((neighbor, -dir, 0)))
+                    .map_err(|_| PathError::FrontierFull)?;
+                shortest_distance_to_pos[neighbor] =
+                    shortest_distance_to_pos[try_pos].saturating_add(1);
                 step_to_previous_in_path[neighbor] = -dir;
             }
 
-            if destinations.get(neighbor) && (allow_wall_destination || can_walk) {
+            if is_destination(neighbor) && (rules.allow_impassable_destination || can_walk) {
                 // Backtrack and finish if this is a valid destination (and
-                // walkable, or allow_wall_destination is set).
-                let mut path_to_start = Path::default();
-                let mut path_end = if can_walk { neighbor } else { try_pos };
-                while path_end != from && !path_to_start.is_full() {
-                    let dir = step_to_previous_in_path[path_end];
-                    path_end = path_end + dir;
-                    path_to_start.add_step(dir);
+                // walkable, or allow_impassable_destination is set).
+                let path_end = if can_walk { neighbor } else { try_pos };
+                let (path, reachable_end) = backtrack(from, path_end, &step_to_previous_in_path);
+                if reachable_end != path_end {
+                    return Ok((path, reachable_end));
                 }
-                if path_end == from {
-                    return Some(path_to_start.reverse());
-                } else {
-                    return None;
+                return Ok((path, neighbor));
+            }
+        }
+    }
+
+    Err(PathError::Unreachable)
+}
+
+/// Returns true if `dir` is a diagonal step from `pos` which passes between two
+/// impassable (or out of bounds) tiles.
+fn cuts_corner(
+    pos: TilePosition,
+    dir: Direction,
+    in_bounds: impl Fn(TilePosition) -> bool,
+    step_cost: impl Fn(TilePosition) -> Option<u8>,
+) -> bool {
+    let Some((a, b)) = dir.orthogonal_parts() else {
+        return false;
+    };
+    let blocked = |side: TilePosition| !in_bounds(side) || step_cost(side).is_none();
+    blocked(pos + a) && blocked(pos + b)
+}
+
+/// A* version of [`find_path_where`] for when there's just one destination,
+/// using the distance to `to` as the heuristic. Only searches around the
+/// direct route instead of the whole map, so it's much faster for nearby
+/// destinations.
+///
+/// The frontier has room for each tile to be pushed twice, if the search
+/// needs more than that, it fails with [`PathError::FrontierFull`].
+fn find_path_a_star(
+    from: TilePosition,
+    to: TilePosition,
+    (width, height): (usize, usize),
+    step_cost: impl Fn(TilePosition) -> Option<u8>,
+    rules: SearchRules,
+    temp_arena: &LinearAllocator,
+) -> Result<Path, PathError> {
+    let in_bounds = |pos: TilePosition| {
+        pos.x >= 0 && pos.y >= 0 && (pos.x as usize) < width && (pos.y as usize) < height
+    };
+    if !in_bounds(from) {
+        return Err(PathError::Unreachable);
+    } else if from == to {
+        return Ok(Path::default());
+    }
+    // Every step costs at least 1, so this never overestimates
+    let heuristic = |pos: TilePosition| {
+        if rules.diagonal_movement {
+            pos.x.abs_diff(to.x).max(pos.y.abs_diff(to.y))
+        } else {
+            pos.manhattan_distance(*to)
+        }
+    };
+
+    let mut frontier =
+        Frontier::new(temp_arena, width * height * 2).ok_or(PathError::OutOfMemory)?;
+    let mut settled = BitGrid::new(temp_arena, (width, height)).ok_or(PathError::OutOfMemory)?;
+    // The costs are offset by one, so that 0 can mean "not reached yet"
+    let mut lowest_cost_to_pos: Grid<u16> =
+        Grid::new_zeroed(temp_arena, (width, height)).ok_or(PathError::OutOfMemory)?;
+    let mut step_to_previous_in_path: Grid<Direction> =
+        Grid::new_zeroed(temp_arena, (width, height)).ok_or(PathError::OutOfMemory)?;
+
+    frontier.push(1 + heuristic(from), from)?;
+    lowest_cost_to_pos[from] = 1;
+
+    // The frontier is ordered by the cost so far plus the estimate of the cost
+    // left, so the destination is settled (or found next to a settled tile)
+    // via the cheapest path, like in Dijkstra's algorithm, but the tiles
+    // leading away from the destination are mostly left unexplored.
+    while let Some((_, try_pos)) = frontier.pop() {
+        if settled.get(try_pos) {
+            continue; // Already settled via a cheaper path
+        }
+        settled.set(try_pos, true);
+        if try_pos == to {
+            return Ok(backtrack(from, try_pos, &step_to_previous_in_path).0);
+        }
+
+        let cost_to_pos = lowest_cost_to_pos[try_pos];
+        for &dir in Direction::neighbors(rules.diagonal_movement) {
+            let neighbor = try_pos + dir;
+            if !in_bounds(neighbor) || settled.get(neighbor) {
+                continue;
+            }
+            if cuts_corner(try_pos, dir, in_bounds, &step_cost) {
+                continue;
+            }
+
+            let Some(cost) = step_cost(neighbor) else {
+                if rules.allow_impassable_destination && neighbor == to {
+                    return Ok(backtrack(from, try_pos, &step_to_previous_in_path).0);
                 }
+                continue;
+            };
+            let cost_to_neighbor = cost_to_pos.saturating_add(cost.max(1) as u16);
+            let previous_cost = lowest_cost_to_pos[neighbor];
+            if previous_cost == 0 || cost_to_neighbor < previous_cost {
+                lowest_cost_to_pos[neighbor] = cost_to_neighbor;
+                step_to_previous_in_path[neighbor] = -dir;
+                let estimate = cost_to_neighbor.saturating_add(heuristic(neighbor));
+                frontier.push(estimate, neighbor)?;
             }
         }
     }
 
+    Err(PathError::Unreachable)
+}
+
+/// Follows the steps back from `path_end` to `from`, returning the path from
+/// `from` to `path_end`, and where the path ends. Paths longer than
+/// [`Path::MAX_STEPS`] are cut short, leading only partway to `path_end`, so
+/// that following them still gets closer, and the rest of the way can be found
+/// from there.
+fn backtrack(
+    from: TilePosition,
+    path_end: TilePosition,
+    step_to_previous_in_path: &Grid<Direction>,
+) -> (Path, TilePosition) {
+    let step_back = |pos: TilePosition| pos + step_to_previous_in_path[pos];
+
+    let mut path_len = 0;
+    let mut pos = path_end;
+    while pos != from {
+        pos = step_back(pos);
+        path_len += 1;
+    }
+    let mut reachable_end = path_end;
+    for _ in Path::MAX_STEPS as usize..path_len {
+        reachable_end = step_back(reachable_end);
+    }
+
+    let mut path_to_start = Path::default();
+    let mut pos = reachable_end;
+    while pos != from {
+        let added = path_to_start.add_step(step_to_previous_in_path[pos]);
+        debug_assert!(added, "the path should've been cut short enough to fit");
+        pos = step_back(pos);
+    }
+    (path_to_start.reverse(), reachable_end)
+}
+
+/// A binary min-heap of tiles by their cost, for A*.
+struct Frontier<'a> {
+    heap: FixedVec<'a, (u16, TilePosition)>,
+}
+
+impl Frontier<'_> {
+    fn new(temp_arena: &LinearAllocator, capacity: usize) -> Option<Frontier<'_>> {
+        Some(Frontier {
+            heap: FixedVec::new(temp_arena, capacity)?,
+        })
+    }
+
+    fn push(&mut self, cost: u16, pos: TilePosition) -> Result<(), PathError> {
+        (self.heap.push((cost, pos))).map_err(|_| PathError::FrontierFull)?;
+        let mut i = self.heap.len() - 1;
+        while i > 0 {
+            let parent = (i - 1) / 2;
+            if self.heap[parent].0 <= self.heap[i].0 {
+                break;
+            }
+            self.heap.swap(parent, i);
+            i = parent;
+        }
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Option<(u16, TilePosition)> {
+        let last = self.heap.len().checked_sub(1)?;
+        self.heap.swap(0, last);
+        let lowest = self.heap.pop();
+        let mut i = 0;
+        loop {
+            let (left, right) = (i * 2 + 1, i * 2 + 2);
+            let mut smallest = i;
+            if left < self.heap.len() && self.heap[left].0 < self.heap[smallest].0 {
+                smallest = left;
+            }
+            if right < self.heap.len() && self.heap[right].0 < self.heap[smallest].0 {
+                smallest = right;
+            }
+            if smallest == i {
+                break;
+            }
+            self.heap.swap(smallest, i);
+            i = smallest;
+        }
+        lowest
+    }
+}
+
+/// Returns a grid of the tiles that can be walked to from any of `from` in
+/// `max_distance` steps or less, including the `from` tiles themselves.
+pub fn flood_fill<'a>(
+    from: &[TilePosition],
+    max_distance: u8,
+    walls: &BitGrid,
+    temp_arena: &'a LinearAllocator,
+) -> Option<BitGrid<'a>> {
+    let mut filled = BitGrid::new(temp_arena, walls.size())?;
+    let mut distances: Grid<u8> = Grid::new_zeroed(temp_arena, walls.size())?;
+    let mut try_positions: Queue<TilePosition> =
+        Queue::new(temp_arena, walls.width() * walls.height())?;
+
+    for pos in from {
+        if walls.in_bounds(*pos) && !filled.get(*pos) {
+            filled.set(*pos, true);
+            let _ = try_positions.push_back(*pos);
+        }
+    }
+
+    while let Some(try_pos) = try_positions.pop_front() {
+        if distances[try_pos] >= max_distance {
+            continue;
+        }
+        for dir in Direction::ALL {
+            let neighbor = try_pos + dir;
+            if !walls.in_bounds(neighbor) || filled.get(neighbor) || walls.get(neighbor) {
+                continue;
+            }
+            filled.set(neighbor, true);
+            distances[neighbor] = distances[try_pos] + 1;
+            let could_add_neighbor = try_positions.push_back(neighbor);
+            debug_assert!(could_add_neighbor.is_ok());
+        }
+    }
+
+    Some(filled)
+}
+
+/// Returns the closest tile (by manhattan distance) to `from` which isn't a
+/// wall or a soft obstacle, ignoring any walls in between.
+pub fn nearest_free_tile(
+    from: TilePosition,
+    walls: &BitGrid,
+    soft_obstacles: &BitGrid,
+) -> Option<TilePosition> {
+    let max_distance = (walls.width() + walls.height()) as i16;
+    for distance in 1..=max_distance {
+        for dx in -distance..=distance {
+            let dy = distance - dx.abs();
+            for pos in [
+                TilePosition::new(from.x + dx, from.y + dy),
+                TilePosition::new(from.x + dx, from.y - dy),
+            ] {
+                if walls.get_checked(pos) == Some(false) && !soft_obstacles.get(pos) {
+                    return Some(pos);
+                }
+            }
+        }
+    }
     None
 }
 
@@ -100,6 +552,10 @@ pub enum Direction {
     Down,
     Left,
     Right,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
 }
 
 impl Direction {
@@ -110,30 +566,74 @@ impl Direction {
         Direction::Left,
     ];
 
+    pub const ALL_WITH_DIAGONALS: [Direction; 8] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::Right,
+        Direction::Left,
+        Direction::UpLeft,
+        Direction::UpRight,
+        Direction::DownLeft,
+        Direction::DownRight,
+    ];
+
     const fn to_u8(self) -> u8 {
         match self {
-            Direction::Up => 0b00,
-            Direction::Down => 0b01,
-            Direction::Left => 0b10,
-            Direction::Right => 0b11,
+            Direction::Up => 0b000,
+            Direction::Down => 0b001,
+            Direction::Left => 0b010,
+            Direction::Right => 0b011,
+            Direction::UpLeft => 0b100,
+            Direction::UpRight => 0b101,
+            Direction::DownLeft => 0b110,
+            Direction::DownRight => 0b111,
         }
     }
 
     const fn from_u8(u: u8) -> Direction {
         match u {
-            0b00 => Direction::Up,
-            0b01 => Direction::Down,
-            0b10 => Direction::Left,
-            _ => Direction::Right,
+            0b000 => Direction::Up,
+            0b001 => Direction::Down,
+            0b010 => Direction::Left,
+            0b011 => Direction::Right,
+            0b100 => Direction::UpLeft,
+            0b101 => Direction::UpRight,
+            0b110 => Direction::DownLeft,
+            _ => Direction::DownRight,
         }
     }
 
+    /// Returns the direction rotated a quarter turn clockwise.
     pub const fn next_clockwise(self) -> Direction {
         match self {
             Direction::Up => Direction::Right,
             Direction::Down => Direction::Left,
             Direction::Left => Direction::Up,
             Direction::Right => Direction::Down,
+            Direction::UpLeft => Direction::UpRight,
+            Direction::UpRight => Direction::DownRight,
+            Direction::DownRight => Direction::DownLeft,
+            Direction::DownLeft => Direction::UpLeft,
+        }
+    }
+
+    /// For diagonal directions, returns the two cardinal directions the
+    /// diagonal step passes between.
+    pub const fn orthogonal_parts(self) -> Option<(Direction, Direction)> {
+        match self {
+            Direction::Up | Direction::Down | Direction::Left | Direction::Right => None,
+            Direction::UpLeft => Some((Direction::Up, Direction::Left)),
+            Direction::UpRight => Some((Direction::Up, Direction::Right)),
+            Direction::DownLeft => Some((Direction::Down, Direction::Left)),
+            Direction::DownRight => Some((Direction::Down, Direction::Right)),
+        }
+    }
+
+    const fn neighbors(diagonal_movement: bool) -> &'static [Direction] {
+        if diagonal_movement {
+            &Direction::ALL_WITH_DIAGONALS
+        } else {
+            &Direction::ALL
         }
     }
 }
@@ -147,6 +647,10 @@ impl Neg for Direction {
             Direction::Down => Direction::Up,
             Direction::Left => Direction::Right,
             Direction::Right => Direction::Left,
+            Direction::UpLeft => Direction::DownRight,
+            Direction::UpRight => Direction::DownLeft,
+            Direction::DownLeft => Direction::UpRight,
+            Direction::DownRight => Direction::UpLeft,
         }
     }
 }
@@ -160,16 +664,25 @@ impl Add<Direction> for TilePosition {
             Direction::Down => TilePosition(self.0.add(I16Vec2::new(0, 1))),
             Direction::Left => TilePosition(self.0.add(I16Vec2::new(-1, 0))),
             Direction::Right => TilePosition(self.0.add(I16Vec2::new(1, 0))),
+            Direction::UpLeft => TilePosition(self.0.add(I16Vec2::new(-1, -1))),
+            Direction::UpRight => TilePosition(self.0.add(I16Vec2::new(1, -1))),
+            Direction::DownLeft => TilePosition(self.0.add(I16Vec2::new(-1, 1))),
+            Direction::DownRight => TilePosition(self.0.add(I16Vec2::new(1, 1))),
         }
     }
 }
 
+const BITS_PER_STEP: u8 = 3;
+const STEPS_PER_CHUNK: u8 = 5;
+const STEP_MASK: u16 = 0b111;
+const MAX_CHUNKS: usize = 45;
+
 #[derive(Default, Clone)]
 pub struct Path {
-    /// Each u8 represents 4 steps, so the maximum length for a path is 224
-    /// steps.
-    step_quads: ArrayVec<u8, 56>,
-    steps_in_last_quad: u8,
+    /// Each u16 represents 5 steps of 3 bits each, so the maximum length for a
+    /// path is [`Path::MAX_STEPS`].
+    step_chunks: ArrayVec<u16, MAX_CHUNKS>,
+    steps_in_last_chunk: u8,
 }
 
 impl Debug for Path {
@@ -181,6 +694,10 @@ impl Debug for Path {
                 Direction::Down => list = list.entry(&'↓'),
                 Direction::Left => list = list.entry(&'←'),
                 Direction::Right => list = list.entry(&'→'),
+                Direction::UpLeft => list = list.entry(&'↖'),
+                Direction::UpRight => list = list.entry(&'↗'),
+                Direction::DownLeft => list = list.entry(&'↙'),
+                Direction::DownRight => list = list.entry(&'↘'),
             }
         }
         list.finish()
@@ -188,19 +705,23 @@ impl Debug for Path {
 }
 
 impl Path {
+    /// The most steps a path can have, 225.
+    pub const MAX_STEPS: u8 = MAX_CHUNKS as u8 * STEPS_PER_CHUNK;
+
     /// Adds a step to the end of the path.
     ///
-    /// Returns `false` if the Path is full (480 steps is the maximum).
+    /// Returns `false` if the Path is full, at [`Path::MAX_STEPS`] steps.
     pub fn add_step(&mut self, direction: Direction) -> bool {
-        if self.steps_in_last_quad % 4 == 0 {
-            if self.step_quads.try_push(direction.to_u8()).is_err() {
+        let step = direction.to_u8() as u16;
+        if self.steps_in_last_chunk % STEPS_PER_CHUNK == 0 {
+            if self.step_chunks.try_push(step).is_err() {
                 return false;
             }
-            self.steps_in_last_quad = (self.steps_in_last_quad + 1) % 4;
+            self.steps_in_last_chunk = 1;
         } else {
-            let quad = self.step_quads.last_mut().unwrap();
-            *quad |= direction.to_u8() << (self.steps_in_last_quad * 2);
-            self.steps_in_last_quad += 1;
+            let chunk = self.step_chunks.last_mut().unwrap();
+            *chunk |= step << (self.steps_in_last_chunk * BITS_PER_STEP);
+            self.steps_in_last_chunk += 1;
         }
         true
     }
@@ -214,15 +735,55 @@ impl Path {
     }
 
     pub fn is_empty(&self) -> bool {
-        self.step_quads.is_empty()
+        self.step_chunks.is_empty()
     }
 
     pub fn is_full(&self) -> bool {
-        self.steps_in_last_quad == 4 && self.step_quads.is_full()
+        self.steps_in_last_chunk == STEPS_PER_CHUNK && self.step_chunks.is_full()
     }
 
     pub fn len(&self) -> u8 {
-        self.steps_in_last_quad + (self.step_quads.len() as u8).saturating_sub(1) * 4
+        self.steps_in_last_chunk
+            + (self.step_chunks.len() as u8).saturating_sub(1) * STEPS_PER_CHUNK
+    }
+
+    /// Returns the step at index `i`, or `None` if the path isn't that long.
+    pub fn nth(&self, i: u8) -> Option<Direction> {
+        if i >= self.len() {
+            return None;
+        }
+        let chunk = self.step_chunks[(i / STEPS_PER_CHUNK) as usize];
+        Some(PathIterator::step_in_chunk(chunk, i % STEPS_PER_CHUNK))
+    }
+
+    /// Returns the tiles the path walks through when started from `start`,
+    /// i.e. where each step ends up. The last one is the destination, and
+    /// `start` itself isn't included.
+    pub fn positions_from(&self, start: TilePosition) -> impl Iterator<Item = TilePosition> {
+        (0..self.len()).scan(start, |pos, i| {
+            *pos = *pos + self.nth(i)?;
+            Some(*pos)
+        })
+    }
+
+    pub fn save(&self, writer: &mut SaveWriter) {
+        writer.write_u8(self.len());
+        for step in self {
+            writer.write_u8(step.to_u8());
+        }
+    }
+
+    /// Reads a path written by [`Path::save`]. Returns `None` if the save is
+    /// cut short or the path doesn't fit in a [`Path`].
+    pub fn load(reader: &mut SaveReader) -> Option<Path> {
+        let mut path = Path::default();
+        for _ in 0..reader.read_u8()? {
+            let step = reader.read_u8()?;
+            if step > STEP_MASK as u8 || !path.add_step(Direction::from_u8(step)) {
+                return None;
+            }
+        }
+        Some(path)
     }
 }
 
@@ -232,44 +793,49 @@ impl IntoIterator for &Path {
 
     fn into_iter(self) -> Self::IntoIter {
         PathIterator {
-            current_quad_step_offset: 0,
-            current_quad_index: 0,
-            steps_in_last_quad: self.steps_in_last_quad,
-            step_quads: self.step_quads.clone(),
+            current_chunk_step_offset: 0,
+            current_chunk_index: 0,
+            steps_in_last_chunk: self.steps_in_last_chunk,
+            step_chunks: self.step_chunks.clone(),
         }
     }
 }
 
 pub struct PathIterator {
-    current_quad_step_offset: u8,
-    current_quad_index: u8,
-    steps_in_last_quad: u8,
-    step_quads: ArrayVec<u8, 56>,
+    current_chunk_step_offset: u8,
+    current_chunk_index: u8,
+    steps_in_last_chunk: u8,
+    step_chunks: ArrayVec<u16, MAX_CHUNKS>,
+}
+
+impl PathIterator {
+    fn step_in_chunk(chunk: u16, offset: u8) -> Direction {
+        Direction::from_u8(((chunk >> (offset * BITS_PER_STEP)) & STEP_MASK) as u8)
+    }
 }
 
 impl Iterator for PathIterator {
     type Item = Direction;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let idx = self.current_quad_index as usize;
-        if idx >= self.step_quads.len() {
+        let idx = self.current_chunk_index as usize;
+        if idx >= self.step_chunks.len() {
             return None;
         }
 
-        let steps_in_current_quad = if idx == self.step_quads.len() - 1 {
-            self.steps_in_last_quad
+        let steps_in_current_chunk = if idx == self.step_chunks.len() - 1 {
+            self.steps_in_last_chunk
         } else {
-            4
+            STEPS_PER_CHUNK
         };
 
-        let current_quad = self.step_quads[idx];
-        let direction =
-            Direction::from_u8((current_quad >> (self.current_quad_step_offset * 2)) & 0b11);
+        let current_chunk = self.step_chunks[idx];
+        let direction = Self::step_in_chunk(current_chunk, self.current_chunk_step_offset);
 
-        self.current_quad_step_offset += 1;
-        if self.current_quad_step_offset == steps_in_current_quad {
-            self.current_quad_step_offset = 0;
-            self.current_quad_index += 1;
+        self.current_chunk_step_offset += 1;
+        if self.current_chunk_step_offset == steps_in_current_chunk {
+            self.current_chunk_step_offset = 0;
+            self.current_chunk_index += 1;
         }
 
         Some(direction)
@@ -278,15 +844,15 @@ impl Iterator for PathIterator {
 
 impl DoubleEndedIterator for PathIterator {
     fn next_back(&mut self) -> Option<Self::Item> {
-        let current_quad = self.step_quads.last()?;
-        let last_quad_offset = self.steps_in_last_quad - 1;
-        let direction = Direction::from_u8((current_quad >> (last_quad_offset * 2)) & 0b11);
+        let current_chunk = *self.step_chunks.last()?;
+        let last_chunk_offset = self.steps_in_last_chunk - 1;
+        let direction = Self::step_in_chunk(current_chunk, last_chunk_offset);
 
-        self.steps_in_last_quad -= 1;
-        if self.steps_in_last_quad == 0 {
-            let more_steps_left = self.step_quads.pop().is_some();
+        self.steps_in_last_chunk -= 1;
+        if self.steps_in_last_chunk == 0 {
+            let more_steps_left = self.step_chunks.pop().is_some();
             if more_steps_left {
-                self.steps_in_last_quad = 4;
+                self.steps_in_last_chunk = STEPS_PER_CHUNK;
             }
         }
 
@@ -296,12 +862,16 @@ impl DoubleEndedIterator for PathIterator {
 
 #[cfg(test)]
 mod tests {
+    use arrayvec::ArrayVec;
     use engine::{allocators::LinearAllocator, static_allocator};
 
     use crate::{
         game_object::TilePosition,
-        grid::BitGrid,
-        pathfinding::{Direction, Path, find_path_to},
+        grid::{BitGrid, Grid},
+        pathfinding::{
+            Direction, Path, PathError, PathOptions, SearchRules, find_path_generic, find_path_to,
+            find_path_to_any, find_path_to_nearest, find_path_where, flood_fill,
+        },
     };
 
     #[test]
@@ -327,19 +897,390 @@ mod tests {
         map.set(TilePosition::new(3, 1), true);
         map.set(TilePosition::new(3, 2), true);
         map.set(TilePosition::new(4, 1), true);
+        let no_characters = BitGrid::new(ARENA, (5, 4)).unwrap();
 
         let path = find_path_to(
             TilePosition::new(0, 1),
             TilePosition::new(4, 2),
-            false,
-            &map,
+            &PathOptions::new(&map, &no_characters),
             ARENA,
         );
-        assert!(path.is_some(), "should be able to find the way");
+        assert!(path.is_ok(), "should be able to find the way");
         assert_eq!(
             expected_path.len(),
             path.unwrap().len(),
             "did not find the shortest path"
         );
     }
+
+    #[test]
+    pub fn flood_fill_stops_at_walls_and_distance() {
+        // The map (start is @, . is walkable):
+        // @ . . # .
+        // . . . # .
+        static ARENA: &LinearAllocator = static_allocator!(1000);
+        let mut map = BitGrid::new(ARENA, (5, 2)).unwrap();
+        map.set(TilePosition::new(3, 0), true);
+        map.set(TilePosition::new(3, 1), true);
+
+        let filled = flood_fill(&[TilePosition::new(0, 0)], 2, &map, ARENA).unwrap();
+        for (x, y, expected) in [(0, 0, true), (2, 0, true), (1, 1, true), (2, 1, false)] {
+            assert_eq!(filled.get(TilePosition::new(x, y)), expected, "at {x}, {y}");
+        }
+        assert!(!filled.get(TilePosition::new(4, 0)));
+    }
+
+    #[test]
+    pub fn paths_go_through_characters_only_if_needed() {
+        // The map (start is @, end is *, C is a character, . is walkable):
+        // . . . . .
+        // @ . C . *
+        // # # # # #
+        static ARENA: &LinearAllocator = static_allocator!(2000);
+        let mut map = BitGrid::new(ARENA, (5, 3)).unwrap();
+        for x in 0..5 {
+            map.set(TilePosition::new(x, 2), true);
+        }
+        let mut characters = BitGrid::new(ARENA, (5, 3)).unwrap();
+        characters.set(TilePosition::new(2, 1), true);
+        let from = TilePosition::new(0, 1);
+        let to = TilePosition::new(4, 1);
+
+        // Detouring around the character is cheaper than walking through
+        let path = find_path_to(from, to, &PathOptions::new(&map, &characters), ARENA).unwrap();
+        assert_eq!(6, path.len(), "should have detoured around the character");
+
+        // With the detour walled off, the character is walked through
+        for x in 0..5 {
+            map.set(TilePosition::new(x, 0), true);
+        }
+        let path = find_path_to(from, to, &PathOptions::new(&map, &characters), ARENA).unwrap();
+        assert_eq!(4, path.len(), "should have walked through the character");
+    }
+
+    #[test]
+    fn closure_maps_find_the_same_paths_as_bit_grids() {
+        static ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        // A wall across the middle, with a gap at the bottom
+        let is_wall = |pos: TilePosition| pos.x == 4 && pos.y < 6;
+        let mut walls = BitGrid::new(ARENA, (8, 8)).unwrap();
+        for y in 0..8 {
+            for x in 0..8 {
+                walls.set(TilePosition::new(x, y), is_wall(TilePosition::new(x, y)));
+            }
+        }
+        let no_characters = BitGrid::new(ARENA, (8, 8)).unwrap();
+
+        let (from, to) = (TilePosition::new(1, 1), TilePosition::new(6, 1));
+        let options = PathOptions::new(&walls, &no_characters);
+        let bit_grid_path = find_path_to(from, to, &options, ARENA).unwrap();
+        let closure_path = find_path_generic(from, to, |pos| !is_wall(pos), (8, 8), ARENA).unwrap();
+        let steps = |path: &Path| path.into_iter().collect::<ArrayVec<Direction, 32>>();
+        assert_eq!(steps(&bit_grid_path), steps(&closure_path));
+        assert_eq!(closure_path.len(), 15);
+    }
+
+    #[test]
+    fn nearest_destination_is_returned_with_the_path() {
+        static ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        let walls = BitGrid::new(ARENA, (8, 8)).unwrap();
+        let no_characters = BitGrid::new(ARENA, (8, 8)).unwrap();
+        let mut destinations = BitGrid::new(ARENA, (8, 8)).unwrap();
+        let (near, far) = (TilePosition::new(2, 5), TilePosition::new(7, 0));
+        destinations.set(near, true);
+        destinations.set(far, true);
+
+        let from = TilePosition::new(1, 2);
+        let options = PathOptions::new(&walls, &no_characters);
+        let (path, target) = find_path_to_nearest(from, &destinations, &options, ARENA).unwrap();
+        assert_eq!(target, near);
+        assert_eq!(path.into_iter().fold(from, |pos, step| pos + step), near);
+    }
+
+    #[test]
+    fn costly_tiles_are_walked_around_when_cheaper() {
+        static ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        let walls = BitGrid::new(ARENA, (9, 5)).unwrap();
+        let no_characters = BitGrid::new(ARENA, (9, 5)).unwrap();
+        let (from, to) = (TilePosition::new(0, 2), TilePosition::new(8, 2));
+        let mut destinations = BitGrid::new(ARENA, (9, 5)).unwrap();
+        destinations.set(to, true);
+
+        // A costly strip across the direct route, except for the top row
+        let mut tile_costs: Grid<u8> = Grid::new_zeroed(ARENA, (9, 5)).unwrap();
+        for y in 1..5 {
+            tile_costs[TilePosition::new(4, y)] = 10;
+        }
+
+        let end = |path: &Path| path.into_iter().fold(from, |pos, step| pos + step);
+        let options = PathOptions::new(&walls, &no_characters).with_tile_costs(Some(&tile_costs));
+        let detour = find_path_to_any(from, &destinations, &options, ARENA).unwrap();
+        assert_eq!(end(&detour), to);
+        assert_eq!(detour.len(), 12);
+        let mut pos = from;
+        for step in &detour {
+            pos = pos + step;
+            assert_ne!(tile_costs[pos], 10, "walked through the costly strip");
+        }
+
+        // The detour isn't worth it if the strip is cheap enough
+        for y in 1..5 {
+            tile_costs[TilePosition::new(4, y)] = 2;
+        }
+        let options = PathOptions::new(&walls, &no_characters).with_tile_costs(Some(&tile_costs));
+        let straight = find_path_to_any(from, &destinations, &options, ARENA).unwrap();
+        assert_eq!(end(&straight), to);
+        assert_eq!(straight.len(), 8);
+    }
+
+    #[test]
+    fn uniform_tile_costs_find_the_same_paths_as_no_costs() {
+        static ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        let mut walls = BitGrid::new(ARENA, (8, 8)).unwrap();
+        let mut characters = BitGrid::new(ARENA, (8, 8)).unwrap();
+        for y in 1..7 {
+            walls.set(TilePosition::new(3, y), true);
+        }
+        characters.set(TilePosition::new(5, 2), true);
+        let mut destinations = BitGrid::new(ARENA, (8, 8)).unwrap();
+        destinations.set(TilePosition::new(6, 3), true);
+        destinations.set(TilePosition::new(7, 7), true);
+        let mut tile_costs: Grid<u8> = Grid::new_zeroed(ARENA, (8, 8)).unwrap();
+        for y in 0..8 {
+            for x in 0..8 {
+                tile_costs[(x, y)] = 1;
+            }
+        }
+
+        let from = TilePosition::new(1, 3);
+        let steps = |path: &Path| path.into_iter().collect::<ArrayVec<Direction, 32>>();
+        let find_path = |tile_costs| {
+            let options = PathOptions::new(&walls, &characters).with_tile_costs(tile_costs);
+            find_path_to_nearest(from, &destinations, &options, ARENA).unwrap()
+        };
+        let (uncosted_path, uncosted_target) = find_path(None);
+        let (costed_path, costed_target) = find_path(Some(&tile_costs));
+        assert_eq!(costed_target, uncosted_target);
+        assert_eq!(steps(&costed_path), steps(&uncosted_path));
+    }
+
+    #[test]
+    fn full_frontier_is_an_error() {
+        static ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        let (from, to) = (TilePosition::new(0, 0), TilePosition::new(7, 7));
+        let search = |queue_capacity| {
+            find_path_where(
+                from,
+                (8, 8),
+                |pos| pos == to,
+                |_| Some(1),
+                SearchRules::default(),
+                queue_capacity,
+                ARENA,
+            )
+        };
+        assert!(search(8 * 8).is_ok());
+        assert_eq!(search(2).err(), Some(PathError::FrontierFull));
+    }
+
+    #[test]
+    fn diagonal_steps_survive_the_path_encoding() {
+        let steps = (Direction::ALL_WITH_DIAGONALS.iter().cycle().take(24))
+            .copied()
+            .collect::<ArrayVec<Direction, 24>>();
+        let mut path = Path::default();
+        for step in &steps {
+            assert!(path.add_step(*step));
+        }
+        assert_eq!(path.len() as usize, steps.len());
+        assert!(path.into_iter().eq(steps.iter().copied()));
+        assert!(path.into_iter().rev().eq(steps.iter().rev().copied()));
+        assert!(
+            path.reverse()
+                .into_iter()
+                .eq(steps.iter().rev().map(|step| -*step))
+        );
+    }
+
+    #[test]
+    fn empty_paths_have_no_steps_or_positions() {
+        let path = Path::default();
+        assert_eq!(path.nth(0), None);
+        assert_eq!(path.positions_from(TilePosition::new(3, 3)).next(), None);
+    }
+
+    #[test]
+    fn single_step_paths_end_next_to_the_start() {
+        let mut path = Path::default();
+        path.add_step(Direction::DownLeft);
+        assert_eq!(path.nth(0), Some(Direction::DownLeft));
+        assert_eq!(path.nth(1), None);
+        assert!(
+            path.positions_from(TilePosition::new(3, 3))
+                .eq([TilePosition::new(2, 4)])
+        );
+    }
+
+    #[test]
+    fn indexing_works_across_step_chunks() {
+        let steps = [
+            Direction::Right,
+            Direction::Right,
+            Direction::Down,
+            Direction::Right,
+            Direction::UpRight, // The last step in the first chunk
+            Direction::Up,      // The first step in the second chunk
+            Direction::Left,
+        ];
+        let mut path = Path::default();
+        for step in steps {
+            assert!(path.add_step(step));
+        }
+        for (i, step) in steps.iter().enumerate() {
+            assert_eq!(path.nth(i as u8), Some(*step), "wrong step at {i}");
+        }
+        assert_eq!(path.nth(steps.len() as u8), None);
+
+        let start = TilePosition::new(0, 5);
+        let expected = [(1, 5), (2, 5), (2, 6), (3, 6), (4, 5), (4, 4), (3, 4)];
+        assert!(
+            path.positions_from(start)
+                .eq(expected.map(|(x, y)| TilePosition::new(x, y)))
+        );
+        let walked = path.into_iter().fold(start, |pos, step| pos + step);
+        assert_eq!(path.positions_from(start).last(), Some(walked));
+    }
+
+    #[test]
+    fn too_long_paths_are_cut_short_toward_the_destination() {
+        // A corridor longer than a path can hold
+        static ARENA: &LinearAllocator = static_allocator!(64 * 1024);
+        let size = (Path::MAX_STEPS as usize + 75, 1);
+        let walls = BitGrid::new(ARENA, size).unwrap();
+        let no_characters = BitGrid::new(ARENA, size).unwrap();
+        let from = TilePosition::new(0, 0);
+        let to = TilePosition::new(size.0 as i16 - 1, 0);
+        let partway = TilePosition::new(Path::MAX_STEPS as i16, 0);
+
+        let options = PathOptions::new(&walls, &no_characters);
+        let path = find_path_to(from, to, &options, ARENA).unwrap();
+        assert!(path.is_full());
+        assert_eq!(path.positions_from(from).last(), Some(partway));
+
+        let mut destinations = BitGrid::new(ARENA, size).unwrap();
+        destinations.set(to, true);
+        let (path, destination) =
+            find_path_to_nearest(from, &destinations, &options, ARENA).unwrap();
+        assert_eq!(destination, partway);
+        assert!(path.is_full());
+        assert_eq!(path.positions_from(from).last(), Some(partway));
+
+        // Walking the partial path leaves a path that fits
+        let rest = find_path_to(partway, to, &options, ARENA).unwrap();
+        assert_eq!(rest.positions_from(partway).last(), Some(to));
+    }
+
+    #[test]
+    fn diagonal_paths_are_shorter_but_dont_cut_corners() {
+        static ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        let mut walls = BitGrid::new(ARENA, (6, 6)).unwrap();
+        let no_characters = BitGrid::new(ARENA, (6, 6)).unwrap();
+        let no_costs: Grid<u8> = Grid::new_zeroed(ARENA, (6, 6)).unwrap();
+        let mut destinations = BitGrid::new(ARENA, (6, 6)).unwrap();
+        let from = TilePosition::new(0, 0);
+        let search = |destinations: &BitGrid, walls: &BitGrid, diagonal, tile_costs| {
+            let options = PathOptions {
+                diagonal_movement: diagonal,
+                ..PathOptions::new(walls, &no_characters).with_tile_costs(tile_costs)
+            };
+            find_path_to_any(from, destinations, &options, ARENA)
+        };
+
+        destinations.set(TilePosition::new(4, 4), true);
+        for tile_costs in [None, Some(&no_costs)] {
+            let straight = search(&destinations, &walls, false, tile_costs).unwrap();
+            assert_eq!(straight.len(), 8);
+            let diagonal = search(&destinations, &walls, true, tile_costs).unwrap();
+            assert_eq!(diagonal.len(), 4);
+            assert!(
+                diagonal
+                    .into_iter()
+                    .all(|step| step == Direction::DownRight)
+            );
+        }
+
+        // A diagonal step next to one wall is fine, but not between two
+        destinations.set(TilePosition::new(4, 4), false);
+        destinations.set(TilePosition::new(1, 1), true);
+        walls.set(TilePosition::new(1, 0), true);
+        for tile_costs in [None, Some(&no_costs)] {
+            let path = search(&destinations, &walls, true, tile_costs).unwrap();
+            assert_eq!(path.len(), 1);
+        }
+        walls.set(TilePosition::new(0, 1), true);
+        for tile_costs in [None, Some(&no_costs)] {
+            let result = search(&destinations, &walls, true, tile_costs);
+            assert_eq!(result.err(), Some(PathError::Unreachable));
+        }
+    }
+
+    #[test]
+    fn a_star_paths_are_as_short_as_breadth_first_ones() {
+        static ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        let size = (16, 16);
+        let mut temp_arena = LinearAllocator::new(ARENA, 512 * 1024).unwrap();
+        let mut walls = BitGrid::new(ARENA, size).unwrap();
+        let no_characters = BitGrid::new(ARENA, size).unwrap();
+        let random_pos =
+            |rand: u64| TilePosition::new((rand % 16) as i16, ((rand >> 8) % 16) as i16);
+
+        for seed in 0..50u64 {
+            for y in 0..size.1 as i16 {
+                for x in 0..size.0 as i16 {
+                    let rand = seahash::hash(bytemuck::bytes_of(&[seed, x as u64, y as u64]));
+                    walls.set(TilePosition::new(x, y), rand % 10 < 3);
+                }
+            }
+            let rand = seahash::hash(&seed.to_le_bytes());
+            let (from, to) = (random_pos(rand), random_pos(rand >> 16));
+            walls.set(from, false);
+
+            for allow_wall_destination in [false, true] {
+                let options = PathOptions {
+                    allow_wall_destination,
+                    ..PathOptions::new(&walls, &no_characters)
+                };
+                let a_star = find_path_to(from, to, &options, &temp_arena);
+                let breadth_first = find_path_where(
+                    from,
+                    size,
+                    |pos| pos == to,
+                    |pos| if walls.get(pos) { None } else { Some(1) },
+                    SearchRules {
+                        allow_impassable_destination: allow_wall_destination,
+                        diagonal_movement: false,
+                    },
+                    size.0 * size.1,
+                    &temp_arena,
+                );
+                match (a_star, breadth_first) {
+                    (Ok(a_star), Ok((breadth_first, _))) => {
+                        assert_eq!(a_star.len(), breadth_first.len(), "seed {seed}");
+                        let mut pos = from;
+                        for step in &a_star {
+                            pos = pos + step;
+                            assert!(!walls.get(pos), "walked into a wall with seed {seed}");
+                        }
+                        // Paths to walls end next to them, not necessarily on the same side
+                        let distance_to_end = if walls.get(to) { 1 } else { 0 };
+                        assert_eq!(pos.manhattan_distance(*to), distance_to_end, "seed {seed}");
+                    }
+                    (a_star, breadth_first) => {
+                        assert_eq!(a_star.err(), breadth_first.err(), "seed {seed}");
+                    }
+                }
+                temp_arena.reset();
+            }
+        }
+    }
 }
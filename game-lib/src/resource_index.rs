@@ -0,0 +1,199 @@
+//! Lookup for where resources can be found, so that brains don't need to scan
+//! every stockpile in the scene each time they look for something.
+
+use arrayvec::ArrayVec;
+use engine::{allocators::LinearAllocator, define_system, game_objects::Scene};
+use tracing::trace;
+
+use crate::{
+    game_object::{ResourceVariant, Stockpile, TilePosition},
+    grid::BitGrid,
+};
+
+const MAX_INDEXED_VARIANTS: usize = 8;
+
+/// The tiles with non-reserved resources in a stockpile, per
+/// [`ResourceVariant`].
+///
+/// Each variant's tiles are found once per tick, the first time they're looked
+/// up, and shared by all the brains looking for that variant. After stockpiles
+/// change, [`ResourceIndex::invalidate`] should be called to have the tiles
+/// found again on the next lookup.
+pub struct ResourceIndex<'a> {
+    variants: ArrayVec<IndexedVariant<'a>, MAX_INDEXED_VARIANTS>,
+    scans: u32,
+}
+
+struct IndexedVariant<'a> {
+    variant: ResourceVariant,
+    tiles: BitGrid<'a>,
+    stale: bool,
+}
+
+impl ResourceIndex<'_> {
+    pub fn new<'a>(
+        arena: &'a LinearAllocator,
+        map_size: (usize, usize),
+    ) -> Option<ResourceIndex<'a>> {
+        let mut variants = ArrayVec::new();
+        for variant in ResourceVariant::ALL {
+            variants.push(IndexedVariant {
+                variant: *variant,
+                tiles: BitGrid::new(arena, map_size)?,
+                stale: true,
+            });
+        }
+        Some(ResourceIndex { variants, scans: 0 })
+    }
+
+    /// Marks the index out of date, so that the next lookups scan the scene
+    /// again.
+    pub fn invalidate(&mut self) {
+        for indexed in &mut self.variants {
+            indexed.stale = true;
+        }
+    }
+
+    /// How many times the scene has been scanned for resources, for checking
+    /// that the lookups are actually shared.
+    #[cfg(test)]
+    pub fn scans(&self) -> u32 {
+        self.scans
+    }
+
+    /// Returns the tiles which have non-reserved `resource` in a stockpile,
+    /// scanning the scene first if they're out of date.
+    pub fn destinations(
+        &mut self,
+        scene: &mut Scene,
+        resource: ResourceVariant,
+    ) -> Option<&BitGrid> {
+        let indexed = (self.variants.iter_mut()).find(|indexed| indexed.variant == resource)?;
+        if indexed.stale {
+            indexed.tiles.clear();
+            scene.run_system(define_system!(
+                |_, positions: &[TilePosition], stockpiles: &[Stockpile]| {
+                    for (pos, stockpile) in positions.iter().zip(stockpiles) {
+                        if stockpile.has_non_reserved_resources(resource) {
+                            indexed.tiles.set(*pos, true);
+                            trace!("found potential {resource:?} at: {pos:?}");
+                        }
+                    }
+                }
+            ));
+            indexed.stale = false;
+            self.scans += 1;
+        }
+        Some(&indexed.tiles)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytemuck::Zeroable;
+    use engine::{
+        allocators::LinearAllocator, define_system, game_objects::Scene, static_allocator,
+    };
+
+    use crate::{
+        game_object::{Resource, ResourceVariant, Stockpile, StockpileReliantTag, TilePosition},
+        grid::BitGrid,
+    };
+
+    use super::ResourceIndex;
+
+    fn spawn_pile(scene: &mut Scene, pos: TilePosition, stockpile: Stockpile) {
+        let spawned = scene.spawn(Resource {
+            position: pos,
+            stockpile,
+            stockpile_reliant: StockpileReliantTag {},
+        });
+        assert!(spawned.is_ok());
+    }
+
+    #[test]
+    fn index_agrees_with_a_full_scan() {
+        static ARENA: &LinearAllocator = static_allocator!(1_000_000);
+        let mut scene = Scene::builder()
+            .with_game_object_type::<Resource>(16)
+            .build(ARENA, ARENA)
+            .unwrap();
+        let size = (16, 16);
+
+        let pile = |variant, amount, reserved| {
+            Stockpile::zeroed().with_resource(variant, amount, reserved)
+        };
+        spawn_pile(
+            &mut scene,
+            TilePosition::new(1, 1),
+            pile(ResourceVariant::MAGMA, 3, false),
+        );
+        spawn_pile(
+            &mut scene,
+            TilePosition::new(2, 5),
+            pile(ResourceVariant::MAGMA, 2, true),
+        );
+        spawn_pile(
+            &mut scene,
+            TilePosition::new(7, 3),
+            pile(ResourceVariant::OXYGEN, 1, false),
+        );
+        spawn_pile(
+            &mut scene,
+            TilePosition::new(9, 9),
+            pile(ResourceVariant::ENERGY, 0, false),
+        );
+        spawn_pile(
+            &mut scene,
+            TilePosition::new(4, 12),
+            pile(ResourceVariant::ENERGY, 4, false).with_resource(ResourceVariant::MAGMA, 1, false),
+        );
+
+        let mut index = ResourceIndex::new(ARENA, size).unwrap();
+
+        let check_against_scan = |scene: &mut Scene, index: &mut ResourceIndex| {
+            for variant in ResourceVariant::ALL {
+                let mut scanned = BitGrid::new(ARENA, size).unwrap();
+                scene.run_system(define_system!(
+                    |_, positions: &[TilePosition], stockpiles: &[Stockpile]| {
+                        for (pos, stockpile) in positions.iter().zip(stockpiles) {
+                            if stockpile.has_non_reserved_resources(*variant) {
+                                scanned.set(*pos, true);
+                            }
+                        }
+                    }
+                ));
+                let indexed = index.destinations(scene, *variant).unwrap();
+                for y in 0..size.1 as i16 {
+                    for x in 0..size.0 as i16 {
+                        let pos = TilePosition::new(x, y);
+                        assert_eq!(
+                            indexed.get(pos),
+                            scanned.get(pos),
+                            "index and scan disagree on {variant:?} at {pos:?}",
+                        );
+                    }
+                }
+            }
+        };
+        check_against_scan(&mut scene, &mut index);
+
+        // Reserve the magma at (1, 1) and spawn more elsewhere
+        scene.run_system(define_system!(
+            |_, positions: &[TilePosition], stockpiles: &mut [Stockpile]| {
+                for (pos, stockpile) in positions.iter().zip(stockpiles) {
+                    if *pos == TilePosition::new(1, 1) {
+                        stockpile.mark_reserved(ResourceVariant::MAGMA, true);
+                    }
+                }
+            }
+        ));
+        spawn_pile(
+            &mut scene,
+            TilePosition::new(14, 0),
+            pile(ResourceVariant::MAGMA, 1, false),
+        );
+        index.invalidate();
+        check_against_scan(&mut scene, &mut index);
+    }
+}
@@ -0,0 +1,151 @@
+//! A compact binary format for saving games.
+//!
+//! Saves start with [`SAVE_MAGIC`] and [`SAVE_VERSION`], followed by the state
+//! of the game, written by the `save` functions next to each part of it.
+//! Numbers are little-endian, but [`Pod`] components are written as-is, so
+//! saves only load on platforms with the same endianness.
+
+use core::fmt::Write;
+
+use arrayvec::ArrayString;
+use bytemuck::Pod;
+
+pub const SAVE_MAGIC: [u8; 4] = *b"LD57";
+/// Bumped whenever the format changes, since older saves can't be read.
+pub const SAVE_VERSION: u8 = 4;
+/// How many save files the player can pick between.
+pub const SAVE_SLOTS: u8 = 3;
+
+/// Where the save files are kept. The game doesn't do any file I/O itself, so
+/// this is implemented by the platform-specific side of the game.
+pub trait SaveStorage {
+    /// Returns true if there's a file called `name`.
+    fn exists(&self, name: &str) -> bool;
+    /// Writes `save` into the file called `name`, replacing what was there.
+    /// Returns false if the file couldn't be written.
+    fn write(&self, name: &str, save: &[u8]) -> bool;
+    /// Reads the file called `name` into `buffer`, returning its length, or
+    /// `None` if there's no such file or it doesn't fit in `buffer`.
+    fn read(&self, name: &str, buffer: &mut [u8]) -> Option<usize>;
+}
+
+/// The name of the save file for the slot, e.g. `save0.dat`.
+pub fn save_file_name(slot: u8) -> ArrayString<16> {
+    let mut name = ArrayString::new();
+    let _ = write!(&mut name, "save{slot}.dat");
+    name
+}
+
+/// Writes save data into a byte buffer. Running out of room doesn't stop the
+/// writes, but makes [`SaveWriter::finish`] return `None`.
+pub struct SaveWriter<'a> {
+    buffer: &'a mut [u8],
+    len: usize,
+    overflowed: bool,
+}
+
+impl SaveWriter<'_> {
+    pub fn new(buffer: &mut [u8]) -> SaveWriter<'_> {
+        SaveWriter {
+            buffer,
+            len: 0,
+            overflowed: false,
+        }
+    }
+
+    /// Returns the amount of bytes written, or `None` if they didn't fit.
+    pub fn finish(self) -> Option<usize> {
+        (!self.overflowed).then_some(self.len)
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        let end = self.len + bytes.len();
+        if let Some(dst) = self.buffer.get_mut(self.len..end) {
+            dst.copy_from_slice(bytes);
+            self.len = end;
+        } else {
+            self.overflowed = true;
+        }
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.write_bytes(&[value]);
+    }
+
+    pub fn write_u16(&mut self, value: u16) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_f32(&mut self, value: f32) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.write_u8(value as u8);
+    }
+
+    /// Writes the value's bytes as they are in memory.
+    pub fn write_pod<T: Pod>(&mut self, value: &T) {
+        self.write_bytes(bytemuck::bytes_of(value));
+    }
+}
+
+/// Reads save data written by a [`SaveWriter`]. Each read returns `None` if
+/// the save ends before the value does.
+pub struct SaveReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl SaveReader<'_> {
+    pub fn new(bytes: &[u8]) -> SaveReader<'_> {
+        SaveReader { bytes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn read_bytes(&mut self, len: usize) -> Option<&[u8]> {
+        let (bytes, rest) = self.bytes.split_at_checked(len)?;
+        self.bytes = rest;
+        Some(bytes)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        self.read_bytes(N)?.try_into().ok()
+    }
+
+    pub fn read_u8(&mut self) -> Option<u8> {
+        Some(self.read_array::<1>()?[0])
+    }
+
+    pub fn read_u16(&mut self) -> Option<u16> {
+        Some(u16::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_f32(&mut self) -> Option<f32> {
+        Some(f32::from_le_bytes(self.read_array()?))
+    }
+
+    /// Reads a bool, returning `None` for anything other than a 0 or a 1.
+    pub fn read_bool(&mut self) -> Option<bool> {
+        match self.read_u8()? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+
+    pub fn read_pod<T: Pod>(&mut self) -> Option<T> {
+        let bytes = self.read_bytes(size_of::<T>())?;
+        Some(bytemuck::pod_read_unaligned(bytes))
+    }
+}
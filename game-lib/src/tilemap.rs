@@ -1,10 +1,10 @@
-use core::{f32::consts::PI, fmt::Write};
+use core::{f32::consts::PI, fmt::Write, hash::Hasher};
 
-use arrayvec::ArrayString;
+use arrayvec::{ArrayString, ArrayVec};
 use bytemuck::Zeroable;
 use engine::{
     allocators::LinearAllocator,
-    collections::FixedVec,
+    collections::{FixedVec, Queue},
     geom::Rect,
     renderer::DrawQueue,
     resources::{ResourceDatabase, ResourceLoader, sprite::SpriteHandle},
@@ -12,7 +12,14 @@ use engine::{
 use glam::{USizeVec2, Vec2};
 use libm::{ceilf, cosf, floorf, sinf};
 
-use crate::{DrawLayer, camera::Camera, grid::Grid};
+use crate::{
+    DrawLayer, Sprite,
+    camera::Camera,
+    game_object::{ResourceVariant, TilePosition},
+    grid::{BitGrid, Grid},
+    pathfinding::Direction,
+    save::{SaveReader, SaveWriter},
+};
 
 #[derive(Clone, Copy, Debug, Zeroable)]
 #[repr(u8)]
@@ -20,71 +27,490 @@ pub enum Tile {
     Seafloor,
     Wall,
     GeothermalVent,
+    /// Walkable, but seals rooms like walls do.
+    Door,
+    /// Walkable, but drains the oxygen of anyone standing on it.
+    GasPocket,
     _Count,
 }
 
+impl Tile {
+    /// The tile with the given discriminant, if there is one.
+    pub const fn from_u8(value: u8) -> Option<Tile> {
+        match value {
+            0 => Some(Tile::Seafloor),
+            1 => Some(Tile::Wall),
+            2 => Some(Tile::GeothermalVent),
+            3 => Some(Tile::Door),
+            4 => Some(Tile::GasPocket),
+            _ => None,
+        }
+    }
+
+    pub const fn is_walkable(self) -> bool {
+        match self {
+            Tile::Seafloor | Tile::Door | Tile::GasPocket => true,
+            Tile::Wall | Tile::GeothermalVent => false,
+            Tile::_Count => {
+                debug_assert!(false, "Tile::_Count in the tilemap?");
+                false
+            }
+        }
+    }
+
+    /// Returns true if job stations can't be placed on this tile.
+    pub const fn blocks_build(self) -> bool {
+        match self {
+            Tile::Seafloor => false,
+            Tile::Wall | Tile::GeothermalVent | Tile::Door | Tile::GasPocket => true,
+            Tile::_Count => {
+                debug_assert!(false, "Tile::_Count in the tilemap?");
+                true
+            }
+        }
+    }
+
+    /// Returns true if this tile separates rooms from each other.
+    pub const fn is_room_boundary(self) -> bool {
+        match self {
+            Tile::Wall | Tile::GeothermalVent | Tile::Door => true,
+            Tile::Seafloor | Tile::GasPocket => false,
+            Tile::_Count => {
+                debug_assert!(false, "Tile::_Count in the tilemap?");
+                true
+            }
+        }
+    }
+}
+
+/// How many resources are mined out of a wall tile with a vein.
+pub const VEIN_RESOURCE_AMOUNT: u8 = 3;
+/// The richest vents produce this much magma per magma tick, the poorest 1.
+pub const MAX_VENT_RICHNESS: u8 = 3;
+/// How many steps' worth walking over a gas pocket costs, when pathfinding
+/// with the costs from [`Tilemap::mark_hazard_costs`].
+pub const GAS_POCKET_PATH_COST: u8 = 8;
+
+/// Large regions of the map with differing terrain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Zeroable)]
+#[repr(u8)]
+pub enum Biome {
+    OpenPlain,
+    DenseRock,
+    VentField,
+}
+
+impl Biome {
+    /// The biome with the given discriminant, if there is one.
+    const fn from_u8(value: u8) -> Option<Biome> {
+        match value {
+            0 => Some(Biome::OpenPlain),
+            1 => Some(Biome::DenseRock),
+            2 => Some(Biome::VentField),
+            _ => None,
+        }
+    }
+
+    /// Added to [`TerrainParams::wall_threshold`] for tiles in this biome.
+    const fn wall_threshold_offset(self) -> f32 {
+        match self {
+            Biome::OpenPlain => -0.15,
+            Biome::DenseRock => 0.15,
+            Biome::VentField => 0.0,
+        }
+    }
+
+    /// Tiles with noise values above this are geothermal vents.
+    const fn vent_threshold(self) -> f32 {
+        match self {
+            Biome::OpenPlain | Biome::DenseRock => 0.6,
+            Biome::VentField => 0.4,
+        }
+    }
+}
+
+/// The tiles reachable from some tile without crossing walls.
+pub struct Room<'a> {
+    pub tiles: BitGrid<'a>,
+    /// True if the room doesn't reach the edge of the map.
+    pub enclosed: bool,
+}
+
+/// Statistics about a map, for checking that world generation produces
+/// playable maps.
+#[derive(Clone, Copy, Debug)]
+pub struct WorldSummary {
+    /// How many tiles of each [`Tile`] variant there are, indexed by the
+    /// variant.
+    pub tile_counts: [usize; Tile::_Count as usize],
+    pub vent_count: usize,
+    /// How many tiles are in the largest region of walkable tiles connected to
+    /// each other.
+    pub largest_region_size: usize,
+    /// True if there's a vent next to the region the start position is in.
+    pub start_connects_to_vent: bool,
+}
+
+/// Tuning knobs for the terrain generation.
+#[derive(Clone, Copy, Debug)]
+pub struct TerrainParams {
+    /// How many tiles one unit of noise spans. Bigger values make for bigger
+    /// caves and walls.
+    pub scale: f32,
+    /// Tiles with noise values at or below this are walls. Bigger values make
+    /// for more walls.
+    pub wall_threshold: f32,
+    /// How many layers of noise, each with double the frequency of the
+    /// previous one, are summed up. More octaves make for rougher walls.
+    pub octaves: u8,
+    /// How much each octave's amplitude is scaled compared to the previous
+    /// one's.
+    pub persistence: f32,
+    /// How many tiles one unit of the biome noise spans.
+    pub biome_scale: f32,
+    /// Wall tiles with vein noise values above this contain resources.
+    /// Bigger values make for fewer veins.
+    pub vein_threshold: f32,
+    /// Seafloor tiles with gas noise values above this are gas pockets.
+    /// Bigger values make for fewer gas pockets.
+    pub gas_pocket_threshold: f32,
+}
+
+impl Default for TerrainParams {
+    fn default() -> Self {
+        TerrainParams {
+            scale: 4.0,
+            wall_threshold: -0.2,
+            octaves: 3,
+            persistence: 0.5,
+            biome_scale: 32.0,
+            vein_threshold: 0.35,
+            gas_pocket_threshold: 0.45,
+        }
+    }
+}
+
 pub struct Tilemap<'a> {
     pub tiles: Grid<'a, Tile>,
+    biomes: Grid<'a, Biome>,
+    /// The resources hidden in wall tiles, revealed by mining.
+    veins: Grid<'a, ResourceVariant>,
+    /// How much magma a vent on each tile would produce. Rolled for every
+    /// tile, so that vents placed after generation have a richness as well.
+    vent_richness: Grid<'a, u8>,
     tile_sprites: FixedVec<'a, SpriteHandle>,
 }
 
+impl<'a> Tilemap<'a> {
+    /// Finds the sprites for each tile type, so that the tilemap can be
+    /// rendered.
+    pub fn load_sprites(&mut self, arena: &'a LinearAllocator, resources: &ResourceDatabase) {
+        let tile_types: [Tile; Tile::_Count as usize] = [
+            Tile::Seafloor,
+            Tile::Wall,
+            Tile::GeothermalVent,
+            Tile::Door,
+            Tile::GasPocket,
+        ];
+        let mut tile_sprites = FixedVec::new(arena, Tile::_Count as usize).unwrap();
+        for tile in tile_types {
+            let mut name = ArrayString::<27>::new();
+            write!(&mut name, "{tile:?}").expect("tile name too long");
+            let Some(sprite) = resources.find_sprite(&name) else {
+                panic!("sprite {name} is missing from resources.db");
+            };
+            tile_sprites.push(sprite).unwrap();
+        }
+        self.tile_sprites = tile_sprites;
+    }
+}
+
 impl Tilemap<'_> {
     pub fn new<'a>(
         arena: &'a LinearAllocator,
         resources: &ResourceDatabase,
         seed: u64,
+        terrain_params: &TerrainParams,
     ) -> Tilemap<'a> {
-        let rand = seahash::hash(&seed.to_le_bytes());
-        let x_off = (rand & 0xFFFF) as f32;
-        let y_off = ((rand >> 16) & 0xFFFF) as f32;
-
-        let (width, height) = (128, 128);
-        let mut tiles = Grid::new_zeroed(arena, (width, height)).unwrap();
-        for y in 0..height {
-            for x in 0..width {
-                let noise = perlin_noise(Vec2::new(x as f32 + x_off, y as f32 + y_off) / 4.0);
-                tiles[(x, y)] = if noise > 0.6 {
-                    Tile::GeothermalVent
-                } else if noise > -0.2 {
-                    Tile::Seafloor
-                } else {
-                    Tile::Wall
-                };
-            }
+        let mut tilemap = Tilemap::generate(arena, seed, terrain_params).unwrap();
+        tilemap.load_sprites(arena, resources);
+        tilemap
+    }
+
+    /// Feeds everything about the terrain into `hasher`, other than the
+    /// sprites, which don't affect the simulation.
+    pub fn hash_state<H: Hasher>(&self, hasher: &mut H) {
+        for tile in self.tiles.values() {
+            hasher.write_u8(*tile as u8);
+        }
+        for biome in self.biomes.values() {
+            hasher.write_u8(*biome as u8);
         }
+        hasher.write(bytemuck::cast_slice(self.veins.values()));
+        hasher.write(self.vent_richness.values());
+    }
 
-        let tile_types: [Tile; Tile::_Count as usize] =
-            [Tile::Seafloor, Tile::Wall, Tile::GeothermalVent];
-        let mut tile_sprites = FixedVec::new(arena, Tile::_Count as usize).unwrap();
-        for tile in tile_types {
-            let mut name = ArrayString::<27>::new();
-            write!(&mut name, "{tile:?}").expect("tile name too long");
-            let sprite = resources.find_sprite(&name).unwrap();
-            tile_sprites.push(sprite).unwrap();
+    /// Writes everything about the terrain into the save, other than the
+    /// sprites.
+    pub fn save(&self, writer: &mut SaveWriter) {
+        let (width, height) = self.tiles.size();
+        writer.write_u16(width as u16);
+        writer.write_u16(height as u16);
+        for tile in self.tiles.values() {
+            writer.write_u8(*tile as u8);
         }
+        for biome in self.biomes.values() {
+            writer.write_u8(*biome as u8);
+        }
+        writer.write_bytes(bytemuck::cast_slice(self.veins.values()));
+        writer.write_bytes(self.vent_richness.values());
+    }
+
+    /// Allocates an all-seafloor tilemap of the default map size, to be
+    /// overwritten with [`Tilemap::load`]. Like [`Tilemap::generate`], this
+    /// doesn't load any sprites.
+    pub fn new_zeroed<'a>(arena: &'a LinearAllocator) -> Option<Tilemap<'a>> {
+        Some(Tilemap {
+            tiles: Grid::new_zeroed(arena, MAP_SIZE)?,
+            biomes: Grid::new_zeroed(arena, MAP_SIZE)?,
+            veins: Grid::new_zeroed(arena, MAP_SIZE)?,
+            vent_richness: Grid::new_zeroed(arena, MAP_SIZE)?,
+            tile_sprites: FixedVec::new(arena, 0)?,
+        })
+    }
 
+    /// Reads a tilemap written by [`Tilemap::save`] over this one, reusing its
+    /// grids. The sprites are left as they were. Returns `None` if the save
+    /// is cut short, has unknown tiles, or is of a different size than this
+    /// tilemap, in which case the tilemap may have been partially
+    /// overwritten.
+    pub fn load(&mut self, reader: &mut SaveReader) -> Option<()> {
+        let size = (reader.read_u16()? as usize, reader.read_u16()? as usize);
+        if size != self.tiles.size() {
+            return None;
+        }
+        for y in 0..size.1 {
+            for x in 0..size.0 {
+                self.tiles[(x, y)] = Tile::from_u8(reader.read_u8()?)?;
+            }
+        }
+        for y in 0..size.1 {
+            for x in 0..size.0 {
+                self.biomes[(x, y)] = Biome::from_u8(reader.read_u8()?)?;
+            }
+        }
+        for y in 0..size.1 {
+            for x in 0..size.0 {
+                self.veins[(x, y)] = reader.read_pod()?;
+            }
+        }
+        for y in 0..size.1 {
+            for x in 0..size.0 {
+                self.vent_richness[(x, y)] = reader.read_u8()?;
+            }
+        }
+        Some(())
+    }
+
+    /// Generates the terrain without loading any sprites, so the tilemap can
+    /// be inspected but not rendered.
+    pub fn generate<'a>(
+        arena: &'a LinearAllocator,
+        seed: u64,
+        terrain_params: &TerrainParams,
+    ) -> Option<Tilemap<'a>> {
+        let biomes = generate_biomes(arena, seed, terrain_params)?;
+        let mut tiles = generate_tiles(arena, seed, terrain_params, &biomes)?;
+        place_gas_pockets(seed, terrain_params, &mut tiles);
+        let veins = generate_veins(arena, seed, terrain_params, &tiles)?;
+        let vent_richness = generate_vent_richness(arena, seed, tiles.size())?;
+        Some(Tilemap {
+            tiles,
+            biomes,
+            veins,
+            vent_richness,
+            tile_sprites: FixedVec::new(arena, 0)?,
+        })
+    }
+
+    /// Creates a tilemap out of the given tiles, with no veins and no sprites.
+    #[cfg(test)]
+    pub fn from_tiles<'a>(arena: &'a LinearAllocator, tiles: Grid<'a, Tile>) -> Tilemap<'a> {
         Tilemap {
+            biomes: Grid::new_zeroed(arena, tiles.size()).unwrap(),
+            veins: Grid::new_zeroed(arena, tiles.size()).unwrap(),
+            vent_richness: generate_vent_richness(arena, 0, tiles.size()).unwrap(),
             tiles,
-            tile_sprites,
+            tile_sprites: FixedVec::new(arena, 0).unwrap(),
         }
     }
 
+    /// Turns the wall at `pos` into seafloor, returning the resource that was
+    /// in it, if any.
+    pub fn mine(&mut self, pos: TilePosition) -> Option<ResourceVariant> {
+        let tile = self.tiles.get_mut(pos)?;
+        if !matches!(tile, Tile::Wall) {
+            return None;
+        }
+        *tile = Tile::Seafloor;
+        let vein = self.veins[pos];
+        self.veins[pos] = ResourceVariant::zeroed();
+        if vein == ResourceVariant::zeroed() {
+            None
+        } else {
+            Some(vein)
+        }
+    }
+
+    pub fn biome_at(&self, pos: TilePosition) -> Biome {
+        self.biomes[pos]
+    }
+
+    /// Returns how much magma the vent at `pos` produces per magma tick, or 0
+    /// if there's no vent there.
+    pub fn vent_richness(&self, pos: TilePosition) -> u8 {
+        match self.tiles.get(pos) {
+            Some(Tile::GeothermalVent) => self.vent_richness[pos],
+            _ => 0,
+        }
+    }
+
+    /// Counts up the tiles and measures the regions of walkable tiles, with
+    /// `start` being where the characters start out.
+    pub fn summary(&self, start: TilePosition, arena: &LinearAllocator) -> Option<WorldSummary> {
+        let mut summary = WorldSummary {
+            tile_counts: [0; Tile::_Count as usize],
+            vent_count: 0,
+            largest_region_size: 0,
+            start_connects_to_vent: false,
+        };
+        for y in 0..self.tiles.height() {
+            for x in 0..self.tiles.width() {
+                summary.tile_counts[self.tiles[(x, y)] as usize] += 1;
+            }
+        }
+        summary.vent_count = summary.tile_counts[Tile::GeothermalVent as usize];
+
+        let mut visited = BitGrid::new(arena, self.tiles.size())?;
+        let mut try_positions: Queue<TilePosition> =
+            Queue::new(arena, self.tiles.width() * self.tiles.height())?;
+        for y in 0..self.tiles.height() as i16 {
+            for x in 0..self.tiles.width() as i16 {
+                let pos = TilePosition::new(x, y);
+                if visited.get(pos) || !self.tiles[pos].is_walkable() {
+                    continue;
+                }
+
+                visited.set(pos, true);
+                let _ = try_positions.push_back(pos);
+                let mut region_size = 0;
+                let mut contains_start = false;
+                let mut next_to_vent = false;
+                while let Some(try_pos) = try_positions.pop_front() {
+                    region_size += 1;
+                    contains_start |= try_pos == start;
+                    for dir in Direction::ALL {
+                        let neighbor = try_pos + dir;
+                        match self.tiles.get(neighbor) {
+                            Some(Tile::GeothermalVent) => next_to_vent = true,
+                            Some(tile) if tile.is_walkable() && !visited.get(neighbor) => {
+                                visited.set(neighbor, true);
+                                let could_add_neighbor = try_positions.push_back(neighbor);
+                                debug_assert!(could_add_neighbor.is_ok());
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+
+                summary.largest_region_size = summary.largest_region_size.max(region_size);
+                if contains_start {
+                    summary.start_connects_to_vent = next_to_vent;
+                }
+            }
+        }
+
+        Some(summary)
+    }
+
+    /// Marks the tiles which can't be walked on in `walls`.
+    pub fn mark_walls(&self, walls: &mut BitGrid) {
+        for y in 0..self.tiles.height() {
+            for x in 0..self.tiles.width() {
+                if !self.tiles[(x, y)].is_walkable() {
+                    walls.set(TilePosition::new(x as i16, y as i16), true);
+                }
+            }
+        }
+    }
+
+    /// Raises the costs in `tile_costs` for tiles which are dangerous to walk
+    /// over, for pathfinding around them.
+    pub fn mark_hazard_costs(&self, tile_costs: &mut Grid<u8>) {
+        for y in 0..self.tiles.height() {
+            for x in 0..self.tiles.width() {
+                if matches!(self.tiles[(x, y)], Tile::GasPocket) {
+                    let cost = &mut tile_costs[(x, y)];
+                    *cost = (*cost).max(GAS_POCKET_PATH_COST);
+                }
+            }
+        }
+    }
+
+    /// Returns true if `pos` is a gas pocket.
+    pub fn is_gas_pocket(&self, pos: TilePosition) -> bool {
+        matches!(self.tiles.get(pos), Some(Tile::GasPocket))
+    }
+
+    /// Flood fills the room `pos` is in. Returns None if `pos` is not inside
+    /// the map or is a wall, or if there's not enough memory for the fill.
+    pub fn room_at<'a>(&self, pos: TilePosition, arena: &'a LinearAllocator) -> Option<Room<'a>> {
+        if !self.tiles.in_bounds(pos) || self.is_room_boundary(pos) {
+            return None;
+        }
+
+        let mut tiles = BitGrid::new(arena, self.tiles.size())?;
+        let mut try_positions: Queue<TilePosition> =
+            Queue::new(arena, self.tiles.width() * self.tiles.height())?;
+        let mut enclosed = true;
+
+        tiles.set(pos, true);
+        let _ = try_positions.push_back(pos);
+        while let Some(try_pos) = try_positions.pop_front() {
+            for dir in Direction::ALL {
+                let neighbor = try_pos + dir;
+                if !self.tiles.in_bounds(neighbor) {
+                    enclosed = false;
+                    continue;
+                }
+                if tiles.get(neighbor) || self.is_room_boundary(neighbor) {
+                    continue;
+                }
+                tiles.set(neighbor, true);
+                let could_add_neighbor = try_positions.push_back(neighbor);
+                debug_assert!(could_add_neighbor.is_ok());
+            }
+        }
+
+        Some(Room { tiles, enclosed })
+    }
+
+    fn is_room_boundary(&self, pos: TilePosition) -> bool {
+        self.tiles[pos].is_room_boundary()
+    }
+
     pub fn render(
         &self,
         draw_queue: &mut DrawQueue,
         resources: &ResourceDatabase,
         resource_loader: &mut ResourceLoader,
         camera: &Camera,
+        sprites: &[SpriteHandle],
         temp_arena: &LinearAllocator,
     ) {
-        let top_left = (camera.position - camera.size / 2. - Vec2::ONE)
-            .max(Vec2::ZERO)
-            .as_usizevec2();
-        let bottom_right = (camera.position + camera.size / 2.)
-            .max(Vec2::ZERO)
-            .ceil()
-            .as_usizevec2()
-            .min(USizeVec2::new(self.tiles.width(), self.tiles.height()));
+        let Some((top_left, bottom_right)) = self.visible_tiles(camera) else {
+            return;
+        };
 
         let mut tile_sprites = FixedVec::new(temp_arena, self.tile_sprites.len()).unwrap();
         for sprite in &*self.tile_sprites {
@@ -106,11 +532,220 @@ impl Tilemap<'_> {
                     resources,
                     resource_loader,
                 );
+
+                for edge in self.edge_sprites(TilePosition::new(x as i16, y as i16)) {
+                    let sprite = resources.get_sprite(sprites[edge as usize]);
+                    let _ = sprite.draw(
+                        dst,
+                        DrawLayer::TileEdges as u8,
+                        draw_queue,
+                        resources,
+                        resource_loader,
+                    );
+                }
             }
         }
+    }
+
+    /// Returns the outline sprites to draw on the seafloor tile at `pos`, one
+    /// for each side bordering a wall. Where walls meet at a corner, the
+    /// corner sprite is drawn instead of the two edges.
+    fn edge_sprites(&self, pos: TilePosition) -> ArrayVec<Sprite, 4> {
+        let mut edges = ArrayVec::new();
+        if !matches!(self.tiles.get(pos), Some(Tile::Seafloor)) {
+            return edges;
+        }
+
+        let is_wall = |dir: Direction| matches!(self.tiles.get(pos + dir), Some(Tile::Wall));
+        let [up, down, left, right] = [
+            Direction::Up,
+            Direction::Down,
+            Direction::Left,
+            Direction::Right,
+        ]
+        .map(is_wall);
+
+        let corners = [
+            (up && left, Sprite::TileCornerUpLeft),
+            (up && right, Sprite::TileCornerUpRight),
+            (down && left, Sprite::TileCornerDownLeft),
+            (down && right, Sprite::TileCornerDownRight),
+        ];
+        for (is_corner, sprite) in corners {
+            if is_corner {
+                edges.push(sprite);
+            }
+        }
+
+        // Edges not already covered by one of the corners above
+        let straight_edges = [
+            (up && !(left || right), Sprite::TileEdgeUp),
+            (down && !(left || right), Sprite::TileEdgeDown),
+            (left && !(up || down), Sprite::TileEdgeLeft),
+            (right && !(up || down), Sprite::TileEdgeRight),
+        ];
+        for (is_edge, sprite) in straight_edges {
+            if is_edge {
+                edges.push(sprite);
+            }
+        }
+
+        edges
+    }
+
+    /// Returns the top left (inclusive) and bottom right (exclusive) corners
+    /// of the tiles in the camera's view, or None if none of the map is in
+    /// view.
+    fn visible_tiles(&self, camera: &Camera) -> Option<(USizeVec2, USizeVec2)> {
+        let top_left = (camera.to_world(Vec2::ZERO) - Vec2::ONE)
+            .max(Vec2::ZERO)
+            .as_usizevec2();
+        let bottom_right = (camera.to_world(camera.output_size))
+            .max(Vec2::ZERO)
+            .ceil()
+            .as_usizevec2()
+            .min(USizeVec2::new(self.tiles.width(), self.tiles.height()));
+        if top_left.x >= bottom_right.x || top_left.y >= bottom_right.y {
+            None
+        } else {
+            Some((top_left, bottom_right))
+        }
+    }
+}
+
+const MAP_SIZE: (usize, usize) = (128, 128);
+
+fn generate_biomes<'a>(
+    arena: &'a LinearAllocator,
+    seed: u64,
+    params: &TerrainParams,
+) -> Option<Grid<'a, Biome>> {
+    let rand = seahash::hash(&seed.to_le_bytes());
+    let x_off = ((rand >> 32) & 0xFFFF) as f32;
+    let y_off = ((rand >> 48) & 0xFFFF) as f32;
+
+    let (width, height) = MAP_SIZE;
+    let mut biomes = Grid::new_zeroed(arena, (width, height))?;
+    for y in 0..height {
+        for x in 0..width {
+            let sample_point = Vec2::new(x as f32 + x_off, y as f32 + y_off) / params.biome_scale;
+            let noise = perlin_noise(sample_point);
+            biomes[(x, y)] = if noise > 0.15 {
+                Biome::VentField
+            } else if noise < -0.15 {
+                Biome::DenseRock
+            } else {
+                Biome::OpenPlain
+            };
+        }
+    }
+    Some(biomes)
+}
+
+fn generate_tiles<'a>(
+    arena: &'a LinearAllocator,
+    seed: u64,
+    params: &TerrainParams,
+    biomes: &Grid<Biome>,
+) -> Option<Grid<'a, Tile>> {
+    let rand = seahash::hash(&seed.to_le_bytes());
+    let x_off = (rand & 0xFFFF) as f32;
+    let y_off = ((rand >> 16) & 0xFFFF) as f32;
+
+    let (width, height) = MAP_SIZE;
+    let mut tiles = Grid::new_zeroed(arena, (width, height))?;
+    for y in 0..height {
+        for x in 0..width {
+            let sample_point = Vec2::new(x as f32 + x_off, y as f32 + y_off) / params.scale;
+            let noise = fbm(sample_point, params.octaves, params.persistence);
+            let biome = biomes[(x, y)];
+            tiles[(x, y)] = if noise > biome.vent_threshold() {
+                Tile::GeothermalVent
+            } else if noise > params.wall_threshold + biome.wall_threshold_offset() {
+                Tile::Seafloor
+            } else {
+                Tile::Wall
+            };
+        }
+    }
+    Some(tiles)
+}
 
-        // TODO: draw an "outline" on tile edges between differing tiles
+/// Sums up `octaves` layers of perlin noise, each with double the frequency
+/// and `persistence` times the amplitude of the previous layer. The sum is
+/// normalized to the range of a single layer.
+fn fbm(sample_point: Vec2, octaves: u8, persistence: f32) -> f32 {
+    let mut sum = 0.0;
+    let mut total_amplitude = 0.0;
+    let mut amplitude = 1.0;
+    let mut frequency = 1.0;
+    for _ in 0..octaves.max(1) {
+        sum += perlin_noise(sample_point * frequency) * amplitude;
+        total_amplitude += amplitude;
+        amplitude *= persistence;
+        frequency *= 2.0;
     }
+    sum / total_amplitude
+}
+
+fn generate_veins<'a>(
+    arena: &'a LinearAllocator,
+    seed: u64,
+    params: &TerrainParams,
+    tiles: &Grid<Tile>,
+) -> Option<Grid<'a, ResourceVariant>> {
+    let rand = seahash::hash(&seed.wrapping_add(1).to_le_bytes());
+    let x_off = (rand & 0xFFFF) as f32;
+    let y_off = ((rand >> 16) & 0xFFFF) as f32;
+
+    let mut veins = Grid::new_zeroed(arena, tiles.size())?;
+    for y in 0..tiles.height() {
+        for x in 0..tiles.width() {
+            if !matches!(tiles[(x, y)], Tile::Wall) {
+                continue;
+            }
+            let sample_point = Vec2::new(x as f32 + x_off, y as f32 + y_off) / params.scale;
+            if fbm(sample_point, params.octaves, params.persistence) > params.vein_threshold {
+                veins[(x, y)] = ResourceVariant::MAGMA;
+            }
+        }
+    }
+    Some(veins)
+}
+
+/// Turns some of the seafloor into gas pockets, in small clumps where the gas
+/// noise peaks.
+fn place_gas_pockets(seed: u64, params: &TerrainParams, tiles: &mut Grid<Tile>) {
+    let rand = seahash::hash(&seed.wrapping_add(2).to_le_bytes());
+    let x_off = (rand & 0xFFFF) as f32;
+    let y_off = ((rand >> 16) & 0xFFFF) as f32;
+
+    for y in 0..tiles.height() {
+        for x in 0..tiles.width() {
+            if !matches!(tiles[(x, y)], Tile::Seafloor) {
+                continue;
+            }
+            let sample_point = Vec2::new(x as f32 + x_off, y as f32 + y_off) / params.scale;
+            if fbm(sample_point, params.octaves, params.persistence) > params.gas_pocket_threshold {
+                tiles[(x, y)] = Tile::GasPocket;
+            }
+        }
+    }
+}
+
+fn generate_vent_richness<'a>(
+    arena: &'a LinearAllocator,
+    seed: u64,
+    (width, height): (usize, usize),
+) -> Option<Grid<'a, u8>> {
+    let mut richness = Grid::new_zeroed(arena, (width, height))?;
+    for y in 0..height {
+        for x in 0..width {
+            let rand = seahash::hash(bytemuck::bytes_of(&[seed, x as u64, y as u64]));
+            richness[(x, y)] = 1 + (rand % MAX_VENT_RICHNESS as u64) as u8;
+        }
+    }
+    Some(richness)
 }
 
 fn perlin_noise(sample_point: Vec2) -> f32 {
@@ -141,3 +776,381 @@ fn smoothstep(x: f32) -> f32 {
     let x2 = x * x;
     3. * x2 - 2. * x2 * x
 }
+
+#[cfg(test)]
+mod tests {
+    use bytemuck::Zeroable;
+    use engine::{allocators::LinearAllocator, static_allocator};
+
+    use crate::{
+        Sprite,
+        camera::{Camera, CameraShake},
+        game_object::{ResourceVariant, TilePosition},
+        grid::{BitGrid, Grid},
+        pathfinding::{Path, PathOptions, find_path_to, find_path_to_any},
+    };
+
+    use glam::Vec2;
+
+    use super::{
+        MAX_VENT_RICHNESS, TerrainParams, Tile, Tilemap, fbm, generate_biomes, generate_tiles,
+        generate_veins, generate_vent_richness,
+    };
+
+    #[test]
+    fn tile_semantics_cover_every_tile() {
+        // (tile, walkable, blocks building, room boundary)
+        let expected = [
+            (Tile::Seafloor, true, false, false),
+            (Tile::Wall, false, true, true),
+            (Tile::GeothermalVent, false, true, true),
+            (Tile::Door, true, true, true),
+            (Tile::GasPocket, true, true, false),
+        ];
+        assert_eq!(expected.len(), Tile::_Count as usize);
+        for (tile, walkable, blocks_build, room_boundary) in expected {
+            assert_eq!(tile.is_walkable(), walkable, "{tile:?} walkability");
+            assert_eq!(tile.blocks_build(), blocks_build, "{tile:?} buildability");
+            assert_eq!(
+                tile.is_room_boundary(),
+                room_boundary,
+                "{tile:?} room boundary"
+            );
+        }
+    }
+
+    /// Creates a tilemap from rows of characters, where # is a wall, D is a
+    /// door, G is a gas pocket, and anything else is seafloor.
+    fn tilemap_from_rows(arena: &'static LinearAllocator, rows: &[&str]) -> Tilemap<'static> {
+        let mut tiles = Grid::new_zeroed(arena, (rows[0].len(), rows.len())).unwrap();
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                tiles[(x, y)] = match c {
+                    '#' => Tile::Wall,
+                    'D' => Tile::Door,
+                    'G' => Tile::GasPocket,
+                    _ => Tile::Seafloor,
+                };
+            }
+        }
+        Tilemap::from_tiles(arena, tiles)
+    }
+
+    #[test]
+    fn summaries_count_tiles_and_regions() {
+        static ARENA: &LinearAllocator = static_allocator!(10_000);
+        let mut tilemap = tilemap_from_rows(ARENA, &["..#...", "..#.#.", "###.#."]);
+        tilemap.tiles[(5, 0)] = Tile::GeothermalVent;
+
+        let summary = tilemap.summary(TilePosition::new(3, 2), ARENA).unwrap();
+        assert_eq!(summary.tile_counts[Tile::Seafloor as usize], 10);
+        assert_eq!(summary.tile_counts[Tile::Wall as usize], 7);
+        assert_eq!(summary.vent_count, 1);
+        assert_eq!(summary.largest_region_size, 4);
+        assert!(summary.start_connects_to_vent);
+
+        let summary = tilemap.summary(TilePosition::new(0, 0), ARENA).unwrap();
+        assert!(!summary.start_connects_to_vent);
+    }
+
+    #[test]
+    fn generated_maps_are_mostly_connected() {
+        static ARENA: &LinearAllocator = static_allocator!(2_000_000);
+        let params = TerrainParams::default();
+        for seed in 0..8 {
+            let biomes = generate_biomes(ARENA, seed, &params).unwrap();
+            let tiles = generate_tiles(ARENA, seed, &params, &biomes).unwrap();
+            let tilemap = Tilemap::from_tiles(ARENA, tiles);
+            let summary = tilemap.summary(TilePosition::new(0, 0), ARENA).unwrap();
+            let tile_count = tilemap.tiles.width() * tilemap.tiles.height();
+            assert!(
+                summary.largest_region_size > tile_count / 2,
+                "the largest region of seed {seed} only has {} tiles",
+                summary.largest_region_size,
+            );
+            assert!(summary.vent_count > 0, "no vents with seed {seed}");
+        }
+    }
+
+    #[test]
+    fn vent_richness_is_deterministic_and_varied() {
+        static ARENA: &LinearAllocator = static_allocator!(600_000);
+        let tilemap = Tilemap::generate(ARENA, 1234, &TerrainParams::default()).unwrap();
+        let size = tilemap.tiles.size();
+        let richness = generate_vent_richness(ARENA, 1234, size).unwrap();
+        let richness_again = generate_vent_richness(ARENA, 1234, size).unwrap();
+        let other_seed_richness = generate_vent_richness(ARENA, 4321, size).unwrap();
+
+        let mut seen = [false; MAX_VENT_RICHNESS as usize + 1];
+        let mut differs_between_seeds = false;
+        for y in 0..tilemap.tiles.height() as i16 {
+            for x in 0..tilemap.tiles.width() as i16 {
+                let pos = TilePosition::new(x, y);
+                assert_eq!(richness[pos], richness_again[pos]);
+                differs_between_seeds |= richness[pos] != other_seed_richness[pos];
+                let vent_richness = tilemap.vent_richness(pos);
+                if matches!(tilemap.tiles[pos], Tile::GeothermalVent) {
+                    assert_eq!(vent_richness, richness[pos]);
+                    assert!((1..=MAX_VENT_RICHNESS).contains(&vent_richness));
+                } else {
+                    assert_eq!(vent_richness, 0);
+                }
+                seen[vent_richness as usize] = true;
+            }
+        }
+        let richness_count = seen[1..].iter().filter(|seen| **seen).count();
+        assert!(richness_count >= 2, "all vents are equally rich");
+        assert!(differs_between_seeds);
+    }
+
+    #[test]
+    fn sealed_rooms_are_enclosed() {
+        static ARENA: &LinearAllocator = static_allocator!(10_000);
+        let tilemap = tilemap_from_rows(ARENA, &["......", ".####.", ".#..#.", ".####.", "......"]);
+
+        let room = tilemap.room_at(TilePosition::new(2, 2), ARENA).unwrap();
+        assert!(room.enclosed);
+        assert!(room.tiles.get(TilePosition::new(3, 2)));
+        assert!(!room.tiles.get(TilePosition::new(0, 0)));
+
+        let outside = tilemap.room_at(TilePosition::new(0, 0), ARENA).unwrap();
+        assert!(!outside.enclosed);
+        assert!(!outside.tiles.get(TilePosition::new(2, 2)));
+
+        assert!(tilemap.room_at(TilePosition::new(1, 1), ARENA).is_none());
+    }
+
+    #[test]
+    fn open_areas_are_not_enclosed() {
+        static ARENA: &LinearAllocator = static_allocator!(10_000);
+        let tilemap = tilemap_from_rows(ARENA, &["######", "#....#", "#.##.#", "#.#...", "######"]);
+
+        let room = tilemap.room_at(TilePosition::new(1, 1), ARENA).unwrap();
+        assert!(!room.enclosed);
+        assert!(room.tiles.get(TilePosition::new(5, 3)));
+    }
+
+    #[test]
+    fn doors_seal_rooms_but_can_be_walked_through() {
+        static ARENA: &LinearAllocator = static_allocator!(10_000);
+        let tilemap = tilemap_from_rows(ARENA, &["......", ".#D##.", ".#..#.", ".####.", "......"]);
+
+        let room = tilemap.room_at(TilePosition::new(2, 2), ARENA).unwrap();
+        assert!(room.enclosed);
+        assert!(!room.tiles.get(TilePosition::new(2, 1)));
+
+        let mut walls = BitGrid::new(ARENA, tilemap.tiles.size()).unwrap();
+        tilemap.mark_walls(&mut walls);
+        let no_characters = BitGrid::new(ARENA, tilemap.tiles.size()).unwrap();
+        let from = TilePosition::new(2, 0);
+        let to = TilePosition::new(3, 2);
+        let path = find_path_to(from, to, &PathOptions::new(&walls, &no_characters), ARENA);
+        assert_eq!(path.map(|path| path.len()), Ok(3));
+    }
+
+    #[test]
+    fn gas_pockets_are_rare() {
+        static ARENA: &LinearAllocator = static_allocator!(1024 * 1024);
+        let mut total_gas_pockets = 0;
+        for seed in 0..5 {
+            let tilemap = Tilemap::generate(ARENA, seed, &TerrainParams::default()).unwrap();
+            let summary = tilemap.summary(TilePosition::new(0, 0), ARENA).unwrap();
+            let gas_pockets = summary.tile_counts[Tile::GasPocket as usize];
+            let seafloor = summary.tile_counts[Tile::Seafloor as usize];
+            assert!(
+                gas_pockets * 20 < seafloor,
+                "{gas_pockets} gas pockets vs {seafloor} seafloor tiles for seed {seed}"
+            );
+            total_gas_pockets += gas_pockets;
+        }
+        assert!(total_gas_pockets > 0);
+    }
+
+    #[test]
+    fn hazard_costs_route_paths_around_gas_pockets() {
+        static ARENA: &LinearAllocator = static_allocator!(100_000);
+        let tilemap = tilemap_from_rows(ARENA, &[".....", "..G..", "....."]);
+        let mut walls = BitGrid::new(ARENA, tilemap.tiles.size()).unwrap();
+        tilemap.mark_walls(&mut walls);
+        let no_characters = BitGrid::new(ARENA, tilemap.tiles.size()).unwrap();
+        let mut tile_costs: Grid<u8> = Grid::new_zeroed(ARENA, tilemap.tiles.size()).unwrap();
+        tilemap.mark_hazard_costs(&mut tile_costs);
+        let (from, to) = (TilePosition::new(0, 1), TilePosition::new(4, 1));
+        let mut destinations = BitGrid::new(ARENA, tilemap.tiles.size()).unwrap();
+        destinations.set(to, true);
+
+        let crosses_gas = |path: &Path| {
+            let mut pos = from;
+            path.into_iter().any(|step| {
+                pos = pos + step;
+                tilemap.is_gas_pocket(pos)
+            })
+        };
+        let find_path = |tile_costs| {
+            let options = PathOptions::new(&walls, &no_characters).with_tile_costs(tile_costs);
+            find_path_to_any(from, &destinations, &options, ARENA).unwrap()
+        };
+
+        let straight = find_path(None);
+        assert_eq!(straight.len(), 4);
+        assert!(crosses_gas(&straight));
+        let detour = find_path(Some(&tile_costs));
+        assert_eq!(detour.len(), 6);
+        assert!(!crosses_gas(&detour));
+    }
+
+    #[test]
+    fn higher_wall_threshold_makes_more_walls() {
+        static ARENA: &LinearAllocator = static_allocator!(100_000);
+        let count_walls = |wall_threshold: f32| {
+            let params = TerrainParams {
+                wall_threshold,
+                octaves: 3,
+                ..TerrainParams::default()
+            };
+            let biomes = Grid::new_zeroed(ARENA, (128, 128)).unwrap();
+            let tiles = generate_tiles(ARENA, 1234, &params, &biomes).unwrap();
+            let mut walls = 0;
+            for y in 0..tiles.height() {
+                for x in 0..tiles.width() {
+                    if matches!(tiles[(x, y)], Tile::Wall) {
+                        walls += 1;
+                    }
+                }
+            }
+            walls
+        };
+        assert!(count_walls(0.0) > count_walls(-0.2));
+    }
+
+    #[test]
+    fn fbm_is_bounded_and_deterministic() {
+        for y in 0..50 {
+            for x in 0..50 {
+                let point = Vec2::new(x as f32 * 0.37 + 1234.0, y as f32 * 0.29 + 4321.0);
+                let noise = fbm(point, 4, 0.5);
+                assert!(
+                    (-1.0..=1.0).contains(&noise),
+                    "{noise} out of bounds at {point}"
+                );
+                assert_eq!(noise, fbm(point, 4, 0.5));
+            }
+        }
+    }
+
+    #[test]
+    fn biomes_are_deterministic_and_varied() {
+        static ARENA: &LinearAllocator = static_allocator!(100_000);
+        let params = TerrainParams::default();
+        let biomes = generate_biomes(ARENA, 1234, &params).unwrap();
+        let biomes_again = generate_biomes(ARENA, 1234, &params).unwrap();
+
+        let mut seen = [false; 3];
+        for y in 0..biomes.height() {
+            for x in 0..biomes.width() {
+                assert_eq!(biomes[(x, y)], biomes_again[(x, y)]);
+                seen[biomes[(x, y)] as usize] = true;
+            }
+        }
+        let biome_count = seen.iter().filter(|seen| **seen).count();
+        assert!(biome_count >= 2, "only one biome on the whole map");
+    }
+
+    #[test]
+    fn veins_are_deterministic_and_inside_walls() {
+        static ARENA: &LinearAllocator = static_allocator!(200_000);
+        let params = TerrainParams::default();
+        let biomes = generate_biomes(ARENA, 1234, &params).unwrap();
+        let tiles = generate_tiles(ARENA, 1234, &params, &biomes).unwrap();
+        let veins = generate_veins(ARENA, 1234, &params, &tiles).unwrap();
+        let veins_again = generate_veins(ARENA, 1234, &params, &tiles).unwrap();
+
+        let mut vein_count = 0;
+        for y in 0..tiles.height() {
+            for x in 0..tiles.width() {
+                assert!(veins[(x, y)] == veins_again[(x, y)]);
+                if veins[(x, y)] != ResourceVariant::zeroed() {
+                    assert!(matches!(tiles[(x, y)], Tile::Wall), "vein outside walls");
+                    vein_count += 1;
+                }
+            }
+        }
+        assert!(vein_count > 0, "no veins on the whole map");
+    }
+
+    #[test]
+    fn off_map_cameras_see_no_tiles() {
+        static ARENA: &LinearAllocator = static_allocator!(10_000);
+        let tilemap = tilemap_from_rows(ARENA, &["....", "....", "...."]);
+        let camera = |x, y| Camera {
+            position: Vec2::new(x, y),
+            size: Vec2::new(4., 2.),
+            output_size: Vec2::new(400., 200.),
+            shake: CameraShake::default(),
+        };
+
+        let (top_left, bottom_right) = tilemap.visible_tiles(&camera(2., 1.)).unwrap();
+        assert_eq!((top_left.x, top_left.y), (0, 0));
+        assert_eq!((bottom_right.x, bottom_right.y), (4, 2));
+
+        for (x, y) in [(20., 1.), (2., 20.), (-20., 1.), (2., -20.)] {
+            assert!(
+                tilemap.visible_tiles(&camera(x, y)).is_none(),
+                "at {x}, {y}"
+            );
+        }
+    }
+
+    #[test]
+    fn visible_tiles_cover_the_view_at_any_zoom() {
+        static ARENA: &LinearAllocator = static_allocator!(100_000);
+        let tiles = Grid::new_zeroed(ARENA, (64, 64)).unwrap();
+        let tilemap = Tilemap::from_tiles(ARENA, tiles);
+
+        for view_height in [0.0, 16.0, f32::MAX] {
+            for position in [
+                Vec2::new(0.5, 0.5),
+                Vec2::new(32., 32.),
+                Vec2::new(63.5, 63.5),
+            ] {
+                let mut camera = Camera {
+                    position,
+                    size: Vec2::ZERO,
+                    output_size: Vec2::new(1280., 720.),
+                    shake: CameraShake::default(),
+                };
+                camera.set_view_height(view_height);
+                let (top_left, bottom_right) = tilemap.visible_tiles(&camera).unwrap();
+                assert!(bottom_right.x <= 64 && bottom_right.y <= 64);
+
+                let view_min = (position - camera.size / 2.).max(Vec2::ZERO);
+                let view_max = (position + camera.size / 2.).min(Vec2::splat(64.));
+                assert!(top_left.x as f32 <= view_min.x && top_left.y as f32 <= view_min.y);
+                assert!(
+                    bottom_right.x as f32 >= view_max.x && bottom_right.y as f32 >= view_max.y,
+                    "{bottom_right:?} doesn't reach {view_max} at {view_height}",
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn edges_are_drawn_on_seafloor_next_to_walls() {
+        static ARENA: &LinearAllocator = static_allocator!(10_000);
+        let tilemap = tilemap_from_rows(ARENA, &["#####", "#...#", "#.#.#", "#...#", "D...."]);
+        let edges = |x, y| tilemap.edge_sprites(TilePosition::new(x, y));
+
+        assert_eq!(&edges(1, 1)[..], &[Sprite::TileCornerUpLeft]);
+        assert_eq!(&edges(3, 1)[..], &[Sprite::TileCornerUpRight]);
+        assert_eq!(
+            &edges(2, 1)[..],
+            &[Sprite::TileEdgeUp, Sprite::TileEdgeDown]
+        );
+        assert_eq!(&edges(1, 3)[..], &[Sprite::TileEdgeLeft]);
+        assert_eq!(&edges(3, 3)[..], &[Sprite::TileEdgeRight]);
+        assert_eq!(&edges(4, 4)[..], &[Sprite::TileEdgeUp]);
+        // Doors aren't walls, and walls themselves don't get edges
+        assert!(edges(1, 4).is_empty());
+        assert!(edges(0, 0).is_empty());
+    }
+}